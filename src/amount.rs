@@ -0,0 +1,139 @@
+// Typed Amounts
+// This module wraps raw u64 amounts in their unit so mixing units fails to compile
+
+use borsh::{BorshDeserialize, BorshSerialize};                     // Serialized exactly like a bare u64
+
+/// Declares a transparent u64 newtype carrying one unit
+macro_rules! amount_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(
+            BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+        )]
+        #[repr(transparent)]
+        pub struct $name(pub u64);
+
+        impl $name {
+            /// The zero amount
+            pub const ZERO: Self = Self(0);
+
+            /// The raw amount
+            pub const fn get(self) -> u64 {
+                self.0
+            }
+
+            /// Adds an amount of the same unit, returning `None` on overflow
+            pub fn checked_add(self, other: Self) -> Option<Self> {
+                self.0.checked_add(other.0).map(Self)
+            }
+
+            /// Subtracts an amount of the same unit, returning `None` on underflow
+            pub fn checked_sub(self, other: Self) -> Option<Self> {
+                self.0.checked_sub(other.0).map(Self)
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+amount_type!(
+    /// Native SOL, in lamports
+    Lamports
+);
+
+amount_type!(
+    /// The pool's SPL token, in the mint's base units
+    TokenAmount
+);
+
+amount_type!(
+    /// LP tokens, in the LP mint's base units
+    LpAmount
+);
+
+amount_type!(
+    /// A rate in basis points (1 bp = 0.01%)
+    Bps
+);
+
+impl Bps {
+    /// 100%
+    pub const MAX: Self = Self(10000);
+
+    /// The basis points left after applying this rate, `10000 - self`
+    /// 
+    /// Returns `None` for rates above 100%.
+    pub fn complement(self) -> Option<u64> {
+        Self::MAX.0.checked_sub(self.0)
+    }
+}
+
+/// One side of a swap, SOL (mint A for token pair pools) or the pool's token
+/// 
+/// Swap inputs and outputs change unit with the trade direction, so they
+/// carry their side instead of a separate `is_sol_input` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwapAmount {
+    /// SOL, or mint A for token pair pools
+    Sol(Lamports),
+    /// The pool's token
+    Token(TokenAmount),
+}
+
+impl SwapAmount {
+    /// An amount of SOL if `is_sol`, otherwise of the token
+    pub const fn new(amount: u64, is_sol: bool) -> Self {
+        if is_sol {
+            Self::Sol(Lamports(amount))
+        } else {
+            Self::Token(TokenAmount(amount))
+        }
+    }
+
+    /// The raw amount
+    pub const fn get(self) -> u64 {
+        match self {
+            Self::Sol(amount) => amount.0,
+            Self::Token(amount) => amount.0,
+        }
+    }
+
+    /// Whether this is an amount of SOL
+    pub const fn is_sol(self) -> bool {
+        matches!(self, Self::Sol(_))
+    }
+
+    /// Another amount on the same side
+    pub const fn same_side(self, amount: u64) -> Self {
+        Self::new(amount, self.is_sol())
+    }
+
+    /// An amount on the other side, such as a swap's output for this input
+    pub const fn other_side(self, amount: u64) -> Self {
+        Self::new(amount, !self.is_sol())
+    }
+
+    /// The reserve on this amount's side, then the reserve on the other
+    pub const fn reserves(self, sol_reserve: Lamports, token_reserve: TokenAmount) -> (u64, u64) {
+        if self.is_sol() {
+            (sol_reserve.0, token_reserve.0)
+        } else {
+            (token_reserve.0, sol_reserve.0)
+        }
+    }
+
+    /// The SOL and token amounts of a swap with this input and `amount_out`
+    /// 
+    /// Returns `None` if both are on the same side.
+    pub const fn with_output(self, amount_out: Self) -> Option<(Lamports, TokenAmount)> {
+        match (self, amount_out) {
+            (Self::Sol(sol), Self::Token(token)) | (Self::Token(token), Self::Sol(sol)) => Some((sol, token)),
+            _ => None,
+        }
+    }
+}
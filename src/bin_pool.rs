@@ -0,0 +1,319 @@
+// Bin Liquidity
+// This module defines pools whose liquidity sits in discrete price bins, each trading at one fixed price
+
+use borsh::{BorshDeserialize, BorshSerialize};                 // For account serialization
+use solana_program::{
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+};
+
+use crate::{
+    error::TokenExchangeError,                                 // Custom errors
+    math::{self, Rounding},                                    // Rounded and 256-bit division
+};
+
+/// Seed used to derive a bin pool PDA
+pub const BIN_POOL_SEED: &[u8] = b"bin_pool";
+
+/// Seed used to derive a bin PDA of a bin pool
+pub const BIN_SEED: &[u8] = b"bin";
+
+/// Seed used to derive a position PDA in a bin pool
+pub const BIN_POSITION_SEED: &[u8] = b"bin_position";
+
+/// Largest price step between neighbouring bins, in basis points (5%)
+pub const MAX_BIN_STEP: u16 = 500;
+
+/// Most bins one position may span
+pub const MAX_BIN_POSITION_WIDTH: usize = 16;
+
+/// One in Q64.64
+const Q64: u128 = 1 << 64;
+
+/// Bin prices stay below 2^32, and above 2^-32, in Q64.64
+const MAX_BIN_PRICE: u128 = 1 << 96;
+
+/// Q64.64 price of token A in token B at a bin, `(1 + bin_step / 10000)^bin_id`
+/// 
+/// Computed by repeated squaring, each product rounded down. Bins whose
+/// price leaves the range between 2^-32 and 2^32 are rejected with
+/// `InvalidBin`.
+pub fn bin_price(bin_step: u16, bin_id: i32) -> Result<u128, ProgramError> {
+    let base = Q64 + ((bin_step as u128) << 64) / 10000;
+    let mut exponent = bin_id.unsigned_abs();
+    let (mut price, mut power) = (Q64, Some(base));
+    while exponent > 0 {
+        let factor = power.ok_or(TokenExchangeError::InvalidBin)?;
+        if exponent & 1 == 1 {
+            price = math::mul_div_wide(price, factor, Q64, Rounding::Down)
+                .filter(|price| *price < MAX_BIN_PRICE)
+                .ok_or(TokenExchangeError::InvalidBin)?;
+        }
+        exponent >>= 1;
+        power = math::mul_div_wide(factor, factor, Q64, Rounding::Down);
+    }
+    if bin_id < 0 {
+        price = math::mul_div_wide(Q64, Q64, price, Rounding::Down).ok_or(TokenExchangeError::InvalidBin)?;
+    }
+    Ok(price)
+}
+
+/// A pool between two SPL mints whose liquidity sits in discrete price bins
+/// 
+/// Each bin trades at its own fixed price, so swaps within a bin have no
+/// slippage. Bins above the active bin hold only token A and bins below it
+/// only token B; a swap drains the active bin's output token and then
+/// moves to the next bin. Trading fees stay in the bin they were paid to,
+/// growing its shares' value. Both vaults are token accounts owned by the
+/// pool PDA.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct BinPool {
+    pub authority: Pubkey,        // The pool's creator
+    pub mint_a: Pubkey,           // Mint of token A, priced in token B
+    pub mint_b: Pubkey,           // Mint of token B
+    pub vault_a: Pubkey,          // Token account holding the pool's token A
+    pub vault_b: Pubkey,          // Token account holding the pool's token B
+    pub fee_rate: u64,            // Trading fee in basis points
+    pub bin_step: u16,            // Price step between neighbouring bins, in basis points
+    pub active_bin: i32,          // Bin the pool currently trades in
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// One price bin of a bin pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct BinState {
+    pub pool: Pubkey,             // The pool the bin belongs to
+    pub id: i32,                  // The bin, whose price is `bin_price(bin_step, id)`
+    pub amount_a: u64,            // Token A held in the bin
+    pub amount_b: u64,            // Token B held in the bin
+    pub liquidity_supply: u128,   // Shares of the bin issued to positions
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// A liquidity position over a range of bins
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct BinPosition {
+    pub pool: Pubkey,             // The pool provided to
+    pub owner: Pubkey,            // The liquidity provider
+    pub lower_bin: i32,           // Lowest bin of the range
+    pub width: u16,               // Number of bins in the range
+    pub shares: [u128; MAX_BIN_POSITION_WIDTH], // Shares held in each bin of the range, from lower_bin up
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// Result of swapping within one bin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinSwap {
+    pub amount_in: u64,           // Input traded at the bin's price, excluding the fee
+    pub amount_out: u64,          // Output paid from the bin
+    pub fee: u64,                 // Trading fee charged on the input
+}
+
+impl BinPool {
+    /// Total size of the BinPool structure when serialized
+    /// 
+    /// Breakdown:
+    /// - authority, mint_a, mint_b, vault_a, vault_b (Pubkey): 160 bytes
+    /// - fee_rate (u64): 8 bytes
+    /// - bin_step (u16): 2 bytes
+    /// - active_bin (i32): 4 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 * 5 + 8 + 2 + 4 + 1;
+
+    /// Derives the PDA of the pool for a pair of mints and a bin step
+    /// 
+    /// `mint_a` must sort below `mint_b`, so each pair has one pool per step.
+    pub fn find_address(mint_a: &Pubkey, mint_b: &Pubkey, bin_step: u16, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[BIN_POOL_SEED, mint_a.as_ref(), mint_b.as_ref(), &bin_step.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Recomputes the pool's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[
+                BIN_POOL_SEED,
+                self.mint_a.as_ref(),
+                self.mint_b.as_ref(),
+                &self.bin_step.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+}
+
+impl BinState {
+    /// Total size of the BinState structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - id (i32): 4 bytes
+    /// - amount_a, amount_b (u64): 16 bytes
+    /// - liquidity_supply (u128): 16 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 4 + 8 * 2 + 16 + 1;
+
+    /// Derives the PDA of a bin of a pool
+    pub fn find_address(pool: &Pubkey, id: i32, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[BIN_SEED, pool.as_ref(), &id.to_le_bytes()], program_id)
+    }
+
+    /// Recomputes the bin's address from its pool, id and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[BIN_SEED, self.pool.as_ref(), &self.id.to_le_bytes(), &[self.bump]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Value of the bin's tokens in token B at the bin's price, rounded as asked
+    pub fn value(&self, price: u128, rounding: Rounding) -> Option<u128> {
+        math::mul_div_wide(self.amount_a as u128, price, Q64, rounding)?.checked_add(self.amount_b as u128)
+    }
+
+    /// Swaps up to `amount_remaining` of input at the bin's price
+    /// 
+    /// When the input, less the fee, buys all of the bin's output token, the
+    /// bin is drained and the fee is charged on what was used; otherwise the
+    /// whole remaining input is consumed. The input and fee stay in the bin.
+    /// Inputs round up and outputs down, so the bin's value never falls.
+    /// 
+    /// # Arguments
+    /// * `price` - The bin's Q64.64 price
+    /// * `amount_remaining` - Input left to swap, including the fee
+    /// * `fee_rate` - Trading fee in basis points
+    /// * `a_to_b` - Whether token A is sold
+    pub fn swap(
+        &mut self,
+        price: u128,
+        amount_remaining: u64,
+        fee_rate: u64,
+        a_to_b: bool,
+    ) -> Result<BinSwap, ProgramError> {
+        if fee_rate >= 10000 {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+        let available = if a_to_b { self.amount_b } else { self.amount_a };
+        let (in_scale, out_scale) = if a_to_b { (price, Q64) } else { (Q64, price) };
+        let amount_less_fee = amount_remaining as u128 * (10000 - fee_rate) as u128 / 10000;
+
+        // Input that buys out the bin; out of reach of any u64 input if it overflows
+        let drain_in = math::mul_div_wide(available as u128, out_scale, in_scale, Rounding::Up)
+            .filter(|drain_in| *drain_in <= amount_less_fee);
+        let result = match drain_in {
+            Some(amount_in) => {
+                let fee = math::mul_div(amount_in, fee_rate as u128, (10000 - fee_rate) as u128, Rounding::Up)
+                    .ok_or(TokenExchangeError::MathOverflow)?;
+                BinSwap { amount_in: amount_in as u64, amount_out: available, fee: fee as u64 }
+            }
+            None => {
+                let amount_out = math::mul_div_wide(amount_less_fee, in_scale, out_scale, Rounding::Down)
+                    .ok_or(TokenExchangeError::MathOverflow)?
+                    .min(available as u128) as u64;
+                let amount_in = amount_less_fee as u64;
+                BinSwap { amount_in, amount_out, fee: amount_remaining - amount_in }
+            }
+        };
+
+        let (input, output) = if a_to_b {
+            (&mut self.amount_a, &mut self.amount_b)
+        } else {
+            (&mut self.amount_b, &mut self.amount_a)
+        };
+        *input = input.checked_add(result.amount_in + result.fee)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        *output -= result.amount_out;
+        Ok(result)
+    }
+
+    /// Adds tokens to the bin and returns the shares they are worth
+    /// 
+    /// Shares are issued in proportion to the value added, at the bin's
+    /// price, rounded down against a bin value rounded up; the first deposit
+    /// issues its value in token B.
+    pub fn deposit(&mut self, price: u128, amount_a: u64, amount_b: u64) -> Result<u128, ProgramError> {
+        let added = BinState { amount_a, amount_b, ..Default::default() };
+        let value_added = added.value(price, Rounding::Down).ok_or(TokenExchangeError::MathOverflow)?;
+        let shares = if self.liquidity_supply == 0 {
+            value_added
+        } else {
+            let value = self.value(price, Rounding::Up).ok_or(TokenExchangeError::MathOverflow)?;
+            math::mul_div_wide(value_added, self.liquidity_supply, value, Rounding::Down)
+                .ok_or(TokenExchangeError::MathOverflow)?
+        };
+        self.amount_a = self.amount_a.checked_add(amount_a)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.amount_b = self.amount_b.checked_add(amount_b)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.liquidity_supply = self.liquidity_supply.checked_add(shares)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        Ok(shares)
+    }
+
+    /// Redeems shares of the bin for their part of each token, rounded down
+    pub fn withdraw(&mut self, shares: u128) -> Result<(u64, u64), ProgramError> {
+        if shares > self.liquidity_supply {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let part = |amount: u64| {
+            math::mul_div_wide(amount as u128, shares, self.liquidity_supply, Rounding::Down).unwrap_or(0) as u64
+        };
+        let (amount_a, amount_b) = (part(self.amount_a), part(self.amount_b));
+        self.amount_a -= amount_a;
+        self.amount_b -= amount_b;
+        self.liquidity_supply -= shares;
+        Ok((amount_a, amount_b))
+    }
+}
+
+impl BinPosition {
+    /// Total size of the BinPosition structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool, owner (Pubkey): 64 bytes
+    /// - lower_bin (i32): 4 bytes
+    /// - width (u16): 2 bytes
+    /// - shares (u128): 16 * 16 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 64 + 4 + 2 + 16 * MAX_BIN_POSITION_WIDTH + 1;
+
+    /// Derives the PDA of an owner's position over a range of bins of a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        lower_bin: i32,
+        width: u16,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[BIN_POSITION_SEED, pool.as_ref(), owner.as_ref(), &lower_bin.to_le_bytes(), &width.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Recomputes the position's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[
+                BIN_POSITION_SEED,
+                self.pool.as_ref(),
+                self.owner.as_ref(),
+                &self.lower_bin.to_le_bytes(),
+                &self.width.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// The bins of the position's range, from lowest to highest
+    pub fn bins(&self) -> impl Iterator<Item = i32> {
+        self.lower_bin..self.lower_bin + self.width as i32
+    }
+}
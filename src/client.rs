@@ -0,0 +1,358 @@
+// Client Transaction Builders
+// This module assembles complete, correctly ordered swap transactions for integrators
+
+use borsh::BorshSerialize;                                     // For instruction data serialization
+use solana_program::{
+    instruction::{AccountMeta, Instruction},                   // Instruction building
+    program_error::ProgramError,                               // Error handling
+    pubkey,                                                    // Public key literal macro
+    pubkey::Pubkey,                                            // Public key type
+    system_program,                                            // System program ID
+    sysvar,                                                    // Instructions sysvar ID
+};
+
+use crate::{
+    amount::Bps,                                               // Typed amounts
+    instruction::TokenExchangeInstruction,                     // Instruction definitions
+    state::{PoolState, ProtocolConfig},                        // Pool and config PDAs
+    token_interface,                                           // SPL Token and Token-2022
+};
+
+/// Compute budget program ID
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+/// Associated token account program ID
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Compute unit limit requested by default, enough for a swap with every companion account
+pub const DEFAULT_SWAP_COMPUTE_UNITS: u32 = 150_000;
+
+/// Compute budget `SetComputeUnitLimit` discriminant
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+
+/// Compute budget `SetComputeUnitPrice` discriminant
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+
+/// Associated token account `CreateIdempotent` discriminant
+const CREATE_IDEMPOTENT_TAG: u8 = 1;
+
+/// Address of `owner`'s associated token account for `mint`
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Creates `owner`'s associated token account for `mint` unless it already exists
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+        &[CREATE_IDEMPOTENT_TAG],
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint, token_program_id), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+    )
+}
+
+/// Builds a swap transaction: compute budget, account setup, the swap, then cleanup
+/// 
+/// The pool pays and takes native SOL, so wrapped SOL is handled around the
+/// swap: a SOL-input swap can first unwrap the user's WSOL account, and a
+/// SOL-output swap can deliver straight into the user's WSOL account and sync it.
+/// 
+/// `build` returns the instructions in execution order, ready for
+/// `Transaction::new_with_payer` with the user as fee payer.
+#[derive(Debug, Clone)]
+pub struct SwapTxBuilder {
+    program_id: Pubkey,
+    user: Pubkey,
+    pool: Pubkey,
+    token_mint: Pubkey,
+    pool_token_account: Pubkey,
+    token_program_id: Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    is_sol_input: bool,
+    platform_fee: Option<(Bps, Pubkey)>,
+    referral: Option<(Bps, Pubkey)>,
+    sandwich_protection: bool,
+    price_oracle: Option<Pubkey>,
+    companion_accounts: Vec<Pubkey>,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: u64,
+    create_token_account: bool,
+    unwrap_wsol_input: bool,
+    wrap_sol_output: bool,
+    close_token_account: bool,
+    token_pair: Option<(Pubkey, Pubkey)>,
+    wsol_pool: bool,
+}
+
+impl SwapTxBuilder {
+    /// Starts a swap of `amount_in` SOL (or tokens when `is_sol_input` is false)
+    /// 
+    /// Defaults: SPL Token program, `DEFAULT_SWAP_COMPUTE_UNITS`, no priority
+    /// fee, and the user's token ATA created if missing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        program_id: Pubkey,
+        user: Pubkey,
+        pool: Pubkey,
+        token_mint: Pubkey,
+        pool_token_account: Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        is_sol_input: bool,
+    ) -> Self {
+        Self {
+            program_id,
+            user,
+            pool,
+            token_mint,
+            pool_token_account,
+            token_program_id: spl_token::id(),
+            amount_in,
+            minimum_amount_out,
+            is_sol_input,
+            platform_fee: None,
+            referral: None,
+            sandwich_protection: false,
+            price_oracle: None,
+            companion_accounts: Vec::new(),
+            compute_unit_limit: Some(DEFAULT_SWAP_COMPUTE_UNITS),
+            compute_unit_price: 0,
+            create_token_account: true,
+            unwrap_wsol_input: false,
+            wrap_sol_output: false,
+            close_token_account: false,
+            token_pair: None,
+            wsol_pool: false,
+        }
+    }
+
+    /// Uses a different token program for the pool's mint (e.g. Token-2022)
+    pub fn token_program(mut self, token_program_id: Pubkey) -> Self {
+        self.token_program_id = token_program_id;
+        self
+    }
+
+    /// Takes an integrator fee from the output, paid to `fee_account`, bounded by the protocol config
+    pub fn platform_fee(mut self, platform_fee_bps: Bps, fee_account: Pubkey) -> Self {
+        self.platform_fee = Some((platform_fee_bps, fee_account));
+        self
+    }
+
+    /// Pays `referrer_account` a share of the trading fee, bounded by the protocol config
+    pub fn referral(mut self, referral_fee_share_bps: Bps, referrer_account: Pubkey) -> Self {
+        self.referral = Some((referral_fee_share_bps, referrer_account));
+        self
+    }
+
+    /// Fails the swap if any other instruction in the transaction references the pool
+    pub fn sandwich_protection(mut self, sandwich_protection: bool) -> Self {
+        self.sandwich_protection = sandwich_protection;
+        self
+    }
+
+    /// Passes the pool's oracle price account, required when the pool has a price oracle
+    pub fn price_oracle(mut self, oracle_account: Pubkey) -> Self {
+        self.price_oracle = Some(oracle_account);
+        self
+    }
+
+    /// Appends an optional companion account (stats, observations, volume stats or mint)
+    pub fn companion_account(mut self, account: Pubkey) -> Self {
+        self.companion_accounts.push(account);
+        self
+    }
+
+    /// Sets the compute unit limit, or `None` to leave the runtime default
+    pub fn compute_unit_limit(mut self, units: Option<u32>) -> Self {
+        self.compute_unit_limit = units;
+        self
+    }
+
+    /// Sets the priority fee in micro-lamports per compute unit (0 for none)
+    pub fn compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = micro_lamports;
+        self
+    }
+
+    /// Whether to create the user's token ATA if it does not exist yet
+    pub fn create_token_account(mut self, create: bool) -> Self {
+        self.create_token_account = create;
+        self
+    }
+
+    /// Funds a SOL-input swap by first closing the user's WSOL ATA into their wallet
+    pub fn unwrap_wsol_input(mut self, unwrap: bool) -> Self {
+        self.unwrap_wsol_input = unwrap;
+        self
+    }
+
+    /// Delivers a SOL-output swap into the user's WSOL ATA, created if missing
+    pub fn wrap_sol_output(mut self, wrap: bool) -> Self {
+        self.wrap_sol_output = wrap;
+        self
+    }
+
+    /// Targets a token pair pool, moving side A between the user's mint A ATA and `pool_vault_a`
+    /// 
+    /// SOL amounts and WSOL options then refer to mint A; WSOL options are ignored.
+    pub fn token_pair(mut self, mint_a: Pubkey, pool_vault_a: Pubkey) -> Self {
+        self.token_pair = Some((mint_a, pool_vault_a));
+        self
+    }
+
+    /// Targets a wSOL-mode pool, moving SOL through the user's WSOL ATA (created if missing)
+    /// 
+    /// The WSOL options are then ignored: the swap itself spends and delivers WSOL.
+    pub fn wsol_pool(mut self, wsol: bool) -> Self {
+        self.wsol_pool = wsol;
+        self
+    }
+
+    /// Closes the user's token ATA after the swap (it must then hold no tokens)
+    pub fn close_token_account(mut self, close: bool) -> Self {
+        self.close_token_account = close;
+        self
+    }
+
+    /// Assembles the instructions in execution order
+    pub fn build(&self) -> Result<Vec<Instruction>, ProgramError> {
+        let mut instructions = Vec::new();
+        let user_token_account = associated_token_address(&self.user, &self.token_mint, &self.token_program_id);
+        let wsol_account = associated_token_address(&self.user, &spl_token::native_mint::id(), &spl_token::id());
+
+        // Compute budget instructions must come first
+        if let Some(units) = self.compute_unit_limit {
+            let mut data = vec![SET_COMPUTE_UNIT_LIMIT_TAG];
+            data.extend_from_slice(&units.to_le_bytes());
+            instructions.push(Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, vec![]));
+        }
+        if self.compute_unit_price > 0 {
+            let mut data = vec![SET_COMPUTE_UNIT_PRICE_TAG];
+            data.extend_from_slice(&self.compute_unit_price.to_le_bytes());
+            instructions.push(Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, vec![]));
+        }
+
+        // Account setup
+        if self.create_token_account {
+            instructions.push(create_associated_token_account_idempotent(
+                &self.user,
+                &self.user,
+                &self.token_mint,
+                &self.token_program_id,
+            ));
+            if let Some((mint_a, _)) = self.token_pair {
+                instructions.push(create_associated_token_account_idempotent(
+                    &self.user,
+                    &self.user,
+                    &mint_a,
+                    &self.token_program_id,
+                ));
+            }
+        }
+        let native_sol = self.token_pair.is_none() && !self.wsol_pool;
+        let wraps_output = native_sol && self.wrap_sol_output && !self.is_sol_input;
+        if wraps_output || (self.wsol_pool && self.create_token_account) {
+            instructions.push(create_associated_token_account_idempotent(
+                &self.user,
+                &self.user,
+                &spl_token::native_mint::id(),
+                &spl_token::id(),
+            ));
+        }
+        if native_sol && self.unwrap_wsol_input && self.is_sol_input {
+            instructions.push(token_interface::close_account(
+                &spl_token::id(),
+                &wsol_account,
+                &self.user,
+                &self.user,
+                &[],
+            )?);
+        }
+
+        // The swap itself
+        let (platform_fee_bps, platform_fee_account) = match self.platform_fee {
+            Some((bps, account)) => (bps, Some(account)),
+            None => (Bps::ZERO, None),
+        };
+        let (referral_fee_share_bps, referrer_account) = match self.referral {
+            Some((bps, account)) => (bps, Some(account)),
+            None => (Bps::ZERO, None),
+        };
+        let user_sol_account = match self.token_pair {
+            Some((mint_a, _)) => associated_token_address(&self.user, &mint_a, &self.token_program_id),
+            None if wraps_output || self.wsol_pool => wsol_account,
+            None => self.user,
+        };
+        let mut accounts = vec![
+            AccountMeta::new(self.user, true),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(user_sol_account, false),
+            AccountMeta::new(user_token_account, false),
+            AccountMeta::new(self.pool_token_account, false),
+            AccountMeta::new_readonly(self.token_program_id, false),
+        ];
+        let side_a_vault = match self.token_pair {
+            Some((_, pool_vault_a)) => pool_vault_a,
+            None => PoolState::find_sol_vault_address(&self.pool, &self.program_id).0,
+        };
+        accounts.push(AccountMeta::new(side_a_vault, false));
+        if let Some(fee_account) = platform_fee_account {
+            accounts.push(AccountMeta::new(fee_account, false));
+        }
+        if platform_fee_account.is_some() || referrer_account.is_some() {
+            accounts.push(AccountMeta::new_readonly(ProtocolConfig::find_address(&self.program_id).0, false));
+        }
+        if let Some(referrer_account) = referrer_account {
+            accounts.push(AccountMeta::new(referrer_account, false));
+        }
+        if self.sandwich_protection {
+            accounts.push(AccountMeta::new_readonly(sysvar::instructions::id(), false));
+        }
+        if let Some(oracle_account) = self.price_oracle {
+            accounts.push(AccountMeta::new_readonly(oracle_account, false));
+        }
+        accounts.extend(self.companion_accounts.iter().map(|account| AccountMeta::new(*account, false)));
+        let data = TokenExchangeInstruction::Swap {
+            amount_in: self.amount_in,
+            minimum_amount_out: self.minimum_amount_out,
+            is_sol_input: self.is_sol_input,
+            platform_fee_bps,
+            referral_fee_share_bps,
+            sandwich_protection: self.sandwich_protection,
+        }
+        .try_to_vec()?;
+        instructions.push(Instruction::new_with_bytes(self.program_id, &data, accounts));
+
+        // Cleanup
+        if wraps_output {
+            instructions.push(spl_token::instruction::sync_native(&spl_token::id(), &wsol_account)?);
+        }
+        if self.close_token_account {
+            instructions.push(token_interface::close_account(
+                &self.token_program_id,
+                &user_token_account,
+                &self.user,
+                &self.user,
+                &[],
+            )?);
+        }
+
+        Ok(instructions)
+    }
+}
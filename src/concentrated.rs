@@ -0,0 +1,517 @@
+// Concentrated Liquidity
+// This module defines tick-based pools whose liquidity providers each choose the price range they provide in
+
+use borsh::{BorshDeserialize, BorshSerialize};                 // For account serialization
+use solana_program::{
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+};
+
+use crate::{
+    error::TokenExchangeError,                                 // Custom errors
+    math::{self, Rounding, Q64},                               // Rounded and 256-bit division
+};
+
+/// Seed used to derive a concentrated liquidity pool PDA
+pub const CONCENTRATED_POOL_SEED: &[u8] = b"cl_pool";
+
+/// Seed used to derive a tick PDA of a concentrated liquidity pool
+pub const TICK_SEED: &[u8] = b"cl_tick";
+
+/// Seed used to derive a position PDA in a concentrated liquidity pool
+pub const CONCENTRATED_POSITION_SEED: &[u8] = b"cl_position";
+
+/// Lowest tick a price may reach, where the Q64.64 square root price is about 2^-32
+/// 
+/// Only ticks strictly inside `MIN_TICK..MAX_TICK` can be initialized; the
+/// bounds stand in for "no initialized tick" in the tick links.
+pub const MIN_TICK: i32 = -443_636;
+
+/// Highest tick a price may reach, where the Q64.64 square root price is about 2^32
+pub const MAX_TICK: i32 = 443_636;
+
+/// Largest spacing between the ticks of a pool's positions
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+/// `2^64 / sqrt(1.0001)^(2^i)`, rounded down, for each bit `i` of a tick
+const TICK_RATIOS: [u128; 19] = [
+    0xfffc_b933_bd6f_ad37,
+    0xfff9_7272_373d_4132,
+    0xfff2_e50f_5f65_6932,
+    0xffe5_caca_7e10_e4e6,
+    0xffcb_9843_d60f_6159,
+    0xff97_3b41_fa98_c081,
+    0xff2e_a164_66c9_6a38,
+    0xfe5d_ee04_6a99_a2a8,
+    0xfcbe_86c7_900a_88ae,
+    0xf987_a725_3ac4_1317,
+    0xf339_2b08_22b7_0005,
+    0xe715_9475_a2c2_9b74,
+    0xd097_f3bd_fd20_22b8,
+    0xa9f7_4646_2d87_0fdf,
+    0x70d8_69a1_56d2_a1b8,
+    0x31be_135f_97d0_8fd9,
+    0x09aa_508b_5b7a_84e1,
+    0x005d_6af8_dedb_8119,
+    0x0000_2216_e584_f5fa,
+];
+
+/// Q64.64 square root of the price `1.0001^tick`
+/// 
+/// Multiplies the ratios of the set bits of `|tick|`, then inverts for
+/// positive ticks. Strictly increasing in `tick`.
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128, ProgramError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(TokenExchangeError::InvalidTick.into());
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = Q64;
+    for (bit, factor) in TICK_RATIOS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * factor) >> 64;
+        }
+    }
+    Ok(if tick > 0 { u128::MAX / ratio } else { ratio })
+}
+
+/// Highest tick in `lower..=upper` whose square root price is at most `sqrt_price`
+/// 
+/// `sqrt_price` must be at least the square root price of `lower`.
+pub fn tick_at_sqrt_price(sqrt_price: u128, lower: i32, upper: i32) -> Result<i32, ProgramError> {
+    let (mut low, mut high) = (lower, upper);
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(middle)? <= sqrt_price {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Token A that `liquidity` holds between two square root prices
+/// 
+/// `L * (sqrt_upper - sqrt_lower) / (sqrt_lower * sqrt_upper)`, with each
+/// division rounded as asked.
+pub fn amount_a_delta(sqrt_lower: u128, sqrt_upper: u128, liquidity: u128, rounding: Rounding) -> Option<u128> {
+    let scaled = math::mul_div_wide(liquidity, sqrt_upper.checked_sub(sqrt_lower)?, sqrt_upper, rounding)?;
+    math::mul_div_wide(scaled, Q64, sqrt_lower, rounding)
+}
+
+/// Token B that `liquidity` holds between two square root prices
+/// 
+/// `L * (sqrt_upper - sqrt_lower)`, rounded as asked.
+pub fn amount_b_delta(sqrt_lower: u128, sqrt_upper: u128, liquidity: u128, rounding: Rounding) -> Option<u128> {
+    math::mul_div_wide(liquidity, sqrt_upper.checked_sub(sqrt_lower)?, Q64, rounding)
+}
+
+/// Square root price after `amount` of token A is sold into `liquidity`
+/// 
+/// `1 / sqrt(P)` grows by `amount / L`. Rounded up, so the price falls no
+/// further than the input pays for.
+fn sqrt_price_after_a_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    let denominator = liquidity.checked_add(math::mul_div_wide(amount as u128, sqrt_price, Q64, Rounding::Down)?)?;
+    math::mul_div_wide(liquidity, sqrt_price, denominator, Rounding::Up)
+}
+
+/// Square root price after `amount` of token B is sold into `liquidity`
+/// 
+/// `sqrt(P)` grows by `amount / L`. Rounded down, so the price rises no
+/// further than the input pays for.
+fn sqrt_price_after_b_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    sqrt_price.checked_add(math::mul_div_wide(amount as u128, Q64, liquidity, Rounding::Down)?)
+}
+
+/// One step of a swap, within a price range of constant liquidity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,    // Square root price the step ends at
+    pub amount_in: u64,           // Input the price move takes, excluding the fee
+    pub amount_out: u64,          // Output paid for the move
+    pub fee: u64,                 // Trading fee charged on the input
+}
+
+impl SwapStep {
+    /// Whether the step ended at its target price rather than running out of input
+    pub fn reached(&self, sqrt_price_target: u128) -> bool {
+        self.sqrt_price_next == sqrt_price_target
+    }
+}
+
+/// Swaps up to `amount_remaining` of input toward `sqrt_price_target`
+/// 
+/// Selling token A (`a_to_b`) lowers the price, selling token B raises it.
+/// When the input, less the fee, carries the price all the way to the
+/// target, the step stops there and charges the fee on what it used;
+/// otherwise it consumes the whole remaining input. Inputs round up and
+/// outputs down, in the pool's favor.
+/// 
+/// # Arguments
+/// * `sqrt_price` - Current Q64.64 square root price
+/// * `sqrt_price_target` - Price the step may move to, at or past the current one
+/// * `liquidity` - Liquidity in range between the two prices
+/// * `amount_remaining` - Input left to swap, including the fee
+/// * `fee_rate` - Trading fee in basis points
+/// * `a_to_b` - Whether token A is sold
+pub fn swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_rate: u64,
+    a_to_b: bool,
+) -> Result<SwapStep, ProgramError> {
+    if fee_rate >= 10000 {
+        return Err(TokenExchangeError::InvalidFeeRate.into());
+    }
+    let amount_in_between = |lower: u128, upper: u128| {
+        if a_to_b {
+            amount_a_delta(lower, upper, liquidity, Rounding::Up)
+        } else {
+            amount_b_delta(lower, upper, liquidity, Rounding::Up)
+        }
+    };
+    let (lower, upper) = if a_to_b { (sqrt_price_target, sqrt_price) } else { (sqrt_price, sqrt_price_target) };
+    let amount_less_fee = amount_remaining as u128 * (10000 - fee_rate) as u128 / 10000;
+
+    // A target out of reach of any u64 input is one this input cannot reach
+    let max_in = amount_in_between(lower, upper).filter(|max_in| *max_in <= amount_less_fee);
+    let (sqrt_price_next, amount_in, fee) = match max_in {
+        Some(max_in) => {
+            let fee = math::mul_div(max_in, fee_rate as u128, (10000 - fee_rate) as u128, Rounding::Up)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            (sqrt_price_target, max_in as u64, fee as u64)
+        }
+        None => {
+            let sqrt_price_next = if a_to_b {
+                sqrt_price_after_a_in(sqrt_price, liquidity, amount_less_fee as u64)
+            } else {
+                sqrt_price_after_b_in(sqrt_price, liquidity, amount_less_fee as u64)
+            }
+            .ok_or(TokenExchangeError::MathOverflow)?;
+            let (lower, upper) = if a_to_b { (sqrt_price_next, sqrt_price) } else { (sqrt_price, sqrt_price_next) };
+            let amount_in = amount_in_between(lower, upper)
+                .ok_or(TokenExchangeError::MathOverflow)?
+                .min(amount_remaining as u128) as u64;
+            (sqrt_price_next, amount_in, amount_remaining - amount_in)
+        }
+    };
+
+    let (lower, upper) = if a_to_b { (sqrt_price_next, sqrt_price) } else { (sqrt_price, sqrt_price_next) };
+    let amount_out = if a_to_b {
+        amount_b_delta(lower, upper, liquidity, Rounding::Down)
+    } else {
+        amount_a_delta(lower, upper, liquidity, Rounding::Down)
+    }
+    .and_then(|amount_out| u64::try_from(amount_out).ok())
+    .ok_or(TokenExchangeError::MathOverflow)?;
+
+    Ok(SwapStep { sqrt_price_next, amount_in, amount_out, fee })
+}
+
+/// A concentrated liquidity pool between two SPL mints
+/// 
+/// Liquidity providers deposit into a tick range of their choice, and only
+/// positions whose range contains the current price earn fees and back
+/// swaps. The price is of token A in token B, kept as its Q64.64 square
+/// root. Initialized ticks form a list linked through their `TickState`
+/// accounts, and the pool tracks the nearest one on each side of the
+/// current price, so swaps visit every tick they cross. Both vaults are
+/// token accounts owned by the pool PDA.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ConcentratedPool {
+    pub authority: Pubkey,        // The pool's creator
+    pub mint_a: Pubkey,           // Mint of token A, priced in token B
+    pub mint_b: Pubkey,           // Mint of token B
+    pub vault_a: Pubkey,          // Token account holding the pool's token A
+    pub vault_b: Pubkey,          // Token account holding the pool's token B
+    pub fee_rate: u64,            // Trading fee in basis points
+    pub tick_spacing: u16,        // Position ticks are multiples of this
+    pub sqrt_price: u128,         // Square root of the price of A in B, Q64.64
+    pub current_tick: i32,        // Tick of the current price, rounded down
+    pub liquidity: u128,          // Liquidity of the positions in range at the current price
+    pub fee_growth_global_a: u128, // Fees per unit of liquidity earned in token A, Q64.64, wrapping
+    pub fee_growth_global_b: u128, // Fees per unit of liquidity earned in token B, Q64.64, wrapping
+    pub tick_below: i32,          // Highest initialized tick at or below current_tick, MIN_TICK if none
+    pub tick_above: i32,          // Lowest initialized tick above current_tick, MAX_TICK if none
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// An initialized tick of a concentrated liquidity pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct TickState {
+    pub pool: Pubkey,             // The pool the tick belongs to
+    pub index: i32,               // The tick
+    pub liquidity_net: i128,      // Liquidity added when the price crosses the tick upward
+    pub liquidity_gross: u128,    // Liquidity of every position bounded by the tick
+    pub fee_growth_outside_a: u128, // Token A fee growth on the far side of the tick from the price, wrapping
+    pub fee_growth_outside_b: u128, // Token B fee growth on the far side of the tick from the price, wrapping
+    pub prev_tick: i32,           // Next initialized tick below, MIN_TICK if none
+    pub next_tick: i32,           // Next initialized tick above, MAX_TICK if none
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// A liquidity position in a concentrated liquidity pool
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ConcentratedPosition {
+    pub pool: Pubkey,             // The pool provided to
+    pub owner: Pubkey,            // The liquidity provider
+    pub tick_lower: i32,          // Lower tick of the range
+    pub tick_upper: i32,          // Upper tick of the range
+    pub liquidity: u128,          // Liquidity provided across the range
+    pub fee_growth_inside_a: u128, // Token A fee growth inside the range when last settled
+    pub fee_growth_inside_b: u128, // Token B fee growth inside the range when last settled
+    pub fees_owed_a: u64,         // Settled token A fees not yet paid out
+    pub fees_owed_b: u64,         // Settled token B fees not yet paid out
+    pub bump: u8,                 // PDA bump seed
+}
+
+impl ConcentratedPool {
+    /// Total size of the ConcentratedPool structure when serialized
+    /// 
+    /// Breakdown:
+    /// - authority, mint_a, mint_b, vault_a, vault_b (Pubkey): 160 bytes
+    /// - fee_rate (u64): 8 bytes
+    /// - tick_spacing (u16): 2 bytes
+    /// - sqrt_price (u128): 16 bytes
+    /// - current_tick (i32): 4 bytes
+    /// - liquidity, fee_growth_global_a, fee_growth_global_b (u128): 48 bytes
+    /// - tick_below, tick_above (i32): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 * 5 + 8 + 2 + 16 + 4 + 16 * 3 + 4 * 2 + 1;
+
+    /// Derives the PDA of the pool for a pair of mints and a tick spacing
+    /// 
+    /// `mint_a` must sort below `mint_b`, so each pair has one pool per spacing.
+    pub fn find_address(mint_a: &Pubkey, mint_b: &Pubkey, tick_spacing: u16, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[CONCENTRATED_POOL_SEED, mint_a.as_ref(), mint_b.as_ref(), &tick_spacing.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Recomputes the pool's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[
+                CONCENTRATED_POOL_SEED,
+                self.mint_a.as_ref(),
+                self.mint_b.as_ref(),
+                &self.tick_spacing.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Rejects ticks a position of this pool cannot use
+    pub fn check_tick(&self, tick: i32) -> Result<(), ProgramError> {
+        if tick <= MIN_TICK || tick >= MAX_TICK || tick % self.tick_spacing as i32 != 0 {
+            return Err(TokenExchangeError::InvalidTick.into());
+        }
+        Ok(())
+    }
+
+    /// Fee growth per unit of liquidity inside a tick range, in tokens A and B
+    /// 
+    /// The global growth less what each bounding tick records on its far
+    /// side from the range. Wraps; only differences are meaningful.
+    pub fn fee_growth_inside(&self, lower: &TickState, upper: &TickState) -> (u128, u128) {
+        let below = |global: u128, outside: u128| {
+            if self.current_tick >= lower.index { outside } else { global.wrapping_sub(outside) }
+        };
+        let above = |global: u128, outside: u128| {
+            if self.current_tick < upper.index { outside } else { global.wrapping_sub(outside) }
+        };
+        let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+            global.wrapping_sub(below(global, lower_outside)).wrapping_sub(above(global, upper_outside))
+        };
+        (
+            inside(self.fee_growth_global_a, lower.fee_growth_outside_a, upper.fee_growth_outside_a),
+            inside(self.fee_growth_global_b, lower.fee_growth_outside_b, upper.fee_growth_outside_b),
+        )
+    }
+
+    /// Tokens A and B that `liquidity` holds across a tick range at the current price
+    /// 
+    /// Below the range it is all token A, above it all token B, and inside
+    /// it token A for the part of the range above the price and token B for
+    /// the part below.
+    pub fn amounts_for_liquidity(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        rounding: Rounding,
+    ) -> Result<(u64, u64), ProgramError> {
+        let (sqrt_lower, sqrt_upper) = (sqrt_price_at_tick(tick_lower)?, sqrt_price_at_tick(tick_upper)?);
+        let (amount_a, amount_b) = if self.current_tick < tick_lower {
+            (amount_a_delta(sqrt_lower, sqrt_upper, liquidity, rounding), Some(0))
+        } else if self.current_tick >= tick_upper {
+            (Some(0), amount_b_delta(sqrt_lower, sqrt_upper, liquidity, rounding))
+        } else {
+            (
+                amount_a_delta(self.sqrt_price, sqrt_upper, liquidity, rounding),
+                amount_b_delta(sqrt_lower, self.sqrt_price, liquidity, rounding),
+            )
+        };
+        let to_u64 = |amount: Option<u128>| amount.and_then(|amount| u64::try_from(amount).ok());
+        Ok((
+            to_u64(amount_a).ok_or(TokenExchangeError::MathOverflow)?,
+            to_u64(amount_b).ok_or(TokenExchangeError::MathOverflow)?,
+        ))
+    }
+
+    /// Whether a tick range contains the current price, so its liquidity is active
+    pub fn in_range(&self, tick_lower: i32, tick_upper: i32) -> bool {
+        tick_lower <= self.current_tick && self.current_tick < tick_upper
+    }
+
+    /// Adds fees charged on a swap step's input to the global fee growth
+    /// 
+    /// Fees earned while no liquidity is in range stay in the vault.
+    pub fn accrue_fee(&mut self, fee: u64, a_to_b: bool) {
+        if self.liquidity == 0 {
+            return;
+        }
+        let growth = ((fee as u128) << 64) / self.liquidity;
+        if a_to_b {
+            self.fee_growth_global_a = self.fee_growth_global_a.wrapping_add(growth);
+        } else {
+            self.fee_growth_global_b = self.fee_growth_global_b.wrapping_add(growth);
+        }
+    }
+
+    /// Moves the price across an initialized tick, in the swap's direction
+    /// 
+    /// Crossing upward adds the tick's net liquidity and leaves the price at
+    /// the tick; crossing downward removes it and leaves the price just
+    /// below. The tick's outside fee growth flips to the other side.
+    pub fn cross(&mut self, tick: &mut TickState, a_to_b: bool) -> Result<(), ProgramError> {
+        tick.fee_growth_outside_a = self.fee_growth_global_a.wrapping_sub(tick.fee_growth_outside_a);
+        tick.fee_growth_outside_b = self.fee_growth_global_b.wrapping_sub(tick.fee_growth_outside_b);
+        let liquidity_net = if a_to_b { tick.liquidity_net.checked_neg() } else { Some(tick.liquidity_net) }
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.liquidity = self.liquidity.checked_add_signed(liquidity_net)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.sqrt_price = sqrt_price_at_tick(tick.index)?;
+        if a_to_b {
+            self.current_tick = tick.index - 1;
+            self.tick_above = tick.index;
+            self.tick_below = tick.prev_tick;
+        } else {
+            self.current_tick = tick.index;
+            self.tick_below = tick.index;
+            self.tick_above = tick.next_tick;
+        }
+        Ok(())
+    }
+}
+
+impl TickState {
+    /// Total size of the TickState structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - index (i32): 4 bytes
+    /// - liquidity_net (i128): 16 bytes
+    /// - liquidity_gross, fee_growth_outside_a, fee_growth_outside_b (u128): 48 bytes
+    /// - prev_tick, next_tick (i32): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 4 + 16 + 16 * 3 + 4 * 2 + 1;
+
+    /// Derives the PDA of a tick of a pool
+    pub fn find_address(pool: &Pubkey, index: i32, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[TICK_SEED, pool.as_ref(), &index.to_le_bytes()], program_id)
+    }
+
+    /// Recomputes the tick's address from its pool, index and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[TICK_SEED, self.pool.as_ref(), &self.index.to_le_bytes(), &[self.bump]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Adds a position's liquidity change at this tick, as its lower or upper bound
+    pub fn update_liquidity(&mut self, liquidity_delta: i128, is_upper: bool) -> Result<(), ProgramError> {
+        self.liquidity_gross = self.liquidity_gross.checked_add_signed(liquidity_delta)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let net_delta = if is_upper { liquidity_delta.checked_neg() } else { Some(liquidity_delta) }
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.liquidity_net = self.liquidity_net.checked_add(net_delta)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+impl ConcentratedPosition {
+    /// Total size of the ConcentratedPosition structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool, owner (Pubkey): 64 bytes
+    /// - tick_lower, tick_upper (i32): 8 bytes
+    /// - liquidity, fee_growth_inside_a, fee_growth_inside_b (u128): 48 bytes
+    /// - fees_owed_a, fees_owed_b (u64): 16 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 64 + 4 * 2 + 16 * 3 + 8 * 2 + 1;
+
+    /// Derives the PDA of an owner's position over a tick range of a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        tick_lower: i32,
+        tick_upper: i32,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                CONCENTRATED_POSITION_SEED,
+                pool.as_ref(),
+                owner.as_ref(),
+                &tick_lower.to_le_bytes(),
+                &tick_upper.to_le_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Recomputes the position's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[
+                CONCENTRATED_POSITION_SEED,
+                self.pool.as_ref(),
+                self.owner.as_ref(),
+                &self.tick_lower.to_le_bytes(),
+                &self.tick_upper.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Moves the fees earned since the last settlement into `fees_owed_a` and `fees_owed_b`
+    /// 
+    /// Must run before `liquidity` changes, with the range's current fee growth.
+    pub fn settle(&mut self, fee_growth_inside_a: u128, fee_growth_inside_b: u128) -> Result<(), ProgramError> {
+        let earned = |growth: u128, checkpoint: u128| {
+            math::mul_div_wide(self.liquidity, growth.wrapping_sub(checkpoint), Q64, Rounding::Down)
+                .and_then(|fees| u64::try_from(fees).ok())
+                .ok_or(TokenExchangeError::MathOverflow)
+        };
+        let earned_a = earned(fee_growth_inside_a, self.fee_growth_inside_a)?;
+        let earned_b = earned(fee_growth_inside_b, self.fee_growth_inside_b)?;
+        self.fees_owed_a = self.fees_owed_a.checked_add(earned_a)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.fees_owed_b = self.fees_owed_b.checked_add(earned_b)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.fee_growth_inside_a = fee_growth_inside_a;
+        self.fee_growth_inside_b = fee_growth_inside_b;
+        Ok(())
+    }
+}
@@ -0,0 +1,645 @@
+// Swap Curves
+// This module prices swaps, deposits and withdrawals for each pool curve
+
+use solana_program::program_error::ProgramError;              // Error handling
+
+use crate::{
+    amount::{Bps, Lamports, LpAmount, SwapAmount, TokenAmount}, // Typed amounts
+    error::TokenExchangeError,                                // Custom errors
+    math::{self, Rounding, Q64},                              // Integer roots, powers and rounded division
+    state::{CurveType, PoolState},                            // Pool curves
+};
+
+/// Largest amplification coefficient a stable pool may use
+pub const MAX_AMPLIFICATION: u64 = 10_000;
+
+/// Smallest weight either side of a weighted pool may have, in basis points
+/// 
+/// Bounds the exponent ratio of weighted swaps at 99, keeping their powers
+/// well inside Q64.64.
+pub const MIN_WEIGHT_BPS: u64 = 100;
+
+/// Newton iterations allowed for the StableSwap invariant to converge
+const STABLE_MAX_ITERATIONS: usize = 64;
+
+/// Pricing rules of a pool curve
+/// 
+/// Swap amounts carry their side, telling curves that treat the two sides
+/// differently which reserve they trade against.
+/// Deposits and withdrawals default to pro-rata shares of the reserves.
+/// 
+/// Products of amounts are taken in u128, so no realistic reserves overflow.
+/// Every result rounds in the pool's favor: outputs, mints and withdrawals
+/// down, required inputs up.
+pub trait SwapCurve {
+    /// Output of a swap of `amount_in`, after the pool fee, on the other side
+    /// 
+    /// # Arguments
+    /// * `amount_in` - Input amount, SOL (mint A for token pair pools) or token
+    /// * `sol_reserve` - SOL reserve
+    /// * `token_reserve` - Token reserve
+    /// * `fee_rate` - Fee rate in basis points
+    fn swap_out(
+        &self,
+        amount_in: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError>;
+
+    /// Smallest input, on the other side, whose `swap_out` is at least `amount_out`
+    /// 
+    /// # Arguments
+    /// * `amount_out` - Desired output amount, SOL (mint A for token pair pools) or token
+    /// * `sol_reserve` - SOL reserve
+    /// * `token_reserve` - Token reserve
+    /// * `fee_rate` - Fee rate in basis points
+    fn swap_in(
+        &self,
+        amount_out: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError>;
+
+    /// Price of the token in SOL (mint A for token pair pools) at the margin,
+    /// before the fee, as a Q64.64 number rounded down
+    /// 
+    /// The constant product price is the reserve ratio. Returns `None` when
+    /// the pool holds no tokens or the price overflows.
+    /// 
+    /// # Arguments
+    /// * `sol_reserve` - SOL reserve
+    /// * `token_reserve` - Token reserve
+    fn spot_price(&self, sol_reserve: Lamports, token_reserve: TokenAmount) -> Option<u128> {
+        math::mul_div_wide(sol_reserve.get() as u128, Q64, token_reserve.get() as u128, Rounding::Down)
+    }
+
+    /// LP tokens minted for a deposit
+    /// 
+    /// The first deposit mints the geometric mean of both amounts; later
+    /// deposits mint in proportion to the smaller of the two deposit ratios,
+    /// rounded down.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool state before the deposit
+    /// * `sol_amount` - SOL deposited
+    /// * `token_amount` - Tokens deposited
+    /// * `total_supply` - Current LP token supply
+    fn deposit_lp(
+        &self,
+        pool_state: &PoolState,
+        sol_amount: Lamports,
+        token_amount: TokenAmount,
+        total_supply: LpAmount,
+    ) -> Result<LpAmount, ProgramError> {
+        if pool_state.sol_reserve == 0 {
+            // Initial liquidity: Use geometric mean
+            return Ok(LpAmount(math::geometric_mean(sol_amount.get(), token_amount.get())));
+        }
+
+        // Subsequent liquidity: Proportional to existing reserves
+        pro_rata_lp(pool_state, sol_amount, token_amount, total_supply)
+    }
+
+    /// SOL and tokens released by burning LP tokens, each rounded down
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool state before the withdrawal
+    /// * `lp_tokens` - LP tokens being burned
+    /// * `total_supply` - Current LP token supply
+    fn withdraw_lp(
+        &self,
+        pool_state: &PoolState,
+        lp_tokens: LpAmount,
+        total_supply: LpAmount,
+    ) -> Result<(Lamports, TokenAmount), ProgramError> {
+        let sol_amount = mul_div(pool_state.sol_reserve, lp_tokens.get(), total_supply.get(), Rounding::Down)?;
+        let token_amount = mul_div(pool_state.token_reserve, lp_tokens.get(), total_supply.get(), Rounding::Down)?;
+        Ok((Lamports(sol_amount), TokenAmount(token_amount)))
+    }
+}
+
+/// LP tokens minted for a deposit into a funded pool, in proportion to the
+/// smaller of the two deposit ratios, rounded down
+fn pro_rata_lp(
+    pool_state: &PoolState,
+    sol_amount: Lamports,
+    token_amount: TokenAmount,
+    total_supply: LpAmount,
+) -> Result<LpAmount, ProgramError> {
+    let sol_share = mul_div(sol_amount.get(), total_supply.get(), pool_state.sol_reserve, Rounding::Down)?;
+    let token_share = mul_div(token_amount.get(), total_supply.get(), pool_state.token_reserve, Rounding::Down)?;
+    Ok(LpAmount(sol_share.min(token_share)))
+}
+
+/// Largest deposit within the desired amounts that matches the reserve ratio
+/// 
+/// One side is kept whole and the other trimmed to the ratio, rounded down
+/// so a deposit never tips the price, as Uniswap's `addLiquidity` does.
+/// Empty reserves take both amounts as given.
+/// 
+/// # Arguments
+/// * `sol_reserve` - Current SOL reserve
+/// * `token_reserve` - Current token reserve
+/// * `sol_desired` - Most SOL to deposit
+/// * `token_desired` - Most tokens to deposit
+pub fn fit_to_ratio(
+    sol_reserve: Lamports,
+    token_reserve: TokenAmount,
+    sol_desired: Lamports,
+    token_desired: TokenAmount,
+) -> Result<(Lamports, TokenAmount), ProgramError> {
+    if sol_reserve == Lamports::ZERO || token_reserve == TokenAmount::ZERO {
+        return Ok((sol_desired, token_desired));
+    }
+
+    // Tokens matching all the desired SOL, or else SOL matching all the desired tokens
+    let token_optimal = math::mul_div(
+        sol_desired.get() as u128,
+        token_reserve.get() as u128,
+        sol_reserve.get() as u128,
+        Rounding::Down,
+    )
+    .ok_or(TokenExchangeError::MathOverflow)?;
+    if token_optimal <= token_desired.get() as u128 {
+        return Ok((sol_desired, TokenAmount(token_optimal as u64)));
+    }
+    let sol_optimal = mul_div(token_desired.get(), sol_reserve.get(), token_reserve.get(), Rounding::Down)?;
+    Ok((Lamports(sol_optimal), token_desired))
+}
+
+/// `a * b / c` over u64 amounts, rounded as asked through a u128 product
+/// 
+/// Fails with `MathOverflow` when `c` is zero or the quotient exceeds a u64.
+fn mul_div(a: u64, b: u64, c: u64, rounding: Rounding) -> Result<u64, ProgramError> {
+    math::mul_div(a as u128, b as u128, c as u128, rounding)
+        .and_then(|quotient| u64::try_from(quotient).ok())
+        .ok_or(TokenExchangeError::MathOverflow.into())
+}
+
+/// Narrows a u128 result back to a u64 amount
+fn to_u64(value: u128) -> Result<u64, ProgramError> {
+    u64::try_from(value).map_err(|_| TokenExchangeError::MathOverflow.into())
+}
+
+/// Input left after the fee, `amount_in * (10000 - fee) / 10000` rounded down
+fn amount_after_fee(amount_in: u128, fee_rate: Bps) -> Result<u128, ProgramError> {
+    let remaining = fee_rate.complement().ok_or(TokenExchangeError::MathOverflow)?;
+    math::mul_div(amount_in, remaining as u128, 10000, Rounding::Down).ok_or(TokenExchangeError::MathOverflow.into())
+}
+
+/// Smallest input whose `amount_after_fee` is at least `amount_in_with_fee`,
+/// `ceil(dx' * 10000 / (10000 - fee))`
+fn amount_before_fee(amount_in_with_fee: u128, fee_rate: Bps) -> Result<u128, ProgramError> {
+    let remaining = fee_rate
+        .complement()
+        .filter(|remaining| *remaining > 0)
+        .ok_or(TokenExchangeError::InvalidFeeRate)?;
+    math::mul_div(amount_in_with_fee, 10000, remaining as u128, Rounding::Up).ok_or(TokenExchangeError::MathOverflow.into())
+}
+
+/// Constant product output over u128 reserves, `y * dx' / (x + dx')` rounded down
+fn constant_product_out(amount_in: u128, reserve_in: u128, reserve_out: u128, fee_rate: Bps) -> Result<u128, ProgramError> {
+    let amount_in_with_fee = amount_after_fee(amount_in, fee_rate)?;
+    let denominator = reserve_in
+        .checked_add(amount_in_with_fee)
+        .ok_or(TokenExchangeError::MathOverflow)?;
+    math::mul_div(reserve_out, amount_in_with_fee, denominator, Rounding::Down)
+        .ok_or(TokenExchangeError::MathOverflow.into())
+}
+
+/// Smallest constant product input over u128 reserves whose output is at least `amount_out`
+fn constant_product_in(amount_out: u128, reserve_in: u128, reserve_out: u128, fee_rate: Bps) -> Result<u128, ProgramError> {
+    // The pool can never pay out its whole reserve
+    if amount_out == 0 || amount_out >= reserve_out {
+        return Err(TokenExchangeError::InsufficientLiquidity.into());
+    }
+
+    // Input after fees: ceil(x * dy / (y - dy))
+    let amount_in_with_fee = math::mul_div(reserve_in, amount_out, reserve_out - amount_out, Rounding::Up)
+        .ok_or(TokenExchangeError::MathOverflow)?;
+    amount_before_fee(amount_in_with_fee, fee_rate)
+}
+
+/// Smallest input that `covers` accepts, bracketed by an estimate
+/// 
+/// For curves whose inverse is only solved approximately: the estimate is
+/// doubled until it is accepted, then bisected down to the smallest accepted
+/// input, so `swap_in` always agrees with the curve's own `swap_out`.
+fn smallest_covering_input(
+    estimate: u64,
+    covers: impl Fn(u64) -> Result<bool, ProgramError>,
+) -> Result<u64, ProgramError> {
+    let mut high = estimate;
+    while !covers(high)? {
+        high = high.checked_add(high.max(1)).ok_or(TokenExchangeError::MathOverflow)?;
+    }
+    let mut low = 0;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if covers(mid)? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(high)
+}
+
+/// The curve a pool prices on, carrying its parameters
+pub fn for_pool(pool_state: &PoolState) -> Box<dyn SwapCurve> {
+    match pool_state.curve_type {
+        CurveType::ConstantProduct => Box::new(ConstantProduct),
+        CurveType::Stable => Box::new(Stable { amplification: pool_state.amplification }),
+        CurveType::Offset => {
+            Box::new(Offset { sol_offset: pool_state.sol_offset, token_offset: pool_state.token_offset })
+        }
+        CurveType::Weighted => {
+            let [sol_weight, token_weight] = pool_state.weights;
+            Box::new(Weighted { sol_weight, token_weight })
+        }
+    }
+}
+
+/// Constant product curve, `x * y = k`
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantProduct;
+
+impl SwapCurve for ConstantProduct {
+    fn swap_out(
+        &self,
+        amount_in: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_in, reserve_out) = amount_in.reserves(sol_reserve, token_reserve);
+        let (reserve_in, reserve_out) = (reserve_in as u128, reserve_out as u128);
+        let amount_out = constant_product_out(amount_in.get() as u128, reserve_in, reserve_out, fee_rate)?;
+        to_u64(amount_out).map(|amount_out| amount_in.other_side(amount_out))
+    }
+
+    /// Inverts `swap_out`, rounding up at each step so the result is the
+    /// smallest input whose output is at least `amount_out`
+    fn swap_in(
+        &self,
+        amount_out: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_out, reserve_in) = amount_out.reserves(sol_reserve, token_reserve);
+        let (reserve_in, reserve_out) = (reserve_in as u128, reserve_out as u128);
+        let amount_in = constant_product_in(amount_out.get() as u128, reserve_in, reserve_out, fee_rate)?;
+        to_u64(amount_in).map(|amount_in| amount_out.other_side(amount_in))
+    }
+}
+
+/// StableSwap curve, flattened around the 1:1 price by `amplification`
+/// 
+/// Suits pegged pairs with the same decimals. The fee comes off the input as
+/// on the constant product curve.
+#[derive(Debug, Clone, Copy)]
+pub struct Stable {
+    pub amplification: u64,
+}
+
+impl Stable {
+    /// The StableSwap invariant `D` of two reserves
+    /// 
+    /// Solves `4A(x + y) + D = 4AD + D^3 / (4xy)` by Newton's method from
+    /// `D = x + y`, landing within one unit of the exact root. Reserves large
+    /// enough to overflow the u128 intermediates are rejected with `MathOverflow`.
+    pub fn invariant(&self, reserve_a: u64, reserve_b: u64) -> Result<u128, ProgramError> {
+        if reserve_a == 0 || reserve_b == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let (x, y) = (reserve_a as u128, reserve_b as u128);
+        let sum = x + y;
+        let ann = (self.amplification as u128)
+            .checked_mul(4)
+            .filter(|ann| *ann > 1)
+            .ok_or(TokenExchangeError::InvalidCurveParameters)?;
+
+        let mut d = sum;
+        for _ in 0..STABLE_MAX_ITERATIONS {
+            // D_P = D^3 / (4xy)
+            let d_p = math::mul_div(d, d, x * 2, Rounding::Down)
+                .and_then(|value| math::mul_div(value, d, y * 2, Rounding::Down))
+                .ok_or(TokenExchangeError::MathOverflow)?;
+
+            // D' = (Ann * S + 2 * D_P) * D / ((Ann - 1) * D + 3 * D_P)
+            let numerator = ann
+                .checked_mul(sum)
+                .and_then(|value| value.checked_add(d_p.checked_mul(2)?))
+                .and_then(|value| value.checked_mul(d))
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let denominator = (ann - 1)
+                .checked_mul(d)
+                .and_then(|value| value.checked_add(d_p.checked_mul(3)?))
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let previous = d;
+            d = math::div(numerator, denominator, Rounding::Down).ok_or(TokenExchangeError::MathOverflow)?;
+            if d.abs_diff(previous) <= 1 {
+                return Ok(d);
+            }
+        }
+
+        // Newton's method failing to settle is treated like an overflow
+        Err(TokenExchangeError::MathOverflow.into())
+    }
+
+    /// The other reserve that keeps the StableSwap invariant at `d`
+    /// 
+    /// Solves `y^2 + (x + D / 4A - D) * y = D^3 / (16Ax)` by Newton's method
+    /// from `y = D`, which approaches the root from above.
+    fn reserve(&self, x: u128, d: u128) -> Result<u128, ProgramError> {
+        let ann = self.amplification as u128 * 4;
+        if x == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+
+        // c = D^3 / (4x * Ann), rounded up so the root, and the reserve kept, only grows
+        // b = x + D / Ann, rounded down for the same reason
+        let c = math::mul_div(d, d, x * 2, Rounding::Up)
+            .and_then(|value| math::mul_div(value, d, ann * 2, Rounding::Up))
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let b = math::div(d, ann, Rounding::Down)
+            .map(|value| x + value)
+            .ok_or(TokenExchangeError::InvalidCurveParameters)?;
+
+        let mut y = d;
+        for _ in 0..STABLE_MAX_ITERATIONS {
+            // y' = (y^2 + c) / (2y + b - D)
+            let numerator = y
+                .checked_mul(y)
+                .and_then(|value| value.checked_add(c))
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let denominator = (y * 2 + b)
+                .checked_sub(d)
+                .filter(|value| *value > 0)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let previous = y;
+            y = math::div(numerator, denominator, Rounding::Down).ok_or(TokenExchangeError::MathOverflow)?;
+            if y.abs_diff(previous) <= 1 {
+                return Ok(y);
+            }
+        }
+        Err(TokenExchangeError::MathOverflow.into())
+    }
+}
+
+impl SwapCurve for Stable {
+    /// Priced against `D + 1` with the output rounded down by one unit to
+    /// absorb Newton's method rounding, so the invariant never shrinks
+    fn swap_out(
+        &self,
+        amount_in: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_in, reserve_out) = amount_in.reserves(sol_reserve, token_reserve);
+        let amount_in_with_fee = amount_after_fee(amount_in.get() as u128, fee_rate)?;
+
+        // D is within a unit of the exact root, so price against D + 1 to never shrink it
+        let d = self.invariant(reserve_in, reserve_out)? + 1;
+        let new_reserve_in = reserve_in as u128 + amount_in_with_fee;
+        let new_reserve_out = self.reserve(new_reserve_in, d)?;
+        let amount_out = (reserve_out as u128)
+            .saturating_sub(new_reserve_out)
+            .saturating_sub(1) as u64;
+        Ok(amount_in.other_side(amount_out))
+    }
+
+    /// Solves the invariant for the input reserve, then bisects to the
+    /// smallest input whose `swap_out` covers `amount_out`
+    fn swap_in(
+        &self,
+        desired_out: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_out, reserve_in) = desired_out.reserves(sol_reserve, token_reserve);
+        let amount_out = desired_out.get();
+
+        // The pool can never pay out its whole reserve, nor the unit held back for rounding
+        if amount_out == 0 || amount_out >= reserve_out.saturating_sub(1) {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+
+        // Input after fees, from the input reserve that keeps D + 1 with one extra unit out
+        let d = self.invariant(reserve_in, reserve_out)? + 1;
+        let new_reserve_out = (reserve_out - amount_out - 1) as u128;
+        let new_reserve_in = self.reserve(new_reserve_out, d)?;
+        let amount_in_with_fee = new_reserve_in.saturating_sub(reserve_in as u128);
+
+        // Gross up for the fee
+        let estimate = to_u64(amount_before_fee(amount_in_with_fee, fee_rate)?)?;
+
+        // Newton's method rounds a unit or two either way, so settle on the
+        // smallest input the forward price accepts, bracketed by the estimate
+        smallest_covering_input(estimate, |amount_in| {
+            let amount_in = desired_out.other_side(amount_in);
+            Ok(self.swap_out(amount_in, sol_reserve, token_reserve, fee_rate)?.get() >= amount_out)
+        })
+        .map(|amount_in| desired_out.other_side(amount_in))
+    }
+
+    /// The invariant's slope, `x * (Ann * y + D_P) / (y * (Ann * x + D_P))`
+    /// with `D_P = D^3 / (4xy)`, which is 1 for balanced reserves
+    fn spot_price(&self, sol_reserve: Lamports, token_reserve: TokenAmount) -> Option<u128> {
+        let (x, y) = (sol_reserve.get() as u128, token_reserve.get() as u128);
+        let d = self.invariant(sol_reserve.get(), token_reserve.get()).ok()?;
+        let d_p = math::mul_div(d, d, x * 2, Rounding::Down)
+            .and_then(|value| math::mul_div(value, d, y * 2, Rounding::Down))?;
+        let ann = self.amplification as u128 * 4;
+        let slope = math::mul_div_wide(ann * y + d_p, Q64, ann * x + d_p, Rounding::Down)?;
+        math::mul_div_wide(slope, x, y, Rounding::Down)
+    }
+}
+
+/// Constant product curve over the reserves plus a virtual `sol_offset` and `token_offset`
+/// 
+/// The token offset lets a pool seeded with little or no token liquidity
+/// quote a price, and the SOL offset lets a pool seeded with tokens alone
+/// sell them from a bonding curve; swaps can never take more of either side
+/// than the pool really holds.
+#[derive(Debug, Clone, Copy)]
+pub struct Offset {
+    pub sol_offset: u64,
+    pub token_offset: u64,
+}
+
+impl Offset {
+    /// Reserves in trade direction with each side's offset added
+    /// 
+    /// Widened to u128, so a reserve near `u64::MAX` plus its offset still
+    /// prices.
+    fn offset_reserves(&self, reserve_in: u64, reserve_out: u64, is_sol_input: bool) -> (u128, u128) {
+        let (sol_offset, token_offset) = (self.sol_offset as u128, self.token_offset as u128);
+        if is_sol_input {
+            (reserve_in as u128 + sol_offset, reserve_out as u128 + token_offset)
+        } else {
+            (reserve_in as u128 + token_offset, reserve_out as u128 + sol_offset)
+        }
+    }
+}
+
+impl SwapCurve for Offset {
+    fn swap_out(
+        &self,
+        amount_in: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_in, reserve_out) = amount_in.reserves(sol_reserve, token_reserve);
+        let (offset_in, offset_out) = self.offset_reserves(reserve_in, reserve_out, amount_in.is_sol());
+        let amount_out = constant_product_out(amount_in.get() as u128, offset_in, offset_out, fee_rate)?;
+        if amount_out > 0 && amount_out >= reserve_out as u128 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        to_u64(amount_out).map(|amount_out| amount_in.other_side(amount_out))
+    }
+
+    fn swap_in(
+        &self,
+        amount_out: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_out, reserve_in) = amount_out.reserves(sol_reserve, token_reserve);
+        if amount_out.get() >= reserve_out {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let (offset_in, offset_out) = self.offset_reserves(reserve_in, reserve_out, !amount_out.is_sol());
+        let amount_in = constant_product_in(amount_out.get() as u128, offset_in, offset_out, fee_rate)?;
+        to_u64(amount_in).map(|amount_in| amount_out.other_side(amount_in))
+    }
+
+    /// The ratio of the reserves with their offsets, so a bonding curve
+    /// holding no SOL still quotes its starting price
+    fn spot_price(&self, sol_reserve: Lamports, token_reserve: TokenAmount) -> Option<u128> {
+        let (virtual_sol, virtual_token) = self.offset_reserves(sol_reserve.get(), token_reserve.get(), true);
+        math::mul_div_wide(virtual_sol, Q64, virtual_token, Rounding::Down)
+    }
+}
+
+/// Weighted constant mean curve, `x^w_x * y^w_y = k`, as in Balancer pools
+/// 
+/// Each side's weight, in basis points summing to 10000, is the share of the
+/// pool's value it holds at the spot price, so an 80/20 SOL/token pool keeps
+/// four times its token reserve's value in SOL. Equal weights give the
+/// constant product curve, and the fee comes off the input as it does there.
+/// 
+/// Powers are taken in Q64.64 through `math::pow`, whose error margin is
+/// pushed in the pool's favor. Deposits into a funded pool and withdrawals
+/// stay pro rata, which leaves the spot price, and so the weights, intact.
+#[derive(Debug, Clone, Copy)]
+pub struct Weighted {
+    pub sol_weight: u64,
+    pub token_weight: u64,
+}
+
+impl Weighted {
+    /// Weights of the input and output sides, in trade direction
+    fn directed_weights(&self, is_sol_input: bool) -> (u64, u64) {
+        if is_sol_input {
+            (self.sol_weight, self.token_weight)
+        } else {
+            (self.token_weight, self.sol_weight)
+        }
+    }
+}
+
+impl SwapCurve for Weighted {
+    /// `y * (1 - (x / (x + dx'))^(w_in / w_out))`, with the power rounded up
+    /// so the output only rounds down
+    fn swap_out(
+        &self,
+        amount_in: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_in, reserve_out) = amount_in.reserves(sol_reserve, token_reserve);
+        if reserve_in == 0 || reserve_out == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let (weight_in, weight_out) = self.directed_weights(amount_in.is_sol());
+        let amount_in_with_fee = amount_after_fee(amount_in.get() as u128, fee_rate)?;
+
+        // Share of the output reserve kept: (x / (x + dx'))^(w_in / w_out)
+        let new_reserve_in = reserve_in as u128 + amount_in_with_fee;
+        let kept = math::pow(reserve_in as u128, new_reserve_in, weight_in, weight_out, Rounding::Up)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let amount_out = math::mul_div(reserve_out as u128, Q64.saturating_sub(kept), Q64, Rounding::Down)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        to_u64(amount_out).map(|amount_out| amount_in.other_side(amount_out))
+    }
+
+    /// Estimates the input from `x * ((y / (y - dy))^(w_out / w_in) - 1)`,
+    /// then bisects to the smallest input whose `swap_out` covers `amount_out`
+    fn swap_in(
+        &self,
+        desired_out: SwapAmount,
+        sol_reserve: Lamports,
+        token_reserve: TokenAmount,
+        fee_rate: Bps,
+    ) -> Result<SwapAmount, ProgramError> {
+        let (reserve_out, reserve_in) = desired_out.reserves(sol_reserve, token_reserve);
+        let amount_out = desired_out.get();
+
+        // The pool can never pay out its whole reserve
+        if amount_out == 0 || amount_out >= reserve_out || reserve_in == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let (weight_in, weight_out) = self.directed_weights(!desired_out.is_sol());
+
+        // Input after fees, grown by the power of the output reserve's shrinkage
+        let new_reserve_out = (reserve_out - amount_out) as u128;
+        let growth = math::pow(reserve_out as u128, new_reserve_out, weight_out, weight_in, Rounding::Up)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let amount_in_with_fee = math::mul_div_wide(reserve_in as u128, growth.saturating_sub(Q64), Q64, Rounding::Up)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let estimate = to_u64(amount_before_fee(amount_in_with_fee, fee_rate)?)?;
+
+        // The powers carry a small error margin, so settle on the smallest
+        // input the forward price accepts, bracketed by the estimate
+        smallest_covering_input(estimate, |amount_in| {
+            let amount_in = desired_out.other_side(amount_in);
+            Ok(self.swap_out(amount_in, sol_reserve, token_reserve, fee_rate)?.get() >= amount_out)
+        })
+        .map(|amount_in| desired_out.other_side(amount_in))
+    }
+
+    /// Each reserve per unit of its weight, `(x / w_x) / (y / w_y)`
+    fn spot_price(&self, sol_reserve: Lamports, token_reserve: TokenAmount) -> Option<u128> {
+        let weighted_sol = sol_reserve.get() as u128 * self.token_weight as u128;
+        let weighted_token = token_reserve.get() as u128 * self.sol_weight as u128;
+        math::mul_div_wide(weighted_sol, Q64, weighted_token, Rounding::Down)
+    }
+
+    /// The first deposit mints the weighted geometric mean of both amounts,
+    /// the weighted invariant's own measure of the pool; later deposits mint
+    /// pro rata
+    fn deposit_lp(
+        &self,
+        pool_state: &PoolState,
+        sol_amount: Lamports,
+        token_amount: TokenAmount,
+        total_supply: LpAmount,
+    ) -> Result<LpAmount, ProgramError> {
+        if pool_state.sol_reserve == 0 {
+            let (sol_amount, token_amount) = (sol_amount.get(), token_amount.get());
+            return math::weighted_geometric_mean(sol_amount, token_amount, self.sol_weight, self.token_weight)
+                .map(LpAmount)
+                .ok_or(TokenExchangeError::MathOverflow.into());
+        }
+        pro_rata_lp(pool_state, sol_amount, token_amount, total_supply)
+    }
+}
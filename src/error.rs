@@ -2,7 +2,9 @@
 // This module defines all possible errors that can occur in the token exchange program
 
 use num_derive::FromPrimitive;                                      // For converting numbers to enum variants
+use num_traits::FromPrimitive as _;                                 // For decoding error codes
 use solana_program::{decode_error::DecodeError,                     // For error decoding functionality
+                    instruction::InstructionError,                   // Error reported for a failed instruction
                     program_error::ProgramError};                    // Base Solana program error type
 use thiserror::Error;                                              // For error handling macros
 
@@ -51,6 +53,318 @@ pub enum TokenExchangeError {
     /// The user position account is invalid or doesn't exist
     #[error("Invalid user position")]
     InvalidUserPosition,
+    
+    /// The requested platform fee exceeds the program-wide cap
+    #[error("Invalid platform fee")]
+    InvalidPlatformFee,
+    
+    /// The provided account is not the program's protocol stats PDA
+    #[error("Invalid protocol stats account")]
+    InvalidProtocolStats,
+    
+    /// The provided account is not the pool's observations PDA
+    #[error("Invalid observations account")]
+    InvalidObservations,
+    
+    /// The requested observation cardinality is out of range or not an increase
+    #[error("Invalid observation cardinality")]
+    InvalidObservationCardinality,
+    
+    /// The provided account is not the expected limit order PDA
+    #[error("Invalid limit order")]
+    InvalidLimitOrder,
+    
+    /// The pool price does not satisfy the order's limit price
+    #[error("Limit price not reached")]
+    LimitPriceNotReached,
+    
+    /// The requested fill exceeds the order's remaining size or is zero
+    #[error("Invalid fill amount")]
+    InvalidFillAmount,
+    
+    /// The order has passed its expiry slot
+    #[error("Order expired")]
+    OrderExpired,
+    
+    /// The order has not expired and can only be cancelled by its owner
+    #[error("Order not expired")]
+    OrderNotExpired,
+    
+    /// The keeper bounty share is outside the acceptable range
+    #[error("Invalid keeper bounty")]
+    InvalidKeeperBounty,
+    
+    /// The pool price has not reached the stop order's trigger price
+    #[error("Stop price not triggered")]
+    StopNotTriggered,
+    
+    /// The oracle account is not a valid, trading price feed
+    #[error("Invalid oracle")]
+    InvalidOracle,
+    
+    /// The oracle price is older than the allowed staleness window
+    #[error("Stale oracle price")]
+    StaleOracle,
+    
+    /// The provided account is not the expected liquidity schedule PDA
+    #[error("Invalid liquidity schedule")]
+    InvalidLiquiditySchedule,
+    
+    /// The schedule's next tranche is not due yet or none remain
+    #[error("Tranche not due")]
+    TrancheNotDue,
+    
+    /// The provided account is not a valid trading allowance for this operator
+    #[error("Invalid trading allowance")]
+    InvalidTradingAllowance,
+    
+    /// The allowance does not cover the pool being traded
+    #[error("Pool not allowed")]
+    PoolNotAllowed,
+    
+    /// The trade would exceed the allowance's notional cap for the window
+    #[error("Allowance exceeded")]
+    AllowanceExceeded,
+    
+    /// The transaction does not carry a valid ed25519 signature over the order
+    #[error("Invalid signature")]
+    InvalidSignature,
+    
+    /// The keeper is not registered with one of the pools
+    #[error("Keeper not registered")]
+    KeeperNotRegistered,
+    
+    /// The arbitrage loop would not return the required profit
+    #[error("Arbitrage unprofitable")]
+    ArbitrageUnprofitable,
+    
+    /// The provided account is not the expected user stats PDA
+    #[error("Invalid user stats")]
+    InvalidUserStats,
+    
+    /// The signed order's nonce is not the owner's next nonce
+    #[error("Invalid order nonce")]
+    InvalidNonce,
+    
+    /// The provided account is not a valid sealed order or committee
+    #[error("Invalid sealed order")]
+    InvalidSealedOrder,
+    
+    /// The revealed terms do not match the sealed order's commitment
+    #[error("Commitment mismatch")]
+    CommitmentMismatch,
+    
+    /// Fewer committee members signed than the committee's threshold
+    #[error("Committee threshold not met")]
+    CommitteeThresholdNotMet,
+    
+    /// The sealed order cannot be executed or cancelled in the current slot
+    #[error("Sealed order not ready")]
+    SealedOrderNotReady,
+    
+    /// The token account is not the pool's vault
+    #[error("Invalid token vault")]
+    InvalidTokenVault,
+    
+    /// The LP mint does not support the requested LP configuration
+    #[error("Invalid LP mint")]
+    InvalidLpMint,
+    
+    /// The liquidity provider is not on the pool's allowlist
+    #[error("Liquidity provider not allowlisted")]
+    LiquidityProviderNotAllowed,
+    
+    /// The pool metadata account or its contents are invalid
+    #[error("Invalid pool metadata")]
+    InvalidPoolMetadata,
+    
+    /// The pool is deprecated and only allows withdrawals
+    #[error("Pool is deprecated")]
+    PoolDeprecated,
+    
+    /// The provided account is not the pool's volume stats PDA
+    #[error("Invalid volume stats account")]
+    InvalidVolumeStats,
+    
+    /// The operation moves native SOL and is not available on token pair pools
+    #[error("Operation not supported for token pair pools")]
+    UnsupportedTokenPair,
+    
+    /// The provided account is not the pool PDA derived from its mints and fee rate
+    #[error("Invalid pool account")]
+    InvalidPoolAccount,
+    
+    /// The provided account is not the pool's SOL vault PDA
+    #[error("Invalid SOL vault")]
+    InvalidSolVault,
+    
+    /// The provided account is not the protocol configuration PDA
+    #[error("Invalid protocol config")]
+    InvalidProtocolConfig,
+    
+    /// The signer is not the protocol authority (or the program's upgrade authority)
+    #[error("Invalid protocol authority")]
+    InvalidProtocolAuthority,
+    
+    /// wSOL mode requires the pool token under SPL Token and matching modes across pools
+    #[error("Unsupported wrapped SOL mode")]
+    UnsupportedWsolMode,
+    
+    /// The pool is paused and only allows withdrawals
+    #[error("Pool is paused")]
+    PoolPaused,
+    
+    /// Stable pools need an amplification between 1 and `MAX_AMPLIFICATION`,
+    /// offset pools a nonzero SOL or token offset and weighted pools weights
+    /// of at least `MIN_WEIGHT_BPS`; other curves take none of them, only
+    /// offset pools may graduate, and interest-bearing tokens take neither the
+    /// stable nor the offset curve
+    #[error("Invalid curve parameters")]
+    InvalidCurveParameters,
+    
+    /// The pool has a flash loan outstanding, which only `FlashRepay` may settle
+    #[error("Pool has an outstanding flash loan")]
+    FlashLoanActive,
+    
+    /// A flash loan has no `FlashRepay` for the same pool later in the
+    /// transaction, or its repayment fell short
+    #[error("Flash loan not repaid")]
+    FlashLoanNotRepaid,
+    
+    /// `FlashRepay` was called on a pool with no outstanding flash loan
+    #[error("No outstanding flash loan")]
+    NoFlashLoan,
+    
+    /// In fee-on-transfer mode, the pool's vault received less than an input
+    /// whose amount was already fixed by the price
+    #[error("Transfer fee not covered")]
+    TransferFeeNotCovered,
+    
+    /// Account data does not start with the discriminator of the expected
+    /// account type
+    #[error("Invalid account discriminator")]
+    InvalidAccountDiscriminator,
+    
+    /// The pool state is laid out in an older or unknown `StateVersion`;
+    /// older layouts must first be upgraded with `MigratePool`
+    #[error("Invalid state version")]
+    InvalidStateVersion,
+    
+    /// The protocol config has paused pool creation
+    #[error("Pool creation is paused")]
+    PoolCreationPaused,
+    
+    /// The pool registry or registry entry account is not the expected PDA
+    #[error("Invalid pool registry")]
+    InvalidPoolRegistry,
+    
+    /// The position still has fees owed and must claim them before closing
+    #[error("Position has unclaimed fees")]
+    PositionHasUnclaimedFees,
+    
+    /// The farm or stake account is not the expected PDA, or its vaults do not match
+    #[error("Invalid farm")]
+    InvalidFarm,
+    
+    /// The emission rate or duration is zero, or the total emission overflows
+    #[error("Invalid emission schedule")]
+    InvalidEmissionSchedule,
+    
+    /// More LP tokens were unstaked than are staked
+    #[error("Insufficient staked LP tokens")]
+    InsufficientStake,
+    
+    /// The farm already emits the maximum number of reward tokens
+    #[error("Farm reward slots are full")]
+    FarmRewardsFull,
+    
+    /// The referral fee share exceeds the protocol config's maximum
+    #[error("Invalid referral fee")]
+    InvalidReferralFee,
+    
+    /// The pool's trade start time has not been reached
+    #[error("Trading has not started")]
+    TradingNotStarted,
+    
+    /// A buy during the pool's launch window exceeds its per-swap cap
+    #[error("Launch buy cap exceeded")]
+    LaunchBuyCapExceeded,
+    
+    /// The launch window or launch fee schedule is out of bounds
+    #[error("Invalid launch protection")]
+    InvalidLaunchProtection,
+    
+    /// The variable fee cap exceeds the protocol's bound
+    #[error("Invalid variable fee")]
+    InvalidVariableFee,
+    
+    /// The swap would move the pool price by more than its price impact limit
+    #[error("Price impact too high")]
+    PriceImpactTooHigh,
+    
+    /// The swap would execute too far from the pool's oracle price
+    #[error("Swap price deviates from the oracle price")]
+    OraclePriceDeviation,
+    
+    /// The oracle price band is out of bounds
+    #[error("Invalid oracle price band")]
+    InvalidOracleBand,
+    
+    /// The concentrated liquidity pool, tick or position account is not the expected PDA
+    #[error("Invalid concentrated liquidity account")]
+    InvalidConcentratedPool,
+    
+    /// The tick is out of range, off the pool's tick spacing, or misordered
+    #[error("Invalid tick")]
+    InvalidTick,
+    
+    /// The tick accounts passed do not match the pool's initialized tick list
+    #[error("Invalid tick list")]
+    InvalidTickList,
+    
+    /// The bin pool or position account is not the expected PDA
+    #[error("Invalid bin pool account")]
+    InvalidBinPool,
+    
+    /// The bin's price is out of range, or the account is not the expected bin
+    #[error("Invalid bin")]
+    InvalidBin,
+    
+    /// The bonding curve has reached its graduation threshold and trades again once graduated
+    #[error("Bonding curve is complete")]
+    BondingCurveComplete,
+    
+    /// Liquidity cannot be added to a bonding curve before it graduates
+    #[error("Bonding curve has not graduated")]
+    BondingCurveActive,
+    
+    /// The pool is not a bonding curve at its graduation threshold
+    #[error("Graduation threshold not reached")]
+    GraduationNotReached,
+    
+    /// The provided account is not the expected DCA position PDA
+    #[error("Invalid DCA position")]
+    InvalidDcaPosition,
+    
+    /// A sandwich-protected swap shares its transaction with another
+    /// instruction that references the same pool
+    #[error("Pool referenced elsewhere in the transaction")]
+    PoolSandwiched,
+    
+    /// The pool's reserves already lost as much as its per-slot outflow
+    /// limit allows this slot
+    #[error("Outflow limit exceeded")]
+    OutflowLimitExceeded,
+    
+    /// The pool is in emergency mode, so withdrawals priced off its
+    /// reserves are disabled in favor of `EmergencyWithdraw`
+    #[error("Pool is in emergency mode")]
+    PoolInEmergency,
+    
+    /// `EmergencyWithdraw` is only available once the protocol authority
+    /// puts the pool in emergency mode
+    #[error("Pool is not in emergency mode")]
+    PoolNotInEmergency,
 }
 
 /// Converts our custom error into a Solana program error
@@ -71,4 +385,42 @@ impl<T> DecodeError<T> for TokenExchangeError {
     fn type_of() -> &'static str {
         "TokenExchangeError"
     }
-} 
\ No newline at end of file
+} 
+
+impl TokenExchangeError {
+    /// Decodes a custom program error code into its variant
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::from_u32(code)
+    }
+
+    /// Decodes the program's error from a failed instruction
+    /// 
+    /// Clients receive this inside
+    /// `TransactionError::InstructionError(index, error)`.
+    pub fn from_instruction_error(error: &InstructionError) -> Option<Self> {
+        match error {
+            InstructionError::Custom(code) => Self::from_code(*code),
+            _ => None,
+        }
+    }
+
+    /// Decodes the program's error from a `ProgramError`
+    pub fn from_program_error(error: &ProgramError) -> Option<Self> {
+        match error {
+            ProgramError::Custom(code) => Self::from_code(*code),
+            _ => None,
+        }
+    }
+}
+
+/// Human-readable message for a failed instruction, suitable for end users
+/// 
+/// Custom codes from this program map to their `TokenExchangeError` message;
+/// unknown codes and runtime errors fall back to the runtime's description.
+pub fn describe_instruction_error(error: &InstructionError) -> String {
+    match (TokenExchangeError::from_instruction_error(error), error) {
+        (Some(decoded), _) => decoded.to_string(),
+        (None, InstructionError::Custom(code)) => format!("Unknown program error {code}"),
+        (None, error) => error.to_string(),
+    }
+}
@@ -0,0 +1,152 @@
+// Program Events
+// This module defines the structured events handlers log for indexers
+
+use borsh::{BorshDeserialize, BorshSerialize};                 // Event serialization
+use solana_program::{
+    log::sol_log_data,                                         // Structured program logs
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+};
+
+use crate::state::CurveType;                                   // Pool curves
+
+/// An event logged through `sol_log_data`
+/// 
+/// Each event is one `Program data:` log line with two base64 fields: the
+/// event's 8-byte discriminator, then its Borsh encoding. Indexers match the
+/// first field and decode the second with `try_from_slice`.
+pub trait Event: BorshSerialize {
+    /// Tag identifying the event type in the first log field
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Logs the event
+    fn emit(&self) -> Result<(), ProgramError> {
+        sol_log_data(&[&Self::DISCRIMINATOR, &self.try_to_vec()?]);
+        Ok(())
+    }
+}
+
+/// A pool was created by `InitializePool`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PoolCreatedEvent {
+    pub pool: Pubkey,                                          // The new pool
+    pub creator: Pubkey,                                       // Initializer, and the pool authority
+    pub mint_a: Pubkey,                                        // Side A mint (default for native SOL pools)
+    pub token_mint: Pubkey,                                    // Side B mint
+    pub lp_mint: Pubkey,                                       // LP token mint
+    pub fee_rate: u64,                                         // Trading fee in basis points
+    pub curve_type: CurveType,                                 // Pricing curve
+    pub sol_reserve: u64,                                      // Initial SOL (mint A) reserve
+    pub token_reserve: u64,                                    // Initial token reserve
+}
+
+/// A swap executed against a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub pool: Pubkey,                                          // Pool swapped against
+    pub user: Pubkey,                                          // Trader
+    pub is_sol_input: bool,                                    // Whether SOL (mint A) was sold
+    pub amount_in: u64,                                        // Input the pool priced the swap on
+    pub amount_out: u64,                                       // Output paid to the user, after any platform fee
+    pub fee_paid: u64,                                         // Trading fee charged on the input
+    pub sol_reserve: u64,                                      // SOL (mint A) reserve after the swap
+    pub token_reserve: u64,                                    // Token reserve after the swap
+    pub referrer: Pubkey,                                      // Account paid the referral fee (default if none)
+    pub referral_fee: u64,                                     // Part of fee_paid paid to the referrer, in the input
+}
+
+/// Liquidity was deposited into a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityAddedEvent {
+    pub pool: Pubkey,                                          // Pool deposited into
+    pub provider: Pubkey,                                      // Liquidity provider
+    pub sol_amount: u64,                                       // SOL the pool received
+    pub token_amount: u64,                                     // Tokens the pool received
+    pub lp_tokens: u64,                                        // LP tokens minted
+    pub sol_reserve: u64,                                      // SOL reserve afterwards
+    pub token_reserve: u64,                                    // Token reserve afterwards
+}
+
+/// Liquidity was withdrawn from a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityRemovedEvent {
+    pub pool: Pubkey,                                          // Pool withdrawn from
+    pub provider: Pubkey,                                      // Liquidity provider
+    pub sol_amount: u64,                                       // SOL paid out
+    pub token_amount: u64,                                     // Tokens paid out
+    pub lp_tokens: u64,                                        // LP tokens burned
+    pub sol_reserve: u64,                                      // SOL reserve afterwards
+    pub token_reserve: u64,                                    // Token reserve afterwards
+}
+
+/// A position claimed its trading fees
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeesClaimedEvent {
+    pub pool: Pubkey,                                          // Pool the fees were earned in
+    pub owner: Pubkey,                                         // Position owner
+    pub fees_sol: u64,                                         // SOL (mint A) fees paid out
+    pub fees_token: u64,                                       // Token fees paid out
+}
+
+/// A bonding curve graduated into a constant product pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PoolGraduatedEvent {
+    pub pool: Pubkey,                                          // Pool that graduated
+    pub sol_reserve: u64,                                      // SOL (mint A) reserve the pool reopens with
+    pub token_reserve: u64,                                    // Token reserve the pool reopens with
+    pub tokens_burned: u64,                                    // Tokens the curve's price no longer backed
+    pub lp_tokens_locked: u64,                                 // LP tokens minted to the pool's locked account
+}
+
+/// A keeper was paid for cranking an order fill or DCA tranche
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeeperBountyPaidEvent {
+    pub pool: Pubkey,                                          // Pool the crank traded against
+    pub keeper: Pubkey,                                        // Keeper that ran the crank
+    pub amount: u64,                                           // Bounty paid, in the crank's input asset
+    pub is_sol: bool,                                          // Whether the bounty was paid in SOL (mint A)
+}
+
+/// Vault balances beyond a pool's accounting were folded in by `SyncReserves`,
+/// or paid to the treasury by `Skim`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReservesSyncedEvent {
+    pub pool: Pubkey,                                          // Pool that was synced
+    pub sol_excess: u64,                                       // SOL (mint A) found beyond the pool's accounting
+    pub token_excess: u64,                                     // Tokens found beyond the pool's accounting
+    pub skimmed: bool,                                         // Whether the excess went to the protocol
+    pub sol_reserve: u64,                                      // SOL (mint A) reserve after the sync
+    pub token_reserve: u64,                                    // Token reserve after the sync
+}
+
+impl Event for PoolCreatedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"poolmade";
+}
+
+impl Event for SwapEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"swapexec";
+}
+
+impl Event for LiquidityAddedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"liqadded";
+}
+
+impl Event for LiquidityRemovedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"liqremvd";
+}
+
+impl Event for FeesClaimedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"feeclaim";
+}
+
+impl Event for PoolGraduatedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"poolgrad";
+}
+
+impl Event for KeeperBountyPaidEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"keeprpay";
+}
+
+impl Event for ReservesSyncedEvent {
+    const DISCRIMINATOR: [u8; 8] = *b"rsrvsync";
+}
@@ -0,0 +1,180 @@
+// LP Farming
+// This module defines reward farms that pay emissions to LP tokens staked with them
+
+use borsh::{BorshDeserialize, BorshSerialize};                 // For account serialization
+use solana_program::{
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+};
+
+use crate::{
+    error::TokenExchangeError,                                 // Custom errors
+    math::{self, Rounding},                                    // Rounded division
+};
+
+/// Seed used to derive a pool's farm PDA
+pub const FARM_SEED: &[u8] = b"farm";
+
+/// Seed used to derive a staker's stake PDA in a farm
+pub const FARM_STAKE_SEED: &[u8] = b"farm_stake";
+
+/// Maximum number of reward tokens a farm emits
+pub const MAX_FARM_REWARDS: usize = 2;
+
+/// Reward tokens an emission releases in total, which are funded up front
+/// 
+/// The schedule must release something and its total must fit a u64.
+pub fn emission_total(emission_rate: u64, duration_slots: u64) -> Result<u64, ProgramError> {
+    emission_rate
+        .checked_mul(duration_slots)
+        .filter(|total| *total > 0)
+        .ok_or(TokenExchangeError::InvalidEmissionSchedule.into())
+}
+
+/// A reward farm attached to a pool
+/// 
+/// The pool's authority funds each reward's vault with its whole emission up
+/// front, and it is released at `emission_rate` per slot until
+/// `emission_end_slot`, shared among the LP tokens staked in the LP vault
+/// at each slot. All vaults are token accounts owned by the farm PDA.
+/// Emissions during slots with nothing staked stay in the reward vault.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct FarmState {
+    pub pool: Pubkey,             // The pool whose LP tokens are staked
+    pub authority: Pubkey,        // Pool authority that created and funds the farm
+    pub lp_vault: Pubkey,         // Token account holding the staked LP tokens
+    pub rewards: [FarmReward; MAX_FARM_REWARDS], // Reward tokens, unused slots have a default mint
+    pub total_staked: u64,        // LP tokens in the LP vault
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// One reward token of a farm, with its own emission schedule
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct FarmReward {
+    pub mint: Pubkey,             // Mint of the reward token
+    pub vault: Pubkey,            // Token account holding undistributed and unclaimed rewards
+    pub emission_rate: u64,       // Reward tokens released per slot
+    pub emission_end_slot: u64,   // First slot that releases nothing
+    pub reward_per_share: u128,   // Rewards released per staked LP token, Q64.64, wrapping
+    pub last_update_slot: u64,    // Slot up to which reward_per_share has accrued
+}
+
+/// A staker's LP tokens in a farm
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct FarmStake {
+    pub farm: Pubkey,             // The farm staked in
+    pub owner: Pubkey,            // The staker
+    pub amount: u64,              // LP tokens staked
+    pub reward_per_share_checkpoints: [u128; MAX_FARM_REWARDS], // Each reward_per_share when last settled
+    pub rewards_owed: [u64; MAX_FARM_REWARDS], // Settled rewards not yet harvested
+    pub bump: u8,                 // PDA bump seed
+}
+
+impl FarmState {
+    /// Total size of the FarmState structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool, authority, lp_vault (Pubkey): 96 bytes
+    /// - rewards (FarmReward): 2 * 104 bytes
+    /// - total_staked (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 * 3 + FarmReward::LEN * MAX_FARM_REWARDS + 8 + 1;
+
+    /// Derives the farm PDA of a pool
+    pub fn find_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[FARM_SEED, pool.as_ref()], program_id)
+    }
+
+    /// Recomputes the farm's address from its pool and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(&[FARM_SEED, self.pool.as_ref(), &[self.bump]], program_id)
+            .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Releases every reward's emissions up to `slot`
+    /// 
+    /// Must run before `total_staked` changes.
+    pub fn accrue(&mut self, slot: u64) -> Result<(), ProgramError> {
+        for reward in self.rewards.iter_mut().filter(|reward| reward.is_active()) {
+            reward.accrue(slot, self.total_staked)?;
+        }
+        Ok(())
+    }
+}
+
+impl FarmReward {
+    /// Serialized size: mint and vault (Pubkey), rate and end slot (u64),
+    /// reward_per_share (u128) and last_update_slot (u64)
+    pub const LEN: usize = 32 * 2 + 8 + 8 + 16 + 8;
+
+    /// Whether this reward slot holds a reward token
+    pub fn is_active(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+
+    /// Releases the emissions of the slots since `last_update_slot`, up to `slot`
+    /// 
+    /// Each staked LP token's share is added to `reward_per_share`, rounded
+    /// down so stakers are never owed more than was released.
+    pub fn accrue(&mut self, slot: u64, total_staked: u64) -> Result<(), ProgramError> {
+        let end = slot.min(self.emission_end_slot);
+        if end <= self.last_update_slot {
+            return Ok(());
+        }
+        if total_staked > 0 {
+            let released = self.emission_rate as u128 * (end - self.last_update_slot) as u128;
+            let per_share = math::mul_div(released, 1 << 64, total_staked as u128, Rounding::Down)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            self.reward_per_share = self.reward_per_share.wrapping_add(per_share);
+        }
+        self.last_update_slot = end;
+        Ok(())
+    }
+}
+
+impl FarmStake {
+    /// Total size of the FarmStake structure when serialized
+    /// 
+    /// Breakdown:
+    /// - farm, owner (Pubkey): 64 bytes
+    /// - amount (u64): 8 bytes
+    /// - reward_per_share_checkpoints (u128): 2 * 16 bytes
+    /// - rewards_owed (u64): 2 * 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 64 + 8 + (16 + 8) * MAX_FARM_REWARDS + 1;
+
+    /// Derives the stake PDA of an owner in a farm
+    pub fn find_address(farm: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[FARM_STAKE_SEED, farm.as_ref(), owner.as_ref()], program_id)
+    }
+
+    /// Recomputes the stake's address from its farm, owner and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[FARM_STAKE_SEED, self.farm.as_ref(), self.owner.as_ref(), &[self.bump]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Moves the rewards earned since the last checkpoints into `rewards_owed`
+    /// 
+    /// The farm must have accrued to the current slot first. Must run before
+    /// `amount` changes, so the old stake earns the rewards up to now.
+    pub fn settle(&mut self, farm: &FarmState) -> Result<(), ProgramError> {
+        for (index, reward) in farm.rewards.iter().enumerate() {
+            let earned = math::mul_div(
+                self.amount as u128,
+                reward.reward_per_share.wrapping_sub(self.reward_per_share_checkpoints[index]),
+                1 << 64,
+                Rounding::Down,
+            )
+            .and_then(|rewards| u64::try_from(rewards).ok())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+            self.rewards_owed[index] = self.rewards_owed[index].checked_add(earned)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            self.reward_per_share_checkpoints[index] = reward.reward_per_share;
+        }
+        Ok(())
+    }
+}
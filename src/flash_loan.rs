@@ -0,0 +1,56 @@
+// Flash Loans
+// This module prices flash loans and checks, by instruction introspection, that each one is repaid
+
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked}, // Instruction introspection
+};
+
+use crate::{
+    amount::Bps,                                               // Typed amounts
+    error::TokenExchangeError,                                 // Custom errors
+    math::{self, Rounding},                                    // Rounded division
+};
+
+/// Instruction tag of `FlashRepay`
+const FLASH_REPAY_TAG: u8 = 49;
+
+/// Index of the pool state account in `FlashRepay`'s accounts
+const FLASH_REPAY_POOL_INDEX: usize = 1;
+
+/// Fee owed on a flash loan of `amount` at the pool's trading fee, rounded up
+pub fn flash_loan_fee(amount: u64, fee_rate: Bps) -> Result<u64, ProgramError> {
+    math::mul_div(amount as u128, fee_rate.get() as u128, 10000, Rounding::Up)
+        .and_then(|fee| u64::try_from(fee).ok())
+        .ok_or(TokenExchangeError::MathOverflow.into())
+}
+
+/// Checks that a top-level `FlashRepay` for `pool` follows the current instruction
+/// 
+/// The runtime executes every instruction of a transaction or none, so a
+/// later repay either settles the loan or fails the whole transaction.
+/// 
+/// # Arguments
+/// * `instructions_sysvar` - The instructions sysvar account
+/// * `program_id` - The program's public key
+/// * `pool` - The pool lending the funds
+pub fn verify_repay_follows(
+    instructions_sysvar: &AccountInfo,
+    program_id: &Pubkey,
+    pool: &Pubkey,
+) -> Result<(), ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    let mut index = current_index + 1;
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions_sysvar) {
+        if instruction.program_id == *program_id
+            && instruction.data.first() == Some(&FLASH_REPAY_TAG)
+            && instruction.accounts.get(FLASH_REPAY_POOL_INDEX).map(|meta| &meta.pubkey) == Some(pool)
+        {
+            return Ok(());
+        }
+        index += 1;
+    }
+    Err(TokenExchangeError::FlashLoanNotRepaid.into())
+}
@@ -1,10 +1,22 @@
 // Instruction Definitions
-// This module defines all instructions that can be executed by the token exchange program
+// This module defines all instructions that can be executed by the token exchange program, with client builders for the common ones
 
 use borsh::{BorshDeserialize, BorshSerialize};                     // For instruction data serialization
-use solana_program::instruction::AccountMeta;                       // For account metadata
-use solana_program::program_error::ProgramError;                    // For error handling
-use solana_program::{pubkey::Pubkey, system_program, sysvar};      // For Solana primitives
+use solana_program::{
+    instruction::{AccountMeta, Instruction},                        // Client-side instruction building
+    program_error::ProgramError,                                    // Error handling
+    pubkey::Pubkey,                                                 // Public key type
+    system_program,                                                 // System program ID
+};
+
+use crate::{
+    amount::{Bps, Lamports, LpAmount, TokenAmount},                // Typed amounts
+    signed_order::SignedOrder,                                      // Off-chain signed orders
+    state::{                                                        // Pool curves, protocol bounds and sealed order terms
+        CurveType, PoolRegistry, PoolRegistryEntry, PoolState, ProtocolConfig, ProtocolParameters,
+        SealedOrderTerms,
+    },
+};
 
 /// Defines all instructions supported by the Token Exchange program
 /// 
@@ -14,36 +26,174 @@ use solana_program::{pubkey::Pubkey, system_program, sysvar};      // For Solana
 pub enum TokenExchangeInstruction {
     /// Creates and initializes a new liquidity pool
     /// 
-    /// This instruction sets up a new trading pair between SOL and an SPL token.
-    /// It requires initial liquidity to be provided in both assets.
+    /// This instruction sets up a new trading pair between SOL and an SPL token,
+    /// or between two SPL mints when `token_pair` is set. It requires initial
+    /// liquidity to be provided in both assets.
+    /// 
+    /// Token pair pools hold side A in an SPL vault and track it in
+    /// `sol_reserve`; pairing wrapped SOL this way is just another mint. The
+    /// initializer pays `sol_amount` of mint A into that vault. Swaps, flash
+    /// loans and liquidity deposits and withdrawals move side A through it;
+    /// instructions built around native SOL, such as limit orders, reject
+    /// token pair pools.
+    /// 
+    /// The pool state account is created here at the PDA derived from
+    /// `["pool", mint_a, token_mint, fee_rate]` (`mint_a` is the default key
+    /// for native SOL pools), so there is one pool per pair and fee tier and
+    /// every later instruction can reject forged pool accounts. The pool PDA
+    /// owns its vaults and is the LP mint authority. Native SOL pools keep
+    /// their SOL reserve in a separate SOL vault PDA, into which the
+    /// initializer pays `sol_amount`. The initializer's `token_amount` moves
+    /// into the pool token vault, which is credited with what it actually
+    /// receives, and the initializer is minted the first LP tokens at the
+    /// geometric mean of the deposit (weighted for weighted pools).
+    /// 
+    /// With `wsol` set, the SOL vault PDA is created as a wrapped SOL token
+    /// account owned by the pool, and every SOL leg of later instructions
+    /// moves wSOL through SPL Token instead of raw lamports: users pass wSOL
+    /// token accounts wherever a SOL account is expected. The pool's token
+    /// must then be an SPL Token mint so one token program serves both sides.
+    /// 
+    /// `curve_type` picks the pricing curve for the pool's lifetime. The
+    /// stable curve suits pegged pairs with the same decimals (e.g. SOL/mSOL
+    /// in wSOL mode) and needs an `amplification` between 1 and
+    /// `MAX_AMPLIFICATION`. The offset curve prices as if the pool held
+    /// `sol_offset` more SOL and `token_offset` more tokens, so it can launch
+    /// with little token liquidity, or with tokens alone to sell them from a
+    /// bonding curve. With a `graduation_threshold`, the offset pool is a
+    /// bonding curve: nobody may add liquidity, swaps stop once the SOL
+    /// reserve reaches the threshold, and `Graduate` then reopens it as a
+    /// constant product pool; its initial deposit mints no LP tokens. The
+    /// weighted curve holds `sol_weight_bps` of the pool's value in SOL (or
+    /// mint A) and the rest in tokens, with each side's weight between
+    /// `MIN_WEIGHT_BPS` and `10000 - MIN_WEIGHT_BPS`.
+    /// Parameters of other curves are left at 0. Interest-bearing tokens may
+    /// only use the constant product and weighted curves.
+    /// 
+    /// The fee rate must be one of the protocol config's fee tiers, the pool
+    /// starts with the config's protocol fee share, and the initializer pays
+    /// the config's pool-creation fee to its treasury. Until the protocol
+    /// config is created, the built-in bounds apply (tiers of 1, 5, 30 and
+    /// 100 bps) and creation is free. Each tier of a pair gets its own pool,
+    /// found with `PoolState::find_tier_addresses`.
+    /// 
+    /// Every pool is appended to the pool registry, so clients can enumerate
+    /// pools by reading `PoolRegistry::pool_count` and deriving each
+    /// `PoolRegistryEntry` instead of scanning program accounts.
     /// 
     /// Accounts expected:
-    /// 0. `[signer]` The account creating the pool (will be the pool authority)
-    /// 1. `[writable]` The pool state account (PDA to store pool data)
-    /// 2. `[]` The token mint (SPL token to be traded)
-    /// 3. `[writable]` The LP token mint (must be created beforehand)
+    /// 0. `[signer, writable]` The account creating the pool (will be the pool
+    ///    authority; pays the pool's rent)
+    /// 1. `[writable]` The pool state PDA (created at `PoolState::LEN` bytes
+    ///    by this instruction; if it already belongs to the program it must be
+    ///    that size, rent-exempt and uninitialized)
+    /// 2. `[]` The token mint (SPL Token or Token-2022 mint to be traded; side B)
+    /// 3. `[writable]` The LP token mint (must be created beforehand with the
+    ///    pool PDA as mint authority and no supply; a Token-2022
+    ///    non-transferable mint for soulbound LP)
     /// 4. `[]` The system program (for rent and account creation)
+    /// 5. `[writable]` The SOL vault PDA derived from `["sol_vault", pool]`
+    ///    (created by this instruction), or `[]` mint A when `token_pair` is set
+    /// 6. `[]` The protocol config PDA (need not exist yet)
+    /// 7. `[writable]` The protocol treasury (only when the config charges a
+    ///    pool-creation fee)
+    /// 8. `[writable]` The pool registry PDA (created by the first pool)
+    /// 9. `[writable]` The registry entry PDA derived from the registry's
+    ///    current `pool_count` (created by this instruction)
+    /// 10. `[]` The token program of the pool's token (SPL Token when `wsol`
+    ///     is set)
+    /// 11. `[writable]` The initializer's token account (pays `token_amount`)
+    /// 12. `[writable]` The pool token vault (a token account of the token
+    ///     mint owned by the pool PDA)
+    /// 13. `[writable]` The initializer's LP token account (receives the
+    ///     initial LP tokens)
+    /// 14. `[]` The native mint (only when `wsol` is set), or `[writable]` the
+    ///     initializer's mint A token account (only when `token_pair` is set;
+    ///     pays `sol_amount`)
+    /// 15. `[writable]` The pool's mint A vault (only when `token_pair` is
+    ///     set; a token account of mint A owned by the pool PDA, under the
+    ///     token program)
+    /// 16. `[writable]` The protocol stats PDA (optional, counts the new pool;
+    ///     right after the accounts above that are passed)
     InitializePool {
         /// Initial amount of SOL to deposit
-        sol_amount: u64,
+        sol_amount: Lamports,
         /// Initial amount of SPL tokens to deposit
-        token_amount: u64,
+        token_amount: TokenAmount,
         /// Trading fee percentage in basis points (1 bp = 0.01%)
-        fee_rate: u64,
+        fee_rate: Bps,
+        /// Whether LP tokens are soulbound (optional, defaults to false)
+        non_transferable_lp: bool,
+        /// Whether the pool pairs two SPL mints (optional, defaults to false)
+        token_pair: bool,
+        /// Whether the SOL vault holds wrapped SOL (optional, defaults to false)
+        wsol: bool,
+        /// Pricing curve (optional, defaults to constant product)
+        curve_type: CurveType,
+        /// StableSwap amplification coefficient (optional, defaults to 0)
+        amplification: u64,
+        /// Virtual token reserve of offset pools (optional, defaults to 0)
+        token_offset: u64,
+        /// Unix time before which no swap executes (optional, defaults to 0)
+        trade_start_timestamp: i64,
+        /// Seconds after trade start during which buys are capped, at most
+        /// `MAX_LAUNCH_WINDOW_SECONDS` (optional, defaults to 0)
+        launch_window_seconds: u64,
+        /// Most tokens a single buy may take during the launch window, 0 for
+        /// no cap (optional, defaults to 0)
+        max_swap_per_wallet_during_launch: u64,
+        /// Trading fee at trade start in basis points, above `fee_rate` and at
+        /// most `MAX_LAUNCH_FEE_BPS`; 0 for no launch fee (optional, defaults to 0)
+        launch_fee_bps: u64,
+        /// Seconds over which the launch fee decays linearly to `fee_rate`, at
+        /// most `MAX_LAUNCH_WINDOW_SECONDS` (optional, defaults to 0)
+        launch_fee_decay_seconds: u64,
+        /// SOL (or mint A) weight of weighted pools in basis points, the token
+        /// taking the rest (optional, defaults to 0)
+        sol_weight_bps: u64,
+        /// Virtual SOL (or mint A) reserve of offset pools (optional, defaults to 0)
+        sol_offset: u64,
+        /// SOL (or mint A) reserve at which an offset pool's bonding curve
+        /// stops trading until it graduates, 0 for none (optional, defaults to 0)
+        graduation_threshold: u64,
     },
 
     /// Executes a token swap between SOL and SPL tokens
     /// 
-    /// Allows users to trade between SOL and the pool's SPL token.
-    /// Implements constant product AMM formula (x * y = k).
+    /// Allows users to trade between SOL and the pool's SPL token, priced on
+    /// the pool's curve (constant product `x * y = k`, or StableSwap).
+    /// 
+    /// The output is priced on what the pool's vault actually receives, so a
+    /// Token-2022 transfer fee on the input is borne by the trader.
+    /// 
+    /// Writes a `SwapResult` to return data.
     /// 
     /// Accounts expected:
     /// 0. `[signer]` The user performing the swap
     /// 1. `[writable]` The pool state account (stores reserves)
-    /// 2. `[writable]` User's SOL account (system account)
+    /// 2. `[writable]` User's SOL account (system account), their wSOL token
+    ///    account for wSOL pools, or their mint A token account for token pair
+    ///    pools (where "SOL" below means side A)
     /// 3. `[writable]` User's token account (SPL token account)
     /// 4. `[writable]` Pool's token account (holds pool's SPL tokens)
     /// 5. `[]` Token program (for SPL token operations)
+    /// 6. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools (under the same token program as the pool's token)
+    /// 7. `[writable]` Platform fee account (optional, required when `platform_fee_bps > 0`;
+    ///    a system account for SOL output or a token account for token output)
+    /// 8. `[]` The protocol config PDA (optional, required when `platform_fee_bps > 0` or
+    ///    `referral_fee_share_bps > 0`)
+    /// 9. `[writable]` Referrer account (optional, required when `referral_fee_share_bps > 0`;
+    ///    a system account for SOL input or a token account for token input)
+    /// 10. `[]` The instructions sysvar (optional, required when `sandwich_protection` is set)
+    /// 11. `[]` The pool's oracle price account (optional, required when the
+    ///     pool has a price oracle)
+    /// 12. `[writable]` Optional companion accounts, in any order, after the
+    ///     accounts above that are present:
+    ///     - The protocol stats PDA (aggregates volume and fees)
+    ///     - The pool's observations PDA (records a price observation)
+    ///     - The pool's volume stats PDA (adds to the hourly volume bucket)
+    ///     - The token mint (required with observations for interest-bearing pools)
     Swap {
         /// Amount of input token (SOL or SPL) to swap
         amount_in: u64,
@@ -51,6 +201,15 @@ pub enum TokenExchangeInstruction {
         minimum_amount_out: u64,
         /// Direction of the swap (true = SOL→Token, false = Token→SOL)
         is_sol_input: bool,
+        /// Integrator fee in basis points taken from the output, at most the protocol
+        /// config's `max_platform_fee_bps` (optional, defaults to 0)
+        platform_fee_bps: Bps,
+        /// Share of the trading fee paid to the referrer, in basis points, at most the
+        /// protocol config's `max_referral_fee_share_bps` (optional, defaults to 0)
+        referral_fee_share_bps: Bps,
+        /// Fails the swap if any other instruction in the transaction references
+        /// the pool (optional, defaults to false)
+        sandwich_protection: bool,
     },
 
     /// Adds liquidity to the pool
@@ -58,22 +217,47 @@ pub enum TokenExchangeInstruction {
     /// Allows liquidity providers to deposit both SOL and SPL tokens
     /// in exchange for LP tokens representing their share of the pool.
     /// 
+    /// LP tokens are minted for the tokens the pool's vault actually
+    /// receives, net of any Token-2022 transfer fee.
+    /// 
+    /// By default both amounts are taken as given, and whatever exceeds the
+    /// pool ratio is donated to the pool. With `match_pool_ratio` they are
+    /// the desired amounts instead: one side is scaled down to the pool ratio
+    /// and only that much is transferred, failing if it falls below the
+    /// side's minimum.
+    /// 
+    /// Writes a `LiquidityResult` to return data.
+    /// 
     /// Accounts expected:
     /// 0. `[signer]` The liquidity provider
     /// 1. `[writable]` The pool state account
-    /// 2. `[writable]` Provider's SOL account (system account)
+    /// 2. `[writable]` Provider's SOL account (system account), their wSOL
+    ///    token account for wSOL pools, or their mint A token account for
+    ///    token pair pools (where "SOL" means side A)
     /// 3. `[writable]` Provider's token account (SPL tokens to deposit)
     /// 4. `[writable]` Pool's token account
     /// 5. `[writable]` Provider's LP token account (to receive LP tokens)
     /// 6. `[writable]` LP token mint
     /// 7. `[]` Token program
+    /// 8. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 9. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[]` The provider's allowlist PDA (deposit-permissioned pools)
+    ///    - `[writable]` The provider's position PDA (credited the minted LP tokens)
     AddLiquidity {
         /// Amount of SOL to deposit
-        sol_amount: u64,
+        sol_amount: Lamports,
         /// Amount of SPL tokens to deposit
-        token_amount: u64,
+        token_amount: TokenAmount,
         /// Minimum LP tokens to accept (slippage protection)
-        minimum_lp_tokens: u64,
+        minimum_lp_tokens: LpAmount,
+        /// Whether to deposit only the pool-ratio part of the amounts (optional, defaults to false)
+        match_pool_ratio: bool,
+        /// Minimum SOL to deposit when matching the pool ratio (optional, defaults to 0)
+        minimum_sol: Lamports,
+        /// Minimum tokens to deposit when matching the pool ratio (optional, defaults to 0)
+        minimum_token: TokenAmount,
     },
 
     /// Removes liquidity from the pool
@@ -81,71 +265,2379 @@ pub enum TokenExchangeInstruction {
     /// Allows liquidity providers to burn their LP tokens
     /// and withdraw their share of SOL and SPL tokens.
     /// 
+    /// Writes a `LiquidityResult` to return data.
+    /// 
     /// Accounts expected:
     /// 0. `[signer]` The liquidity provider
     /// 1. `[writable]` The pool state account
-    /// 2. `[writable]` Provider's SOL account (to receive SOL)
+    /// 2. `[writable]` Provider's SOL account (to receive SOL), their wSOL token
+    ///    account for wSOL pools, or their mint A token account for token pair
+    ///    pools (where "SOL" means side A)
     /// 3. `[writable]` Provider's token account (to receive tokens)
     /// 4. `[writable]` Pool's token account
     /// 5. `[writable]` Provider's LP token account (tokens to burn)
     /// 6. `[writable]` LP token mint
     /// 7. `[]` Token program
+    /// 8. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 9. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[writable]` The provider's position PDA (debited the burned LP tokens)
     RemoveLiquidity {
         /// Amount of LP tokens to burn
-        lp_tokens: u64,
+        lp_tokens: LpAmount,
         /// Minimum SOL to accept (slippage protection)
-        minimum_sol: u64,
+        minimum_sol: Lamports,
         /// Minimum tokens to accept (slippage protection)
-        minimum_token: u64,
+        minimum_token: TokenAmount,
     },
-}
 
-impl TokenExchangeInstruction {
-    /// Deserializes a byte buffer into a TokenExchangeInstruction
+    /// Creates the program-wide statistics account
     /// 
-    /// The first byte (tag) determines which instruction variant to deserialize.
-    /// Remaining bytes are parsed according to the instruction's parameters.
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(match tag {
-            0 => Self::InitializePool {
-                sol_amount: Self::unpack_u64(rest, 0)?,
-                token_amount: Self::unpack_u64(rest, 8)?,
-                fee_rate: Self::unpack_u64(rest, 16)?,
-            },
-            1 => Self::Swap {
-                amount_in: Self::unpack_u64(rest, 0)?,
-                minimum_amount_out: Self::unpack_u64(rest, 8)?,
-                is_sol_input: rest[16] != 0,
-            },
-            2 => Self::AddLiquidity {
-                sol_amount: Self::unpack_u64(rest, 0)?,
-                token_amount: Self::unpack_u64(rest, 8)?,
-                minimum_lp_tokens: Self::unpack_u64(rest, 16)?,
-            },
-            3 => Self::RemoveLiquidity {
-                lp_tokens: Self::unpack_u64(rest, 0)?,
-                minimum_sol: Self::unpack_u64(rest, 8)?,
-                minimum_token: Self::unpack_u64(rest, 16)?,
-            },
-            _ => return Err(ProgramError::InvalidInstructionData),
-        })
-    }
+    /// The stats account is a PDA derived from `["protocol_stats"]` and only
+    /// needs to be created once per program deployment.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer funding the account's rent
+    /// 1. `[writable]` The protocol stats PDA
+    /// 2. `[]` The system program
+    InitializeProtocolStats,
 
-    /// Helper function to deserialize a u64 from a byte slice
+    /// Creates a pool's price observations ring buffer
     /// 
-    /// # Arguments
-    /// * `input` - The byte slice containing the u64
-    /// * `start` - Starting position in the slice
+    /// The observations account is a PDA derived from `["observations", pool]`.
+    /// Once created, swaps that pass it record one price observation per slot.
     /// 
-    /// # Returns
-    /// * `Result<u64, ProgramError>` - The deserialized number or an error
-    fn unpack_u64(input: &[u8], start: usize) -> Result<u64, ProgramError> {
-        let value = input
-            .get(start..start + 8)
-            .and_then(|slice| slice.try_into().ok())
-            .map(u64::from_le_bytes)
-            .ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(value)
-    }
-} 
\ No newline at end of file
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer funding the account's rent
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The observations PDA
+    /// 3. `[]` The system program
+    InitializeObservations {
+        /// Number of observations the buffer holds initially
+        cardinality: u16,
+    },
+
+    /// Grows a pool's observations ring buffer
+    /// 
+    /// Anyone may pay to extend the buffer so longer TWAP windows remain
+    /// available. The cardinality can only increase.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer funding the additional rent
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The observations PDA
+    /// 3. `[]` The system program
+    IncreaseObservationCardinality {
+        /// New number of observations the buffer should hold
+        new_cardinality: u16,
+    },
+
+    /// Migrates liquidity from one pool to another atomically
+    /// 
+    /// Burns the provider's LP tokens in the source pool and deposits the
+    /// released assets into a destination pool trading the same token.
+    /// Any amount the destination ratio cannot absorb is refunded. Both
+    /// pools must hold SOL the same way (both native or both wSOL).
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The liquidity provider
+    /// 1. `[writable]` The source pool state account
+    /// 2. `[writable]` Source pool's token account
+    /// 3. `[writable]` Source LP token mint
+    /// 4. `[writable]` Provider's source LP token account (tokens to burn)
+    /// 5. `[writable]` The destination pool state account
+    /// 6. `[writable]` Destination pool's token account
+    /// 7. `[writable]` Destination LP token mint
+    /// 8. `[writable]` Provider's destination LP token account (to receive LP tokens)
+    /// 9. `[writable]` Provider's SOL account (receives any SOL refund), or their
+    ///    wSOL token account for wSOL pools
+    /// 10. `[writable]` Provider's token account (receives any token refund)
+    /// 11. `[]` Token program
+    /// 12. `[writable]` The source pool's SOL vault PDA
+    /// 13. `[writable]` The destination pool's SOL vault PDA
+    /// 14. Optional trailing accounts, in any order:
+    ///     - `[]` LP token program (when an LP mint is owned by a different token program)
+    ///     - `[]` The provider's allowlist PDA for the destination pool (deposit-permissioned pools)
+    MigrateLiquidity {
+        /// Amount of source LP tokens to burn
+        lp_tokens: LpAmount,
+        /// Minimum SOL released from the source pool (slippage protection)
+        minimum_sol: Lamports,
+        /// Minimum tokens released from the source pool (slippage protection)
+        minimum_token: TokenAmount,
+        /// Minimum destination LP tokens to accept (slippage protection)
+        minimum_lp_tokens: LpAmount,
+    },
+
+    /// Places a resting limit order against a pool
+    /// 
+    /// Escrows `amount` of the input asset in a per-order PDA derived from
+    /// `["limit_order", pool, owner, order_id]`. SOL is held as the order's
+    /// lamports; tokens are held in an escrow token account owned by the order PDA.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The order owner
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The limit order PDA
+    /// 3. `[writable]` Owner's token account (source of tokens when selling tokens)
+    /// 4. `[writable]` Order escrow token account (owned by the order PDA)
+    /// 5. `[]` Token program
+    /// 6. `[]` The system program
+    PlaceLimitOrder {
+        /// Owner-chosen identifier, unique per owner and pool
+        order_id: u64,
+        /// Amount of input asset to sell
+        amount: u64,
+        /// Minimum output per input, scaled by `LIMIT_PRICE_SCALE`
+        limit_price: u64,
+        /// Direction of the order (true = SOL→Token, false = Token→SOL)
+        is_sol_input: bool,
+        /// Slot after which the order expires (optional, 0 = good-till-cancelled)
+        expiry_slot: u64,
+    },
+
+    /// Fills part or all of a resting limit order against the pool
+    /// 
+    /// Anyone may fill an order once the pool price satisfies its limit price.
+    /// Each fill executes `fill_amount` of the remaining input as a swap and
+    /// delivers the output to the owner; the order keeps resting until its
+    /// remaining size reaches zero. When the keeper's token account is passed,
+    /// the taker earns the pool's keeper bounty like `MatchLimitOrders` pays.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The taker or keeper executing the fill (writable to
+    ///    receive SOL bounties)
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The limit order PDA
+    /// 3. `[writable]` Order escrow token account
+    /// 4. `[writable]` Pool's token account
+    /// 5. `[writable]` Owner's SOL account (receives SOL output)
+    /// 6. `[writable]` Owner's token account (receives token output)
+    /// 7. `[]` Token program
+    /// 8. `[writable]` The pool's SOL vault PDA
+    /// 9. `[writable]` Keeper's token account (optional, receives token bounties)
+    FillLimitOrder {
+        /// Amount of the order's remaining input to fill
+        fill_amount: u64,
+    },
+
+    /// Changes the price and/or remaining size of a resting limit order
+    /// 
+    /// Growing the order escrows the additional input from the owner;
+    /// shrinking it refunds the difference.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The order owner
+    /// 1. `[writable]` The limit order PDA
+    /// 2. `[writable]` Owner's token account (source or destination of the size change)
+    /// 3. `[writable]` Order escrow token account
+    /// 4. `[]` Token program
+    /// 5. `[]` The system program
+    ModifyLimitOrder {
+        /// New minimum output per input, scaled by `LIMIT_PRICE_SCALE`
+        new_limit_price: u64,
+        /// New remaining input size
+        new_remaining_amount: u64,
+    },
+
+    /// Cancels a limit order, returning escrowed funds and rent to the owner
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The order owner (receives SOL escrow and rent)
+    /// 1. `[writable]` The limit order PDA (closed)
+    /// 2. `[writable]` Owner's token account (receives token escrow)
+    /// 3. `[writable]` Order escrow token account (closed for token orders)
+    /// 4. `[]` Token program
+    CancelLimitOrder,
+
+    /// Cancels an expired limit order on the owner's behalf
+    /// 
+    /// Anyone may crank this once the order's expiry slot has passed. The
+    /// escrow and remaining rent go back to the owner, minus a small rent
+    /// rebate paid to the keeper.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The keeper (receives the rent rebate)
+    /// 1. `[writable]` The limit order PDA (closed)
+    /// 2. `[writable]` The order owner (receives SOL escrow and remaining rent)
+    /// 3. `[writable]` Owner's token account (receives token escrow)
+    /// 4. `[writable]` Order escrow token account (closed for token orders)
+    /// 5. `[]` Token program
+    CancelExpiredLimitOrder,
+
+    /// Fills every provided limit order whose limit price the pool crosses
+    /// 
+    /// Permissionless crank. Each crossed order is filled for as much of its
+    /// remaining size as the pool price allows; orders that are not crossed
+    /// or have expired are skipped. The keeper earns the pool's configured
+    /// share of the trading fee on every fill, paid in the order's input asset.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The keeper (receives SOL bounties)
+    /// 1. `[writable]` Keeper's token account (receives token bounties)
+    /// 2. `[writable]` The pool state account
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[]` Token program
+    /// 5. `[writable]` The pool's SOL vault PDA
+    /// 6. Then, for each order, four accounts:
+    ///    - `[writable]` The limit order PDA
+    ///    - `[writable]` Order escrow token account
+    ///    - `[writable]` Owner's SOL account
+    ///    - `[writable]` Owner's token account
+    MatchLimitOrders,
+
+    /// Sets the share of the trading fee paid to keepers that fill limit orders
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` The protocol config PDA
+    UpdateKeeperBounty {
+        /// Keeper share of the trading fee, at most the protocol config's `max_keeper_bounty_bps`
+        keeper_bounty_bps: Bps,
+    },
+
+    /// Places a stop-loss order against a pool
+    /// 
+    /// Escrows the input exactly like `PlaceLimitOrder`. Once the pool price
+    /// (output per input) falls to `stop_price`, keepers may execute the order
+    /// at market through `FillLimitOrder` or `MatchLimitOrders`, accepting at
+    /// most `max_slippage_bps` below the stop price.
+    /// 
+    /// Accounts expected: same as `PlaceLimitOrder`.
+    PlaceStopOrder {
+        /// Owner-chosen identifier, unique per owner and pool
+        order_id: u64,
+        /// Amount of input asset to sell
+        amount: u64,
+        /// Trigger price (output per input), scaled by `LIMIT_PRICE_SCALE`
+        stop_price: u64,
+        /// Maximum execution shortfall below the stop price, in basis points
+        max_slippage_bps: u64,
+        /// Direction of the order (true = SOL→Token, false = Token→SOL)
+        is_sol_input: bool,
+        /// Slot after which the order expires (optional, 0 = good-till-cancelled)
+        expiry_slot: u64,
+    },
+
+    /// Places an order conditioned on an external oracle price
+    /// 
+    /// Escrows the input like `PlaceLimitOrder`, but the order only executes
+    /// once the oracle price crosses `trigger_price` (e.g. "buy if SOL/USD < X").
+    /// Execution happens at market through `ExecuteOracleOrder`, never below
+    /// `limit_price`.
+    /// 
+    /// Accounts expected:
+    /// 0-6. Same as `PlaceLimitOrder`
+    /// 7. `[]` The oracle price account (Pyth)
+    PlaceOracleOrder {
+        /// Owner-chosen identifier, unique per owner and pool
+        order_id: u64,
+        /// Amount of input asset to sell
+        amount: u64,
+        /// Minimum output per input, scaled by `LIMIT_PRICE_SCALE` (slippage protection)
+        limit_price: u64,
+        /// Oracle trigger price, scaled by `LIMIT_PRICE_SCALE`
+        trigger_price: u64,
+        /// Trigger when the oracle is at or below (true) or at or above (false) the trigger
+        trigger_below: bool,
+        /// Direction of the order (true = SOL→Token, false = Token→SOL)
+        is_sol_input: bool,
+        /// Slot after which the order expires (optional, 0 = good-till-cancelled)
+        expiry_slot: u64,
+    },
+
+    /// Executes a triggered oracle-conditioned order against the pool
+    /// 
+    /// Permissionless crank. Checks the order's oracle for a fresh price past
+    /// the trigger, then fills as much of the order as its limit price allows,
+    /// paying the keeper the pool's bounty share of the trading fee.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The keeper (receives SOL bounties)
+    /// 1. `[writable]` Keeper's token account (receives token bounties)
+    /// 2. `[writable]` The pool state account
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[]` Token program
+    /// 5. `[writable]` The order PDA
+    /// 6. `[writable]` Order escrow token account
+    /// 7. `[writable]` Owner's SOL account
+    /// 8. `[writable]` Owner's token account
+    /// 9. `[]` The oracle price account recorded on the order
+    /// 10. `[writable]` The pool's SOL vault PDA
+    ExecuteOracleOrder,
+
+    /// Creates a recurring liquidity provision schedule
+    /// 
+    /// Escrows `tranche_count` tranches of SOL and tokens in a schedule PDA
+    /// derived from `["liquidity_schedule", pool, owner, schedule_id]`. The
+    /// first tranche is due immediately, then one every `interval_slots`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The schedule owner
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The liquidity schedule PDA
+    /// 3. `[writable]` Owner's token account (source of escrowed tokens)
+    /// 4. `[writable]` Schedule escrow token account (owned by the schedule PDA)
+    /// 5. `[]` Token program
+    /// 6. `[]` The system program
+    CreateLiquiditySchedule {
+        /// Owner-chosen identifier, unique per owner and pool
+        schedule_id: u64,
+        /// SOL offered per tranche
+        sol_per_tranche: u64,
+        /// Tokens offered per tranche
+        token_per_tranche: u64,
+        /// Number of tranches to escrow
+        tranche_count: u64,
+        /// Slots between tranches
+        interval_slots: u64,
+    },
+
+    /// Deposits the next due tranche of a liquidity schedule
+    /// 
+    /// Permissionless crank. The tranche is fitted to the pool's current
+    /// ratio; anything the ratio does not absorb stays in escrow and is
+    /// returned when the schedule closes.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The keeper
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The liquidity schedule PDA
+    /// 3. `[writable]` Schedule escrow token account
+    /// 4. `[writable]` Pool's token account
+    /// 5. `[writable]` Owner's LP token account (to receive LP tokens)
+    /// 6. `[writable]` LP token mint
+    /// 7. `[]` Token program
+    /// 8. `[writable]` The pool's SOL vault PDA
+    /// 9. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[]` The schedule owner's allowlist PDA (deposit-permissioned pools)
+    ExecuteLiquidityTranche,
+
+    /// Closes a liquidity schedule, refunding unspent escrow and rent
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The schedule owner
+    /// 1. `[writable]` The liquidity schedule PDA (closed)
+    /// 2. `[writable]` Owner's token account (receives remaining tokens)
+    /// 3. `[writable]` Schedule escrow token account (closed)
+    /// 4. `[]` Token program
+    CloseLiquiditySchedule,
+
+    /// Grants an operator key a bounded trading allowance
+    /// 
+    /// Creates the allowance PDA derived from `["trading_allowance", owner, operator]`.
+    /// To let the operator sell tokens, the owner approves the allowance PDA
+    /// as delegate on their token account; to let it sell SOL, the owner
+    /// transfers lamports to the allowance PDA.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The owner granting the allowance
+    /// 1. `[]` The operator key
+    /// 2. `[writable]` The trading allowance PDA
+    /// 3. `[]` The system program
+    /// 4. `[]` One or more pool state accounts the operator may trade in
+    ///    (up to `TradingAllowance::MAX_POOLS`)
+    GrantTradingAllowance {
+        /// SOL-side volume cap per window, in lamports
+        max_notional_per_window: u64,
+        /// Length of a spending window in slots
+        window_slots: u64,
+    },
+
+    /// Revokes a trading allowance
+    /// 
+    /// Closes the allowance PDA, returning its rent and any unspent SOL
+    /// trading balance to the owner.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The owner
+    /// 1. `[writable]` The trading allowance PDA (closed)
+    RevokeTradingAllowance,
+
+    /// Swaps on the owner's behalf within a trading allowance
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The operator
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The trading allowance PDA
+    /// 3. `[writable]` Owner's SOL account (receives SOL output)
+    /// 4. `[writable]` Owner's token account (delegated to the allowance PDA for token input)
+    /// 5. `[writable]` Pool's token account
+    /// 6. `[]` Token program
+    /// 7. `[writable]` The pool's SOL vault PDA
+    /// 8. `[writable]` Optional companion accounts, in any order:
+    ///    - The protocol stats PDA (aggregates volume and fees)
+    ///    - The pool's observations PDA (records a price observation)
+    ///    - The pool's volume stats PDA (adds to the hourly volume bucket)
+    ///    - The token mint (required with observations for interest-bearing pools)
+    OperatorSwap {
+        /// Amount of input token (SOL or SPL) to swap
+        amount_in: u64,
+        /// Minimum amount of output token to receive (slippage protection)
+        minimum_amount_out: u64,
+        /// Direction of the swap (true = SOL→Token, false = Token→SOL)
+        is_sol_input: bool,
+    },
+
+    /// Executes a swap order signed off-chain by its owner
+    /// 
+    /// Any relayer may submit the order. The instruction immediately before
+    /// this one must be an ed25519 program instruction verifying the owner's
+    /// signature over `SignedOrder::message`. The owner's order authority PDA
+    /// (`["order_authority", owner]`) spends the input: as SPL delegate on the
+    /// owner's token account, or from its own lamports for SOL input. The
+    /// order's nonce must match the owner's user stats PDA, which is then
+    /// incremented so the signature cannot be replayed. The order's
+    /// `relayer_fee` is paid to the relayer out of the output;
+    /// `minimum_amount_out` bounds what remains.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The relayer
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The owner's order authority PDA
+    /// 3. `[writable]` The owner's user stats PDA
+    /// 4. `[writable]` Owner's SOL account (receives SOL output)
+    /// 5. `[writable]` Owner's token account
+    /// 6. `[writable]` Pool's token account
+    /// 7. `[]` Token program
+    /// 8. `[]` The system program
+    /// 9. `[]` The instructions sysvar
+    /// 10. `[writable]` The pool's SOL vault PDA
+    /// 11. `[writable]` Relayer's token account (required when the order sells SOL
+    ///     with a non-zero `relayer_fee`; SOL fees go to the relayer account itself)
+    /// 12. `[writable]` Optional companion accounts, in any order, after the relayer
+    ///     token account (or from index 11 when it is omitted):
+    ///     - The protocol stats PDA (aggregates volume and fees)
+    ///     - The pool's observations PDA (records a price observation)
+    ///     - The token mint (required with observations for interest-bearing pools)
+    ExecuteSignedOrder {
+        /// The order the owner signed
+        order: SignedOrder,
+    },
+
+    /// Registers a keeper with a pool
+    /// 
+    /// Creates the registration PDA derived from `["keeper_registration", pool, keeper]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (pays the registration's rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[]` The keeper key
+    /// 3. `[writable]` The keeper registration PDA
+    /// 4. `[]` The system program
+    RegisterKeeper,
+
+    /// Removes a keeper's registration with a pool
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (receives the rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The keeper registration PDA (closed)
+    DeregisterKeeper,
+
+    /// Runs a closed arbitrage loop across two or more pools
+    /// 
+    /// Pools are traded in pairs sharing a token mint: the first pool of each
+    /// pair sells SOL for tokens, the second sells those tokens back for SOL.
+    /// The keeper's SOL enters the first pool and the proceeds of the last
+    /// pool return to the keeper, who must be registered with every pool.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The keeper
+    /// 1. `[]` Token program
+    /// 2. Then, for each pool in loop order (an even number, at least two), four accounts:
+    ///    - `[writable]` The pool state account
+    ///    - `[writable]` Pool's token account
+    ///    - `[writable]` The pool's SOL vault PDA
+    ///    - `[]` The keeper's registration PDA for that pool
+    /// 3. `[writable]` The keeper's wSOL token account (only for loops of wSOL
+    ///    pools, which must not be mixed with native SOL pools)
+    ArbitragePools {
+        /// SOL the keeper puts into the loop
+        amount_in: u64,
+        /// Minimum SOL the loop must return on top of `amount_in`
+        minimum_profit: u64,
+    },
+
+    /// Settles a closed arbitrage loop internally for the pools' LPs
+    /// 
+    /// Runs the same loop as `ArbitragePools`, but the last pool's SOL
+    /// proceeds fund the first pool's input and the profit is split evenly
+    /// across the pools' SOL reserves. Permissionless, since no value leaves
+    /// the pools.
+    /// 
+    /// Accounts expected:
+    /// 0. `[]` Token program
+    /// 1. Then, for each pool in loop order (an even number, at least two), three accounts:
+    ///    - `[writable]` The pool state account
+    ///    - `[writable]` Pool's token account
+    ///    - `[writable]` The pool's SOL vault PDA
+    CaptureArbitrage {
+        /// SOL routed through the loop
+        amount_in: u64,
+        /// Minimum SOL the loop must return on top of `amount_in`
+        minimum_profit: u64,
+    },
+
+    /// Creates a user's stats account
+    /// 
+    /// The stats account is a PDA derived from `["user_stats", owner]` and
+    /// must exist before the owner's signed orders can execute.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer funding the account's rent
+    /// 1. `[]` The user the stats belong to
+    /// 2. `[writable]` The user stats PDA
+    /// 3. `[]` The system program
+    InitializeUserStats,
+
+    /// Opts a pool in to sealed orders by creating its committee
+    /// 
+    /// Creates the committee PDA derived from `["sealed_committee", pool]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (pays the committee's rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The sealed order committee PDA
+    /// 3. `[]` The system program
+    /// 4. `[]` One or more committee member keys
+    ///    (up to `SealedOrderCommittee::MAX_MEMBERS`)
+    ConfigureSealedOrders {
+        /// Member signatures required to execute an order
+        threshold: u8,
+        /// Slots between submission and earliest execution
+        reveal_delay_slots: u64,
+    },
+
+    /// Submits a sealed order, escrowing up to `escrow_amount` of input
+    /// 
+    /// Creates the order PDA derived from `["sealed_order", pool, owner, order_id]`.
+    /// The order's terms stay hidden until the committee reveals them.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The order owner
+    /// 1. `[]` The pool state account
+    /// 2. `[]` The pool's sealed order committee PDA
+    /// 3. `[writable]` The sealed order PDA
+    /// 4. `[writable]` Owner's token account (source of escrowed tokens)
+    /// 5. `[writable]` Order escrow token account (owned by the order PDA)
+    /// 6. `[]` Token program
+    /// 7. `[]` The system program
+    SubmitSealedOrder {
+        /// Owner-chosen identifier, unique per owner and pool
+        order_id: u64,
+        /// Input to escrow, bounding the hidden order size
+        escrow_amount: u64,
+        /// Direction of the order (true = sells SOL, false = sells tokens)
+        is_sol_input: bool,
+        /// SHA-256 commitment to the order's `SealedOrderTerms`
+        commitment: [u8; 32],
+        /// Order terms encrypted to the committee (trailing bytes)
+        ciphertext: Vec<u8>,
+    },
+
+    /// Reveals and executes a sealed order once its delay has passed
+    /// 
+    /// Requires `threshold` committee members to sign. If the pool cannot
+    /// meet the revealed minimum output, the order is refunded instead.
+    /// Either way, unused escrow and the order's rent return to the owner.
+    /// 
+    /// Accounts expected:
+    /// 0. `[writable]` The pool state account
+    /// 1. `[]` The pool's sealed order committee PDA
+    /// 2. `[writable]` The sealed order PDA (closed)
+    /// 3. `[writable]` Order escrow token account (closed for token orders)
+    /// 4. `[writable]` Pool's token account
+    /// 5. `[writable]` Owner's SOL account
+    /// 6. `[writable]` Owner's token account
+    /// 7. `[]` Token program
+    /// 8. `[writable]` The pool's SOL vault PDA
+    /// 9. `[signer]` Committee members, at least `threshold` of them
+    ExecuteSealedOrder {
+        /// The revealed terms
+        terms: SealedOrderTerms,
+    },
+
+    /// Cancels a sealed order the committee failed to execute in time
+    /// 
+    /// Allowed once `SEALED_ORDER_EXECUTION_WINDOW_SLOTS` have passed since
+    /// the order's reveal slot.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The order owner
+    /// 1. `[writable]` The sealed order PDA (closed)
+    /// 2. `[writable]` Owner's token account (receives escrowed tokens)
+    /// 3. `[writable]` Order escrow token account (closed for token orders)
+    /// 4. `[]` Token program
+    CancelSealedOrder,
+
+    /// Switches a pool into or out of rebasing mode
+    /// 
+    /// In rebasing mode every operation first re-reads the token reserve
+    /// from the pinned vault, so tokens whose balances change out-of-band
+    /// cannot drift from the stored reserve. Operations must then pass the
+    /// pinned vault as the pool's token account.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` Pool's token account (pinned as the vault when enabling)
+    SetRebasingMode {
+        /// Whether the pool should track its vault balance
+        enabled: bool,
+    },
+
+    /// Restricts or reopens liquidity deposits into a pool
+    /// 
+    /// While deposit-permissioned, swaps stay open to everyone but adding
+    /// liquidity requires the provider's allowlist PDA among the trailing
+    /// accounts of `AddLiquidity`, `MigrateLiquidity` (destination pool) and
+    /// `ExecuteLiquidityTranche` (schedule owner).
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    SetDepositPermissioned {
+        /// Whether only allowlisted providers may add liquidity
+        enabled: bool,
+    },
+
+    /// Allowlists a liquidity provider on a pool
+    /// 
+    /// Creates the allowlist PDA derived from `["lp_allowlist", pool, provider]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (pays the allowlist entry's rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[]` The provider key
+    /// 3. `[writable]` The allowlist PDA
+    /// 4. `[]` The system program
+    AllowLiquidityProvider,
+
+    /// Removes a liquidity provider from a pool's allowlist
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (receives the rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The allowlist PDA (closed)
+    DisallowLiquidityProvider,
+
+    /// Sets a pool's display name and metadata URI
+    /// 
+    /// Creates the metadata PDA derived from `["pool_metadata", pool]` on
+    /// first use, so creators can publish metadata in the same transaction
+    /// as `InitializePool`; later calls overwrite it.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (pays the metadata's rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The pool metadata PDA
+    /// 3. `[]` The system program
+    SetPoolMetadata {
+        /// Short display name, at most `POOL_NAME_MAX_LEN` bytes
+        name: String,
+        /// URI of off-chain pool details, at most `POOL_URI_MAX_LEN` bytes
+        uri: String,
+    },
+
+    /// Marks a pool as deprecated (withdraw-only) or restores it
+    /// 
+    /// A deprecated pool rejects swaps, order placement and fills, and
+    /// deposits, while `RemoveLiquidity`, migrations out of the pool and
+    /// order cancellations keep working, so liquidity can move to a
+    /// successor pool.
+    /// 
+    /// The protocol authority can sunset any pool, e.g. ahead of a v2
+    /// migration, by passing the protocol config in place of the pool
+    /// authority's signature.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority, or the protocol authority
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` (Optional) The protocol config PDA, when signed by the protocol authority
+    SetPoolDeprecated {
+        /// Whether the pool should be withdraw-only
+        deprecated: bool,
+    },
+
+    /// Creates a pool's volume stats ring buffer
+    /// 
+    /// The volume stats account is a PDA derived from `["volume_stats", pool]`.
+    /// Once created, swaps that pass it add their SOL-valued volume and fee
+    /// to the current hourly bucket.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer funding the account's rent
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The volume stats PDA
+    /// 3. `[]` The system program
+    InitializeVolumeStats,
+
+    /// Buys an exact amount of output, paying at most `maximum_amount_in`
+    /// 
+    /// The input is the smallest amount for which the pool's curve (with
+    /// the pool fee) yields at least `amount_out`, so an
+    /// invoice denominated in the output asset is paid exactly.
+    /// 
+    /// Writes a `SwapResult` to return data.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The user performing the swap
+    /// 1. `[writable]` The pool state account (stores reserves)
+    /// 2. `[writable]` User's SOL account (system account), their wSOL token
+    ///    account for wSOL pools, or their mint A token account for token pair pools
+    /// 3. `[writable]` User's token account (SPL token account)
+    /// 4. `[writable]` Pool's token account (holds pool's SPL tokens)
+    /// 5. `[]` Token program (for SPL token operations)
+    /// 6. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 7. `[]` The pool's oracle price account (optional, required when the
+    ///    pool has a price oracle)
+    /// 8. `[writable]` Optional companion accounts, as for `Swap`
+    SwapExactOut {
+        /// Exact amount of output token (SOL or SPL) to receive
+        amount_out: u64,
+        /// Maximum amount of input token to pay (slippage protection)
+        maximum_amount_in: u64,
+        /// Direction of the swap (true = Token→SOL, false = SOL→Token)
+        is_sol_output: bool,
+    },
+
+    /// Creates the protocol configuration PDA naming the protocol authority
+    /// 
+    /// The configuration account is a PDA derived from `["protocol_config"]`.
+    /// Only the program's upgrade authority may create it.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The program's upgrade authority (pays the rent)
+    /// 1. `[writable]` The protocol config PDA
+    /// 2. `[]` The program's ProgramData account (records the upgrade authority)
+    /// 3. `[]` The system program
+    InitializeProtocolConfig {
+        /// The protocol authority
+        authority: Pubkey,
+        /// Bounds and defaults for new pools (optional, defaults to the
+        /// built-in bounds with no creation fee)
+        parameters: Option<ProtocolParameters>,
+    },
+
+    /// Sets the share of a pool's trading fee accrued to the protocol
+    /// 
+    /// From then on every swap against the pool sets aside this share of the
+    /// fee charged on its input (1667 bps is one sixth), held in the pool's
+    /// vaults but outside its reserves until `CollectProtocolFees`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The protocol authority
+    /// 1. `[]` The protocol config PDA
+    /// 2. `[writable]` The pool state account
+    SetProtocolFeeShare {
+        /// Share of the trading fee in basis points, at most `MAX_PROTOCOL_FEE_SHARE_BPS`
+        protocol_fee_share_bps: Bps,
+    },
+
+    /// Sweeps a pool's accrued protocol fees to treasury accounts
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The protocol authority
+    /// 1. `[]` The protocol config PDA
+    /// 2. `[writable]` The pool state account
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[writable]` Treasury token account (receives token fees)
+    /// 5. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 6. `[writable]` Treasury SOL account (receives SOL fees), a wSOL token
+    ///    account for wSOL pools, or a mint A token account for token pair pools
+    /// 7. `[]` Token program
+    CollectProtocolFees,
+
+    /// Updates a pool's trading fee
+    /// 
+    /// The pool keeps its address, which stays derived from the fee tier it
+    /// was created with. Logs the old and new rate.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    UpdateFeeRate {
+        /// New trading fee in basis points, at most `MAX_FEE_RATE_BPS`
+        new_fee_rate: Bps,
+    },
+
+    /// Halts a pool during an incident
+    /// 
+    /// A paused pool rejects swaps, order placement and fills, and deposits
+    /// with `PoolPaused`, while `RemoveLiquidity`, migrations out of the pool
+    /// and order cancellations keep working so LPs can exit.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    PausePool,
+
+    /// Resumes a paused pool
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    UnpausePool,
+
+    /// Lends pool reserves until a `FlashRepay` later in the same transaction
+    /// 
+    /// A top-level `FlashRepay` for the same pool must follow this
+    /// instruction, checked through the instructions sysvar. Until it runs
+    /// every other instruction on the pool fails with `FlashLoanActive`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The borrower
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The vault lent from: the pool's SOL vault PDA (or its
+    ///    mint A vault for token pair pools) when borrowing SOL, else the
+    ///    pool's token account
+    /// 3. `[writable]` Account receiving the loan: a SOL account (a wSOL token
+    ///    account for wSOL pools, a mint A token account for token pair pools)
+    ///    or a token account
+    /// 4. `[]` Token program
+    /// 5. `[]` The instructions sysvar
+    FlashBorrow {
+        /// Amount to borrow, at most the pool's reserve on that side
+        amount: u64,
+        /// Whether to borrow SOL (or mint A) rather than tokens
+        is_sol: bool,
+    },
+
+    /// Repays a pool's outstanding flash loan with its fee
+    /// 
+    /// The borrower pays back the principal plus the pool's trading fee on it,
+    /// rounded up. The fee accrues to LPs, less the protocol's share.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The borrower
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The vault the loan came from
+    /// 3. `[writable]` Account paying the loan back, of the kind that received
+    ///    it (the borrower itself for native SOL)
+    /// 4. `[]` Token program
+    FlashRepay,
+
+    /// Swaps through a chain of pools in one instruction
+    /// 
+    /// Each hop sells the previous hop's output, so hops alternate between
+    /// selling SOL and selling tokens, starting as `is_sol_input` says (e.g.
+    /// token A → SOL → token B across two pools). A hop buying tokens must be
+    /// followed by a pool of the same token. Only the final output is checked
+    /// against `minimum_amount_out`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The user performing the swap
+    /// 1. `[writable]` User's input account: their SOL account (a wSOL token
+    ///    account for wSOL pools) or their token account for the first pool
+    /// 2. `[writable]` User's output account, of the kind the last hop pays
+    /// 3. `[]` Token program
+    /// 4. Then, for each pool in route order (at least one), three accounts:
+    ///    - `[writable]` The pool state account
+    ///    - `[writable]` Pool's token account
+    ///    - `[writable]` The pool's SOL vault PDA
+    RouteSwap {
+        /// Amount of input to swap into the first pool
+        amount_in: u64,
+        /// Minimum output of the last pool (slippage protection for the whole route)
+        minimum_amount_out: u64,
+        /// Whether the first hop sells SOL (true) or tokens (false)
+        is_sol_input: bool,
+    },
+
+    /// Adds liquidity from one side of the pair only
+    /// 
+    /// Half the deposit is swapped into the other asset at the pool's curve
+    /// and fee, and LP tokens are minted for the two halves as a balanced
+    /// deposit. Whatever part of the swapped half the pool ratio does not
+    /// absorb stays in the pool.
+    /// 
+    /// Writes a `LiquidityResult` with the deposited side only to return data.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The liquidity provider
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` Provider's account for the deposited asset: their SOL
+    ///    account (a wSOL token account for wSOL pools, a mint A token account
+    ///    for token pair pools) or their token account
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[writable]` Provider's LP token account (to receive LP tokens)
+    /// 5. `[writable]` LP token mint
+    /// 6. `[]` Token program
+    /// 7. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 8. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[]` The provider's allowlist PDA (deposit-permissioned pools)
+    ///    - `[writable]` The provider's position PDA (credited the minted LP tokens)
+    DepositSingleSided {
+        /// Amount of SOL or tokens to deposit
+        amount: u64,
+        /// Whether the deposit is SOL (true) or tokens (false)
+        is_sol: bool,
+        /// Minimum LP tokens to accept (slippage protection)
+        minimum_lp_tokens: LpAmount,
+    },
+
+    /// Removes liquidity into one side of the pair only
+    /// 
+    /// The LP tokens' share of the unwanted asset is swapped back into the
+    /// pool for the wanted one, at the pool's curve and fee, and the provider
+    /// receives the wanted share plus the swap output. Deprecated and paused
+    /// pools reject it, since it trades; `RemoveLiquidity` still works there.
+    /// 
+    /// Writes a `LiquidityResult` with the wanted side only to return data.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The liquidity provider
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` Provider's account for the wanted asset: their SOL
+    ///    account (a wSOL token account for wSOL pools, a mint A token account
+    ///    for token pair pools) or their token account
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[writable]` Provider's LP token account (tokens to burn)
+    /// 5. `[writable]` LP token mint
+    /// 6. `[]` Token program
+    /// 7. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 8. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[writable]` The provider's position PDA (debited the burned LP tokens)
+    WithdrawSingleSided {
+        /// Amount of LP tokens to burn
+        lp_tokens: LpAmount,
+        /// Minimum total amount of the wanted asset to receive (slippage protection)
+        minimum_amount_out: u64,
+        /// Whether to receive SOL (true) or tokens (false)
+        want_sol: bool,
+    },
+
+    /// Switches a pool into or out of fee-on-transfer mode
+    /// 
+    /// In fee-on-transfer mode every token input is accounted at the change
+    /// in the pool vault's balance across the transfer rather than at the
+    /// instruction amount, so tokens taxed on transfer cannot inflate the
+    /// token reserve. Inputs whose amount is fixed before the transfer
+    /// (exact-output swaps and limit or sealed order fills) fail with
+    /// `TransferFeeNotCovered` when the vault receives less.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    SetFeeOnTransferMode {
+        /// Whether token inputs should be measured at the vault
+        enabled: bool,
+    },
+
+    /// Upgrades a pool state account laid out in an older `StateVersion`
+    /// 
+    /// Every other instruction rejects such pools with `InvalidStateVersion`.
+    /// Anyone may migrate a pool; the payer covers the rent of the larger
+    /// account. Pools already on the current layout are left unchanged.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer (funds the extra rent)
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` The system program
+    MigratePool,
+
+    /// Replaces the protocol authority and the parameters new pools are checked against
+    /// 
+    /// Existing pools keep their fee rate and protocol fee share.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The protocol authority
+    /// 1. `[writable]` The protocol config PDA
+    UpdateProtocolConfig {
+        /// The protocol authority from now on
+        authority: Pubkey,
+        /// Bounds and defaults for new pools
+        parameters: ProtocolParameters,
+    },
+
+    /// Pays a position the trading fees its liquidity has earned
+    /// 
+    /// Settles the fee growth since the position's last checkpoint and
+    /// transfers everything owed from the pool's vaults, leaving the LP
+    /// tokens untouched. Allowed while the pool is paused or deprecated.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The position owner
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The owner's position PDA
+    /// 3. `[writable]` Pool's token account
+    /// 4. `[writable]` Owner's token account (receives token fees)
+    /// 5. `[writable]` The pool's SOL vault PDA, or its mint A vault for token
+    ///    pair pools
+    /// 6. `[writable]` Owner's SOL account (receives SOL fees), a wSOL token
+    ///    account for wSOL pools, or a mint A token account for token pair pools
+    /// 7. `[]` Token program
+    ClaimFees,
+
+    /// Opens a liquidity provider's position in a pool
+    /// 
+    /// The position is a PDA derived from `["position", pool, owner]` and
+    /// starts with no LP tokens, checkpointed at the pool's current fee
+    /// growth. Liquidity instructions credit it once passed along.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner (funds the account's rent)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The position PDA
+    /// 3. `[]` The system program
+    InitializeUserPosition,
+
+    /// Closes a position and returns its rent to the owner
+    /// 
+    /// Fails with `PositionHasUnclaimedFees` until everything owed is claimed.
+    /// LP tokens still credited to the position stop earning position fees;
+    /// the LP tokens themselves are untouched.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner (receives the rent)
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The position PDA (closed)
+    CloseUserPosition,
+
+    /// Creates a pool's reward farm and funds its whole emission
+    /// 
+    /// The farm is a PDA derived from `["farm", pool]`. Its reward and LP
+    /// vaults are token accounts the client creates beforehand, owned by the
+    /// farm PDA. `emission_rate * duration_slots` reward tokens are moved
+    /// into the reward vault and released from the current slot on.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The pool authority (funds the rent and rewards)
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The farm PDA
+    /// 3. `[writable]` The farm's reward vault
+    /// 4. `[]` The farm's LP vault (holds the pool's LP mint)
+    /// 5. `[writable]` Authority's reward token account
+    /// 6. `[]` Token program of the reward mint
+    /// 7. `[]` The system program
+    InitializeFarm {
+        /// Reward tokens released per slot
+        emission_rate: u64,
+        /// Number of slots the emission lasts
+        duration_slots: u64,
+    },
+
+    /// Stakes LP tokens in a pool's farm
+    /// 
+    /// Creates the staker's stake PDA, derived from `["farm_stake", farm,
+    /// owner]`, on first use. Rewards earned so far are settled first.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The staker (funds the stake account's rent)
+    /// 1. `[writable]` The farm PDA
+    /// 2. `[writable]` The staker's stake PDA
+    /// 3. `[writable]` Staker's LP token account
+    /// 4. `[writable]` The farm's LP vault
+    /// 5. `[]` LP token program
+    /// 6. `[]` The system program
+    StakeLp {
+        /// LP tokens to stake
+        amount: LpAmount,
+    },
+
+    /// Withdraws staked LP tokens from a farm
+    /// 
+    /// Rewards earned so far are settled first and stay harvestable.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The staker
+    /// 1. `[writable]` The farm PDA
+    /// 2. `[writable]` The staker's stake PDA
+    /// 3. `[writable]` The farm's LP vault
+    /// 4. `[writable]` Staker's LP token account
+    /// 5. `[]` LP token program
+    UnstakeLp {
+        /// LP tokens to unstake
+        amount: LpAmount,
+    },
+
+    /// Pays a staker the farm rewards they have earned in every reward token
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The staker
+    /// 1. `[writable]` The farm PDA
+    /// 2. `[writable]` The staker's stake PDA
+    /// 3. Then, for each of the farm's rewards in order:
+    ///    - `[writable]` The reward's vault
+    ///    - `[writable]` Staker's reward token account
+    ///    - `[]` Token program of the reward mint
+    HarvestRewards,
+
+    /// Adds another reward token to a farm and funds its whole emission
+    /// 
+    /// The new reward runs on its own schedule, releasing `emission_rate`
+    /// per slot from the current slot on. A farm emits at most
+    /// `MAX_FARM_REWARDS` reward tokens.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The farm authority (funds the rewards)
+    /// 1. `[writable]` The farm PDA
+    /// 2. `[writable]` The new reward's vault (owned by the farm PDA)
+    /// 3. `[writable]` Authority's reward token account
+    /// 4. `[]` Token program of the reward mint
+    AddRewardToken {
+        /// Reward tokens released per slot
+        emission_rate: u64,
+        /// Number of slots the emission lasts
+        duration_slots: u64,
+    },
+
+    /// Extends one of a farm's emissions at its current rate, funding the extension
+    /// 
+    /// An emission that already ended restarts at the current slot.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The farm authority (funds the rewards)
+    /// 1. `[writable]` The farm PDA
+    /// 2. `[writable]` The reward's vault
+    /// 3. `[writable]` Authority's reward token account
+    /// 4. `[]` Token program of the reward mint
+    ExtendEmission {
+        /// Index of the reward in the farm
+        reward_index: u8,
+        /// Slots to add to the emission
+        duration_slots: u64,
+    },
+
+    /// Sets a pool's variable fee, which raises the trading fee with recent volatility
+    /// 
+    /// Each swap's price move, in basis points, is added to the pool's
+    /// volatility accumulator, which halves every
+    /// `VOLATILITY_HALF_LIFE_SLOTS`. Swaps pay the base fee plus the
+    /// accumulator times `variable_fee_control / 10000`, up to
+    /// `max_variable_fee_bps`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` The protocol config PDA
+    UpdateVariableFee {
+        /// Variable fee in basis points per 10000 bps of accumulated volatility, 0 to disable
+        variable_fee_control: u64,
+        /// Cap on the variable fee, at most the protocol config's `max_variable_fee_bps`
+        max_variable_fee_bps: Bps,
+    },
+
+    /// Sets a pool's price impact limit
+    /// 
+    /// A swap that would move the pool's token price by more than the limit,
+    /// in basis points of the price before it, fails with `PriceImpactTooHigh`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    UpdatePriceImpactLimit {
+        /// Largest price move one swap may cause, in basis points; 0 for no limit
+        max_price_impact_bps: Bps,
+    },
+
+    /// Ties a pool's swaps to a band around an external oracle price
+    /// 
+    /// With a price account, `Swap` and `SwapExactOut` must then pass it and
+    /// fail with `OraclePriceDeviation` when they would execute further than
+    /// `max_deviation_bps` from its price, or with `StaleOracle` when it is
+    /// older than `MAX_ORACLE_STALENESS_SLOTS`. Other swap paths refuse the
+    /// pool. The feed must quote one token in SOL (or mint A) base units.
+    /// Without a price account the band is removed.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    /// 2. `[]` The oracle price account (Pyth; optional, omit to remove the band)
+    SetPriceOracle {
+        /// Furthest a swap may execute from the oracle price, in basis points,
+        /// at most `MAX_ORACLE_DEVIATION_BPS` (ignored when removing the band)
+        max_deviation_bps: Bps,
+    },
+
+    /// Creates a concentrated liquidity pool between two mints
+    /// 
+    /// The pool PDA is derived from `["cl_pool", mint_a, mint_b,
+    /// tick_spacing]`, with `mint_a` sorting below `mint_b`. Its vaults must
+    /// be token accounts of the two mints owned by the pool PDA, under one
+    /// token program. The pool starts with no liquidity at the given price.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The creator (funds the rent)
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[]` The pool's token A vault
+    /// 3. `[]` The pool's token B vault
+    /// 4. `[]` The system program
+    InitializeConcentratedPool {
+        /// Trading fee in basis points, at most `MAX_FEE_RATE_BPS`
+        fee_rate: Bps,
+        /// Position ticks must be multiples of this, at most `MAX_TICK_SPACING`
+        tick_spacing: u16,
+        /// Initial Q64.64 square root of the price of token A in token B
+        initial_sqrt_price: u128,
+    },
+
+    /// Initializes a tick of a concentrated liquidity pool, so positions can be bounded by it
+    /// 
+    /// Creates the tick PDA, derived from `["cl_tick", pool, tick_index]`,
+    /// and links it into the pool's list of initialized ticks between its
+    /// neighbours. Ticks stay initialized once created.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer (funds the rent)
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[writable]` The new tick PDA
+    /// 3. `[]` The system program
+    /// 4. `[writable]` The `prev_tick` account (omitted when it is `MIN_TICK`)
+    /// 5. `[writable]` The `next_tick` account (omitted when it is `MAX_TICK`)
+    InitializeTick {
+        /// The tick, a multiple of the pool's tick spacing
+        tick_index: i32,
+        /// Nearest initialized tick below, or `MIN_TICK` if none
+        prev_tick: i32,
+        /// Nearest initialized tick above, or `MAX_TICK` if none
+        next_tick: i32,
+    },
+
+    /// Opens an empty liquidity position over a tick range of a concentrated liquidity pool
+    /// 
+    /// The position PDA is derived from `["cl_position", pool, owner,
+    /// tick_lower, tick_upper]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner (funds the rent)
+    /// 1. `[]` The pool PDA
+    /// 2. `[writable]` The position PDA
+    /// 3. `[]` The system program
+    OpenPosition {
+        /// Lower tick of the range
+        tick_lower: i32,
+        /// Upper tick of the range, above `tick_lower`
+        tick_upper: i32,
+    },
+
+    /// Adds liquidity to a concentrated liquidity position
+    /// 
+    /// Deposits the tokens the liquidity holds across the position's range
+    /// at the current price, rounded up. Fees earned so far are settled
+    /// first. Both bounding ticks must be initialized.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The position owner
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[writable]` The position PDA
+    /// 3. `[writable]` The lower tick PDA
+    /// 4. `[writable]` The upper tick PDA
+    /// 5. `[writable]` Owner's token A account
+    /// 6. `[writable]` Owner's token B account
+    /// 7. `[writable]` The pool's token A vault
+    /// 8. `[writable]` The pool's token B vault
+    /// 9. `[]` Token program
+    IncreaseLiquidity {
+        /// Liquidity to add
+        liquidity: u128,
+        /// Maximum token A to deposit
+        max_amount_a: u64,
+        /// Maximum token B to deposit
+        max_amount_b: u64,
+    },
+
+    /// Removes liquidity from a concentrated liquidity position and pays out its fees
+    /// 
+    /// Withdraws the tokens the liquidity holds at the current price,
+    /// rounded down, together with every fee the position is owed. Zero
+    /// liquidity only collects the fees.
+    /// 
+    /// Accounts expected: as `IncreaseLiquidity`, with tokens flowing from
+    /// the vaults to the owner's accounts
+    DecreaseLiquidity {
+        /// Liquidity to remove
+        liquidity: u128,
+        /// Minimum token A to receive, excluding fees
+        min_amount_a: u64,
+        /// Minimum token B to receive, excluding fees
+        min_amount_b: u64,
+    },
+
+    /// Swaps against a concentrated liquidity pool
+    /// 
+    /// The price moves through the pool's initialized ticks, adding or
+    /// removing the liquidity of each one it crosses, whose accounts must be
+    /// passed in the order the swap crosses them. A swap that runs out of
+    /// initialized ticks fills partially and only charges the input it used.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The user
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[writable]` User's token A account
+    /// 3. `[writable]` User's token B account
+    /// 4. `[writable]` The pool's token A vault
+    /// 5. `[writable]` The pool's token B vault
+    /// 6. `[]` Token program
+    /// 7. Then `[writable]` each tick PDA crossed, in order
+    SwapConcentrated {
+        /// Input to swap, including the fee
+        amount_in: u64,
+        /// Minimum output to receive
+        minimum_amount_out: u64,
+        /// Whether token A is sold for token B
+        a_to_b: bool,
+    },
+
+    /// Creates a bin pool between two mints
+    /// 
+    /// The pool PDA is derived from `["bin_pool", mint_a, mint_b,
+    /// bin_step]`, with `mint_a` sorting below `mint_b`. Its vaults must be
+    /// token accounts of the two mints owned by the pool PDA, under one
+    /// token program.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The creator (funds the rent)
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[]` The pool's token A vault
+    /// 3. `[]` The pool's token B vault
+    /// 4. `[]` The system program
+    InitializeBinPool {
+        /// Trading fee in basis points, at most `MAX_FEE_RATE_BPS`
+        fee_rate: Bps,
+        /// Price step between neighbouring bins in basis points, at most `MAX_BIN_STEP`
+        bin_step: u16,
+        /// Bin the pool starts trading in
+        active_bin: i32,
+    },
+
+    /// Initializes a bin of a bin pool, so liquidity can be added to it
+    /// 
+    /// Creates the bin PDA, derived from `["bin", pool, bin_id]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The payer (funds the rent)
+    /// 1. `[]` The pool PDA
+    /// 2. `[writable]` The bin PDA
+    /// 3. `[]` The system program
+    InitializeBin {
+        /// The bin, whose price must lie between 2^-32 and 2^32
+        bin_id: i32,
+    },
+
+    /// Opens an empty liquidity position over a range of bins
+    /// 
+    /// The position PDA is derived from `["bin_position", pool, owner,
+    /// lower_bin, width]`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner (funds the rent)
+    /// 1. `[]` The pool PDA
+    /// 2. `[writable]` The position PDA
+    /// 3. `[]` The system program
+    OpenBinPosition {
+        /// Lowest bin of the range
+        lower_bin: i32,
+        /// Number of bins in the range, at most `MAX_BIN_POSITION_WIDTH`
+        width: u16,
+    },
+
+    /// Adds liquidity to every bin of a position's range
+    /// 
+    /// Token A is split evenly across the range's bins at or above the
+    /// active bin, and token B across those at or below it; any remainder
+    /// of the split is not taken. Each bin issues shares worth the value
+    /// added at its price.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The position owner
+    /// 1. `[]` The pool PDA
+    /// 2. `[writable]` The position PDA
+    /// 3. `[writable]` Owner's token A account
+    /// 4. `[writable]` Owner's token B account
+    /// 5. `[writable]` The pool's token A vault
+    /// 6. `[writable]` The pool's token B vault
+    /// 7. `[]` Token program
+    /// 8. Then `[writable]` each bin PDA of the position's range, from the lowest
+    AddBinLiquidity {
+        /// Most token A to deposit
+        amount_a: u64,
+        /// Most token B to deposit
+        amount_b: u64,
+        /// Active bin the split was planned for; fails with `SlippageExceeded` if the pool moved
+        active_bin: i32,
+    },
+
+    /// Removes part of a position's liquidity from every bin of its range
+    /// 
+    /// Accounts expected: as `AddBinLiquidity`, with tokens flowing from the
+    /// vaults to the owner's accounts
+    RemoveBinLiquidity {
+        /// Part of the position's shares in each bin to redeem, in basis points
+        share_bps: Bps,
+        /// Minimum token A to receive
+        min_amount_a: u64,
+        /// Minimum token B to receive
+        min_amount_b: u64,
+    },
+
+    /// Swaps against a bin pool
+    /// 
+    /// Trades at the active bin's price until its output token runs out,
+    /// then moves to the next bin, down when selling token A and up when
+    /// selling token B. Bin accounts are passed from the active bin on, in
+    /// that order; the swap fills partially when they run out and only
+    /// charges the input it used.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The user
+    /// 1. `[writable]` The pool PDA
+    /// 2. `[writable]` User's token A account
+    /// 3. `[writable]` User's token B account
+    /// 4. `[writable]` The pool's token A vault
+    /// 5. `[writable]` The pool's token B vault
+    /// 6. `[]` Token program
+    /// 7. Then `[writable]` each bin PDA traded in, from the active bin
+    SwapBins {
+        /// Input to swap, including the fee
+        amount_in: u64,
+        /// Minimum output to receive
+        minimum_amount_out: u64,
+        /// Whether token A is sold for token B
+        a_to_b: bool,
+    },
+
+    /// Graduates a bonding curve that has reached its threshold into a
+    /// constant product pool
+    /// 
+    /// Anyone may call this once the pool's SOL reserve reaches its
+    /// `graduation_threshold`. The virtual reserves are dropped and the tokens
+    /// the curve's last price no longer backs are burned, so the pool reopens
+    /// at that price with its accumulated reserves. LP tokens for the whole
+    /// pool are minted to a token account the pool itself owns, which no
+    /// instruction moves them from, locking the launch liquidity for good.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The caller
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The pool's token vault
+    /// 3. `[writable]` The token mint (burned from)
+    /// 4. `[writable]` The LP token mint
+    /// 5. `[writable]` The locked LP token account (holds the LP mint, owned by the pool PDA)
+    /// 6. `[]` Token program
+    /// 7. `[]` LP token program (optional, when the LP mint is owned by a
+    ///    different token program)
+    Graduate,
+
+    /// Opens a dollar-cost averaging position
+    /// 
+    /// Escrows `amount` of the input asset in a position PDA derived from
+    /// `["dca_position", pool, owner, dca_id]`. The first tranche is due
+    /// immediately, then one every `interval_slots`. SOL is held as the
+    /// position's lamports; tokens are held in an escrow token account owned
+    /// by the position PDA.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner
+    /// 1. `[]` The pool state account
+    /// 2. `[writable]` The DCA position PDA
+    /// 3. `[writable]` Owner's token account (source of tokens when selling tokens)
+    /// 4. `[writable]` Position escrow token account (owned by the position PDA)
+    /// 5. `[]` Token program
+    /// 6. `[]` The system program
+    /// 7. `[]` The protocol config PDA
+    OpenDca {
+        /// Owner-chosen identifier, unique per owner and pool
+        dca_id: u64,
+        /// Total input to escrow
+        amount: u64,
+        /// Input sold per tranche, keeper tip included
+        amount_per_fill: u64,
+        /// Slots between tranches
+        interval_slots: u64,
+        /// Direction of the position (true = SOL→Token, false = Token→SOL)
+        is_sol_input: bool,
+        /// Share of each tranche paid to the keeper that executes it, at most
+        /// the protocol config's `max_keeper_tip_bps`
+        keeper_tip_bps: Bps,
+        /// Minimum output per input, scaled by `LIMIT_PRICE_SCALE` (optional, 0 = no floor)
+        limit_price: u64,
+    },
+
+    /// Swaps the next due tranche of a DCA position
+    /// 
+    /// Permissionless crank. The keeper receives the position's tip out of
+    /// the tranche's input and the rest is swapped through the pool, with the
+    /// output delivered to the owner. Fails when the tranche would execute
+    /// below the position's floor price.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The keeper (receives the tip when selling SOL)
+    /// 1. `[writable]` The pool state account
+    /// 2. `[writable]` The DCA position PDA
+    /// 3. `[writable]` Position escrow token account
+    /// 4. `[writable]` Pool's token account
+    /// 5. `[writable]` Owner's SOL account (receives SOL when selling tokens)
+    /// 6. `[writable]` Owner's token account (receives tokens when selling SOL)
+    /// 7. `[writable]` Keeper's token account (receives the tip when selling tokens)
+    /// 8. `[]` Token program
+    /// 9. `[writable]` The pool's SOL vault PDA
+    ExecuteDca,
+
+    /// Closes a DCA position, refunding unspent escrow and rent
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The position owner
+    /// 1. `[writable]` The DCA position PDA (closed)
+    /// 2. `[writable]` Owner's token account (receives remaining tokens)
+    /// 3. `[writable]` Position escrow token account (closed when selling tokens)
+    /// 4. `[]` Token program
+    CloseDca,
+
+    /// Sets a pool's per-slot outflow limit
+    /// 
+    /// Swaps and withdrawals that would take more than the limit, in basis
+    /// points of a reserve as it stood before the slot's first outflow, out of
+    /// either reserve within one slot fail with `OutflowLimitExceeded`.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The pool authority
+    /// 1. `[writable]` The pool state account
+    UpdateOutflowLimit {
+        /// Most of either reserve that may leave the pool in one slot, in basis points; 0 for no limit
+        max_outflow_bps: Bps,
+    },
+
+    /// Puts a pool in emergency mode, or takes it out again
+    /// 
+    /// For pools whose reserve bookkeeping can no longer be trusted. Entering
+    /// emergency mode pauses the pool, and while it lasts the pool cannot be
+    /// unpaused, `RemoveLiquidity` and migrations out of the pool fail, and
+    /// LPs exit through `EmergencyWithdraw` instead.
+    /// 
+    /// Accounts expected:
+    /// 0. `[signer]` The protocol authority
+    /// 1. `[]` The protocol config PDA
+    /// 2. `[writable]` The pool state account
+    SetEmergencyMode {
+        /// Whether the pool should be in emergency mode
+        emergency: bool,
+    },
+
+    /// Burns LP tokens for a pro-rata share of the pool's vault balances
+    /// 
+    /// Only available in emergency mode. The share is taken from what the
+    /// vaults actually hold, less uncollected protocol fees and unclaimed
+    /// position fees, rather than from the stored reserves, and is not
+    /// subject to the pool's outflow limit. The stored reserves shrink by the
+    /// amounts paid, saturating at zero.
+    /// 
+    /// Writes a `LiquidityResult` to return data.
+    /// 
+    /// Accounts expected:
+    /// 0-8. As for `RemoveLiquidity`
+    /// 9. Optional trailing accounts, in any order:
+    ///    - `[]` LP token program (when the LP mint is owned by a different token program)
+    ///    - `[writable]` The provider's position PDA (debited the burned LP tokens)
+    EmergencyWithdraw {
+        /// Amount of LP tokens to burn
+        lp_tokens: LpAmount,
+    },
+
+    /// Reconciles a pool's reserves with what its vaults hold
+    /// 
+    /// Permissionless. Any vault balance beyond the reserves, uncollected
+    /// protocol fees and unclaimed position fees (tokens or lamports sent
+    /// straight to a vault) is added to the reserves, or, when the protocol
+    /// config sets `skim_excess_reserves`, to the protocol fees that
+    /// `CollectProtocolFees` sweeps to the treasury.
+    /// 
+    /// Accounts expected:
+    /// 0. `[writable]` The pool state account
+    /// 1. `[]` Pool's token account
+    /// 2. `[]` The pool's SOL vault PDA, or its mint A vault for token pairs
+    /// 3. `[]` The protocol config PDA
+    SyncReserves,
+
+    /// Pays a pool's vault excess to the protocol treasury
+    /// 
+    /// Permissionless. Any vault balance beyond the reserves, uncollected
+    /// protocol fees and unclaimed position fees, as `SyncReserves` measures
+    /// it, goes to accounts of the treasury recorded in the protocol config,
+    /// so dust and accidental transfers fund the protocol. Fails until the
+    /// config names a treasury.
+    /// 
+    /// Accounts expected:
+    /// 0. `[]` The pool state account
+    /// 1. `[writable]` Pool's token account
+    /// 2. `[writable]` The pool's SOL vault PDA, or its mint A vault for token pairs
+    /// 3. `[]` The protocol config PDA
+    /// 4. `[writable]` The treasury's token account
+    /// 5. `[writable]` The treasury itself, or its wSOL or mint A token account
+    /// 6. `[]` Token program
+    Skim,
+
+    /// Initializes a constant product pool at a starting price
+    /// 
+    /// The SOL (or mint A) deposit is derived from `initial_price` and
+    /// `token_amount`, rounded up, so the pool cannot be seeded at an
+    /// unintended ratio. Otherwise identical to `InitializePool` with the
+    /// constant product curve and no launch settings.
+    /// 
+    /// Accounts expected:
+    /// 0-16. As for `InitializePool`
+    InitializePoolWithPrice {
+        /// SOL (or mint A) base units per token base unit, scaled by `LIMIT_PRICE_SCALE`
+        initial_price: u64,
+        /// Initial amount of SPL tokens to deposit
+        token_amount: TokenAmount,
+        /// Trading fee percentage in basis points (1 bp = 0.01%)
+        fee_rate: Bps,
+        /// Whether LP tokens are soulbound (optional, defaults to false)
+        non_transferable_lp: bool,
+        /// Whether the pool pairs two SPL mints (optional, defaults to false)
+        token_pair: bool,
+        /// Whether the SOL vault holds wrapped SOL (optional, defaults to false)
+        wsol: bool,
+    },
+}
+
+/// Execution result `Swap` and `SwapExactOut` write to return data
+/// 
+/// Borsh-encoded, so CPI callers read it with `get_return_data` and
+/// simulations find it in the transaction's return data instead of logs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Input the pool priced the swap on (what its vault received)
+    pub amount_in: u64,
+    /// Output paid to the user, after any platform fee
+    pub amount_out: u64,
+    /// Trading fee charged on the input
+    pub fee_paid: u64,
+    /// SOL (mint A) reserve after the swap
+    pub sol_reserve: u64,
+    /// Token reserve after the swap
+    pub token_reserve: u64,
+}
+
+/// Execution result the liquidity instructions write to return data
+/// 
+/// Deposits report the amounts the pool received and the LP tokens minted;
+/// withdrawals report the amounts paid out and the LP tokens burned.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityResult {
+    /// SOL deposited or withdrawn
+    pub sol_amount: Lamports,
+    /// Tokens deposited or withdrawn
+    pub token_amount: TokenAmount,
+    /// LP tokens minted or burned
+    pub lp_tokens: LpAmount,
+    /// SOL reserve afterwards
+    pub sol_reserve: u64,
+    /// Token reserve afterwards
+    pub token_reserve: u64,
+}
+
+impl TokenExchangeInstruction {
+    /// Deserializes a byte buffer into a TokenExchangeInstruction
+    /// 
+    /// The first byte (tag) determines which instruction variant to deserialize.
+    /// Remaining bytes are parsed according to the instruction's parameters.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(match tag {
+            0 => Self::InitializePool {
+                sol_amount: Lamports(Self::unpack_u64(rest, 0)?),
+                token_amount: TokenAmount(Self::unpack_u64(rest, 8)?),
+                fee_rate: Bps(Self::unpack_u64(rest, 16)?),
+                non_transferable_lp: Self::unpack_optional_bool(rest, 24)?,
+                token_pair: Self::unpack_optional_bool(rest, 25)?,
+                wsol: Self::unpack_optional_bool(rest, 26)?,
+                curve_type: Self::unpack_optional_curve_type(rest, 27)?,
+                amplification: Self::unpack_optional_u64(rest, 28)?,
+                token_offset: Self::unpack_optional_u64(rest, 36)?,
+                trade_start_timestamp: Self::unpack_optional_u64(rest, 44)? as i64,
+                launch_window_seconds: Self::unpack_optional_u64(rest, 52)?,
+                max_swap_per_wallet_during_launch: Self::unpack_optional_u64(rest, 60)?,
+                launch_fee_bps: Self::unpack_optional_u64(rest, 68)?,
+                launch_fee_decay_seconds: Self::unpack_optional_u64(rest, 76)?,
+                sol_weight_bps: Self::unpack_optional_u64(rest, 84)?,
+                sol_offset: Self::unpack_optional_u64(rest, 92)?,
+                graduation_threshold: Self::unpack_optional_u64(rest, 100)?,
+            },
+            1 => Self::Swap {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                is_sol_input: rest[16] != 0,
+                platform_fee_bps: Bps(Self::unpack_optional_u64(rest, 17)?),
+                referral_fee_share_bps: Bps(Self::unpack_optional_u64(rest, 25)?),
+                sandwich_protection: Self::unpack_optional_bool(rest, 33)?,
+            },
+            2 => Self::AddLiquidity {
+                sol_amount: Lamports(Self::unpack_u64(rest, 0)?),
+                token_amount: TokenAmount(Self::unpack_u64(rest, 8)?),
+                minimum_lp_tokens: LpAmount(Self::unpack_u64(rest, 16)?),
+                match_pool_ratio: Self::unpack_optional_bool(rest, 24)?,
+                minimum_sol: Lamports(Self::unpack_optional_u64(rest, 25)?),
+                minimum_token: TokenAmount(Self::unpack_optional_u64(rest, 33)?),
+            },
+            3 => Self::RemoveLiquidity {
+                lp_tokens: LpAmount(Self::unpack_u64(rest, 0)?),
+                minimum_sol: Lamports(Self::unpack_u64(rest, 8)?),
+                minimum_token: TokenAmount(Self::unpack_u64(rest, 16)?),
+            },
+            4 => Self::InitializeProtocolStats,
+            5 => Self::InitializeObservations {
+                cardinality: Self::unpack_u16(rest, 0)?,
+            },
+            6 => Self::IncreaseObservationCardinality {
+                new_cardinality: Self::unpack_u16(rest, 0)?,
+            },
+            7 => Self::MigrateLiquidity {
+                lp_tokens: LpAmount(Self::unpack_u64(rest, 0)?),
+                minimum_sol: Lamports(Self::unpack_u64(rest, 8)?),
+                minimum_token: TokenAmount(Self::unpack_u64(rest, 16)?),
+                minimum_lp_tokens: LpAmount(Self::unpack_u64(rest, 24)?),
+            },
+            8 => Self::PlaceLimitOrder {
+                order_id: Self::unpack_u64(rest, 0)?,
+                amount: Self::unpack_u64(rest, 8)?,
+                limit_price: Self::unpack_u64(rest, 16)?,
+                is_sol_input: Self::unpack_bool(rest, 24)?,
+                expiry_slot: Self::unpack_optional_u64(rest, 25)?,
+            },
+            9 => Self::FillLimitOrder {
+                fill_amount: Self::unpack_u64(rest, 0)?,
+            },
+            10 => Self::ModifyLimitOrder {
+                new_limit_price: Self::unpack_u64(rest, 0)?,
+                new_remaining_amount: Self::unpack_u64(rest, 8)?,
+            },
+            11 => Self::CancelLimitOrder,
+            12 => Self::CancelExpiredLimitOrder,
+            13 => Self::MatchLimitOrders,
+            14 => Self::UpdateKeeperBounty {
+                keeper_bounty_bps: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            15 => Self::PlaceStopOrder {
+                order_id: Self::unpack_u64(rest, 0)?,
+                amount: Self::unpack_u64(rest, 8)?,
+                stop_price: Self::unpack_u64(rest, 16)?,
+                max_slippage_bps: Self::unpack_u64(rest, 24)?,
+                is_sol_input: Self::unpack_bool(rest, 32)?,
+                expiry_slot: Self::unpack_optional_u64(rest, 33)?,
+            },
+            16 => Self::PlaceOracleOrder {
+                order_id: Self::unpack_u64(rest, 0)?,
+                amount: Self::unpack_u64(rest, 8)?,
+                limit_price: Self::unpack_u64(rest, 16)?,
+                trigger_price: Self::unpack_u64(rest, 24)?,
+                trigger_below: Self::unpack_bool(rest, 32)?,
+                is_sol_input: Self::unpack_bool(rest, 33)?,
+                expiry_slot: Self::unpack_optional_u64(rest, 34)?,
+            },
+            17 => Self::ExecuteOracleOrder,
+            18 => Self::CreateLiquiditySchedule {
+                schedule_id: Self::unpack_u64(rest, 0)?,
+                sol_per_tranche: Self::unpack_u64(rest, 8)?,
+                token_per_tranche: Self::unpack_u64(rest, 16)?,
+                tranche_count: Self::unpack_u64(rest, 24)?,
+                interval_slots: Self::unpack_u64(rest, 32)?,
+            },
+            19 => Self::ExecuteLiquidityTranche,
+            20 => Self::CloseLiquiditySchedule,
+            21 => Self::GrantTradingAllowance {
+                max_notional_per_window: Self::unpack_u64(rest, 0)?,
+                window_slots: Self::unpack_u64(rest, 8)?,
+            },
+            22 => Self::RevokeTradingAllowance,
+            23 => Self::OperatorSwap {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                is_sol_input: Self::unpack_bool(rest, 16)?,
+            },
+            24 => Self::ExecuteSignedOrder {
+                order: SignedOrder::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            },
+            25 => Self::RegisterKeeper,
+            26 => Self::DeregisterKeeper,
+            27 => Self::ArbitragePools {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_profit: Self::unpack_u64(rest, 8)?,
+            },
+            28 => Self::CaptureArbitrage {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_profit: Self::unpack_u64(rest, 8)?,
+            },
+            29 => Self::InitializeUserStats,
+            30 => Self::ConfigureSealedOrders {
+                threshold: *rest.first().ok_or(ProgramError::InvalidInstructionData)?,
+                reveal_delay_slots: Self::unpack_u64(rest, 1)?,
+            },
+            31 => Self::SubmitSealedOrder {
+                order_id: Self::unpack_u64(rest, 0)?,
+                escrow_amount: Self::unpack_u64(rest, 8)?,
+                is_sol_input: Self::unpack_bool(rest, 16)?,
+                commitment: rest
+                    .get(17..49)
+                    .and_then(|slice| slice.try_into().ok())
+                    .ok_or(ProgramError::InvalidInstructionData)?,
+                ciphertext: rest[49..].to_vec(),
+            },
+            32 => Self::ExecuteSealedOrder {
+                terms: SealedOrderTerms::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            },
+            33 => Self::CancelSealedOrder,
+            34 => Self::SetRebasingMode {
+                enabled: Self::unpack_bool(rest, 0)?,
+            },
+            35 => Self::SetDepositPermissioned {
+                enabled: Self::unpack_bool(rest, 0)?,
+            },
+            36 => Self::AllowLiquidityProvider,
+            37 => Self::DisallowLiquidityProvider,
+            38 => {
+                let (name, uri) = <(String, String)>::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SetPoolMetadata { name, uri }
+            }
+            39 => Self::SetPoolDeprecated {
+                deprecated: Self::unpack_bool(rest, 0)?,
+            },
+            40 => Self::InitializeVolumeStats,
+            41 => Self::SwapExactOut {
+                amount_out: Self::unpack_u64(rest, 0)?,
+                maximum_amount_in: Self::unpack_u64(rest, 8)?,
+                is_sol_output: Self::unpack_bool(rest, 16)?,
+            },
+            42 => Self::InitializeProtocolConfig {
+                authority: Self::unpack_pubkey(rest, 0)?,
+                parameters: match rest.get(32..) {
+                    None | Some([]) => None,
+                    Some(parameters) => Option::<ProtocolParameters>::try_from_slice(parameters)
+                        .map_err(|_| ProgramError::InvalidInstructionData)?,
+                },
+            },
+            43 => Self::SetProtocolFeeShare {
+                protocol_fee_share_bps: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            44 => Self::CollectProtocolFees,
+            45 => Self::UpdateFeeRate {
+                new_fee_rate: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            46 => Self::PausePool,
+            47 => Self::UnpausePool,
+            48 => Self::FlashBorrow {
+                amount: Self::unpack_u64(rest, 0)?,
+                is_sol: Self::unpack_bool(rest, 8)?,
+            },
+            49 => Self::FlashRepay,
+            50 => Self::RouteSwap {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                is_sol_input: Self::unpack_bool(rest, 16)?,
+            },
+            51 => Self::DepositSingleSided {
+                amount: Self::unpack_u64(rest, 0)?,
+                is_sol: Self::unpack_bool(rest, 8)?,
+                minimum_lp_tokens: LpAmount(Self::unpack_u64(rest, 9)?),
+            },
+            52 => Self::WithdrawSingleSided {
+                lp_tokens: LpAmount(Self::unpack_u64(rest, 0)?),
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                want_sol: Self::unpack_bool(rest, 16)?,
+            },
+            53 => Self::SetFeeOnTransferMode {
+                enabled: Self::unpack_bool(rest, 0)?,
+            },
+            54 => Self::MigratePool,
+            55 => Self::UpdateProtocolConfig {
+                authority: Self::unpack_pubkey(rest, 0)?,
+                parameters: ProtocolParameters::try_from_slice(rest.get(32..).unwrap_or_default())
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            },
+            56 => Self::ClaimFees,
+            57 => Self::InitializeUserPosition,
+            58 => Self::CloseUserPosition,
+            59 => Self::InitializeFarm {
+                emission_rate: Self::unpack_u64(rest, 0)?,
+                duration_slots: Self::unpack_u64(rest, 8)?,
+            },
+            60 => Self::StakeLp {
+                amount: LpAmount(Self::unpack_u64(rest, 0)?),
+            },
+            61 => Self::UnstakeLp {
+                amount: LpAmount(Self::unpack_u64(rest, 0)?),
+            },
+            62 => Self::HarvestRewards,
+            63 => Self::AddRewardToken {
+                emission_rate: Self::unpack_u64(rest, 0)?,
+                duration_slots: Self::unpack_u64(rest, 8)?,
+            },
+            64 => Self::ExtendEmission {
+                reward_index: *rest.first().ok_or(ProgramError::InvalidInstructionData)?,
+                duration_slots: Self::unpack_u64(rest, 1)?,
+            },
+            65 => Self::UpdateVariableFee {
+                variable_fee_control: Self::unpack_u64(rest, 0)?,
+                max_variable_fee_bps: Bps(Self::unpack_u64(rest, 8)?),
+            },
+            66 => Self::UpdatePriceImpactLimit {
+                max_price_impact_bps: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            67 => Self::SetPriceOracle {
+                max_deviation_bps: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            68 => Self::InitializeConcentratedPool {
+                fee_rate: Bps(Self::unpack_u64(rest, 0)?),
+                tick_spacing: Self::unpack_u16(rest, 8)?,
+                initial_sqrt_price: Self::unpack_u128(rest, 10)?,
+            },
+            69 => Self::InitializeTick {
+                tick_index: Self::unpack_i32(rest, 0)?,
+                prev_tick: Self::unpack_i32(rest, 4)?,
+                next_tick: Self::unpack_i32(rest, 8)?,
+            },
+            70 => Self::OpenPosition {
+                tick_lower: Self::unpack_i32(rest, 0)?,
+                tick_upper: Self::unpack_i32(rest, 4)?,
+            },
+            71 => Self::IncreaseLiquidity {
+                liquidity: Self::unpack_u128(rest, 0)?,
+                max_amount_a: Self::unpack_u64(rest, 16)?,
+                max_amount_b: Self::unpack_u64(rest, 24)?,
+            },
+            72 => Self::DecreaseLiquidity {
+                liquidity: Self::unpack_u128(rest, 0)?,
+                min_amount_a: Self::unpack_u64(rest, 16)?,
+                min_amount_b: Self::unpack_u64(rest, 24)?,
+            },
+            73 => Self::SwapConcentrated {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                a_to_b: Self::unpack_bool(rest, 16)?,
+            },
+            74 => Self::InitializeBinPool {
+                fee_rate: Bps(Self::unpack_u64(rest, 0)?),
+                bin_step: Self::unpack_u16(rest, 8)?,
+                active_bin: Self::unpack_i32(rest, 10)?,
+            },
+            75 => Self::InitializeBin {
+                bin_id: Self::unpack_i32(rest, 0)?,
+            },
+            76 => Self::OpenBinPosition {
+                lower_bin: Self::unpack_i32(rest, 0)?,
+                width: Self::unpack_u16(rest, 4)?,
+            },
+            77 => Self::AddBinLiquidity {
+                amount_a: Self::unpack_u64(rest, 0)?,
+                amount_b: Self::unpack_u64(rest, 8)?,
+                active_bin: Self::unpack_i32(rest, 16)?,
+            },
+            78 => Self::RemoveBinLiquidity {
+                share_bps: Bps(Self::unpack_u64(rest, 0)?),
+                min_amount_a: Self::unpack_u64(rest, 8)?,
+                min_amount_b: Self::unpack_u64(rest, 16)?,
+            },
+            79 => Self::SwapBins {
+                amount_in: Self::unpack_u64(rest, 0)?,
+                minimum_amount_out: Self::unpack_u64(rest, 8)?,
+                a_to_b: Self::unpack_bool(rest, 16)?,
+            },
+            80 => Self::Graduate,
+            81 => Self::OpenDca {
+                dca_id: Self::unpack_u64(rest, 0)?,
+                amount: Self::unpack_u64(rest, 8)?,
+                amount_per_fill: Self::unpack_u64(rest, 16)?,
+                interval_slots: Self::unpack_u64(rest, 24)?,
+                is_sol_input: Self::unpack_bool(rest, 32)?,
+                keeper_tip_bps: Bps(Self::unpack_u64(rest, 33)?),
+                limit_price: Self::unpack_optional_u64(rest, 41)?,
+            },
+            82 => Self::ExecuteDca,
+            83 => Self::CloseDca,
+            84 => Self::UpdateOutflowLimit {
+                max_outflow_bps: Bps(Self::unpack_u64(rest, 0)?),
+            },
+            85 => Self::SetEmergencyMode {
+                emergency: Self::unpack_bool(rest, 0)?,
+            },
+            86 => Self::EmergencyWithdraw {
+                lp_tokens: LpAmount(Self::unpack_u64(rest, 0)?),
+            },
+            87 => Self::SyncReserves,
+            88 => Self::Skim,
+            89 => Self::InitializePoolWithPrice {
+                initial_price: Self::unpack_u64(rest, 0)?,
+                token_amount: TokenAmount(Self::unpack_u64(rest, 8)?),
+                fee_rate: Bps(Self::unpack_u64(rest, 16)?),
+                non_transferable_lp: Self::unpack_optional_bool(rest, 24)?,
+                token_pair: Self::unpack_optional_bool(rest, 25)?,
+                wsol: Self::unpack_optional_bool(rest, 26)?,
+            },
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+
+    /// Helper function to deserialize a u64 from a byte slice
+    /// 
+    /// # Arguments
+    /// * `input` - The byte slice containing the u64
+    /// * `start` - Starting position in the slice
+    /// 
+    /// # Returns
+    /// * `Result<u64, ProgramError>` - The deserialized number or an error
+    fn unpack_u64(input: &[u8], start: usize) -> Result<u64, ProgramError> {
+        let value = input
+            .get(start..start + 8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    /// Helper function to deserialize a public key from a byte slice
+    fn unpack_pubkey(input: &[u8], start: usize) -> Result<Pubkey, ProgramError> {
+        input
+            .get(start..start + 32)
+            .and_then(|slice| slice.try_into().ok())
+            .map(Pubkey::new_from_array)
+            .ok_or(ProgramError::InvalidInstructionData)
+    }
+
+    /// Helper function to deserialize a bool from a single byte
+    fn unpack_bool(input: &[u8], start: usize) -> Result<bool, ProgramError> {
+        input
+            .get(start)
+            .map(|byte| *byte != 0)
+            .ok_or(ProgramError::InvalidInstructionData)
+    }
+
+    /// Helper function to deserialize a u16 from a byte slice
+    fn unpack_u16(input: &[u8], start: usize) -> Result<u16, ProgramError> {
+        let value = input
+            .get(start..start + 2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    /// Helper function to deserialize an i32 from a byte slice
+    fn unpack_i32(input: &[u8], start: usize) -> Result<i32, ProgramError> {
+        let value = input
+            .get(start..start + 4)
+            .and_then(|slice| slice.try_into().ok())
+            .map(i32::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    /// Helper function to deserialize a u128 from a byte slice
+    fn unpack_u128(input: &[u8], start: usize) -> Result<u128, ProgramError> {
+        let value = input
+            .get(start..start + 16)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u128::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(value)
+    }
+
+    /// Helper function to deserialize an optional trailing u64 from a byte slice
+    /// 
+    /// Returns 0 when the input ends at `start`, so older clients that omit
+    /// the field keep working.
+    fn unpack_optional_u64(input: &[u8], start: usize) -> Result<u64, ProgramError> {
+        if input.len() <= start {
+            return Ok(0);
+        }
+        Self::unpack_u64(input, start)
+    }
+
+    /// Helper function to deserialize a trailing optional bool
+    /// 
+    /// Returns false when the input ends at `start`, so older clients that
+    /// omit the field keep working.
+    fn unpack_optional_bool(input: &[u8], start: usize) -> Result<bool, ProgramError> {
+        if input.len() <= start {
+            return Ok(false);
+        }
+        Self::unpack_bool(input, start)
+    }
+
+    /// Helper function to deserialize a trailing optional curve type
+    /// 
+    /// Returns the constant product curve when the input ends at `start`, so
+    /// older clients that omit the field keep working.
+    fn unpack_optional_curve_type(input: &[u8], start: usize) -> Result<CurveType, ProgramError> {
+        match input.get(start) {
+            None | Some(0) => Ok(CurveType::ConstantProduct),
+            Some(1) => Ok(CurveType::Stable),
+            Some(2) => Ok(CurveType::Offset),
+            Some(3) => Ok(CurveType::Weighted),
+            Some(_) => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+} 
+
+/// Builds an `InitializePool` instruction for a native SOL constant product pool
+/// 
+/// The pool, SOL vault, protocol config and registry PDAs are derived here.
+/// `pool_index` is the pool registry's current `pool_count`, and `treasury`
+/// is the protocol treasury when the config charges a pool-creation fee.
+/// The initial tokens move from `initializer_token_account` into
+/// `pool_token_account`, a token account owned by the pool PDA, and the
+/// initial LP tokens are minted to `initializer_lp_account`.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    token_mint: &Pubkey,
+    lp_mint: &Pubkey,
+    treasury: Option<&Pubkey>,
+    pool_index: u64,
+    initializer_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    initializer_lp_account: &Pubkey,
+    token_program: &Pubkey,
+    sol_amount: Lamports,
+    token_amount: TokenAmount,
+    fee_rate: Bps,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &TokenExchangeInstruction::InitializePool {
+            sol_amount,
+            token_amount,
+            fee_rate,
+            non_transferable_lp: false,
+            token_pair: false,
+            wsol: false,
+            curve_type: CurveType::ConstantProduct,
+            amplification: 0,
+            token_offset: 0,
+            trade_start_timestamp: 0,
+            launch_window_seconds: 0,
+            max_swap_per_wallet_during_launch: 0,
+            launch_fee_bps: 0,
+            launch_fee_decay_seconds: 0,
+            sol_weight_bps: 0,
+            sol_offset: 0,
+            graduation_threshold: 0,
+        },
+        initialize_pool_accounts(
+            program_id,
+            initializer,
+            token_mint,
+            lp_mint,
+            treasury,
+            pool_index,
+            fee_rate,
+            initializer_token_account,
+            pool_token_account,
+            initializer_lp_account,
+            token_program,
+        ),
+    )
+}
+
+/// Builds an `InitializePoolWithPrice` instruction for a native SOL pool
+/// 
+/// Takes the same accounts as `initialize_pool`; `initial_price` is in
+/// lamports per token base unit, scaled by `LIMIT_PRICE_SCALE`.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_pool_with_price(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    token_mint: &Pubkey,
+    lp_mint: &Pubkey,
+    treasury: Option<&Pubkey>,
+    pool_index: u64,
+    initializer_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    initializer_lp_account: &Pubkey,
+    token_program: &Pubkey,
+    initial_price: u64,
+    token_amount: TokenAmount,
+    fee_rate: Bps,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &TokenExchangeInstruction::InitializePoolWithPrice {
+            initial_price,
+            token_amount,
+            fee_rate,
+            non_transferable_lp: false,
+            token_pair: false,
+            wsol: false,
+        },
+        initialize_pool_accounts(
+            program_id,
+            initializer,
+            token_mint,
+            lp_mint,
+            treasury,
+            pool_index,
+            fee_rate,
+            initializer_token_account,
+            pool_token_account,
+            initializer_lp_account,
+            token_program,
+        ),
+    )
+}
+
+/// Accounts of a native SOL pool's initialization, with its PDAs derived
+#[allow(clippy::too_many_arguments)]
+fn initialize_pool_accounts(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    token_mint: &Pubkey,
+    lp_mint: &Pubkey,
+    treasury: Option<&Pubkey>,
+    pool_index: u64,
+    fee_rate: Bps,
+    initializer_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    initializer_lp_account: &Pubkey,
+    token_program: &Pubkey,
+) -> Vec<AccountMeta> {
+    let pool = PoolState::find_address(&Pubkey::default(), token_mint, fee_rate.get(), program_id).0;
+    let mut accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(pool, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new(*lp_mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(PoolState::find_sol_vault_address(&pool, program_id).0, false),
+        AccountMeta::new_readonly(ProtocolConfig::find_address(program_id).0, false),
+    ];
+    if let Some(treasury) = treasury {
+        accounts.push(AccountMeta::new(*treasury, false));
+    }
+    accounts.push(AccountMeta::new(PoolRegistry::find_address(program_id).0, false));
+    accounts.push(AccountMeta::new(PoolRegistryEntry::find_address(pool_index, program_id).0, false));
+    accounts.extend([
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(*initializer_token_account, false),
+        AccountMeta::new(*pool_token_account, false),
+        AccountMeta::new(*initializer_lp_account, false),
+    ]);
+    accounts
+}
+
+/// Builds a `Swap` instruction against a native SOL pool, without a platform or referral fee
+/// 
+/// The user's wallet is their SOL account.
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    pool: &Pubkey,
+    user_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    is_sol_input: bool,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &TokenExchangeInstruction::Swap {
+            amount_in,
+            minimum_amount_out,
+            is_sol_input,
+            platform_fee_bps: Bps::ZERO,
+            referral_fee_share_bps: Bps::ZERO,
+            sandwich_protection: false,
+        },
+        vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new(*user_token_account, false),
+            AccountMeta::new(*pool_token_account, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(PoolState::find_sol_vault_address(pool, program_id).0, false),
+        ],
+    )
+}
+
+/// Builds an `AddLiquidity` instruction depositing both amounts as given
+/// 
+/// The provider's wallet is their SOL account.
+#[allow(clippy::too_many_arguments)]
+pub fn add_liquidity(
+    program_id: &Pubkey,
+    provider: &Pubkey,
+    pool: &Pubkey,
+    provider_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    provider_lp_account: &Pubkey,
+    lp_mint: &Pubkey,
+    token_program: &Pubkey,
+    sol_amount: Lamports,
+    token_amount: TokenAmount,
+    minimum_lp_tokens: LpAmount,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &TokenExchangeInstruction::AddLiquidity {
+            sol_amount,
+            token_amount,
+            minimum_lp_tokens,
+            match_pool_ratio: false,
+            minimum_sol: Lamports::ZERO,
+            minimum_token: TokenAmount::ZERO,
+        },
+        vec![
+            AccountMeta::new(*provider, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*provider, true),
+            AccountMeta::new(*provider_token_account, false),
+            AccountMeta::new(*pool_token_account, false),
+            AccountMeta::new(*provider_lp_account, false),
+            AccountMeta::new(*lp_mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(PoolState::find_sol_vault_address(pool, program_id).0, false),
+        ],
+    )
+}
+
+/// Builds a `RemoveLiquidity` instruction paying SOL to the provider's wallet
+#[allow(clippy::too_many_arguments)]
+pub fn remove_liquidity(
+    program_id: &Pubkey,
+    provider: &Pubkey,
+    pool: &Pubkey,
+    provider_token_account: &Pubkey,
+    pool_token_account: &Pubkey,
+    provider_lp_account: &Pubkey,
+    lp_mint: &Pubkey,
+    token_program: &Pubkey,
+    lp_tokens: LpAmount,
+    minimum_sol: Lamports,
+    minimum_token: TokenAmount,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        *program_id,
+        &TokenExchangeInstruction::RemoveLiquidity {
+            lp_tokens,
+            minimum_sol,
+            minimum_token,
+        },
+        vec![
+            AccountMeta::new(*provider, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*provider, true),
+            AccountMeta::new(*provider_token_account, false),
+            AccountMeta::new(*pool_token_account, false),
+            AccountMeta::new(*provider_lp_account, false),
+            AccountMeta::new(*lp_mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(PoolState::find_sol_vault_address(pool, program_id).0, false),
+        ],
+    )
+}
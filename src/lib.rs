@@ -3,21 +3,55 @@
 
 use solana_program::{
     account_info::AccountInfo, // For handling account information
-    entrypoint,               // Macro for declaring program entry point
     entrypoint::ProgramResult,// Type for program result handling
     pubkey::Pubkey,          // For handling public keys
 };
 
 // Module declarations for program components
+pub mod amount;     // Unit-typed amount wrappers
+pub mod bin_pool;   // Discrete price bin liquidity pools
+pub mod client;     // Off-chain transaction builders
+pub mod concentrated; // Tick-based concentrated liquidity pools
+pub mod curve;      // Swap, deposit and withdrawal pricing curves
 pub mod error;      // Custom error definitions
+pub mod events;     // Structured events for indexers
+pub mod farm;       // LP staking reward farms
+pub mod flash_loan; // Flash loans checked by instruction introspection
 pub mod instruction;// Instruction handling and definitions
+pub mod lp_report;  // Off-chain LP cost basis and PnL reporting
+pub mod math;       // Deterministic integer math
+pub mod oracle;     // External oracle price feeds
 pub mod processor;  // Core business logic implementation
+#[cfg(not(target_os = "solana"))]
+pub mod quote;      // Off-chain swap and deposit quotes and market depth
+#[cfg(all(feature = "replay", not(target_os = "solana")))]
+pub mod replay;     // Off-chain transaction replay for forensics
+pub mod sandwich;   // Same-transaction sandwich protection by instruction introspection
+pub mod signed_order; // Pre-signed orders submitted by relayers
 pub mod state;      // Program state and account structures
+pub mod token_interface; // SPL Token and Token-2022 compatibility
+pub mod validation; // Account validation shared by every handler
 
 use crate::processor::Processor;
 
+// The program's address, exported as `id()` and `ID` for clients and programs
+// that CPI into it
+//
+// This is the address the TypeScript suites in tests/ deploy to; there is no
+// public deployment. Deployments under another keypair must replace it here and
+// in those suites, as the README's "Program ID" section describes. Handlers use
+// the runtime's `program_id`, so only `id()` callers depend on it.
+solana_program::declare_id!("F11d9Ct1MHaQhyRGR7TgyPavoCSPkrzhGf6Wh9g41JS");
+
 // Declare the program's entry point using Solana's entrypoint macro
-entrypoint!(process_instruction);
+//
+// Off-chain users (simulators, routers, tests) depend on this crate with the
+// `no-entrypoint` feature and call `Processor::process` directly against
+// in-memory `AccountInfo`s; the handler logic is identical either way. Other
+// on-chain programs use the same feature to link against the instruction
+// builders and state types without a second `entrypoint` symbol.
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 /// Program entrypoint - The gateway for all interactions with this Solana program
 /// 
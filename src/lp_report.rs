@@ -0,0 +1,169 @@
+// LP Reporting
+// This module reconstructs a liquidity provider's cost basis and PnL off-chain
+
+/// Whether an LP activity added or removed liquidity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LpActivityKind {
+    /// `AddLiquidity` (or a tranche or migration into the pool)
+    Deposit,
+    /// `RemoveLiquidity` (or a migration out of the pool)
+    Withdrawal,
+}
+
+/// One deposit or withdrawal by the LP, with the pool state right after it
+/// 
+/// Clients decode these from the program's `LiquidityAddedEvent` and
+/// `LiquidityRemovedEvent` logs, with the LP supply read from the LP mint
+/// at the same slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LpActivity {
+    pub slot: u64,              // Slot the activity executed in
+    pub kind: LpActivityKind,   // Deposit or withdrawal
+    pub sol_amount: u64,        // SOL deposited or received
+    pub token_amount: u64,      // Tokens deposited or received
+    pub lp_tokens: u64,         // LP tokens minted or burned
+    pub pool: PoolSnapshot,     // Pool after the activity
+}
+
+/// Pool reserves and LP supply at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub sol_reserve: u64,       // SOL reserve
+    pub token_reserve: u64,     // Token reserve
+    pub lp_supply: u64,         // LP token supply
+}
+
+impl PoolSnapshot {
+    /// Token price in SOL
+    fn price(&self) -> f64 {
+        if self.token_reserve == 0 {
+            return 0.;
+        }
+        self.sol_reserve as f64 / self.token_reserve as f64
+    }
+
+    /// Invariant per LP token, `sqrt(k) / supply`, which only grows with fees
+    fn growth(&self) -> f64 {
+        if self.lp_supply == 0 {
+            return 0.;
+        }
+        (self.sol_reserve as f64 * self.token_reserve as f64).sqrt() / self.lp_supply as f64
+    }
+}
+
+/// An LP's position performance, valued in SOL at the report's pool snapshot
+/// 
+/// Withdrawals release cost basis pro rata (average cost).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LpReport {
+    pub lp_tokens: u64,             // LP tokens still held
+    pub cost_basis_sol: f64,        // Deposit value of the LP tokens still held
+    pub position_value_sol: f64,    // Current value of the held LP tokens
+    pub hodl_value_sol: f64,        // Current value of the held share of deposits, had they not been pooled
+    pub realized_pnl_sol: f64,      // Withdrawal proceeds minus the cost basis they released
+    pub unrealized_pnl_sol: f64,    // Position value minus cost basis
+    pub fee_income_sol: f64,        // Realized and unrealized fee income
+    pub impermanent_loss_sol: f64,  // Fee-free position value minus HODL value (negative = loss)
+}
+
+impl LpReport {
+    /// Serializes the report as a JSON object
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"lp_tokens\":{},\"cost_basis_sol\":{},\"position_value_sol\":{},\"hodl_value_sol\":{},\
+             \"realized_pnl_sol\":{},\"unrealized_pnl_sol\":{},\"fee_income_sol\":{},\"impermanent_loss_sol\":{}}}",
+            self.lp_tokens,
+            self.cost_basis_sol,
+            self.position_value_sol,
+            self.hodl_value_sol,
+            self.realized_pnl_sol,
+            self.unrealized_pnl_sol,
+            self.fee_income_sol,
+            self.impermanent_loss_sol,
+        )
+    }
+}
+
+/// Replays an LP's activities, in slot order, and values the result at `current`
+/// 
+/// # Arguments
+/// * `activities` - The LP's deposits and withdrawals, oldest first
+/// * `current` - The pool state to value the remaining position at
+pub fn build_report(activities: &[LpActivity], current: &PoolSnapshot) -> LpReport {
+    let mut report = LpReport::default();
+    let mut entry_growth = 0.;
+    let (mut hodl_sol, mut hodl_token) = (0., 0.);
+    let mut realized_fees = 0.;
+
+    for activity in activities {
+        let price = activity.pool.price();
+        let value = activity.sol_amount as f64 + activity.token_amount as f64 * price;
+        match activity.kind {
+            LpActivityKind::Deposit => {
+                let held = report.lp_tokens as f64;
+                let added = activity.lp_tokens as f64;
+                if held + added > 0. {
+                    entry_growth = (entry_growth * held + activity.pool.growth() * added) / (held + added);
+                }
+                report.lp_tokens = report.lp_tokens.saturating_add(activity.lp_tokens);
+                report.cost_basis_sol += value;
+                hodl_sol += activity.sol_amount as f64;
+                hodl_token += activity.token_amount as f64;
+            }
+            LpActivityKind::Withdrawal => {
+                if report.lp_tokens == 0 {
+                    continue;
+                }
+                let burned = activity.lp_tokens.min(report.lp_tokens);
+                let fraction = burned as f64 / report.lp_tokens as f64;
+                let released_basis = report.cost_basis_sol * fraction;
+                report.realized_pnl_sol += value - released_basis;
+                realized_fees += burned as f64 * 2. * (activity.pool.growth() - entry_growth) * price.sqrt();
+                report.cost_basis_sol -= released_basis;
+                hodl_sol *= 1. - fraction;
+                hodl_token *= 1. - fraction;
+                report.lp_tokens -= burned;
+            }
+        }
+    }
+
+    // Value what is still held at the current pool state
+    let price = current.price();
+    if current.lp_supply > 0 {
+        let share = report.lp_tokens as f64 / current.lp_supply as f64;
+        report.position_value_sol = share * (current.sol_reserve as f64 + current.token_reserve as f64 * price);
+    }
+    let unrealized_fees = report.lp_tokens as f64 * 2. * (current.growth() - entry_growth) * price.sqrt();
+    report.hodl_value_sol = hodl_sol + hodl_token * price;
+    report.unrealized_pnl_sol = report.position_value_sol - report.cost_basis_sol;
+    report.fee_income_sol = realized_fees + unrealized_fees;
+    report.impermanent_loss_sol = report.position_value_sol - unrealized_fees - report.hodl_value_sol;
+    report
+}
+
+/// Exports activities as CSV, one row per activity with its SOL valuation
+pub fn activities_to_csv(activities: &[LpActivity]) -> String {
+    let mut csv = String::from(
+        "slot,kind,sol_amount,token_amount,lp_tokens,value_sol,sol_reserve,token_reserve,lp_supply\n",
+    );
+    for activity in activities {
+        let kind = match activity.kind {
+            LpActivityKind::Deposit => "deposit",
+            LpActivityKind::Withdrawal => "withdrawal",
+        };
+        let value = activity.sol_amount as f64 + activity.token_amount as f64 * activity.pool.price();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            activity.slot,
+            kind,
+            activity.sol_amount,
+            activity.token_amount,
+            activity.lp_tokens,
+            value,
+            activity.pool.sol_reserve,
+            activity.pool.token_reserve,
+            activity.pool.lp_supply,
+        ));
+    }
+    csv
+}
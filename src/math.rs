@@ -0,0 +1,213 @@
+// Integer Math
+// This module provides deterministic integer roots and explicitly rounded division for on-chain pricing
+
+/// Integer square root, rounded down
+/// 
+/// Newton's method from a power of two above the root, so every step
+/// decreases until the floor of the root is reached. The square root of a
+/// Q64.64 fixed-point number is its Q32.32 square root.
+pub fn sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let bits = 128 - value.leading_zeros();
+    let mut root = 1u128 << bits.div_ceil(2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// Geometric mean of two amounts, `sqrt(a * b)` rounded down
+/// 
+/// The product of two u64s fits a u128 and its root fits a u64.
+pub fn geometric_mean(a: u64, b: u64) -> u64 {
+    sqrt(a as u128 * b as u128) as u64
+}
+
+/// Direction a division rounds its quotient
+/// 
+/// Pool math always rounds in the pool's favor: outputs, withdrawals and
+/// minted LP tokens round down, while inputs and fees the pool requires
+/// round up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Toward zero, for amounts the pool pays out or mints
+    Down,
+    /// Away from zero, for amounts the pool requires
+    Up,
+}
+
+/// `numerator / denominator` rounded as asked, or `None` when dividing by zero
+pub fn div(numerator: u128, denominator: u128, rounding: Rounding) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    Some(match rounding {
+        Rounding::Down => numerator / denominator,
+        Rounding::Up => numerator.div_ceil(denominator),
+    })
+}
+
+/// `a * b / c` rounded as asked, or `None` on overflow or division by zero
+pub fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> Option<u128> {
+    div(a.checked_mul(b)?, c, rounding)
+}
+
+/// `a * b / c` through a 256-bit product, rounded as asked, or `None` when
+/// the quotient overflows a u128 or `c` is zero
+/// 
+/// For products that may not fit a u128, such as liquidity times a Q64.64
+/// square root price.
+pub fn mul_div_wide(a: u128, b: u128, c: u128, rounding: Rounding) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let (high, low) = widening_mul(a, b);
+    if high == 0 {
+        return div(low, c, rounding);
+    }
+    if high >= c {
+        return None;
+    }
+
+    // Long division of the 256-bit product, one bit at a time; the remainder
+    // stays below `c`, so a bit shifted out of it means it exceeds `c`
+    let mut remainder = high;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let overflow = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if overflow == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    match rounding {
+        Rounding::Up if remainder > 0 => quotient.checked_add(1),
+        _ => Some(quotient),
+    }
+}
+
+/// Full 256-bit product of two u128s, as its high and low halves
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW_MASK: u128 = u64::MAX as u128;
+    let (a_low, a_high) = (a & LOW_MASK, a >> 64);
+    let (b_low, b_high) = (b & LOW_MASK, b >> 64);
+    let low_low = a_low * b_low;
+    let low_high = a_low * b_high;
+    let high_low = a_high * b_low;
+    let middle = (low_low >> 64) + (low_high & LOW_MASK) + (high_low & LOW_MASK);
+    let low = (low_low & LOW_MASK) | (middle << 64);
+    let high = a_high * b_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// One in Q64.64 fixed point
+pub const Q64: u128 = 1 << 64;
+
+/// Relative error allowed for `pow` and `weighted_geometric_mean`, as a
+/// right shift: each result is pushed 2^-40 of itself in its rounding
+/// direction, well past the truncation error of `log2` and `exp2`
+const POW_MARGIN_SHIFT: u32 = 40;
+
+/// Base-2 logarithm of a Q64.64 number, as a signed Q64.64 number rounded
+/// down, or `None` for zero
+/// 
+/// The integer part is the position of the leading bit; the fraction comes
+/// one bit at a time from repeatedly squaring the normalized mantissa.
+pub fn log2(value: u128) -> Option<i128> {
+    if value == 0 {
+        return None;
+    }
+    let leading = 127 - value.leading_zeros() as i128;
+    let mut result = (leading - 64) << 64;
+
+    // Mantissa in [1, 2) as Q1.63, so its square fits a u128
+    let mut mantissa = if leading >= 63 { value >> (leading - 63) } else { value << (63 - leading) };
+    for bit in (0..64).rev() {
+        mantissa = (mantissa * mantissa) >> 63;
+        if mantissa >= 1 << 64 {
+            mantissa >>= 1;
+            result |= 1 << bit;
+        }
+    }
+    Some(result)
+}
+
+/// Two raised to a signed Q64.64 power, as a Q64.64 number rounded down, or
+/// `None` when it overflows a u128
+/// 
+/// The fractional power is `e^(f * ln 2)` summed as a Taylor series, then
+/// shifted by the integer power.
+pub fn exp2(exponent: i128) -> Option<u128> {
+    // ln 2 in Q0.64
+    const LN_2: u128 = 0xB172_17F7_D1CF_79AB;
+    let whole = exponent >> 64;
+    let fraction = (exponent as u128) & (Q64 - 1);
+    let power = (fraction * LN_2) >> 64;
+
+    // Every term after the first is below one, so each product fits a u128
+    let mut term = Q64;
+    let mut sum = Q64;
+    for k in 1.. {
+        term = ((term * power) >> 64) / k;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    match whole {
+        0.. => (whole < 63).then(|| sum << whole),
+        -127..=-1 => Some(sum >> -whole),
+        _ => Some(0),
+    }
+}
+
+/// `(base_numerator / base_denominator)^(exponent_numerator / exponent_denominator)`
+/// as a Q64.64 number rounded as asked, or `None` on overflow or a zero input
+/// 
+/// The base's logarithm is the difference of its parts' logarithms, so a
+/// ratio far from one keeps its full precision. The result is moved by its
+/// error margin in the rounding direction.
+pub fn pow(
+    base_numerator: u128,
+    base_denominator: u128,
+    exponent_numerator: u64,
+    exponent_denominator: u64,
+    rounding: Rounding,
+) -> Option<u128> {
+    if exponent_denominator == 0 {
+        return None;
+    }
+    let log_base = log2(base_numerator)? - log2(base_denominator)?;
+    let exponent = log_base.checked_mul(exponent_numerator as i128)? / exponent_denominator as i128;
+    with_margin(exp2(exponent)?, rounding)
+}
+
+/// Weighted geometric mean `a^weight_a * b^weight_b` of two amounts, with
+/// weights in basis points summing to 10000, rounded down
+/// 
+/// The 50/50 mean is `geometric_mean`; a zero amount gives zero.
+pub fn weighted_geometric_mean(a: u64, b: u64, weight_a: u64, weight_b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    let log_a = log2((a as u128) << 64)?.checked_mul(weight_a as i128)?;
+    let log_b = log2((b as u128) << 64)?.checked_mul(weight_b as i128)?;
+    let mean = with_margin(exp2(log_a.checked_add(log_b)? / 10000)?, Rounding::Down)?;
+    u64::try_from(mean >> 64).ok()
+}
+
+/// Pushes an approximate result by its error margin in the rounding direction
+fn with_margin(value: u128, rounding: Rounding) -> Option<u128> {
+    let margin = (value >> POW_MARGIN_SHIFT) + 1;
+    match rounding {
+        Rounding::Down => Some(value.saturating_sub(margin)),
+        Rounding::Up => value.checked_add(margin),
+    }
+}
@@ -0,0 +1,110 @@
+// Oracle Price Feeds
+// This module reads external price feeds used to condition order execution,
+// and computes time-weighted prices from the pools' own accumulators
+
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    program_error::ProgramError,                               // Error handling
+};
+
+use crate::error::TokenExchangeError;                          // Custom errors
+
+/// Magic number at the start of every Pyth account
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+
+/// Pyth account type tag for price accounts
+const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+
+/// Pyth aggregate status meaning the price is currently trading
+const PYTH_STATUS_TRADING: u32 = 1;
+
+/// Minimum size of a Pyth price account up to the aggregate price fields
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 240;
+
+/// Maximum age of an oracle price, in slots, before it is considered stale
+pub const MAX_ORACLE_STALENESS_SLOTS: u64 = 25;
+
+/// A price read from an external oracle
+/// 
+/// The value is `price * 10^expo`, with `conf` in the same units.
+#[derive(Debug, Clone, Copy)]
+pub struct OraclePrice {
+    pub price: i64,         // Aggregate price
+    pub conf: u64,          // Confidence interval
+    pub expo: i32,          // Decimal exponent applied to price and conf
+    pub publish_slot: u64,  // Slot the aggregate price was published in
+}
+
+impl OraclePrice {
+    /// Loads the aggregate price from a Pyth price account
+    /// 
+    /// Only prices whose aggregate status is `Trading` are accepted.
+    /// 
+    /// # Arguments
+    /// * `account` - The Pyth price account
+    pub fn load_pyth(account: &AccountInfo) -> Result<Self, ProgramError> {
+        let data = account.data.borrow();
+        if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN
+            || read_u32(&data, 0) != PYTH_MAGIC
+            || read_u32(&data, 8) != PYTH_PRICE_ACCOUNT_TYPE
+        {
+            return Err(TokenExchangeError::InvalidOracle.into());
+        }
+        if read_u32(&data, 224) != PYTH_STATUS_TRADING {
+            return Err(TokenExchangeError::InvalidOracle.into());
+        }
+
+        Ok(Self {
+            price: read_u64(&data, 208) as i64,
+            conf: read_u64(&data, 216),
+            expo: read_u32(&data, 20) as i32,
+            publish_slot: read_u64(&data, 232),
+        })
+    }
+
+    /// Rejects prices published more than `max_age_slots` before `current_slot`
+    pub fn check_fresh(&self, current_slot: u64, max_age_slots: u64) -> Result<(), ProgramError> {
+        if current_slot.saturating_sub(self.publish_slot) > max_age_slots {
+            return Err(TokenExchangeError::StaleOracle.into());
+        }
+        Ok(())
+    }
+
+    /// Converts the price to a fixed-point value with `decimals` decimal places
+    /// 
+    /// Returns `None` for non-positive prices or if the result does not fit in a u64.
+    pub fn to_fixed(&self, decimals: u32) -> Option<u64> {
+        if self.price <= 0 {
+            return None;
+        }
+        let shift = decimals as i32 + self.expo;
+        let value = if shift >= 0 {
+            (self.price as u128).checked_mul(10u128.checked_pow(shift as u32)?)?
+        } else {
+            (self.price as u128) / 10u128.checked_pow(shift.unsigned_abs())?
+        };
+        u64::try_from(value).ok()
+    }
+}
+
+/// Time-weighted average price between two readings of a price accumulator
+/// 
+/// Readings are `(price_cumulative, slot)` pairs, e.g. from
+/// `PoolState::price_cumulative_at` or an `Observation`. Returns the Q64.64
+/// token price in SOL, or `None` if the newer reading is not strictly after
+/// the older one. Wrapping subtraction keeps it correct across accumulator
+/// overflow.
+pub fn twap(older_cumulative: u128, older_slot: u64, newer_cumulative: u128, newer_slot: u64) -> Option<u128> {
+    let elapsed = newer_slot.checked_sub(older_slot).filter(|slots| *slots > 0)?;
+    Some(newer_cumulative.wrapping_sub(older_cumulative) / elapsed as u128)
+}
+
+/// Reads a little-endian u32 at `offset`
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads a little-endian u64 at `offset`
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
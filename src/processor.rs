@@ -5,22 +5,199 @@ use borsh::{BorshDeserialize, BorshSerialize};                 // For state seri
 use solana_program::{
     account_info::{next_account_info, AccountInfo},            // Account handling
     entrypoint::ProgramResult,                                 // Program result type
+    instruction::Instruction,                                  // CPI instructions
     msg,                                                       // Logging
-    program::{invoke, invoke_signed},                          // CPI utilities
+    program_option::COption,                                   // Mint authority option
+    program::{invoke, invoke_signed, set_return_data},         // CPI utilities and return data
     program_error::ProgramError,                               // Error handling
-    program_pack::Pack,                                        // Account packing
+    program_pack::Pack,                                        // Token account sizes
     pubkey::Pubkey,                                           // Public key type
     system_instruction,                                        // System program instructions
-    sysvar::{rent::Rent, Sysvar},                            // System variables
+    sysvar::{clock::Clock, rent::Rent, Sysvar},              // System variables
 };
-use spl_token::state::Account as TokenAccount;                // SPL token account type
 
 use crate::{
+    amount::{Bps, Lamports, LpAmount, SwapAmount, TokenAmount}, // Typed amounts
+    bin_pool::{                                               // Bin liquidity pools
+        self, BinPool, BinPosition, BinState, BIN_POOL_SEED, BIN_POSITION_SEED, BIN_SEED, MAX_BIN_POSITION_WIDTH,
+        MAX_BIN_STEP,
+    },
+    concentrated::{                                           // Concentrated liquidity pools
+        self, ConcentratedPool, ConcentratedPosition, TickState, CONCENTRATED_POOL_SEED,
+        CONCENTRATED_POSITION_SEED, MAX_TICK, MAX_TICK_SPACING, MIN_TICK, TICK_SEED,
+    },
+    curve::{self, MAX_AMPLIFICATION, MIN_WEIGHT_BPS},         // Pool pricing curves
     error::TokenExchangeError,                                // Custom errors
-    instruction::TokenExchangeInstruction,                    // Instruction definitions
-    state::{PoolState, UserPosition},                        // Program state
+    events::{                                                 // Indexer events
+        Event, FeesClaimedEvent, KeeperBountyPaidEvent, LiquidityAddedEvent, LiquidityRemovedEvent, PoolCreatedEvent,
+        PoolGraduatedEvent, ReservesSyncedEvent, SwapEvent,
+    },
+    farm::{self, FarmReward, FarmStake, FarmState, FARM_SEED, FARM_STAKE_SEED, MAX_FARM_REWARDS}, // LP reward farms
+    flash_loan,                                               // Flash loan fees and repay checks
+    instruction::{LiquidityResult, SwapResult, TokenExchangeInstruction}, // Instruction definitions and results
+    math::{self, Rounding, Q64},                              // Rounded division and Q64.64 prices
+    oracle::{OraclePrice, MAX_ORACLE_STALENESS_SLOTS},       // External price feeds
+    sandwich,                                                 // Same-transaction sandwich protection
+    signed_order::{                                           // Relayer-submitted orders
+        verify_ed25519_signature, SignedOrder, ORDER_AUTHORITY_SEED,
+    },
+    state::{                                                  // Program state
+        CurveType, DcaPosition, KeeperRegistration, LimitOrder, LiquiditySchedule, LpAllowlistEntry,
+        Observation, ObservationState, OrderType, PoolMetadata, PoolRegistry, PoolRegistryEntry,
+        PoolState, PoolStatePod, ProtocolConfig, ProtocolParameters, ProtocolStats, SealedOrder,
+        SealedOrderCommittee, SealedOrderTerms, StateVersion, TradingAllowance, UserPosition, UserStats, VolumeBucket,
+        VolumeStats,
+        DCA_POSITION_SEED, KEEPER_REGISTRATION_SEED, LIMIT_ORDER_SEED, LIMIT_PRICE_DECIMALS, LIMIT_PRICE_SCALE,
+        LIQUIDITY_SCHEDULE_SEED, LP_ALLOWLIST_SEED, MAX_FEE_TIERS, OBSERVATIONS_SEED,
+        POOL_METADATA_SEED, POOL_REGISTRY_ENTRY_SEED, POOL_REGISTRY_SEED, POOL_SEED,
+        POSITION_SEED, PROTOCOL_CONFIG_SEED, PROTOCOL_STATS_SEED, SEALED_COMMITTEE_SEED,
+        SEALED_ORDER_EXECUTION_WINDOW_SLOTS, SEALED_ORDER_SEED, SOL_VAULT_SEED,
+        TRADING_ALLOWANCE_SEED, USER_STATS_SEED, VOLUME_BUCKET_COUNT, VOLUME_STATS_SEED,
+    },
+    token_interface::{self, InterestBearingConfig, TOKEN_2022_PROGRAM_ID}, // SPL Token and Token-2022
+    validation,                                               // Account validation
 };
 
+/// Maximum integrator fee a swap may carry, in basis points (1%)
+/// 
+/// The protocol config's `max_platform_fee_bps` may lower this further.
+pub const MAX_PLATFORM_FEE_BPS: Bps = Bps(100);
+
+/// Share of an expired order's rent paid to the keeper that closes it, in basis points (10%)
+pub const KEEPER_RENT_REBATE_BPS: u64 = 1000;
+
+/// Maximum share of the trading fee a pool may pay to limit-order keepers, in basis points (50%)
+/// 
+/// The protocol config's `max_keeper_bounty_bps` may lower this further.
+pub const MAX_KEEPER_BOUNTY_BPS: Bps = Bps(5000);
+
+/// Maximum share of each DCA tranche a position may tip its keeper, in basis points (1%)
+/// 
+/// The protocol config's `max_keeper_tip_bps` may lower this further.
+pub const MAX_DCA_KEEPER_TIP_BPS: Bps = Bps(100);
+
+/// Maximum share of the trading fee a pool may accrue to the protocol, in basis points (50%)
+pub const MAX_PROTOCOL_FEE_SHARE_BPS: Bps = Bps(5000);
+
+/// Maximum share of the trading fee the protocol config may let swaps pay referrers, in basis points (50%)
+/// 
+/// Together with `MAX_PROTOCOL_FEE_SHARE_BPS` this never exceeds the whole fee.
+pub const MAX_REFERRAL_FEE_SHARE_BPS: Bps = Bps(5000);
+
+/// Highest variable fee the protocol config may let pools add to their trading fee, in basis points (1%)
+pub const MAX_VARIABLE_FEE_BPS: Bps = Bps(100);
+
+/// Widest band around the oracle price a pool may let swaps execute in, in basis points (50%)
+pub const MAX_ORACLE_DEVIATION_BPS: Bps = Bps(5000);
+
+/// Highest trading fee a pool's launch fee may start at, in basis points (50%)
+pub const MAX_LAUNCH_FEE_BPS: Bps = Bps(5000);
+
+/// Longest launch window a pool may cap buys or decay its launch fee over, in seconds (1 hour)
+pub const MAX_LAUNCH_WINDOW_SECONDS: u64 = 3600;
+
+/// Maximum trading fee a pool may charge, in basis points (1%)
+pub const MAX_FEE_RATE_BPS: Bps = Bps(100);
+
+/// Fee tier offered to new pools before the protocol config sets one, in basis points (0.3%)
+pub const DEFAULT_FEE_RATE_BPS: Bps = Bps(30);
+
+/// Fee tiers offered before the protocol config sets its own, in basis points
+pub const DEFAULT_FEE_TIERS_BPS: [u64; 4] = [1, 5, 30, 100];
+
+/// Optional companion accounts a pool operation may update
+/// 
+/// Passed as trailing accounts in any order and identified by their
+/// derived addresses.
+struct CompanionAccounts<'a, 'b> {
+    stats: Option<&'a AccountInfo<'b>>,        // Protocol stats PDA
+    observations: Option<&'a AccountInfo<'b>>, // Pool's observations PDA
+    token_mint: Option<&'a AccountInfo<'b>>,   // Token-2022 mint, for interest-bearing pools
+    volume_stats: Option<&'a AccountInfo<'b>>, // Pool's volume stats PDA
+}
+
+/// Split of a swap's trading fee, in units of the swap's input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapFees {
+    pub protocol_fee: u64,  // Set aside in the pool's protocol fee balance
+    pub carved_fee: u64,    // Paid out of the input to a referrer or keeper
+    pub position_fee: u64,  // Set aside in the pool's LP fees for position liquidity
+    pub fee_growth: u128,   // Fee growth the position fee adds per credited LP token, in Q64.64
+}
+
+impl SwapFees {
+    /// Part of `amount_in` left for the input reserve
+    pub fn reserve_amount_in(&self, amount_in: u64) -> Option<u64> {
+        amount_in
+            .checked_sub(self.protocol_fee)?
+            .checked_sub(self.carved_fee)?
+            .checked_sub(self.position_fee)
+    }
+}
+
+/// Terms of a new resting order, shared by every order type
+struct OrderTerms {
+    order_id: u64,          // Owner-chosen identifier
+    amount: u64,            // Input amount to escrow
+    limit_price: u64,       // Minimum output per input, scaled by LIMIT_PRICE_SCALE
+    is_sol_input: bool,     // Whether the order sells SOL
+    expiry_slot: u64,       // Slot after which the order expires (0 = never)
+    order_type: OrderType,  // Limit, stop-loss, or oracle-conditioned
+    trigger_price: u64,     // Stop or oracle trigger price (0 for limits)
+    trigger_below: bool,    // Oracle trigger direction
+}
+
+/// Terms of a new DCA position
+struct DcaTerms {
+    dca_id: u64,            // Owner-chosen identifier
+    amount: u64,            // Total input to escrow
+    amount_per_fill: u64,   // Input sold per tranche, keeper tip included
+    interval_slots: u64,    // Slots between tranches
+    is_sol_input: bool,     // Whether the position sells SOL
+    keeper_tip_bps: Bps,    // Share of each tranche paid to the keeper
+    limit_price: u64,       // Minimum output per input, scaled by LIMIT_PRICE_SCALE (0 = no floor)
+}
+
+/// Terms of a new pool beyond its initial deposit and fee tier
+struct PoolTerms {
+    non_transferable_lp: bool,              // Whether LP tokens are soulbound
+    token_pair: bool,                       // Whether the pool pairs two SPL mints
+    wsol: bool,                             // Whether the SOL vault holds wrapped SOL
+    curve_type: CurveType,                  // Pricing curve
+    amplification: u64,                     // StableSwap amplification (0 for other curves)
+    token_offset: u64,                      // Virtual token reserve of offset pools (0 for other curves)
+    trade_start_timestamp: i64,             // Unix time before which no swap executes
+    launch_window_seconds: u64,             // Seconds after trade start during which buys are capped
+    max_swap_per_wallet_during_launch: u64, // Most tokens one buy may take in the launch window (0 = no cap)
+    launch_fee_bps: u64,                    // Trading fee at trade start, decaying to the fee rate (0 = none)
+    launch_fee_decay_seconds: u64,          // Seconds over which the launch fee decays
+    sol_weight_bps: u64,                    // SOL (or mint A) weight of weighted pools (0 for other curves)
+    sol_offset: u64,                        // Virtual SOL reserve of offset pools (0 for other curves)
+    graduation_threshold: u64,              // SOL reserve at which a bonding curve graduates (0 = none)
+}
+
+impl PoolTerms {
+    /// Terms of a constant product pool with no launch settings
+    fn constant_product(non_transferable_lp: bool, token_pair: bool, wsol: bool) -> Self {
+        Self {
+            non_transferable_lp,
+            token_pair,
+            wsol,
+            curve_type: CurveType::ConstantProduct,
+            amplification: 0,
+            token_offset: 0,
+            trade_start_timestamp: 0,
+            launch_window_seconds: 0,
+            max_swap_per_wallet_during_launch: 0,
+            launch_fee_bps: 0,
+            launch_fee_decay_seconds: 0,
+            sol_weight_bps: 0,
+            sol_offset: 0,
+            graduation_threshold: 0,
+        }
+    }
+}
+
 /// Main processor struct for handling program logic
 pub struct Processor;
 
@@ -44,22 +221,73 @@ impl Processor {
                 sol_amount,
                 token_amount,
                 fee_rate,
+                non_transferable_lp,
+                token_pair,
+                wsol,
+                curve_type,
+                amplification,
+                token_offset,
+                trade_start_timestamp,
+                launch_window_seconds,
+                max_swap_per_wallet_during_launch,
+                launch_fee_bps,
+                launch_fee_decay_seconds,
+                sol_weight_bps,
+                sol_offset,
+                graduation_threshold,
             } => {
                 msg!("Instruction: Initialize Pool");
-                Self::process_initialize_pool(accounts, program_id, sol_amount, token_amount, fee_rate)
+                Self::process_initialize_pool(
+                    accounts,
+                    program_id,
+                    sol_amount,
+                    token_amount,
+                    fee_rate,
+                    PoolTerms {
+                        non_transferable_lp,
+                        token_pair,
+                        wsol,
+                        curve_type,
+                        amplification,
+                        token_offset,
+                        trade_start_timestamp,
+                        launch_window_seconds,
+                        max_swap_per_wallet_during_launch,
+                        launch_fee_bps,
+                        launch_fee_decay_seconds,
+                        sol_weight_bps,
+                        sol_offset,
+                        graduation_threshold,
+                    },
+                )
             }
             TokenExchangeInstruction::Swap {
                 amount_in,
                 minimum_amount_out,
                 is_sol_input,
+                platform_fee_bps,
+                referral_fee_share_bps,
+                sandwich_protection,
             } => {
                 msg!("Instruction: Swap");
-                Self::process_swap(accounts, program_id, amount_in, minimum_amount_out, is_sol_input)
+                let amount_in = SwapAmount::new(amount_in, is_sol_input);
+                Self::process_swap(
+                    accounts,
+                    program_id,
+                    amount_in,
+                    amount_in.other_side(minimum_amount_out),
+                    platform_fee_bps,
+                    referral_fee_share_bps,
+                    sandwich_protection,
+                )
             }
             TokenExchangeInstruction::AddLiquidity {
                 sol_amount,
                 token_amount,
                 minimum_lp_tokens,
+                match_pool_ratio,
+                minimum_sol,
+                minimum_token,
             } => {
                 msg!("Instruction: Add Liquidity");
                 Self::process_add_liquidity(
@@ -68,6 +296,7 @@ impl Processor {
                     sol_amount,
                     token_amount,
                     minimum_lp_tokens,
+                    match_pool_ratio.then_some((minimum_sol, minimum_token)),
                 )
             }
             TokenExchangeInstruction::RemoveLiquidity {
@@ -84,29 +313,603 @@ impl Processor {
                     minimum_token,
                 )
             }
+            TokenExchangeInstruction::InitializeProtocolStats => {
+                msg!("Instruction: Initialize Protocol Stats");
+                Self::process_initialize_protocol_stats(accounts, program_id)
+            }
+            TokenExchangeInstruction::InitializeObservations { cardinality } => {
+                msg!("Instruction: Initialize Observations");
+                Self::process_initialize_observations(accounts, program_id, cardinality)
+            }
+            TokenExchangeInstruction::IncreaseObservationCardinality { new_cardinality } => {
+                msg!("Instruction: Increase Observation Cardinality");
+                Self::process_increase_observation_cardinality(accounts, program_id, new_cardinality)
+            }
+            TokenExchangeInstruction::MigrateLiquidity {
+                lp_tokens,
+                minimum_sol,
+                minimum_token,
+                minimum_lp_tokens,
+            } => {
+                msg!("Instruction: Migrate Liquidity");
+                Self::process_migrate_liquidity(
+                    accounts,
+                    program_id,
+                    lp_tokens,
+                    minimum_sol,
+                    minimum_token,
+                    minimum_lp_tokens,
+                )
+            }
+            TokenExchangeInstruction::PlaceLimitOrder {
+                order_id,
+                amount,
+                limit_price,
+                is_sol_input,
+                expiry_slot,
+            } => {
+                msg!("Instruction: Place Limit Order");
+                Self::process_place_order(
+                    accounts,
+                    program_id,
+                    OrderTerms {
+                        order_id,
+                        amount,
+                        limit_price,
+                        is_sol_input,
+                        expiry_slot,
+                        order_type: OrderType::Limit,
+                        trigger_price: 0,
+                        trigger_below: false,
+                    },
+                )
+            }
+            TokenExchangeInstruction::FillLimitOrder { fill_amount } => {
+                msg!("Instruction: Fill Limit Order");
+                Self::process_fill_limit_order(accounts, program_id, fill_amount)
+            }
+            TokenExchangeInstruction::ModifyLimitOrder {
+                new_limit_price,
+                new_remaining_amount,
+            } => {
+                msg!("Instruction: Modify Limit Order");
+                Self::process_modify_limit_order(
+                    accounts,
+                    program_id,
+                    new_limit_price,
+                    new_remaining_amount,
+                )
+            }
+            TokenExchangeInstruction::CancelLimitOrder => {
+                msg!("Instruction: Cancel Limit Order");
+                Self::process_cancel_limit_order(accounts, program_id)
+            }
+            TokenExchangeInstruction::CancelExpiredLimitOrder => {
+                msg!("Instruction: Cancel Expired Limit Order");
+                Self::process_cancel_expired_limit_order(accounts, program_id)
+            }
+            TokenExchangeInstruction::MatchLimitOrders => {
+                msg!("Instruction: Match Limit Orders");
+                Self::process_match_limit_orders(accounts, program_id)
+            }
+            TokenExchangeInstruction::UpdateKeeperBounty { keeper_bounty_bps } => {
+                msg!("Instruction: Update Keeper Bounty");
+                Self::process_update_keeper_bounty(accounts, program_id, keeper_bounty_bps)
+            }
+            TokenExchangeInstruction::PlaceStopOrder {
+                order_id,
+                amount,
+                stop_price,
+                max_slippage_bps,
+                is_sol_input,
+                expiry_slot,
+            } => {
+                msg!("Instruction: Place Stop Order");
+                if max_slippage_bps >= 10000 {
+                    return Err(ProgramError::InvalidArgument);
+                }
+                // The slippage floor becomes the order's limit price
+                let limit_price = (stop_price as u128 * (10000 - max_slippage_bps) as u128 / 10000) as u64;
+                Self::process_place_order(
+                    accounts,
+                    program_id,
+                    OrderTerms {
+                        order_id,
+                        amount,
+                        limit_price,
+                        is_sol_input,
+                        expiry_slot,
+                        order_type: OrderType::StopLoss,
+                        trigger_price: stop_price,
+                        trigger_below: true,
+                    },
+                )
+            }
+            TokenExchangeInstruction::PlaceOracleOrder {
+                order_id,
+                amount,
+                limit_price,
+                trigger_price,
+                trigger_below,
+                is_sol_input,
+                expiry_slot,
+            } => {
+                msg!("Instruction: Place Oracle Order");
+                Self::process_place_order(
+                    accounts,
+                    program_id,
+                    OrderTerms {
+                        order_id,
+                        amount,
+                        limit_price,
+                        is_sol_input,
+                        expiry_slot,
+                        order_type: OrderType::OracleConditioned,
+                        trigger_price,
+                        trigger_below,
+                    },
+                )
+            }
+            TokenExchangeInstruction::ExecuteOracleOrder => {
+                msg!("Instruction: Execute Oracle Order");
+                Self::process_execute_oracle_order(accounts, program_id)
+            }
+            TokenExchangeInstruction::CreateLiquiditySchedule {
+                schedule_id,
+                sol_per_tranche,
+                token_per_tranche,
+                tranche_count,
+                interval_slots,
+            } => {
+                msg!("Instruction: Create Liquidity Schedule");
+                Self::process_create_liquidity_schedule(
+                    accounts,
+                    program_id,
+                    schedule_id,
+                    sol_per_tranche,
+                    token_per_tranche,
+                    tranche_count,
+                    interval_slots,
+                )
+            }
+            TokenExchangeInstruction::ExecuteLiquidityTranche => {
+                msg!("Instruction: Execute Liquidity Tranche");
+                Self::process_execute_liquidity_tranche(accounts, program_id)
+            }
+            TokenExchangeInstruction::CloseLiquiditySchedule => {
+                msg!("Instruction: Close Liquidity Schedule");
+                Self::process_close_liquidity_schedule(accounts, program_id)
+            }
+            TokenExchangeInstruction::GrantTradingAllowance {
+                max_notional_per_window,
+                window_slots,
+            } => {
+                msg!("Instruction: Grant Trading Allowance");
+                Self::process_grant_trading_allowance(
+                    accounts,
+                    program_id,
+                    max_notional_per_window,
+                    window_slots,
+                )
+            }
+            TokenExchangeInstruction::RevokeTradingAllowance => {
+                msg!("Instruction: Revoke Trading Allowance");
+                Self::process_revoke_trading_allowance(accounts, program_id)
+            }
+            TokenExchangeInstruction::OperatorSwap {
+                amount_in,
+                minimum_amount_out,
+                is_sol_input,
+            } => {
+                msg!("Instruction: Operator Swap");
+                Self::process_operator_swap(
+                    accounts,
+                    program_id,
+                    amount_in,
+                    minimum_amount_out,
+                    is_sol_input,
+                )
+            }
+            TokenExchangeInstruction::ExecuteSignedOrder { order } => {
+                msg!("Instruction: Execute Signed Order");
+                Self::process_execute_signed_order(accounts, program_id, order)
+            }
+            TokenExchangeInstruction::RegisterKeeper => {
+                msg!("Instruction: Register Keeper");
+                Self::process_register_keeper(accounts, program_id)
+            }
+            TokenExchangeInstruction::DeregisterKeeper => {
+                msg!("Instruction: Deregister Keeper");
+                Self::process_deregister_keeper(accounts, program_id)
+            }
+            TokenExchangeInstruction::ArbitragePools {
+                amount_in,
+                minimum_profit,
+            } => {
+                msg!("Instruction: Arbitrage Pools");
+                Self::process_arbitrage_pools(accounts, program_id, amount_in, minimum_profit)
+            }
+            TokenExchangeInstruction::CaptureArbitrage {
+                amount_in,
+                minimum_profit,
+            } => {
+                msg!("Instruction: Capture Arbitrage");
+                Self::process_capture_arbitrage(accounts, program_id, amount_in, minimum_profit)
+            }
+            TokenExchangeInstruction::InitializeUserStats => {
+                msg!("Instruction: Initialize User Stats");
+                Self::process_initialize_user_stats(accounts, program_id)
+            }
+            TokenExchangeInstruction::ConfigureSealedOrders {
+                threshold,
+                reveal_delay_slots,
+            } => {
+                msg!("Instruction: Configure Sealed Orders");
+                Self::process_configure_sealed_orders(accounts, program_id, threshold, reveal_delay_slots)
+            }
+            TokenExchangeInstruction::SubmitSealedOrder {
+                order_id,
+                escrow_amount,
+                is_sol_input,
+                commitment,
+                ciphertext,
+            } => {
+                msg!("Instruction: Submit Sealed Order");
+                Self::process_submit_sealed_order(
+                    accounts,
+                    program_id,
+                    order_id,
+                    escrow_amount,
+                    is_sol_input,
+                    commitment,
+                    ciphertext,
+                )
+            }
+            TokenExchangeInstruction::ExecuteSealedOrder { terms } => {
+                msg!("Instruction: Execute Sealed Order");
+                Self::process_execute_sealed_order(accounts, program_id, terms)
+            }
+            TokenExchangeInstruction::CancelSealedOrder => {
+                msg!("Instruction: Cancel Sealed Order");
+                Self::process_cancel_sealed_order(accounts, program_id)
+            }
+            TokenExchangeInstruction::SetRebasingMode { enabled } => {
+                msg!("Instruction: Set Rebasing Mode");
+                Self::process_set_rebasing_mode(accounts, program_id, enabled)
+            }
+            TokenExchangeInstruction::SetDepositPermissioned { enabled } => {
+                msg!("Instruction: Set Deposit Permissioned");
+                Self::process_set_deposit_permissioned(accounts, program_id, enabled)
+            }
+            TokenExchangeInstruction::AllowLiquidityProvider => {
+                msg!("Instruction: Allow Liquidity Provider");
+                Self::process_allow_liquidity_provider(accounts, program_id)
+            }
+            TokenExchangeInstruction::DisallowLiquidityProvider => {
+                msg!("Instruction: Disallow Liquidity Provider");
+                Self::process_disallow_liquidity_provider(accounts, program_id)
+            }
+            TokenExchangeInstruction::SetPoolMetadata { name, uri } => {
+                msg!("Instruction: Set Pool Metadata");
+                Self::process_set_pool_metadata(accounts, program_id, &name, &uri)
+            }
+            TokenExchangeInstruction::SetPoolDeprecated { deprecated } => {
+                msg!("Instruction: Set Pool Deprecated");
+                Self::process_set_pool_deprecated(accounts, program_id, deprecated)
+            }
+            TokenExchangeInstruction::InitializeVolumeStats => {
+                msg!("Instruction: Initialize Volume Stats");
+                Self::process_initialize_volume_stats(accounts, program_id)
+            }
+            TokenExchangeInstruction::SwapExactOut {
+                amount_out,
+                maximum_amount_in,
+                is_sol_output,
+            } => {
+                msg!("Instruction: Swap Exact Out");
+                let amount_out = SwapAmount::new(amount_out, is_sol_output);
+                Self::process_swap_exact_out(accounts, program_id, amount_out, amount_out.other_side(maximum_amount_in))
+            }
+            TokenExchangeInstruction::InitializeProtocolConfig { authority, parameters } => {
+                msg!("Instruction: Initialize Protocol Config");
+                Self::process_initialize_protocol_config(accounts, program_id, authority, parameters)
+            }
+            TokenExchangeInstruction::SetProtocolFeeShare { protocol_fee_share_bps } => {
+                msg!("Instruction: Set Protocol Fee Share");
+                Self::process_set_protocol_fee_share(accounts, program_id, protocol_fee_share_bps)
+            }
+            TokenExchangeInstruction::CollectProtocolFees => {
+                msg!("Instruction: Collect Protocol Fees");
+                Self::process_collect_protocol_fees(accounts, program_id)
+            }
+            TokenExchangeInstruction::UpdateFeeRate { new_fee_rate } => {
+                msg!("Instruction: Update Fee Rate");
+                Self::process_update_fee_rate(accounts, program_id, new_fee_rate)
+            }
+            TokenExchangeInstruction::PausePool => {
+                msg!("Instruction: Pause Pool");
+                Self::process_set_pool_paused(accounts, program_id, true)
+            }
+            TokenExchangeInstruction::UnpausePool => {
+                msg!("Instruction: Unpause Pool");
+                Self::process_set_pool_paused(accounts, program_id, false)
+            }
+            TokenExchangeInstruction::FlashBorrow { amount, is_sol } => {
+                msg!("Instruction: Flash Borrow");
+                Self::process_flash_borrow(accounts, program_id, amount, is_sol)
+            }
+            TokenExchangeInstruction::FlashRepay => {
+                msg!("Instruction: Flash Repay");
+                Self::process_flash_repay(accounts, program_id)
+            }
+            TokenExchangeInstruction::RouteSwap {
+                amount_in,
+                minimum_amount_out,
+                is_sol_input,
+            } => {
+                msg!("Instruction: Route Swap");
+                Self::process_route_swap(accounts, program_id, amount_in, minimum_amount_out, is_sol_input)
+            }
+            TokenExchangeInstruction::DepositSingleSided {
+                amount,
+                is_sol,
+                minimum_lp_tokens,
+            } => {
+                msg!("Instruction: Deposit Single Sided");
+                Self::process_deposit_single_sided(accounts, program_id, amount, is_sol, minimum_lp_tokens)
+            }
+            TokenExchangeInstruction::WithdrawSingleSided {
+                lp_tokens,
+                minimum_amount_out,
+                want_sol,
+            } => {
+                msg!("Instruction: Withdraw Single Sided");
+                Self::process_withdraw_single_sided(accounts, program_id, lp_tokens, minimum_amount_out, want_sol)
+            }
+            TokenExchangeInstruction::SetFeeOnTransferMode { enabled } => {
+                msg!("Instruction: Set Fee On Transfer Mode");
+                Self::process_set_fee_on_transfer_mode(accounts, program_id, enabled)
+            }
+            TokenExchangeInstruction::MigratePool => {
+                msg!("Instruction: Migrate Pool");
+                Self::process_migrate_pool(accounts, program_id)
+            }
+            TokenExchangeInstruction::UpdateProtocolConfig { authority, parameters } => {
+                msg!("Instruction: Update Protocol Config");
+                Self::process_update_protocol_config(accounts, program_id, authority, parameters)
+            }
+            TokenExchangeInstruction::ClaimFees => {
+                msg!("Instruction: Claim Fees");
+                Self::process_claim_fees(accounts, program_id)
+            }
+            TokenExchangeInstruction::InitializeUserPosition => {
+                msg!("Instruction: Initialize User Position");
+                Self::process_initialize_user_position(accounts, program_id)
+            }
+            TokenExchangeInstruction::CloseUserPosition => {
+                msg!("Instruction: Close User Position");
+                Self::process_close_user_position(accounts, program_id)
+            }
+            TokenExchangeInstruction::InitializeFarm { emission_rate, duration_slots } => {
+                msg!("Instruction: Initialize Farm");
+                Self::process_initialize_farm(accounts, program_id, emission_rate, duration_slots)
+            }
+            TokenExchangeInstruction::StakeLp { amount } => {
+                msg!("Instruction: Stake LP");
+                Self::process_stake_lp(accounts, program_id, amount)
+            }
+            TokenExchangeInstruction::UnstakeLp { amount } => {
+                msg!("Instruction: Unstake LP");
+                Self::process_unstake_lp(accounts, program_id, amount)
+            }
+            TokenExchangeInstruction::HarvestRewards => {
+                msg!("Instruction: Harvest Rewards");
+                Self::process_harvest_rewards(accounts, program_id)
+            }
+            TokenExchangeInstruction::AddRewardToken { emission_rate, duration_slots } => {
+                msg!("Instruction: Add Reward Token");
+                Self::process_add_reward_token(accounts, program_id, emission_rate, duration_slots)
+            }
+            TokenExchangeInstruction::ExtendEmission { reward_index, duration_slots } => {
+                msg!("Instruction: Extend Emission");
+                Self::process_extend_emission(accounts, program_id, reward_index, duration_slots)
+            }
+            TokenExchangeInstruction::UpdateVariableFee { variable_fee_control, max_variable_fee_bps } => {
+                msg!("Instruction: Update Variable Fee");
+                Self::process_update_variable_fee(accounts, program_id, variable_fee_control, max_variable_fee_bps)
+            }
+            TokenExchangeInstruction::UpdatePriceImpactLimit { max_price_impact_bps } => {
+                msg!("Instruction: Update Price Impact Limit");
+                Self::process_update_price_impact_limit(accounts, program_id, max_price_impact_bps)
+            }
+            TokenExchangeInstruction::SetPriceOracle { max_deviation_bps } => {
+                msg!("Instruction: Set Price Oracle");
+                Self::process_set_price_oracle(accounts, program_id, max_deviation_bps)
+            }
+            TokenExchangeInstruction::InitializeConcentratedPool { fee_rate, tick_spacing, initial_sqrt_price } => {
+                msg!("Instruction: Initialize Concentrated Pool");
+                Self::process_initialize_concentrated_pool(
+                    accounts,
+                    program_id,
+                    fee_rate,
+                    tick_spacing,
+                    initial_sqrt_price,
+                )
+            }
+            TokenExchangeInstruction::InitializeTick { tick_index, prev_tick, next_tick } => {
+                msg!("Instruction: Initialize Tick");
+                Self::process_initialize_tick(accounts, program_id, tick_index, prev_tick, next_tick)
+            }
+            TokenExchangeInstruction::OpenPosition { tick_lower, tick_upper } => {
+                msg!("Instruction: Open Position");
+                Self::process_open_position(accounts, program_id, tick_lower, tick_upper)
+            }
+            TokenExchangeInstruction::IncreaseLiquidity { liquidity, max_amount_a, max_amount_b } => {
+                msg!("Instruction: Increase Liquidity");
+                Self::process_increase_liquidity(accounts, program_id, liquidity, max_amount_a, max_amount_b)
+            }
+            TokenExchangeInstruction::DecreaseLiquidity { liquidity, min_amount_a, min_amount_b } => {
+                msg!("Instruction: Decrease Liquidity");
+                Self::process_decrease_liquidity(accounts, program_id, liquidity, min_amount_a, min_amount_b)
+            }
+            TokenExchangeInstruction::SwapConcentrated { amount_in, minimum_amount_out, a_to_b } => {
+                msg!("Instruction: Swap Concentrated");
+                Self::process_swap_concentrated(accounts, program_id, amount_in, minimum_amount_out, a_to_b)
+            }
+            TokenExchangeInstruction::InitializeBinPool { fee_rate, bin_step, active_bin } => {
+                msg!("Instruction: Initialize Bin Pool");
+                Self::process_initialize_bin_pool(accounts, program_id, fee_rate, bin_step, active_bin)
+            }
+            TokenExchangeInstruction::InitializeBin { bin_id } => {
+                msg!("Instruction: Initialize Bin");
+                Self::process_initialize_bin(accounts, program_id, bin_id)
+            }
+            TokenExchangeInstruction::OpenBinPosition { lower_bin, width } => {
+                msg!("Instruction: Open Bin Position");
+                Self::process_open_bin_position(accounts, program_id, lower_bin, width)
+            }
+            TokenExchangeInstruction::AddBinLiquidity { amount_a, amount_b, active_bin } => {
+                msg!("Instruction: Add Bin Liquidity");
+                Self::process_add_bin_liquidity(accounts, program_id, amount_a, amount_b, active_bin)
+            }
+            TokenExchangeInstruction::RemoveBinLiquidity { share_bps, min_amount_a, min_amount_b } => {
+                msg!("Instruction: Remove Bin Liquidity");
+                Self::process_remove_bin_liquidity(accounts, program_id, share_bps, min_amount_a, min_amount_b)
+            }
+            TokenExchangeInstruction::SwapBins { amount_in, minimum_amount_out, a_to_b } => {
+                msg!("Instruction: Swap Bins");
+                Self::process_swap_bins(accounts, program_id, amount_in, minimum_amount_out, a_to_b)
+            }
+            TokenExchangeInstruction::Graduate => {
+                msg!("Instruction: Graduate");
+                Self::process_graduate(accounts, program_id)
+            }
+            TokenExchangeInstruction::OpenDca {
+                dca_id,
+                amount,
+                amount_per_fill,
+                interval_slots,
+                is_sol_input,
+                keeper_tip_bps,
+                limit_price,
+            } => {
+                msg!("Instruction: Open DCA");
+                Self::process_open_dca(
+                    accounts,
+                    program_id,
+                    DcaTerms {
+                        dca_id,
+                        amount,
+                        amount_per_fill,
+                        interval_slots,
+                        is_sol_input,
+                        keeper_tip_bps,
+                        limit_price,
+                    },
+                )
+            }
+            TokenExchangeInstruction::ExecuteDca => {
+                msg!("Instruction: Execute DCA");
+                Self::process_execute_dca(accounts, program_id)
+            }
+            TokenExchangeInstruction::CloseDca => {
+                msg!("Instruction: Close DCA");
+                Self::process_close_dca(accounts, program_id)
+            }
+            TokenExchangeInstruction::UpdateOutflowLimit { max_outflow_bps } => {
+                msg!("Instruction: Update Outflow Limit");
+                Self::process_update_outflow_limit(accounts, program_id, max_outflow_bps)
+            }
+            TokenExchangeInstruction::SetEmergencyMode { emergency } => {
+                msg!("Instruction: Set Emergency Mode");
+                Self::process_set_emergency_mode(accounts, program_id, emergency)
+            }
+            TokenExchangeInstruction::EmergencyWithdraw { lp_tokens } => {
+                msg!("Instruction: Emergency Withdraw");
+                Self::process_emergency_withdraw(accounts, program_id, lp_tokens)
+            }
+            TokenExchangeInstruction::SyncReserves => {
+                msg!("Instruction: Sync Reserves");
+                Self::process_sync_reserves(accounts, program_id)
+            }
+            TokenExchangeInstruction::Skim => {
+                msg!("Instruction: Skim");
+                Self::process_skim(accounts, program_id)
+            }
+            TokenExchangeInstruction::InitializePoolWithPrice {
+                initial_price,
+                token_amount,
+                fee_rate,
+                non_transferable_lp,
+                token_pair,
+                wsol,
+            } => {
+                msg!("Instruction: Initialize Pool With Price");
+                let sol_amount = Self::sol_amount_for_price(initial_price, token_amount)?;
+                Self::process_initialize_pool(
+                    accounts,
+                    program_id,
+                    sol_amount,
+                    token_amount,
+                    fee_rate,
+                    PoolTerms::constant_product(non_transferable_lp, token_pair, wsol),
+                )
+            }
         }
     }
 
     /// Initializes a new liquidity pool with initial SOL and token deposits
     /// 
     /// # Arguments
-    /// * `accounts` - Required accounts:
+    /// * `accounts` - Required accounts, in order:
     ///   - Initializer (signer)
-    ///   - Pool state account
+    ///   - Pool state PDA
     ///   - Token mint
     ///   - LP token mint
     ///   - System program
+    ///   - SOL vault PDA, or mint A for token pair pools
+    ///   - Protocol config PDA
+    ///   - Protocol treasury (only when the config charges a pool-creation fee)
+    ///   - Pool registry PDA
+    ///   - Registry entry PDA for the new pool
+    ///   - Token program
+    ///   - Initializer's token account
+    ///   - Pool token vault
+    ///   - Initializer's LP token account
+    ///   - Native mint (wSOL pools only)
+    ///   - Initializer's mint A token account (token pair pools only)
+    ///   - Pool mint A vault (token pair pools only)
+    ///   - Optional companion accounts (the protocol stats PDA)
     /// * `program_id` - The program's public key
-    /// * `sol_amount` - Initial SOL deposit
+    /// * `sol_amount` - Initial SOL deposit (mint A for token pair pools)
     /// * `token_amount` - Initial token deposit
     /// * `fee_rate` - Trading fee in basis points
+    /// * `terms` - The pool's mode, curve and launch settings
     fn process_initialize_pool(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
-        sol_amount: u64,
-        token_amount: u64,
-        fee_rate: u64,
+        sol_amount: Lamports,
+        token_amount: TokenAmount,
+        fee_rate: Bps,
+        terms: PoolTerms,
     ) -> ProgramResult {
+        let PoolTerms {
+            non_transferable_lp,
+            token_pair,
+            wsol,
+            curve_type,
+            amplification,
+            token_offset,
+            trade_start_timestamp,
+            launch_window_seconds,
+            max_swap_per_wallet_during_launch,
+            launch_fee_bps,
+            launch_fee_decay_seconds,
+            sol_weight_bps,
+            sol_offset,
+            graduation_threshold,
+        } = terms;
+
         // Get account references
         let account_info_iter = &mut accounts.iter();
         let initializer = next_account_info(account_info_iter)?;
@@ -114,46 +917,470 @@ impl Processor {
         let token_mint = next_account_info(account_info_iter)?;
         let lp_mint = next_account_info(account_info_iter)?;
         let system_program = next_account_info(account_info_iter)?;
+        let side_a_account = next_account_info(account_info_iter)?;
+        let (mint_a, sol_vault) = if token_pair {
+            (Some(side_a_account), None)
+        } else {
+            (None, Some(side_a_account))
+        };
+        let config_account = next_account_info(account_info_iter)?;
+        let protocol = Self::load_protocol_parameters(config_account, program_id)?;
+        let treasury = if protocol.pool_creation_fee > 0 {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let registry_account = next_account_info(account_info_iter)?;
+        let registry_entry_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let initializer_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let initializer_lp_account = next_account_info(account_info_iter)?;
+        let native_mint = if wsol {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let mint_a_accounts = if token_pair {
+            Some((next_account_info(account_info_iter)?, next_account_info(account_info_iter)?))
+        } else {
+            None
+        };
+        let companions = Self::companion_accounts(account_info_iter, program_id, pool_account.key)?;
 
         // Verify initializer is a signer
         if !initializer.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
+        validation::check_token_program(token_program, pool_token_account)?;
+
+        // The protocol config bounds every new pool
+        if protocol.pool_creation_paused {
+            return Err(TokenExchangeError::PoolCreationPaused.into());
+        }
+        if !protocol.allows_fee_tier(fee_rate.get()) {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+        // Each curve takes only its own parameters, and only offset pools graduate
+        let has_offset = token_offset > 0 || sol_offset > 0;
+        let valid_curve_parameters = match curve_type {
+            CurveType::ConstantProduct => amplification == 0 && !has_offset && sol_weight_bps == 0,
+            CurveType::Stable => {
+                (1..=MAX_AMPLIFICATION).contains(&amplification) && !has_offset && sol_weight_bps == 0
+            }
+            CurveType::Offset => amplification == 0 && has_offset && sol_weight_bps == 0,
+            CurveType::Weighted => {
+                amplification == 0 && !has_offset && (MIN_WEIGHT_BPS..=10000 - MIN_WEIGHT_BPS).contains(&sol_weight_bps)
+            }
+        };
+        if !valid_curve_parameters || (graduation_threshold > 0 && curve_type != CurveType::Offset) {
+            return Err(TokenExchangeError::InvalidCurveParameters.into());
+        }
+
+        // Accrued interest scales the token reserve and every token amount
+        // alike, which the constant product and weighted curves price the same
+        // in raw and UI amounts; a stable peg or a virtual token offset would
+        // drift from the UI price as interest accrues
+        let interest_bearing = *token_mint.owner == TOKEN_2022_PROGRAM_ID
+            && InterestBearingConfig::load(&token_mint.data.borrow()).is_some();
+        if interest_bearing && matches!(curve_type, CurveType::Stable | CurveType::Offset) {
+            return Err(TokenExchangeError::InvalidCurveParameters.into());
+        }
+        let weights = match curve_type {
+            CurveType::Weighted => [sol_weight_bps, 10000 - sol_weight_bps],
+            _ => [0; 2],
+        };
+        let valid_launch_fee = launch_fee_bps == 0
+            || (launch_fee_bps > fee_rate.get()
+                && launch_fee_bps <= MAX_LAUNCH_FEE_BPS.get()
+                && (1..=MAX_LAUNCH_WINDOW_SECONDS).contains(&launch_fee_decay_seconds));
+        if launch_window_seconds > MAX_LAUNCH_WINDOW_SECONDS || !valid_launch_fee {
+            return Err(TokenExchangeError::InvalidLaunchProtection.into());
+        }
+
+        // Side A of a token pair must be a distinct SPL mint
+        if let Some(mint_a) = mint_a {
+            token_interface::check_token_program(mint_a.owner)?;
+            token_interface::unpack_mint(&mint_a.data.borrow())?;
+            if mint_a.key == token_mint.key {
+                return Err(TokenExchangeError::InvalidTokenMint.into());
+            }
+        }
+
+        // Wrapped SOL goes through the pool token's program, which must be SPL Token
+        if wsol && (token_pair || *token_mint.owner != spl_token::id()) {
+            return Err(TokenExchangeError::UnsupportedWsolMode.into());
+        }
+
+        // Soulbound LP relies on Token-2022 refusing transfers of the LP mint
+        if non_transferable_lp
+            && (*lp_mint.owner != TOKEN_2022_PROGRAM_ID
+                || !token_interface::is_non_transferable_mint(&lp_mint.data.borrow()))
+        {
+            return Err(TokenExchangeError::InvalidLpMint.into());
+        }
+
+        // The pool lives at the PDA of its pair and fee tier
+        let mint_a_key = mint_a.map_or(Pubkey::default(), |mint_a| *mint_a.key);
+        let (expected_pool, bump) = PoolState::find_address(&mint_a_key, token_mint.key, fee_rate.get(), program_id);
+        if *pool_account.key != expected_pool {
+            return Err(TokenExchangeError::InvalidPoolAccount.into());
+        }
+
+        // Only the pool PDA may mint LP tokens, so none can exist beforehand
+        token_interface::check_token_program(lp_mint.owner)?;
+        let lp_mint_state = token_interface::unpack_mint(&lp_mint.data.borrow())?;
+        if lp_mint_state.mint_authority != COption::Some(expected_pool) || lp_mint_state.supply != 0 {
+            return Err(TokenExchangeError::InvalidLpMint.into());
+        }
+
+        // Pay the protocol's pool-creation fee
+        if let Some(treasury) = treasury {
+            if *treasury.key != protocol.treasury {
+                return Err(TokenExchangeError::InvalidProtocolConfig.into());
+            }
+            invoke(
+                &system_instruction::transfer(initializer.key, treasury.key, protocol.pool_creation_fee),
+                &[initializer.clone(), treasury.clone(), system_program.clone()],
+            )?;
+        }
+
+        // Create the pool account at its exact size unless it already exists
+        if pool_account.owner == program_id {
+            Self::check_existing_pool_account(pool_account)?;
+        } else {
+            Self::create_pda_account(
+                initializer,
+                pool_account,
+                system_program,
+                program_id,
+                PoolState::LEN,
+                &[
+                    POOL_SEED,
+                    mint_a_key.as_ref(),
+                    token_mint.key.as_ref(),
+                    &fee_rate.get().to_le_bytes(),
+                    &[bump],
+                ],
+            )?;
+        }
+
+        // Native SOL pools hold their SOL reserve in a separate vault PDA
+        if let Some(sol_vault) = sol_vault {
+            let (expected_vault, vault_bump) = PoolState::find_sol_vault_address(pool_account.key, program_id);
+            if *sol_vault.key != expected_vault {
+                return Err(TokenExchangeError::InvalidSolVault.into());
+            }
+            let vault_seeds: &[&[u8]] = &[SOL_VAULT_SEED, pool_account.key.as_ref(), &[vault_bump]];
+            match native_mint {
+                // In wSOL mode the vault is a wrapped SOL token account owned by the pool
+                Some(native_mint) => {
+                    if *native_mint.key != spl_token::native_mint::id() || *token_program.key != spl_token::id() {
+                        return Err(TokenExchangeError::UnsupportedWsolMode.into());
+                    }
+                    Self::create_pda_account(
+                        initializer,
+                        sol_vault,
+                        system_program,
+                        &spl_token::id(),
+                        spl_token::state::Account::LEN,
+                        vault_seeds,
+                    )?;
+                    invoke(
+                        &spl_token::instruction::initialize_account3(
+                            &spl_token::id(),
+                            sol_vault.key,
+                            native_mint.key,
+                            pool_account.key,
+                        )?,
+                        &[sol_vault.clone(), native_mint.clone()],
+                    )?;
+                }
+                None => Self::create_pda_account(
+                    initializer,
+                    sol_vault,
+                    system_program,
+                    program_id,
+                    0,
+                    vault_seeds,
+                )?,
+            }
+        }
 
-        // Create and initialize pool state
+        // Create and initialize pool state, with reserves filled in by the initial deposit
+        let created_at_slot = Clock::get()?.slot;
         let mut pool_state = PoolState {
+            discriminator: PoolState::DISCRIMINATOR,
+            version: StateVersion::CURRENT as u8,
             authority: *initializer.key,
-            sol_reserve: sol_amount,
-            token_reserve: token_amount,
+            sol_reserve: 0,
+            token_reserve: 0,
             lp_mint: *lp_mint.key,
-            fee_rate,
+            fee_rate: fee_rate.get(),
             token_mint: *token_mint.key,
             initialized: true,
+            keeper_bounty_bps: 0,
+            interest_bearing,
+            rebasing: false,
+            token_vault: Pubkey::default(),
+            non_transferable_lp,
+            deposit_permissioned: false,
+            creator: *initializer.key,
+            created_at_slot,
+            deprecated: false,
+            mint_a: mint_a_key,
+            bump,
+            sol_vault: sol_vault.map_or(Pubkey::default(), |sol_vault| *sol_vault.key),
+            protocol_fee_share_bps: protocol.protocol_fee_share_bps,
+            protocol_fee_sol: 0,
+            protocol_fee_token: 0,
+            wsol,
+            fee_tier: fee_rate.get(),
+            paused: false,
+            curve_type,
+            amplification,
+            token_offset,
+            price_cumulative_last: 0,
+            last_update_slot: created_at_slot,
+            flash_loan_amount: 0,
+            flash_loan_sol: false,
+            fee_on_transfer: false,
+            cumulative_sol_volume: 0,
+            cumulative_token_volume: 0,
+            cumulative_fees: 0,
+            lp_supply: 0,
+            position_lp_supply: 0,
+            fee_growth_sol: 0,
+            fee_growth_token: 0,
+            lp_fees_sol: 0,
+            lp_fees_token: 0,
+            trade_start_timestamp,
+            launch_window_seconds,
+            max_swap_per_wallet_during_launch,
+            launch_fee_bps,
+            launch_fee_decay_seconds,
+            volatility_accumulator: 0,
+            volatility_update_slot: 0,
+            variable_fee_control: 0,
+            max_variable_fee_bps: 0,
+            max_price_impact_bps: 0,
+            price_oracle: Pubkey::default(),
+            oracle_max_deviation_bps: 0,
+            ema_price: 0,
+            ema_update_slot: 0,
+            weights,
+            sol_offset,
+            graduation_threshold,
+            max_outflow_bps: 0,
+            outflow_slot: 0,
+            outflow_this_slot: [0; 2],
+            emergency: false,
         };
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+
+        // Transfer the initial deposit, crediting what the pool actually received
+        if let Some(sol_vault) = sol_vault {
+            invoke(
+                &system_instruction::transfer(initializer.key, sol_vault.key, sol_amount.get()),
+                &[initializer.clone(), sol_vault.clone(), system_program.clone()],
+            )?;
+            if wsol {
+                invoke(
+                    &spl_token::instruction::sync_native(token_program.key, sol_vault.key)?,
+                    std::slice::from_ref(sol_vault),
+                )?;
+            }
+        }
+        let sol_received = match mint_a_accounts {
+            Some((initializer_account_a, pool_vault_a)) => {
+                validation::check_pool_vault_a(&pool_state, pool_account.key, pool_vault_a, token_program.key)?;
+                Lamports(Self::deposit_side_a(
+                    &pool_state,
+                    initializer,
+                    initializer_account_a,
+                    pool_vault_a,
+                    token_program,
+                    sol_amount.get(),
+                )?)
+            }
+            None => sol_amount,
+        };
+        let token_received = TokenAmount(Self::transfer_into_vault(pool_token_account, || {
+            invoke(
+                &token_interface::transfer(
+                    token_program.key,
+                    initializer_token_account.key,
+                    pool_token_account.key,
+                    initializer.key,
+                    &[],
+                    token_amount.get(),
+                )?,
+                &[
+                    initializer_token_account.clone(),
+                    pool_token_account.clone(),
+                    initializer.clone(),
+                ],
+            )
+        })?);
+
+        // The initializer gets the first LP tokens; a bonding curve mints
+        // none, as its liquidity is only locked in when it graduates
+        let lp_tokens = if graduation_threshold > 0 {
+            LpAmount::ZERO
+        } else {
+            curve::for_pool(&pool_state).deposit_lp(&pool_state, sol_received, token_received, LpAmount::ZERO)?
+        };
+        pool_state.sol_reserve = sol_received.get();
+        pool_state.token_reserve = token_received.get();
+        pool_state.lp_supply = lp_tokens.get();
 
         // Save pool state to account
         pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
 
+        if lp_tokens > LpAmount::ZERO {
+            Self::invoke_signed_by_pool(
+                &token_interface::mint_to(
+                    lp_mint.owner,
+                    lp_mint.key,
+                    initializer_lp_account.key,
+                    pool_account.key,
+                    &[],
+                    lp_tokens.get(),
+                )?,
+                &[
+                    lp_mint.clone(),
+                    initializer_lp_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        // Record the pool in the registry at the next index
+        Self::register_pool(
+            initializer,
+            registry_account,
+            registry_entry_account,
+            system_program,
+            program_id,
+            &pool_state,
+            pool_account.key,
+        )?;
+
+        // Announce the pool to indexers
+        PoolCreatedEvent {
+            pool: *pool_account.key,
+            creator: *initializer.key,
+            mint_a: mint_a_key,
+            token_mint: *token_mint.key,
+            lp_mint: *lp_mint.key,
+            fee_rate: fee_rate.get(),
+            curve_type,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+
+        // Count the new pool in the protocol totals
+        if let Some(stats_account) = companions.stats {
+            let mut stats = ProtocolStats::try_from_slice(&stats_account.data.borrow())?;
+            stats.total_pools = stats.total_pools
+                .checked_add(1)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            stats.last_update_slot = Clock::get()?.slot;
+            stats.serialize(&mut *stats_account.data.borrow_mut())?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a newly created pool to the pool registry
+    /// 
+    /// The first pool creates the registry itself. Each pool's entry lives at
+    /// the PDA of the registry's count when it was created, so entries are
+    /// numbered `0..pool_count` without gaps.
+    fn register_pool<'a>(
+        payer: &AccountInfo<'a>,
+        registry_account: &AccountInfo<'a>,
+        registry_entry_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        program_id: &Pubkey,
+        pool_state: &PoolState,
+        pool: &Pubkey,
+    ) -> ProgramResult {
+        let (expected_registry, registry_bump) = PoolRegistry::find_address(program_id);
+        if *registry_account.key != expected_registry {
+            return Err(TokenExchangeError::InvalidPoolRegistry.into());
+        }
+        let mut registry = if registry_account.owner == program_id {
+            PoolRegistry::try_from_slice(&registry_account.data.borrow())?
+        } else {
+            Self::create_pda_account(
+                payer,
+                registry_account,
+                system_program,
+                program_id,
+                PoolRegistry::LEN,
+                &[POOL_REGISTRY_SEED, &[registry_bump]],
+            )?;
+            PoolRegistry {
+                pool_count: 0,
+                bump: registry_bump,
+            }
+        };
+
+        let index = registry.pool_count;
+        let (expected_entry, entry_bump) = PoolRegistryEntry::find_address(index, program_id);
+        if *registry_entry_account.key != expected_entry {
+            return Err(TokenExchangeError::InvalidPoolRegistry.into());
+        }
+        Self::create_pda_account(
+            payer,
+            registry_entry_account,
+            system_program,
+            program_id,
+            PoolRegistryEntry::LEN,
+            &[POOL_REGISTRY_ENTRY_SEED, &index.to_le_bytes(), &[entry_bump]],
+        )?;
+        let entry = PoolRegistryEntry {
+            index,
+            pool: *pool,
+            mint_a: pool_state.mint_a,
+            token_mint: pool_state.token_mint,
+            fee_tier: pool_state.fee_tier,
+            bump: entry_bump,
+        };
+        entry.serialize(&mut *registry_entry_account.data.borrow_mut())?;
+
+        registry.pool_count = index
+            .checked_add(1)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        registry.serialize(&mut *registry_account.data.borrow_mut())?;
         Ok(())
     }
 
     /// Executes a token swap between SOL and SPL tokens
     /// 
-    /// Implements constant product AMM formula (x * y = k)
+    /// Prices on the pool's curve (constant product or stable)
     /// with slippage protection and fee calculation.
     /// 
     /// # Arguments
     /// * `accounts` - Required accounts for the swap
     /// * `program_id` - The program's public key
-    /// * `amount_in` - Input token amount
-    /// * `minimum_amount_out` - Minimum acceptable output amount
-    /// * `is_sol_input` - Whether SOL is the input token
+    /// * `amount_in` - Input amount, SOL (mint A for token pair pools) or token
+    /// * `minimum_amount_out` - Minimum acceptable output amount, on the other side
+    /// * `platform_fee_bps` - Integrator fee taken from the output, in basis points
+    /// * `referral_fee_share_bps` - Referrer's share of the trading fee, in basis points
+    /// * `sandwich_protection` - Whether to reject transactions with other instructions touching the pool
+    #[allow(clippy::too_many_arguments)]
     fn process_swap(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
-        amount_in: u64,
-        minimum_amount_out: u64,
-        is_sol_input: bool,
+        amount_in: SwapAmount,
+        minimum_amount_out: SwapAmount,
+        platform_fee_bps: Bps,
+        referral_fee_share_bps: Bps,
+        sandwich_protection: bool,
     ) -> ProgramResult {
         // Get account references
         let account_info_iter = &mut accounts.iter();
@@ -164,115 +1391,441 @@ impl Processor {
         let pool_token_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
 
+        let is_sol_input = amount_in.is_sol();
+
         // Verify user is a signer
         if !user.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
         // Load and verify pool state
-        let mut pool_state = PoolState::try_from_slice(&pool_account.data.borrow())?;
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
         if !pool_state.initialized {
             return Err(TokenExchangeError::PoolNotInitialized.into());
         }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
 
-        // Calculate swap amounts using constant product formula (x * y = k)
-        let (amount_out, new_sol_reserve, new_token_reserve) = if is_sol_input {
-            // SOL → Token swap
-            let amount_out = Self::calculate_output_amount(
-                amount_in,
-                pool_state.sol_reserve,
-                pool_state.token_reserve,
-                pool_state.fee_rate,
-            )?;
-            
-            // Check slippage tolerance
-            if amount_out < minimum_amount_out {
-                return Err(TokenExchangeError::SlippageExceeded.into());
-            }
+        // Side A lives in the SOL vault, or in a mint A vault for token pair
+        // pools; a wSOL vault moves through the token program like the latter
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let pool_vault_a = if pool_state.is_token_pair() {
+            validation::check_pool_vault_a(&pool_state, pool_account.key, side_a_vault, token_program.key)?;
+            Some(side_a_vault)
+        } else {
+            validation::check_sol_vault(&pool_state, side_a_vault)?;
+            pool_state.wsol.then_some(side_a_vault)
+        };
 
-            // Calculate new reserves
-            (
-                amount_out,
-                pool_state.sol_reserve.checked_add(amount_in)
-                    .ok_or(TokenExchangeError::MathOverflow)?,
-                pool_state.token_reserve.checked_sub(amount_out)
-                    .ok_or(TokenExchangeError::MathOverflow)?,
-            )
+        // Integrator fee requires a destination account
+        let platform_fee_account = if platform_fee_bps > Bps::ZERO {
+            Some(next_account_info(account_info_iter)?)
         } else {
-            // Token → SOL swap
-            let amount_out = Self::calculate_output_amount(
-                amount_in,
-                pool_state.token_reserve,
-                pool_state.sol_reserve,
-                pool_state.fee_rate,
-            )?;
+            None
+        };
 
-            // Check slippage tolerance
-            if amount_out < minimum_amount_out {
-                return Err(TokenExchangeError::SlippageExceeded.into());
+        // The integrator fee and referrer's share are bounded by the protocol config,
+        // passed ahead of the referrer when either is charged
+        if platform_fee_bps > Bps::ZERO || referral_fee_share_bps > Bps::ZERO {
+            let config_account = next_account_info(account_info_iter)?;
+            let parameters = Self::load_protocol_parameters(config_account, program_id)?;
+            if platform_fee_bps.get() > parameters.max_platform_fee_bps {
+                return Err(TokenExchangeError::InvalidPlatformFee.into());
+            }
+            if referral_fee_share_bps.get() > parameters.max_referral_fee_share_bps {
+                return Err(TokenExchangeError::InvalidReferralFee.into());
             }
+        }
+        let referrer_account = if referral_fee_share_bps > Bps::ZERO {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
 
-            // Calculate new reserves
-            (
-                amount_out,
-                pool_state.sol_reserve.checked_sub(amount_out)
-                    .ok_or(TokenExchangeError::MathOverflow)?,
-                pool_state.token_reserve.checked_add(amount_in)
-                    .ok_or(TokenExchangeError::MathOverflow)?,
-            )
+        // Opt-in sandwich protection: no other instruction may touch the pool
+        if sandwich_protection {
+            let instructions_sysvar = next_account_info(account_info_iter)?;
+            sandwich::verify_pool_isolated(instructions_sysvar, pool_account.key)?;
+        }
+        let oracle_price = Self::pool_oracle_price(&pool_state, account_info_iter)?;
+        let companions = Self::companion_accounts(account_info_iter, program_id, pool_account.key)?;
+
+        // Collect the input first and price what the pool actually received
+        let received = if is_sol_input {
+            // SOL → Token: Transfer SOL (or mint A) to pool
+            match pool_vault_a {
+                Some(pool_vault_a) => Self::transfer_into_vault(pool_vault_a, || {
+                    invoke(
+                        &token_interface::transfer(
+                            token_program.key,
+                            user_sol_account.key,
+                            pool_vault_a.key,
+                            user.key,
+                            &[],
+                            amount_in.get(),
+                        )?,
+                        &[user_sol_account.clone(), pool_vault_a.clone(), user.clone()],
+                    )
+                })?,
+                None => {
+                    invoke(
+                        &system_instruction::transfer(user.key, side_a_vault.key, amount_in.get()),
+                        &[user.clone(), side_a_vault.clone()],
+                    )?;
+                    amount_in.get()
+                }
+            }
+        } else {
+            // Token → SOL: Transfer tokens to pool
+            Self::transfer_into_vault(pool_token_account, || {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        user_token_account.key,
+                        pool_token_account.key,
+                        user.key,
+                        &[],
+                        amount_in.get(),
+                    )?,
+                    &[
+                        user_token_account.clone(),
+                        pool_token_account.clone(),
+                        user.clone(),
+                    ],
+                )
+            })?
         };
 
-        // Update pool state with new reserves
-        pool_state.sol_reserve = new_sol_reserve;
-        pool_state.token_reserve = new_token_reserve;
-        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        let amount_received = amount_in.same_side(received);
 
-        // Execute token transfers
-        if is_sol_input {
-            // SOL → Token: Transfer SOL to pool
-            invoke(
-                &system_instruction::transfer(user.key, pool_account.key, amount_in),
-                &[user.clone(), pool_account.clone()],
-            )?;
+        // Calculate swap output on the pool's curve, at the fee charged before this swap moves the price
+        let fee_rate = Self::swap_fee_rate(&pool_state)?;
+        let amount_out = Self::calculate_swap_output(&pool_state, amount_received)?;
+
+        // Split the output between the user and the integrator
+        let platform_fee = (amount_out.get() as u128 * platform_fee_bps.get() as u128 / 10000) as u64;
+        let user_amount_out = amount_out
+            .get()
+            .checked_sub(platform_fee)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+
+        // Check slippage tolerance against what the user actually receives
+        if user_amount_out < minimum_amount_out.get() {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Carve the referral fee out of the LP part of the trading fee
+        let referral_fee = match referrer_account {
+            Some(_) => Self::calculate_protocol_fee(amount_received.get(), fee_rate, referral_fee_share_bps)?,
+            None => 0,
+        };
+
+        // Update price observations and protocol totals
+        Self::record_swap(&companions, &pool_state, amount_received, amount_out)?;
+
+        // Update pool state with new reserves, writing back only what changed
+        Self::apply_swap_reserves(&mut pool_state, amount_received, amount_out, referral_fee, oracle_price)?;
+        PoolStatePod::load_mut(&mut pool_account.data.borrow_mut())?.store_swap(&pool_state);
+
+        // Pay the referrer out of the input vault
+        if let Some(referrer_account) = referrer_account.filter(|_| referral_fee > 0) {
+            if is_sol_input && pool_vault_a.is_none() {
+                **side_a_vault.try_borrow_mut_lamports()? -= referral_fee;
+                **referrer_account.try_borrow_mut_lamports()? += referral_fee;
+            } else {
+                let input_vault = if is_sol_input { side_a_vault } else { pool_token_account };
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        input_vault.key,
+                        referrer_account.key,
+                        pool_account.key,
+                        &[],
+                        referral_fee,
+                    )?,
+                    &[input_vault.clone(), referrer_account.clone(), pool_account.clone()],
+                    &pool_state,
+                )?;
+            }
+        }
 
+        // Pay out the output
+        if is_sol_input {
             // Transfer tokens to user
-            invoke(
-                &spl_token::instruction::transfer(
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
                     token_program.key,
                     pool_token_account.key,
                     user_token_account.key,
                     pool_account.key,
                     &[],
-                    amount_out,
+                    user_amount_out,
                 )?,
                 &[
                     pool_token_account.clone(),
                     user_token_account.clone(),
                     pool_account.clone(),
                 ],
+                &pool_state,
             )?;
+
+            // Transfer the integrator's cut
+            if let Some(platform_fee_account) = platform_fee_account {
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        pool_token_account.key,
+                        platform_fee_account.key,
+                        pool_account.key,
+                        &[],
+                        platform_fee,
+                    )?,
+                    &[
+                        pool_token_account.clone(),
+                        platform_fee_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_state,
+                )?;
+            }
         } else {
-            // Token → SOL: Transfer tokens to pool
-            invoke(
-                &spl_token::instruction::transfer(
+            match pool_vault_a {
+                // Transfer mint A to the user and the integrator
+                Some(pool_vault_a) => {
+                    let payouts = std::iter::once((user_sol_account, user_amount_out))
+                        .chain(platform_fee_account.map(|account| (account, platform_fee)));
+                    for (destination, amount) in payouts {
+                        Self::invoke_signed_by_pool(
+                            &token_interface::transfer(
+                                token_program.key,
+                                pool_vault_a.key,
+                                destination.key,
+                                pool_account.key,
+                                &[],
+                                amount,
+                            )?,
+                            &[pool_vault_a.clone(), destination.clone(), pool_account.clone()],
+                            &pool_state,
+                        )?;
+                    }
+                }
+                None => {
+                    // Transfer SOL to user
+                    **side_a_vault.try_borrow_mut_lamports()? -= amount_out.get();
+                    **user_sol_account.try_borrow_mut_lamports()? += user_amount_out;
+
+                    // Transfer the integrator's cut
+                    if let Some(platform_fee_account) = platform_fee_account {
+                        **platform_fee_account.try_borrow_mut_lamports()? += platform_fee;
+                    }
+                }
+            }
+        }
+
+        // Report the execution to CPI callers, simulations and indexers
+        let fee_paid = Self::trading_fee(amount_received.get(), fee_rate)?;
+        SwapEvent {
+            pool: *pool_account.key,
+            user: *user.key,
+            is_sol_input,
+            amount_in: amount_received.get(),
+            amount_out: user_amount_out,
+            fee_paid,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+            referrer: referrer_account.map_or_else(Pubkey::default, |account| *account.key),
+            referral_fee,
+        }
+        .emit()?;
+        Self::set_result(&SwapResult {
+            amount_in: amount_received.get(),
+            amount_out: user_amount_out,
+            fee_paid,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
+        Ok(())
+    }
+
+    /// Executes a swap for an exact output amount
+    /// 
+    /// Charges the smallest input the pool's curve accepts for
+    /// `amount_out`, rejecting the swap if that exceeds `maximum_amount_in`.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the swap
+    /// * `program_id` - The program's public key
+    /// * `amount_out` - Exact output amount to deliver, SOL (mint A for token pair pools) or token
+    /// * `maximum_amount_in` - Maximum acceptable input amount, on the other side
+    fn process_swap_exact_out(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_out: SwapAmount,
+        maximum_amount_in: SwapAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let user = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let user_sol_account = next_account_info(account_info_iter)?;
+        let user_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify user is a signer
+        if !user.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let pool_vault_a = if pool_state.is_token_pair() {
+            validation::check_pool_vault_a(&pool_state, pool_account.key, side_a_vault, token_program.key)?;
+            Some(side_a_vault)
+        } else {
+            validation::check_sol_vault(&pool_state, side_a_vault)?;
+            pool_state.wsol.then_some(side_a_vault)
+        };
+        let oracle_price = Self::pool_oracle_price(&pool_state, account_info_iter)?;
+        let companions = Self::companion_accounts(account_info_iter, program_id, pool_account.key)?;
+
+        // Price the input needed for the exact output, at the fee charged before this swap moves the price
+        let is_sol_input = !amount_out.is_sol();
+        let fee_rate = Self::swap_fee_rate(&pool_state)?;
+        let amount_in = Self::calculate_swap_input(&pool_state, amount_out)?;
+        if amount_in.get() > maximum_amount_in.get() {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Update price observations and protocol totals
+        Self::record_swap(&companions, &pool_state, amount_in, amount_out)?;
+
+        // Update pool state with new reserves, writing back only what changed
+        Self::apply_swap_reserves(&mut pool_state, amount_in, amount_out, 0, oracle_price)?;
+        PoolStatePod::load_mut(&mut pool_account.data.borrow_mut())?.store_swap(&pool_state);
+
+        // Transfers move raw amounts
+        let (amount_in, amount_out) = (amount_in.get(), amount_out.get());
+
+        if is_sol_input {
+            // SOL (or mint A) in, tokens out
+            match pool_vault_a {
+                Some(pool_vault_a) => Self::collect_exact_token_input(&pool_state, pool_vault_a, amount_in, || {
+                    invoke(
+                        &token_interface::transfer(
+                            token_program.key,
+                            user_sol_account.key,
+                            pool_vault_a.key,
+                            user.key,
+                            &[],
+                            amount_in,
+                        )?,
+                        &[user_sol_account.clone(), pool_vault_a.clone(), user.clone()],
+                    )
+                })?,
+                None => invoke(
+                    &system_instruction::transfer(user.key, side_a_vault.key, amount_in),
+                    &[user.clone(), side_a_vault.clone()],
+                )?,
+            }
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
                     token_program.key,
-                    user_token_account.key,
                     pool_token_account.key,
-                    user.key,
+                    user_token_account.key,
+                    pool_account.key,
                     &[],
-                    amount_in,
+                    amount_out,
                 )?,
                 &[
-                    user_token_account.clone(),
                     pool_token_account.clone(),
-                    user.clone(),
+                    user_token_account.clone(),
+                    pool_account.clone(),
                 ],
+                &pool_state,
             )?;
+        } else {
+            // Tokens in, SOL (or mint A) out
+            Self::collect_exact_token_input(&pool_state, pool_token_account, amount_in, || {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        user_token_account.key,
+                        pool_token_account.key,
+                        user.key,
+                        &[],
+                        amount_in,
+                    )?,
+                    &[
+                        user_token_account.clone(),
+                        pool_token_account.clone(),
+                        user.clone(),
+                    ],
+                )
+            })?;
+
+            match pool_vault_a {
+                Some(pool_vault_a) => Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        pool_vault_a.key,
+                        user_sol_account.key,
+                        pool_account.key,
+                        &[],
+                        amount_out,
+                    )?,
+                    &[pool_vault_a.clone(), user_sol_account.clone(), pool_account.clone()],
+                    &pool_state,
+                )?,
+                None => {
+                    **side_a_vault.try_borrow_mut_lamports()? -= amount_out;
+                    **user_sol_account.try_borrow_mut_lamports()? += amount_out;
+                }
+            }
+        }
 
-            // Transfer SOL to user
-            **pool_account.try_borrow_mut_lamports()? -= amount_out;
-            **user_sol_account.try_borrow_mut_lamports()? += amount_out;
+        // Report the execution to CPI callers, simulations and indexers
+        let fee_paid = Self::trading_fee(amount_in, fee_rate)?;
+        SwapEvent {
+            pool: *pool_account.key,
+            user: *user.key,
+            is_sol_input,
+            amount_in,
+            amount_out,
+            fee_paid,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+            referrer: Pubkey::default(),
+            referral_fee: 0,
         }
+        .emit()?;
+        Self::set_result(&SwapResult {
+            amount_in,
+            amount_out,
+            fee_paid,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
 
         Ok(())
     }
@@ -288,12 +1841,15 @@ impl Processor {
     /// * `sol_amount` - Amount of SOL to deposit
     /// * `token_amount` - Amount of tokens to deposit
     /// * `minimum_lp_tokens` - Minimum acceptable LP tokens
+    /// * `ratio_minimums` - When set, the amounts are desired amounts trimmed
+    ///   to the pool ratio, each side no lower than its minimum
     fn process_add_liquidity(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
-        sol_amount: u64,
-        token_amount: u64,
-        minimum_lp_tokens: u64,
+        sol_amount: Lamports,
+        token_amount: TokenAmount,
+        minimum_lp_tokens: LpAmount,
+        ratio_minimums: Option<(Lamports, TokenAmount)>,
     ) -> ProgramResult {
         // Get account references
         let account_info_iter = &mut accounts.iter();
@@ -305,6 +1861,7 @@ impl Processor {
         let provider_lp_account = next_account_info(account_info_iter)?;
         let lp_mint = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
 
         // Verify provider is a signer
         if !provider.is_signer {
@@ -312,106 +1869,166 @@ impl Processor {
         }
 
         // Load pool state
-        let mut pool_state = PoolState::try_from_slice(&pool_account.data.borrow())?;
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
         if !pool_state.initialized {
             return Err(TokenExchangeError::PoolNotInitialized.into());
         }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        Self::check_side_a_vault(&pool_state, pool_account.key, sol_vault, token_program)?;
+        Self::check_deposit_allowed(
+            &pool_state,
+            pool_account.key,
+            provider.key,
+            account_info_iter.as_slice(),
+            program_id,
+        )?;
 
-        // Calculate LP tokens to mint
-        let lp_tokens = if pool_state.sol_reserve == 0 {
-            // Initial liquidity: Use geometric mean
-            ((sol_amount as f64) * (token_amount as f64)).sqrt() as u64
-        } else {
-            // Subsequent liquidity: Proportional to existing reserves
-            let total_supply = spl_token::state::Mint::unpack(&lp_mint.data.borrow())?.supply;
-            let sol_ratio = (sol_amount * 1_000_000) / pool_state.sol_reserve;
-            let token_ratio = (token_amount * 1_000_000) / pool_state.token_reserve;
-            let min_ratio = std::cmp::min(sol_ratio, token_ratio);
-            (min_ratio * total_supply) / 1_000_000
+        // Only pull what the pool ratio absorbs when asked to
+        let (sol_amount, token_amount) = match ratio_minimums {
+            Some((minimum_sol, minimum_token)) => Self::calculate_optimal_deposit(
+                &pool_state,
+                sol_amount,
+                token_amount,
+                minimum_sol,
+                minimum_token,
+            )?,
+            None => (sol_amount, token_amount),
         };
 
+        // Transfer assets, crediting what the pool actually received
+        let sol_received = Lamports(Self::deposit_side_a(
+            &pool_state,
+            provider,
+            provider_sol_account,
+            sol_vault,
+            token_program,
+            sol_amount.get(),
+        )?);
+
+        let token_received = TokenAmount(Self::transfer_into_vault(pool_token_account, || {
+            invoke(
+                &token_interface::transfer(
+                    token_program.key,
+                    provider_token_account.key,
+                    pool_token_account.key,
+                    provider.key,
+                    &[],
+                    token_amount.get(),
+                )?,
+                &[
+                    provider_token_account.clone(),
+                    pool_token_account.clone(),
+                    provider.clone(),
+                ],
+            )
+        })?);
+
+        // Keep the provider's position, if passed, in step with the LP tokens
+        let mut position =
+            Self::find_user_position(account_info_iter.as_slice(), pool_account.key, provider.key, program_id)?;
+
+        // Calculate LP tokens to mint
+        let total_supply = LpAmount(token_interface::unpack_mint(&lp_mint.data.borrow())?.supply);
+        let lp_tokens =
+            curve::for_pool(&pool_state).deposit_lp(&pool_state, sol_received, token_received, total_supply)?;
+
         // Check minimum LP tokens
         if lp_tokens < minimum_lp_tokens {
             return Err(TokenExchangeError::SlippageExceeded.into());
         }
 
         // Update pool state
-        pool_state.sol_reserve = pool_state.sol_reserve.checked_add(sol_amount)
+        pool_state.sol_reserve = pool_state.sol_reserve.checked_add(sol_received.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
-        pool_state.token_reserve = pool_state.token_reserve.checked_add(token_amount)
+        pool_state.token_reserve = pool_state.token_reserve.checked_add(token_received.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        if let Some((_, position)) = position.as_mut() {
+            Self::update_position(&mut pool_state, position, lp_tokens.get(), true)?;
+        }
+        pool_state.lp_supply = total_supply.get().checked_add(lp_tokens.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
         pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
-
-        // Transfer assets
-        invoke(
-            &system_instruction::transfer(provider.key, pool_account.key, sol_amount),
-            &[provider.clone(), pool_account.clone()],
-        )?;
-
-        invoke(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                provider_token_account.key,
-                pool_token_account.key,
-                provider.key,
-                &[],
-                token_amount,
-            )?,
-            &[
-                provider_token_account.clone(),
-                pool_token_account.clone(),
-                provider.clone(),
-            ],
-        )?;
+        if let Some((position_account, position)) = &position {
+            position.serialize(&mut *position_account.data.borrow_mut())?;
+        }
 
         // Mint LP tokens
-        invoke(
-            &spl_token::instruction::mint_to(
-                token_program.key,
+        Self::invoke_signed_by_pool(
+            &token_interface::mint_to(
+                lp_mint.owner,
                 lp_mint.key,
                 provider_lp_account.key,
                 pool_account.key,
                 &[],
-                lp_tokens,
+                lp_tokens.get(),
             )?,
             &[
                 lp_mint.clone(),
                 provider_lp_account.clone(),
                 pool_account.clone(),
             ],
+            &pool_state,
         )?;
 
+        // Report the execution to CPI callers, simulations and indexers
+        LiquidityAddedEvent {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            sol_amount: sol_received.get(),
+            token_amount: token_received.get(),
+            lp_tokens: lp_tokens.get(),
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+        Self::set_result(&LiquidityResult {
+            sol_amount: sol_received,
+            token_amount: token_received,
+            lp_tokens,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
         Ok(())
     }
 
-    /// Removes liquidity from the pool
+    /// Adds liquidity from one side of the pair
     /// 
-    /// Allows LP token holders to burn their tokens and withdraw
-    /// their proportional share of SOL and tokens from the pool.
+    /// The whole deposit enters the pool. Half of it is priced as a swap into
+    /// the other asset, and LP tokens are minted for the other half plus the
+    /// swap output, as a balanced deposit at the post-swap reserves.
     /// 
     /// # Arguments
-    /// * `accounts` - Required accounts for liquidity removal
+    /// * `accounts` - Required accounts for liquidity provision
     /// * `program_id` - The program's public key
-    /// * `lp_tokens` - Amount of LP tokens to burn
-    /// * `minimum_sol` - Minimum SOL to withdraw
-    /// * `minimum_token` - Minimum tokens to withdraw
-    fn process_remove_liquidity(
+    /// * `amount` - Amount of SOL or tokens to deposit
+    /// * `is_sol` - Whether the deposit is SOL
+    /// * `minimum_lp_tokens` - Minimum acceptable LP tokens
+    fn process_deposit_single_sided(
         accounts: &[AccountInfo],
         program_id: &Pubkey,
-        lp_tokens: u64,
-        minimum_sol: u64,
-        minimum_token: u64,
+        amount: u64,
+        is_sol: bool,
+        minimum_lp_tokens: LpAmount,
     ) -> ProgramResult {
         // Get account references
         let account_info_iter = &mut accounts.iter();
         let provider = next_account_info(account_info_iter)?;
         let pool_account = next_account_info(account_info_iter)?;
-        let provider_sol_account = next_account_info(account_info_iter)?;
-        let provider_token_account = next_account_info(account_info_iter)?;
+        let provider_source_account = next_account_info(account_info_iter)?;
         let pool_token_account = next_account_info(account_info_iter)?;
         let provider_lp_account = next_account_info(account_info_iter)?;
         let lp_mint = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
 
         // Verify provider is a signer
         if !provider.is_signer {
@@ -419,37 +2036,221 @@ impl Processor {
         }
 
         // Load pool state
-        let mut pool_state = PoolState::try_from_slice(&pool_account.data.borrow())?;
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
         if !pool_state.initialized {
             return Err(TokenExchangeError::PoolNotInitialized.into());
         }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        Self::check_side_a_vault(&pool_state, pool_account.key, sol_vault, token_program)?;
+        Self::check_deposit_allowed(
+            &pool_state,
+            pool_account.key,
+            provider.key,
+            account_info_iter.as_slice(),
+            program_id,
+        )?;
 
-        // Calculate withdrawal amounts
-        let total_supply = spl_token::state::Mint::unpack(&lp_mint.data.borrow())?.supply;
-        let sol_amount = (pool_state.sol_reserve * lp_tokens) / total_supply;
-        let token_amount = (pool_state.token_reserve * lp_tokens) / total_supply;
+        // Transfer the deposit, crediting what the pool actually received
+        let amount_received = if is_sol {
+            Self::deposit_side_a(&pool_state, provider, provider_source_account, sol_vault, token_program, amount)?
+        } else {
+            Self::transfer_into_vault(pool_token_account, || {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        provider_source_account.key,
+                        pool_token_account.key,
+                        provider.key,
+                        &[],
+                        amount,
+                    )?,
+                    &[
+                        provider_source_account.clone(),
+                        pool_token_account.clone(),
+                        provider.clone(),
+                    ],
+                )
+            })?
+        };
 
-        // Check minimum amounts
-        if sol_amount < minimum_sol || token_amount < minimum_token {
+        // Swap half of the deposit into the other asset
+        let swap_amount = SwapAmount::new(amount_received / 2, is_sol);
+        let swap_output = Self::calculate_swap_output(&pool_state, swap_amount)?;
+        Self::apply_swap_reserves(&mut pool_state, swap_amount, swap_output, 0, None)?;
+
+        // Mint LP tokens for the rest of the deposit plus the swap output
+        let kept_amount = amount_received - swap_amount.get();
+        let (sol_amount, token_amount) = if is_sol {
+            (Lamports(kept_amount), TokenAmount(swap_output.get()))
+        } else {
+            (Lamports(swap_output.get()), TokenAmount(kept_amount))
+        };
+        // Keep the provider's position, if passed, in step with the LP tokens
+        let mut position =
+            Self::find_user_position(account_info_iter.as_slice(), pool_account.key, provider.key, program_id)?;
+
+        let total_supply = LpAmount(token_interface::unpack_mint(&lp_mint.data.borrow())?.supply);
+        let lp_tokens = curve::for_pool(&pool_state).deposit_lp(&pool_state, sol_amount, token_amount, total_supply)?;
+
+        // Check minimum LP tokens
+        if lp_tokens < minimum_lp_tokens {
             return Err(TokenExchangeError::SlippageExceeded.into());
         }
 
-        // Update pool state
-        pool_state.sol_reserve = pool_state.sol_reserve.checked_sub(sol_amount)
+        // Update pool state; the swap output never left the pool
+        pool_state.sol_reserve = pool_state.sol_reserve.checked_add(sol_amount.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
-        pool_state.token_reserve = pool_state.token_reserve.checked_sub(token_amount)
+        pool_state.token_reserve = pool_state.token_reserve.checked_add(token_amount.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
+        if let Some((_, position)) = position.as_mut() {
+            Self::update_position(&mut pool_state, position, lp_tokens.get(), true)?;
+        }
+        pool_state.lp_supply = total_supply.get().checked_add(lp_tokens.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        if let Some((position_account, position)) = &position {
+            position.serialize(&mut *position_account.data.borrow_mut())?;
+        }
+
+        // Mint LP tokens
+        Self::invoke_signed_by_pool(
+            &token_interface::mint_to(
+                lp_mint.owner,
+                lp_mint.key,
+                provider_lp_account.key,
+                pool_account.key,
+                &[],
+                lp_tokens.get(),
+            )?,
+            &[
+                lp_mint.clone(),
+                provider_lp_account.clone(),
+                pool_account.clone(),
+            ],
+            &pool_state,
+        )?;
+
+        // Report the execution to CPI callers, simulations and indexers
+        let (sol_deposited, token_deposited) = if is_sol {
+            (Lamports(amount_received), TokenAmount::ZERO)
+        } else {
+            (Lamports::ZERO, TokenAmount(amount_received))
+        };
+        LiquidityAddedEvent {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            sol_amount: sol_deposited.get(),
+            token_amount: token_deposited.get(),
+            lp_tokens: lp_tokens.get(),
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+        Self::set_result(&LiquidityResult {
+            sol_amount: sol_deposited,
+            token_amount: token_deposited,
+            lp_tokens,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
+        Ok(())
+    }
+
+    /// Removes liquidity from the pool
+    /// 
+    /// Allows LP token holders to burn their tokens and withdraw
+    /// their proportional share of SOL and tokens from the pool.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for liquidity removal
+    /// * `program_id` - The program's public key
+    /// * `lp_tokens` - Amount of LP tokens to burn
+    /// * `minimum_sol` - Minimum SOL to withdraw
+    /// * `minimum_token` - Minimum tokens to withdraw
+    fn process_remove_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lp_tokens: LpAmount,
+        minimum_sol: Lamports,
+        minimum_token: TokenAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let provider = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let provider_sol_account = next_account_info(account_info_iter)?;
+        let provider_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let provider_lp_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify provider is a signer
+        if !provider.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.emergency {
+            return Err(TokenExchangeError::PoolInEmergency.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        Self::check_side_a_vault(&pool_state, pool_account.key, sol_vault, token_program)?;
+
+        // Keep the provider's position, if passed, in step with the LP tokens
+        let mut position =
+            Self::find_user_position(account_info_iter.as_slice(), pool_account.key, provider.key, program_id)?;
+
+        // Calculate withdrawal amounts
+        let total_supply = LpAmount(token_interface::unpack_mint(&lp_mint.data.borrow())?.supply);
+        let (sol_amount, token_amount) =
+            curve::for_pool(&pool_state).withdraw_lp(&pool_state, lp_tokens, total_supply)?;
+
+        // Check minimum amounts
+        if sol_amount < minimum_sol || token_amount < minimum_token {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Update pool state
+        pool_state.record_outflow(Clock::get()?.slot, sol_amount.get(), token_amount.get())?;
+        pool_state.sol_reserve = pool_state.sol_reserve.checked_sub(sol_amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.token_reserve = pool_state.token_reserve.checked_sub(token_amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        if let Some((_, position)) = position.as_mut() {
+            Self::update_position(&mut pool_state, position, lp_tokens.get(), false)?;
+        }
+        pool_state.lp_supply = total_supply.get().saturating_sub(lp_tokens.get());
         pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        if let Some((position_account, position)) = &position {
+            position.serialize(&mut *position_account.data.borrow_mut())?;
+        }
 
         // Burn LP tokens
         invoke(
-            &spl_token::instruction::burn(
-                token_program.key,
+            &token_interface::burn(
+                lp_mint.owner,
                 provider_lp_account.key,
                 lp_mint.key,
                 provider.key,
                 &[],
-                lp_tokens,
+                lp_tokens.get(),
             )?,
             &[
                 provider_lp_account.clone(),
@@ -459,63 +2260,8292 @@ impl Processor {
         )?;
 
         // Transfer assets back to provider
-        **pool_account.try_borrow_mut_lamports()? -= sol_amount;
-        **provider_sol_account.try_borrow_mut_lamports()? += sol_amount;
+        Self::pay_side_a(
+            &pool_state,
+            pool_account,
+            sol_vault,
+            provider_sol_account,
+            token_program,
+            sol_amount.get(),
+        )?;
 
-        invoke(
-            &spl_token::instruction::transfer(
+        Self::invoke_signed_by_pool(
+            &token_interface::transfer(
                 token_program.key,
                 pool_token_account.key,
                 provider_token_account.key,
                 pool_account.key,
                 &[],
-                token_amount,
+                token_amount.get(),
             )?,
             &[
                 pool_token_account.clone(),
                 provider_token_account.clone(),
                 pool_account.clone(),
             ],
+            &pool_state,
         )?;
 
+        // Report the execution to CPI callers, simulations and indexers
+        LiquidityRemovedEvent {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            sol_amount: sol_amount.get(),
+            token_amount: token_amount.get(),
+            lp_tokens: lp_tokens.get(),
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+        Self::set_result(&LiquidityResult {
+            sol_amount,
+            token_amount,
+            lp_tokens,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
         Ok(())
     }
 
-    /// Calculates output amount for a swap using constant product formula
+    /// Removes liquidity into one side of the pair
     /// 
-    /// Implements x * y = k formula with fee adjustment
+    /// The LP tokens' share of the unwanted asset never leaves the pool: it
+    /// is priced as a swap into the wanted asset at the post-withdrawal
+    /// reserves, and only the wanted asset is paid out.
     /// 
     /// # Arguments
-    /// * `amount_in` - Input token amount
-    /// * `reserve_in` - Input token reserve
-    /// * `reserve_out` - Output token reserve
-    /// * `fee_rate` - Fee rate in basis points
+    /// * `accounts` - Required accounts for liquidity removal
+    /// * `program_id` - The program's public key
+    /// * `lp_tokens` - Amount of LP tokens to burn
+    /// * `minimum_amount_out` - Minimum total amount of the wanted asset
+    /// * `want_sol` - Whether to receive SOL
+    fn process_withdraw_single_sided(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lp_tokens: LpAmount,
+        minimum_amount_out: u64,
+        want_sol: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let provider = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let provider_destination_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let provider_lp_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify provider is a signer
+        if !provider.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load pool state; the swap leg needs a tradable pool
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        Self::check_side_a_vault(&pool_state, pool_account.key, sol_vault, token_program)?;
+
+        // Keep the provider's position, if passed, in step with the LP tokens
+        let mut position =
+            Self::find_user_position(account_info_iter.as_slice(), pool_account.key, provider.key, program_id)?;
+
+        // Calculate withdrawal amounts
+        let total_supply = LpAmount(token_interface::unpack_mint(&lp_mint.data.borrow())?.supply);
+        let (sol_amount, token_amount) =
+            curve::for_pool(&pool_state).withdraw_lp(&pool_state, lp_tokens, total_supply)?;
+
+        // Only the wanted share leaves the pool; the unwanted share is swapped straight back in
+        let (sol_outflow, token_outflow) = if want_sol { (sol_amount.get(), 0) } else { (0, token_amount.get()) };
+        pool_state.record_outflow(Clock::get()?.slot, sol_outflow, token_outflow)?;
+        pool_state.sol_reserve = pool_state.sol_reserve.checked_sub(sol_amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.token_reserve = pool_state.token_reserve.checked_sub(token_amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+
+        // Swap the unwanted share back into the pool for the wanted asset
+        let (wanted_amount, unwanted_amount) = if want_sol {
+            (sol_amount.get(), SwapAmount::Token(token_amount))
+        } else {
+            (token_amount.get(), SwapAmount::Sol(sol_amount))
+        };
+        let swap_output = Self::calculate_swap_output(&pool_state, unwanted_amount)?;
+        Self::apply_swap_reserves(&mut pool_state, unwanted_amount, swap_output, 0, None)?;
+        let amount_out = wanted_amount.checked_add(swap_output.get()).ok_or(TokenExchangeError::MathOverflow)?;
+
+        // Check slippage tolerance on the combined output
+        if amount_out < minimum_amount_out {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        if let Some((_, position)) = position.as_mut() {
+            Self::update_position(&mut pool_state, position, lp_tokens.get(), false)?;
+        }
+        pool_state.lp_supply = total_supply.get().saturating_sub(lp_tokens.get());
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        if let Some((position_account, position)) = &position {
+            position.serialize(&mut *position_account.data.borrow_mut())?;
+        }
+
+        // Burn LP tokens
+        invoke(
+            &token_interface::burn(
+                lp_mint.owner,
+                provider_lp_account.key,
+                lp_mint.key,
+                provider.key,
+                &[],
+                lp_tokens.get(),
+            )?,
+            &[
+                provider_lp_account.clone(),
+                lp_mint.clone(),
+                provider.clone(),
+            ],
+        )?;
+
+        // Transfer the wanted asset to the provider
+        if want_sol {
+            Self::pay_side_a(
+                &pool_state,
+                pool_account,
+                sol_vault,
+                provider_destination_account,
+                token_program,
+                amount_out,
+            )?;
+        } else {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    provider_destination_account.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    provider_destination_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        // Report the execution to CPI callers, simulations and indexers
+        let (sol_withdrawn, token_withdrawn) = if want_sol {
+            (Lamports(amount_out), TokenAmount::ZERO)
+        } else {
+            (Lamports::ZERO, TokenAmount(amount_out))
+        };
+        LiquidityRemovedEvent {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            sol_amount: sol_withdrawn.get(),
+            token_amount: token_withdrawn.get(),
+            lp_tokens: lp_tokens.get(),
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+        Self::set_result(&LiquidityResult {
+            sol_amount: sol_withdrawn,
+            token_amount: token_withdrawn,
+            lp_tokens,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
+        Ok(())
+    }
+
+    /// Moves liquidity from one pool into another in a single atomic step
     /// 
-    /// # Returns
-    /// * Amount of output tokens to receive
-    fn calculate_output_amount(
-        amount_in: u64,
-        reserve_in: u64,
-        reserve_out: u64,
-        fee_rate: u64,
-    ) -> Result<u64, ProgramError> {
-        // Calculate amount after fees
-        let amount_in_with_fee = amount_in
-            .checked_mul(10000 - fee_rate)
-            .ok_or(TokenExchangeError::MathOverflow)?
-            .checked_div(10000)
+    /// Burns the provider's LP tokens in the source pool, deposits the released
+    /// assets into the destination pool at its current ratio, and refunds
+    /// whatever the destination ratio does not absorb.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the migration
+    /// * `program_id` - The program's public key
+    /// * `lp_tokens` - Source LP tokens to burn
+    /// * `minimum_sol` - Minimum SOL released from the source pool
+    /// * `minimum_token` - Minimum tokens released from the source pool
+    /// * `minimum_lp_tokens` - Minimum destination LP tokens to receive
+    fn process_migrate_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lp_tokens: LpAmount,
+        minimum_sol: Lamports,
+        minimum_token: TokenAmount,
+        minimum_lp_tokens: LpAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let provider = next_account_info(account_info_iter)?;
+        let source_pool_account = next_account_info(account_info_iter)?;
+        let source_pool_token_account = next_account_info(account_info_iter)?;
+        let source_lp_mint = next_account_info(account_info_iter)?;
+        let provider_source_lp_account = next_account_info(account_info_iter)?;
+        let destination_pool_account = next_account_info(account_info_iter)?;
+        let destination_pool_token_account = next_account_info(account_info_iter)?;
+        let destination_lp_mint = next_account_info(account_info_iter)?;
+        let provider_destination_lp_account = next_account_info(account_info_iter)?;
+        let provider_sol_account = next_account_info(account_info_iter)?;
+        let provider_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let source_sol_vault = next_account_info(account_info_iter)?;
+        let destination_sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify provider is a signer
+        if !provider.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Both pools must be distinct program-owned pools
+        if source_pool_account.key == destination_pool_account.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if source_pool_account.owner != program_id || destination_pool_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // Load both pool states
+        let mut source_state = validation::load_pool(source_pool_account, program_id)?;
+        let mut destination_state = validation::load_pool(destination_pool_account, program_id)?;
+        if !source_state.initialized || !destination_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if destination_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if destination_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        if source_state.emergency {
+            return Err(TokenExchangeError::PoolInEmergency.into());
+        }
+        Self::require_native_sol_pool(&source_state)?;
+        Self::require_native_sol_pool(&destination_state)?;
+        Self::require_matching_sol_mode(&source_state, &destination_state)?;
+        validation::load_pool_vault(&mut source_state, source_pool_account.key, source_pool_token_account)?;
+        validation::load_pool_vault(
+            &mut destination_state,
+            destination_pool_account.key,
+            destination_pool_token_account,
+        )?;
+        validation::check_token_program(token_program, source_pool_token_account)?;
+        validation::check_token_program(token_program, destination_pool_token_account)?;
+        validation::check_lp_mint(&source_state, source_lp_mint)?;
+        validation::check_lp_mint(&destination_state, destination_lp_mint)?;
+        validation::check_sol_vault(&source_state, source_sol_vault)?;
+        validation::check_sol_vault(&destination_state, destination_sol_vault)?;
+        Self::check_deposit_allowed(
+            &destination_state,
+            destination_pool_account.key,
+            provider.key,
+            account_info_iter.as_slice(),
+            program_id,
+        )?;
+
+        // Pools must trade the same token to be compatible
+        if source_state.token_mint != destination_state.token_mint {
+            return Err(TokenExchangeError::InvalidTokenMint.into());
+        }
+
+        // Calculate assets released from the source pool
+        let source_supply = LpAmount(token_interface::unpack_mint(&source_lp_mint.data.borrow())?.supply);
+        let (sol_amount, token_amount) =
+            curve::for_pool(&source_state).withdraw_lp(&source_state, lp_tokens, source_supply)?;
+        if sol_amount < minimum_sol || token_amount < minimum_token {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Fit the released assets to the destination pool's ratio
+        let (sol_deposit, token_deposit) =
+            Self::fit_to_pool_ratio(&destination_state, sol_amount, token_amount)?;
+
+        // Move tokens between pools first and mint destination LP tokens for
+        // what the destination pool received
+        let token_received = TokenAmount(Self::collect_token_input(
+            &destination_state,
+            destination_pool_token_account,
+            token_deposit.get(),
+            || {
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        source_pool_token_account.key,
+                        destination_pool_token_account.key,
+                        source_pool_account.key,
+                        &[],
+                        token_deposit.get(),
+                    )?,
+                    &[
+                        source_pool_token_account.clone(),
+                        destination_pool_token_account.clone(),
+                        source_pool_account.clone(),
+                    ],
+                    &source_state,
+                )
+            },
+        )?);
+        let destination_supply =
+            LpAmount(token_interface::unpack_mint(&destination_lp_mint.data.borrow())?.supply);
+        let minted_lp_tokens = curve::for_pool(&destination_state).deposit_lp(
+            &destination_state,
+            sol_deposit,
+            token_received,
+            destination_supply,
+        )?;
+        if minted_lp_tokens < minimum_lp_tokens {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Update both pool states
+        source_state.record_outflow(Clock::get()?.slot, sol_amount.get(), token_amount.get())?;
+        source_state.sol_reserve = source_state.sol_reserve.checked_sub(sol_amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        source_state.token_reserve = source_state.token_reserve.checked_sub(token_amount.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
+        source_state.lp_supply = source_supply.get().saturating_sub(lp_tokens.get());
+        source_state.serialize(&mut *source_pool_account.data.borrow_mut())?;
 
-        // Calculate output amount: (y * dx) / (x + dx)
-        let numerator = reserve_out
-            .checked_mul(amount_in_with_fee)
+        destination_state.sol_reserve = destination_state.sol_reserve.checked_add(sol_deposit.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
-        let denominator = reserve_in
-            .checked_add(amount_in_with_fee)
+        destination_state.token_reserve = destination_state.token_reserve.checked_add(token_received.get())
             .ok_or(TokenExchangeError::MathOverflow)?;
+        destination_state.lp_supply = destination_supply.get().checked_add(minted_lp_tokens.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        destination_state.serialize(&mut *destination_pool_account.data.borrow_mut())?;
 
-        numerator
-            .checked_div(denominator)
-            .ok_or(TokenExchangeError::MathOverflow.into())
+        // Burn source LP tokens
+        invoke(
+            &token_interface::burn(
+                source_lp_mint.owner,
+                provider_source_lp_account.key,
+                source_lp_mint.key,
+                provider.key,
+                &[],
+                lp_tokens.get(),
+            )?,
+            &[
+                provider_source_lp_account.clone(),
+                source_lp_mint.clone(),
+                provider.clone(),
+            ],
+        )?;
+
+        // Move SOL between pools and refund the unused remainder
+        let sol_refund = sol_amount.checked_sub(sol_deposit).ok_or(TokenExchangeError::MathOverflow)?;
+        Self::pay_from_sol_vault(
+            &source_state,
+            source_pool_account,
+            source_sol_vault,
+            destination_sol_vault,
+            token_program,
+            sol_deposit.get(),
+        )?;
+        if sol_refund > Lamports::ZERO {
+            Self::pay_from_sol_vault(
+                &source_state,
+                source_pool_account,
+                source_sol_vault,
+                provider_sol_account,
+                token_program,
+                sol_refund.get(),
+            )?;
+        }
+
+        // Refund the unused tokens
+        let token_refund = token_amount.checked_sub(token_deposit).ok_or(TokenExchangeError::MathOverflow)?;
+        if token_refund > TokenAmount::ZERO {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    source_pool_token_account.key,
+                    provider_token_account.key,
+                    source_pool_account.key,
+                    &[],
+                    token_refund.get(),
+                )?,
+                &[
+                    source_pool_token_account.clone(),
+                    provider_token_account.clone(),
+                    source_pool_account.clone(),
+                ],
+                &source_state,
+            )?;
+        }
+
+        // Mint destination LP tokens
+        Self::invoke_signed_by_pool(
+            &token_interface::mint_to(
+                destination_lp_mint.owner,
+                destination_lp_mint.key,
+                provider_destination_lp_account.key,
+                destination_pool_account.key,
+                &[],
+                minted_lp_tokens.get(),
+            )?,
+            &[
+                destination_lp_mint.clone(),
+                provider_destination_lp_account.clone(),
+                destination_pool_account.clone(),
+            ],
+            &destination_state,
+        )?;
+
+        Ok(())
+    }
+
+    /// Places a resting order of any type and escrows its input
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for placing the order
+    /// * `program_id` - The program's public key
+    /// * `terms` - The order's size, prices, direction, and type
+    fn process_place_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        terms: OrderTerms,
+    ) -> ProgramResult {
+        let OrderTerms {
+            order_id,
+            amount,
+            limit_price,
+            is_sol_input,
+            expiry_slot,
+            order_type,
+            trigger_price,
+            trigger_below,
+        } = terms;
+
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Oracle-conditioned orders record a readable price feed
+        let oracle = if order_type == OrderType::OracleConditioned {
+            let oracle_account = next_account_info(account_info_iter)?;
+            OraclePrice::load_pyth(oracle_account)?;
+            *oracle_account.key
+        } else {
+            Pubkey::default()
+        };
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+
+        // Verify the order account is the canonical PDA
+        let (expected_address, bump) =
+            LimitOrder::find_address(pool_account.key, owner.key, order_id, program_id);
+        if *order_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+        if amount == 0 || limit_price == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if expiry_slot != 0 && expiry_slot <= Clock::get()?.slot {
+            return Err(TokenExchangeError::OrderExpired.into());
+        }
+
+        Self::create_pda_account(
+            owner,
+            order_account,
+            system_program,
+            program_id,
+            LimitOrder::LEN,
+            &[
+                LIMIT_ORDER_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &order_id.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        // Escrow the input asset
+        if is_sol_input {
+            invoke(
+                &system_instruction::transfer(owner.key, order_account.key, amount),
+                &[owner.clone(), order_account.clone(), system_program.clone()],
+            )?;
+        } else {
+            let escrow = token_interface::unpack_token_account(&escrow_token_account.data.borrow())?;
+            if escrow.owner != *order_account.key || escrow.mint != pool_state.token_mint {
+                return Err(TokenExchangeError::InvalidLimitOrder.into());
+            }
+
+            invoke(
+                &token_interface::transfer(
+                    token_program.key,
+                    owner_token_account.key,
+                    escrow_token_account.key,
+                    owner.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    owner_token_account.clone(),
+                    escrow_token_account.clone(),
+                    owner.clone(),
+                ],
+            )?;
+        }
+
+        let order = LimitOrder {
+            owner: *owner.key,
+            pool: *pool_account.key,
+            order_id,
+            is_sol_input,
+            limit_price,
+            original_amount: amount,
+            remaining_amount: amount,
+            filled_output: 0,
+            expiry_slot,
+            order_type,
+            trigger_price,
+            oracle,
+            trigger_below,
+            bump,
+        };
+        order.serialize(&mut *order_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Fills part or all of a resting limit order
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the fill
+    /// * `program_id` - The program's public key
+    /// * `fill_amount` - Portion of the remaining input to fill
+    fn process_fill_limit_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        fill_amount: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let taker = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+        let keeper_token_account = account_info_iter.next();
+
+        // Verify taker is a signer
+        if !taker.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        if pool_account.owner != program_id || order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+
+        let mut order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+        if order.pool != *pool_account.key {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+
+        Self::fill_limit_order(
+            &mut pool_state,
+            &mut order,
+            pool_account,
+            sol_vault,
+            order_account,
+            escrow_token_account,
+            pool_token_account,
+            owner_sol_account,
+            owner_token_account,
+            token_program,
+            keeper_token_account.map(|keeper_token_account| (taker, keeper_token_account)),
+            fill_amount,
+        )?;
+
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        order.serialize(&mut *order_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Fills every crossed limit order passed to the crank
+    /// 
+    /// # Arguments
+    /// * `accounts` - Keeper and pool accounts followed by four accounts per order
+    /// * `program_id` - The program's public key
+    fn process_match_limit_orders(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let keeper_token_account = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+
+        // Remaining accounts come in groups of four per order
+        let order_accounts = account_info_iter.as_slice();
+        if !order_accounts.len().is_multiple_of(4) {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let current_slot = Clock::get()?.slot;
+        let mut filled_orders = 0u32;
+        for group in order_accounts.chunks_exact(4) {
+            let (order_account, escrow_token_account, owner_sol_account, owner_token_account) =
+                (&group[0], &group[1], &group[2], &group[3]);
+            if order_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            let mut order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+            if order.pool != *pool_account.key
+                || order.remaining_amount == 0
+                || order.is_expired(current_slot)
+            {
+                continue;
+            }
+
+            // Stop orders rest until the pool price falls to their trigger
+            if !order.is_triggered(Self::spot_price(&pool_state, order.is_sol_input)) {
+                continue;
+            }
+
+            // Fill as much as the current pool price allows
+            let fill_amount = Self::max_limit_order_fill(&pool_state, &order)?;
+            if fill_amount == 0 {
+                continue;
+            }
+
+            match Self::fill_limit_order(
+                &mut pool_state,
+                &mut order,
+                pool_account,
+                sol_vault,
+                order_account,
+                escrow_token_account,
+                pool_token_account,
+                owner_sol_account,
+                owner_token_account,
+                token_program,
+                Some((keeper, keeper_token_account)),
+                fill_amount,
+            ) {
+                Ok(_) => {}
+                // Rounding can leave the boundary fill a unit short of the limit
+                Err(error) if error == TokenExchangeError::LimitPriceNotReached.into() => continue,
+                Err(error) => return Err(error),
+            }
+
+            order.serialize(&mut *order_account.data.borrow_mut())?;
+            filled_orders += 1;
+        }
+
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        msg!("Filled {} limit orders", filled_orders);
+
+        Ok(())
+    }
+
+    /// Executes a triggered oracle-conditioned order
+    /// 
+    /// # Arguments
+    /// * `accounts` - Keeper, pool, order, and oracle accounts
+    /// * `program_id` - The program's public key
+    fn process_execute_oracle_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let keeper_token_account = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let oracle_account = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and order state
+        if pool_account.owner != program_id || order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let mut order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+        if order.pool != *pool_account.key || order.order_type != OrderType::OracleConditioned {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+
+        // Check the order's oracle for a fresh price past the trigger
+        if *oracle_account.key != order.oracle {
+            return Err(TokenExchangeError::InvalidOracle.into());
+        }
+        let oracle_price = OraclePrice::load_pyth(oracle_account)?;
+        oracle_price.check_fresh(Clock::get()?.slot, MAX_ORACLE_STALENESS_SLOTS)?;
+        let scaled_price = oracle_price
+            .to_fixed(LIMIT_PRICE_DECIMALS)
+            .ok_or(TokenExchangeError::InvalidOracle)?;
+        if !order.is_oracle_triggered(scaled_price) {
+            return Err(TokenExchangeError::StopNotTriggered.into());
+        }
+
+        // Fill as much as the order's slippage bound allows
+        let fill_amount = Self::max_limit_order_fill(&pool_state, &order)?;
+        if fill_amount == 0 {
+            return Err(TokenExchangeError::LimitPriceNotReached.into());
+        }
+        Self::fill_limit_order(
+            &mut pool_state,
+            &mut order,
+            pool_account,
+            sol_vault,
+            order_account,
+            escrow_token_account,
+            pool_token_account,
+            owner_sol_account,
+            owner_token_account,
+            token_program,
+            Some((keeper, keeper_token_account)),
+            fill_amount,
+        )?;
+
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        order.serialize(&mut *order_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Sets a pool's keeper bounty share
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority, pool state account and protocol config PDA
+    /// * `program_id` - The program's public key
+    /// * `keeper_bounty_bps` - Keeper share of the trading fee, at most the protocol's `max_keeper_bounty_bps`
+    fn process_update_keeper_bounty(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        keeper_bounty_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let parameters = Self::load_protocol_parameters(config_account, program_id)?;
+        if keeper_bounty_bps.get() > parameters.max_keeper_bounty_bps {
+            return Err(TokenExchangeError::InvalidKeeperBounty.into());
+        }
+
+        pool_state.keeper_bounty_bps = keeper_bounty_bps.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Sets a pool's trading fee
+    /// 
+    /// The pool keeps its address: its PDA stays derived from the fee tier
+    /// it was created with.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority and pool state account
+    /// * `program_id` - The program's public key
+    /// * `new_fee_rate` - Trading fee in basis points, at most `MAX_FEE_RATE_BPS`
+    fn process_update_fee_rate(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        new_fee_rate: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        if new_fee_rate > MAX_FEE_RATE_BPS {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+
+        let old_fee_rate = pool_state.fee_rate;
+        pool_state.fee_rate = new_fee_rate.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        msg!("Fee rate updated: {} -> {} bps", old_fee_rate, new_fee_rate);
+
+        Ok(())
+    }
+
+    /// Sets how a pool's trading fee rises with its recent volatility
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority, pool state account and protocol config PDA
+    /// * `program_id` - The program's public key
+    /// * `variable_fee_control` - Variable fee per 10000 bps of accumulated volatility
+    /// * `max_variable_fee_bps` - Cap on the variable fee, at most the protocol's `max_variable_fee_bps`
+    fn process_update_variable_fee(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        variable_fee_control: u64,
+        max_variable_fee_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let parameters = Self::load_protocol_parameters(config_account, program_id)?;
+        if max_variable_fee_bps.get() > parameters.max_variable_fee_bps {
+            return Err(TokenExchangeError::InvalidVariableFee.into());
+        }
+
+        pool_state.variable_fee_control = variable_fee_control;
+        pool_state.max_variable_fee_bps = max_variable_fee_bps.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Sets the largest price move a single swap against a pool may cause
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority and pool state account
+    /// * `program_id` - The program's public key
+    /// * `max_price_impact_bps` - Price move limit in basis points, 0 for none
+    fn process_update_price_impact_limit(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        max_price_impact_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        pool_state.max_price_impact_bps = max_price_impact_bps.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Sets how much of either reserve may leave a pool in a single slot
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority and pool state account
+    /// * `program_id` - The program's public key
+    /// * `max_outflow_bps` - Per-slot outflow limit in basis points, 0 for none
+    fn process_update_outflow_limit(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        max_outflow_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if max_outflow_bps.get() > 10000 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        pool_state.max_outflow_bps = max_outflow_bps.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Ties a pool's swaps to an oracle price band, or removes the band
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool authority, pool state account and, to set a band,
+    ///   the Pyth price account
+    /// * `program_id` - The program's public key
+    /// * `max_deviation_bps` - Width of the band, at most `MAX_ORACLE_DEVIATION_BPS`
+    fn process_set_price_oracle(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        max_deviation_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let oracle_account = account_info_iter.next();
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        match oracle_account {
+            Some(oracle_account) => {
+                if max_deviation_bps == Bps::ZERO || max_deviation_bps > MAX_ORACLE_DEVIATION_BPS {
+                    return Err(TokenExchangeError::InvalidOracleBand.into());
+                }
+                OraclePrice::load_pyth(oracle_account)?;
+                pool_state.price_oracle = *oracle_account.key;
+                pool_state.oracle_max_deviation_bps = max_deviation_bps.get();
+            }
+            None => {
+                pool_state.price_oracle = Pubkey::default();
+                pool_state.oracle_max_deviation_bps = 0;
+            }
+        }
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Switches a pool into or out of rebasing mode
+    /// 
+    /// Enabling pins the pool's token vault and immediately adopts its
+    /// balance as the token reserve; disabling keeps the last synced reserve.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the update
+    /// * `program_id` - The program's public key
+    /// * `enabled` - Whether the pool should track its vault balance
+    fn process_set_rebasing_mode(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        enabled: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        if enabled {
+            // The vault must be a token account of the pool's mint held by the pool
+            pool_state.token_vault = *pool_token_account.key;
+            pool_state.rebasing = true;
+            validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        } else {
+            pool_state.rebasing = false;
+            pool_state.token_vault = Pubkey::default();
+        }
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Switches a pool into or out of fee-on-transfer mode
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the update
+    /// * `program_id` - The program's public key
+    /// * `enabled` - Whether token inputs should be measured at the vault
+    fn process_set_fee_on_transfer_mode(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        enabled: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        pool_state.fee_on_transfer = enabled;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Upgrades a pool state account to the current layout
+    /// 
+    /// Grows the account to `PoolState::LEN`, topping up its rent from the
+    /// payer, and rewrites the older layout in place. A pool already on the
+    /// current layout is left untouched.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the migration
+    /// * `program_id` - The program's public key
+    fn process_migrate_pool(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Identify the pool's current layout
+        if pool_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let version = StateVersion::detect(&pool_account.data.borrow())?;
+        if version == StateVersion::CURRENT {
+            return Ok(());
+        }
+        validation::check_system_program(system_program)?;
+
+        // Top up rent for the larger account, then resize it
+        let required_lamports = Rent::get()?
+            .minimum_balance(PoolState::LEN)
+            .saturating_sub(pool_account.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, pool_account.key, required_lamports),
+                &[payer.clone(), pool_account.clone(), system_program.clone()],
+            )?;
+        }
+        pool_account.realloc(PoolState::LEN, false)?;
+        version.upgrade(&mut pool_account.data.borrow_mut())?;
+
+        // The upgraded state must load as a genuine pool
+        validation::load_pool(pool_account, program_id)?;
+
+        msg!("Pool migrated: {:?} -> {:?}", version, StateVersion::CURRENT);
+
+        Ok(())
+    }
+
+    /// Marks a pool as deprecated or restores it
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool or protocol authority, pool state account and, for
+    ///   the protocol authority, the protocol config PDA
+    /// * `program_id` - The program's public key
+    /// * `deprecated` - Whether the pool should be withdraw-only
+    fn process_set_pool_deprecated(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        deprecated: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let config_account = account_info_iter.next();
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        match config_account {
+            Some(config_account) => Self::check_protocol_authority(authority, config_account, program_id)?,
+            None if pool_state.authority != *authority.key => {
+                return Err(TokenExchangeError::InvalidPoolAuthority.into());
+            }
+            None => {}
+        }
+
+        pool_state.deprecated = deprecated;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Pauses or unpauses a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the update
+    /// * `program_id` - The program's public key
+    /// * `paused` - Whether swaps and deposits should be halted
+    fn process_set_pool_paused(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        paused: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        if pool_state.emergency && !paused {
+            return Err(TokenExchangeError::PoolInEmergency.into());
+        }
+
+        pool_state.paused = paused;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Lends pool reserves to a borrower until a later `FlashRepay`
+    /// 
+    /// The lent amount leaves the pool's reserve, so the pool stays consistent
+    /// with its vaults, and the pool is locked until the repay.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the loan
+    /// * `program_id` - The program's public key
+    /// * `amount` - Amount to lend
+    /// * `is_sol` - Whether to lend SOL (or mint A) rather than tokens
+    fn process_flash_borrow(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount: u64,
+        is_sol: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let borrower = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let destination = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let instructions_sysvar = next_account_info(account_info_iter)?;
+
+        // Verify borrower is a signer
+        if !borrower.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::check_flash_loan_vault(&mut pool_state, pool_account.key, vault, token_program, is_sol)?;
+
+        // Only reserves are lent; uncollected protocol fees stay in the vault
+        if amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let reserve = if is_sol { pool_state.sol_reserve } else { pool_state.token_reserve };
+        let remaining = reserve.checked_sub(amount).ok_or(TokenExchangeError::InsufficientLiquidity)?;
+
+        // The transaction must settle the loan before it ends
+        flash_loan::verify_repay_follows(instructions_sysvar, program_id, pool_account.key)?;
+
+        // Accumulate the pre-loan price, then lock the pool
+        pool_state.accumulate_price(Clock::get()?.slot);
+        if is_sol {
+            pool_state.sol_reserve = remaining;
+        } else {
+            pool_state.token_reserve = remaining;
+        }
+        pool_state.flash_loan_amount = amount;
+        pool_state.flash_loan_sol = is_sol;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        // Pay out the loan
+        if is_sol && !pool_state.is_token_pair() {
+            Self::pay_from_sol_vault(&pool_state, pool_account, vault, destination, token_program, amount)?;
+        } else {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    vault.key,
+                    destination.key,
+                    pool_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[vault.clone(), destination.clone(), pool_account.clone()],
+                &pool_state,
+            )?;
+        }
+
+        msg!("Flash loan: {} {}", amount, if is_sol { "side A" } else { "token" });
+
+        Ok(())
+    }
+
+    /// Settles a pool's outstanding flash loan
+    /// 
+    /// Collects the principal plus the flash loan fee and returns it to the
+    /// reserve, less the protocol's share of the fee, then unlocks the pool.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the repayment
+    /// * `program_id` - The program's public key
+    fn process_flash_repay(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let borrower = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let source = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify borrower is a signer
+        if !borrower.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load the locked pool state
+        let mut pool_state = validation::load_pool_during_flash_loan(pool_account, program_id)?;
+        let (amount, is_sol) = (pool_state.flash_loan_amount, pool_state.flash_loan_sol);
+        if amount == 0 {
+            return Err(TokenExchangeError::NoFlashLoan.into());
+        }
+        Self::check_flash_loan_vault(&mut pool_state, pool_account.key, vault, token_program, is_sol)?;
+
+        let fee = flash_loan::flash_loan_fee(amount, Bps(pool_state.fee_rate))?;
+        let amount_owed = amount.checked_add(fee).ok_or(TokenExchangeError::MathOverflow)?;
+
+        // Collect the repayment, counting what the vault actually received
+        let amount_received = if is_sol && !pool_state.is_token_pair() {
+            Self::deposit_to_sol_vault(&pool_state, borrower, source, vault, token_program, amount_owed)?;
+            amount_owed
+        } else {
+            Self::transfer_into_vault(vault, || {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        source.key,
+                        vault.key,
+                        borrower.key,
+                        &[],
+                        amount_owed,
+                    )?,
+                    &[source.clone(), vault.clone(), borrower.clone()],
+                )
+            })?
+        };
+        if amount_received < amount_owed {
+            return Err(TokenExchangeError::FlashLoanNotRepaid.into());
+        }
+
+        // Return the repayment to the reserve, setting the protocol's cut aside
+        pool_state.accumulate_price(Clock::get()?.slot);
+        let protocol_fee = Self::calculate_protocol_fee(
+            amount,
+            Bps(pool_state.fee_rate),
+            Bps(pool_state.protocol_fee_share_bps),
+        )?;
+        let reserve_amount = amount_received - protocol_fee;
+        if is_sol {
+            pool_state.sol_reserve = pool_state.sol_reserve.checked_add(reserve_amount)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.protocol_fee_sol = pool_state.protocol_fee_sol.checked_add(protocol_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+        } else {
+            pool_state.token_reserve = pool_state.token_reserve.checked_add(reserve_amount)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.protocol_fee_token = pool_state.protocol_fee_token.checked_add(protocol_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+        }
+        pool_state.flash_loan_amount = 0;
+        pool_state.flash_loan_sol = false;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        msg!("Flash loan repaid: {} plus {} fee", amount, fee);
+
+        Ok(())
+    }
+
+    /// Changes the price and remaining size of a limit order
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the modification
+    /// * `program_id` - The program's public key
+    /// * `new_limit_price` - New minimum output per input
+    /// * `new_remaining_amount` - New remaining input size
+    fn process_modify_limit_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        new_limit_price: u64,
+        new_remaining_amount: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the order
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+        if order.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+        if new_limit_price == 0 || new_remaining_amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // Escrow the increase or refund the decrease
+        if new_remaining_amount > order.remaining_amount {
+            let increase = new_remaining_amount - order.remaining_amount;
+            if order.is_sol_input {
+                invoke(
+                    &system_instruction::transfer(owner.key, order_account.key, increase),
+                    &[owner.clone(), order_account.clone(), system_program.clone()],
+                )?;
+            } else {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        owner_token_account.key,
+                        escrow_token_account.key,
+                        owner.key,
+                        &[],
+                        increase,
+                    )?,
+                    &[
+                        owner_token_account.clone(),
+                        escrow_token_account.clone(),
+                        owner.clone(),
+                    ],
+                )?;
+            }
+        } else if new_remaining_amount < order.remaining_amount {
+            let decrease = order.remaining_amount - new_remaining_amount;
+            Self::release_limit_order_escrow(
+                &order,
+                order_account,
+                escrow_token_account,
+                owner,
+                owner_token_account,
+                token_program,
+                decrease,
+            )?;
+        }
+
+        // Keep the filled portion in the original size
+        let filled_amount = order.original_amount - order.remaining_amount;
+        order.original_amount = filled_amount
+            .checked_add(new_remaining_amount)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        order.remaining_amount = new_remaining_amount;
+        order.limit_price = new_limit_price;
+        order.serialize(&mut *order_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Cancels a limit order and closes its accounts
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the cancellation
+    /// * `program_id` - The program's public key
+    fn process_cancel_limit_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the order
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+        if order.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+
+        Self::close_limit_order(
+            &order,
+            order_account,
+            escrow_token_account,
+            owner,
+            owner_token_account,
+            token_program,
+            owner,
+        )
+    }
+
+    /// Closes an expired limit order on behalf of its owner
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the cancellation
+    /// * `program_id` - The program's public key
+    fn process_cancel_expired_limit_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // Only expired orders may be closed by a third party
+        let order = LimitOrder::try_from_slice(&order_account.data.borrow())?;
+        if !order.is_expired(Clock::get()?.slot) {
+            return Err(TokenExchangeError::OrderNotExpired.into());
+        }
+
+        // Pay the keeper a share of the order's rent (never the escrow)
+        let escrowed_lamports = if order.is_sol_input { order.remaining_amount } else { 0 };
+        let rent_lamports = order_account.lamports().saturating_sub(escrowed_lamports);
+        let rebate = rent_lamports * KEEPER_RENT_REBATE_BPS / 10000;
+        **order_account.try_borrow_mut_lamports()? -= rebate;
+        **keeper.try_borrow_mut_lamports()? += rebate;
+
+        Self::close_limit_order(
+            &order,
+            order_account,
+            escrow_token_account,
+            owner,
+            owner_token_account,
+            token_program,
+            owner,
+        )
+    }
+
+    /// Returns escrowed input from a limit order to its owner
+    /// 
+    /// SOL escrow is moved straight out of the order PDA's lamports; token
+    /// escrow is transferred with the order PDA signing.
+    fn release_limit_order_escrow<'a>(
+        order: &LimitOrder,
+        order_account: &AccountInfo<'a>,
+        escrow_token_account: &AccountInfo<'a>,
+        owner: &AccountInfo<'a>,
+        owner_token_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        if order.is_sol_input {
+            **order_account.try_borrow_mut_lamports()? -= amount;
+            **owner.try_borrow_mut_lamports()? += amount;
+            return Ok(());
+        }
+
+        invoke_signed(
+            &token_interface::transfer(
+                token_program.key,
+                escrow_token_account.key,
+                owner_token_account.key,
+                order_account.key,
+                &[],
+                amount,
+            )?,
+            &[
+                escrow_token_account.clone(),
+                owner_token_account.clone(),
+                order_account.clone(),
+            ],
+            &[&[
+                LIMIT_ORDER_SEED,
+                order.pool.as_ref(),
+                order.owner.as_ref(),
+                &order.order_id.to_le_bytes(),
+                &[order.bump],
+            ]],
+        )
+    }
+
+    /// Refunds a limit order's remaining escrow and closes its accounts
+    /// 
+    /// The escrowed input goes back to the owner; the order PDA's rent (and
+    /// the escrow token account's rent, for token orders) goes to
+    /// `rent_destination`.
+    fn close_limit_order<'a>(
+        order: &LimitOrder,
+        order_account: &AccountInfo<'a>,
+        escrow_token_account: &AccountInfo<'a>,
+        owner: &AccountInfo<'a>,
+        owner_token_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        rent_destination: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if order.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+
+        if order.remaining_amount > 0 {
+            Self::release_limit_order_escrow(
+                order,
+                order_account,
+                escrow_token_account,
+                owner,
+                owner_token_account,
+                token_program,
+                order.remaining_amount,
+            )?;
+        }
+
+        if !order.is_sol_input {
+            invoke_signed(
+                &token_interface::close_account(
+                    token_program.key,
+                    escrow_token_account.key,
+                    rent_destination.key,
+                    order_account.key,
+                    &[],
+                )?,
+                &[
+                    escrow_token_account.clone(),
+                    rent_destination.clone(),
+                    order_account.clone(),
+                ],
+                &[&[
+                    LIMIT_ORDER_SEED,
+                    order.pool.as_ref(),
+                    order.owner.as_ref(),
+                    &order.order_id.to_le_bytes(),
+                    &[order.bump],
+                ]],
+            )?;
+        }
+
+        Self::close_program_account(order_account, rent_destination)
+    }
+
+    /// Executes one fill of a limit order against the pool
+    /// 
+    /// Swaps `fill_amount` of the order's escrowed input through the pool,
+    /// enforces the limit price on that chunk, moves the assets, and updates
+    /// the in-memory pool and order state. Callers persist both afterwards.
+    /// When keeper accounts (SOL, token) are given, the keeper receives the
+    /// pool's bounty share of the trading fee out of the input.
+    /// 
+    /// # Returns
+    /// * Output amount delivered to the order owner
+    #[allow(clippy::too_many_arguments)]
+    fn fill_limit_order<'a>(
+        pool_state: &mut PoolState,
+        order: &mut LimitOrder,
+        pool_account: &AccountInfo<'a>,
+        sol_vault: &AccountInfo<'a>,
+        order_account: &AccountInfo<'a>,
+        escrow_token_account: &AccountInfo<'a>,
+        pool_token_account: &AccountInfo<'a>,
+        owner_sol_account: &AccountInfo<'a>,
+        owner_token_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        keeper_accounts: Option<(&AccountInfo<'a>, &AccountInfo<'a>)>,
+        fill_amount: u64,
+    ) -> Result<u64, ProgramError> {
+        if fill_amount == 0 || fill_amount > order.remaining_amount {
+            return Err(TokenExchangeError::InvalidFillAmount.into());
+        }
+        if order.is_expired(Clock::get()?.slot) {
+            return Err(TokenExchangeError::OrderExpired.into());
+        }
+
+        // Proceeds must reach the order owner
+        if order.is_sol_input {
+            let destination = token_interface::unpack_token_account(&owner_token_account.data.borrow())?;
+            if destination.owner != order.owner || destination.mint != pool_state.token_mint {
+                return Err(TokenExchangeError::InvalidLimitOrder.into());
+            }
+        } else if Self::sol_recipient(pool_state, owner_sol_account)? != order.owner {
+            return Err(TokenExchangeError::InvalidLimitOrder.into());
+        }
+
+        // Price the chunk against the pool and enforce the limit price
+        if !order.is_triggered(Self::spot_price(pool_state, order.is_sol_input)) {
+            return Err(TokenExchangeError::StopNotTriggered.into());
+        }
+        let swap_input = SwapAmount::new(fill_amount, order.is_sol_input);
+        let swap_output = Self::calculate_swap_output(pool_state, swap_input)?;
+        let amount_out = swap_output.get();
+        let required_output = order
+            .required_output(fill_amount)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        if amount_out < required_output {
+            return Err(TokenExchangeError::LimitPriceNotReached.into());
+        }
+
+        // Carve the keeper bounty out of the LP part of the trading fee
+        let keeper_bounty = match keeper_accounts {
+            Some(_) => pool_state.keeper_bounty(fill_amount),
+            None => 0,
+        };
+        let pool_amount_in = fill_amount - keeper_bounty;
+
+        // Update pool reserves and order progress
+        Self::apply_swap_reserves(pool_state, swap_input, swap_output, keeper_bounty, None)?;
+        order.remaining_amount -= fill_amount;
+        order.filled_output = order.filled_output.checked_add(amount_out)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+
+        if order.is_sol_input {
+            // Escrowed SOL moves into the pool, tokens go to the owner
+            **order_account.try_borrow_mut_lamports()? -= fill_amount;
+            if let Some((keeper, _)) = keeper_accounts {
+                **keeper.try_borrow_mut_lamports()? += keeper_bounty;
+            }
+            Self::credit_sol_vault(pool_state, sol_vault, token_program, pool_amount_in)?;
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                pool_state,
+            )?;
+        } else {
+            // Escrowed tokens move into the pool, SOL goes to the owner
+            Self::collect_exact_token_input(pool_state, pool_token_account, pool_amount_in, || {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        pool_token_account.key,
+                        order_account.key,
+                        &[],
+                        pool_amount_in,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        pool_token_account.clone(),
+                        order_account.clone(),
+                    ],
+                    &[&[
+                        LIMIT_ORDER_SEED,
+                        order.pool.as_ref(),
+                        order.owner.as_ref(),
+                        &order.order_id.to_le_bytes(),
+                        &[order.bump],
+                    ]],
+                )
+            })?;
+
+            if let Some((_, keeper_token_account)) = keeper_accounts.filter(|_| keeper_bounty > 0) {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        keeper_token_account.key,
+                        order_account.key,
+                        &[],
+                        keeper_bounty,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        keeper_token_account.clone(),
+                        order_account.clone(),
+                    ],
+                    &[&[
+                        LIMIT_ORDER_SEED,
+                        order.pool.as_ref(),
+                        order.owner.as_ref(),
+                        &order.order_id.to_le_bytes(),
+                        &[order.bump],
+                    ]],
+                )?;
+            }
+
+            Self::pay_from_sol_vault(pool_state, pool_account, sol_vault, owner_sol_account, token_program, amount_out)?;
+        }
+
+        if let Some((keeper, _)) = keeper_accounts.filter(|_| keeper_bounty > 0) {
+            KeeperBountyPaidEvent {
+                pool: order.pool,
+                keeper: *keeper.key,
+                amount: keeper_bounty,
+                is_sol: order.is_sol_input,
+            }
+            .emit()?;
+        }
+
+        Ok(amount_out)
+    }
+
+    /// Creates a recurring liquidity schedule and escrows every tranche
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for creating the schedule
+    /// * `program_id` - The program's public key
+    /// * `schedule_id` - Owner-chosen schedule identifier
+    /// * `sol_per_tranche` - SOL offered per tranche
+    /// * `token_per_tranche` - Tokens offered per tranche
+    /// * `tranche_count` - Number of tranches
+    /// * `interval_slots` - Slots between tranches
+    fn process_create_liquidity_schedule(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        schedule_id: u64,
+        sol_per_tranche: u64,
+        token_per_tranche: u64,
+        tranche_count: u64,
+        interval_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let schedule_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+
+        // Verify the schedule account is the canonical PDA
+        let (expected_address, bump) =
+            LiquiditySchedule::find_address(pool_account.key, owner.key, schedule_id, program_id);
+        if *schedule_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidLiquiditySchedule.into());
+        }
+        if sol_per_tranche == 0 || token_per_tranche == 0 || tranche_count == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let escrow = token_interface::unpack_token_account(&escrow_token_account.data.borrow())?;
+        if escrow.owner != *schedule_account.key || escrow.mint != pool_state.token_mint {
+            return Err(TokenExchangeError::InvalidLiquiditySchedule.into());
+        }
+
+        let sol_total = sol_per_tranche
+            .checked_mul(tranche_count)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let token_total = token_per_tranche
+            .checked_mul(tranche_count)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+
+        Self::create_pda_account(
+            owner,
+            schedule_account,
+            system_program,
+            program_id,
+            LiquiditySchedule::LEN,
+            &[
+                LIQUIDITY_SCHEDULE_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &schedule_id.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        // Escrow every tranche up front
+        invoke(
+            &system_instruction::transfer(owner.key, schedule_account.key, sol_total),
+            &[owner.clone(), schedule_account.clone(), system_program.clone()],
+        )?;
+        invoke(
+            &token_interface::transfer(
+                token_program.key,
+                owner_token_account.key,
+                escrow_token_account.key,
+                owner.key,
+                &[],
+                token_total,
+            )?,
+            &[
+                owner_token_account.clone(),
+                escrow_token_account.clone(),
+                owner.clone(),
+            ],
+        )?;
+
+        let schedule = LiquiditySchedule {
+            owner: *owner.key,
+            pool: *pool_account.key,
+            schedule_id,
+            sol_per_tranche,
+            token_per_tranche,
+            interval_slots,
+            next_tranche_slot: Clock::get()?.slot,
+            tranches_remaining: tranche_count,
+            sol_remaining: sol_total,
+            token_remaining: token_total,
+            bump,
+        };
+        schedule.serialize(&mut *schedule_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Deposits the next due tranche of a liquidity schedule into its pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the tranche
+    /// * `program_id` - The program's public key
+    fn process_execute_liquidity_tranche(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let schedule_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let owner_lp_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and schedule state
+        if pool_account.owner != program_id || schedule_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let mut schedule = LiquiditySchedule::try_from_slice(&schedule_account.data.borrow())?;
+        if schedule.pool != *pool_account.key {
+            return Err(TokenExchangeError::InvalidLiquiditySchedule.into());
+        }
+        let owner_lp = token_interface::unpack_token_account(&owner_lp_account.data.borrow())?;
+        if owner_lp.owner != schedule.owner || owner_lp.mint != pool_state.lp_mint {
+            return Err(TokenExchangeError::InvalidLiquiditySchedule.into());
+        }
+        Self::check_deposit_allowed(
+            &pool_state,
+            pool_account.key,
+            &schedule.owner,
+            account_info_iter.as_slice(),
+            program_id,
+        )?;
+
+        // Only one tranche per interval
+        let current_slot = Clock::get()?.slot;
+        if schedule.tranches_remaining == 0 || current_slot < schedule.next_tranche_slot {
+            return Err(TokenExchangeError::TrancheNotDue.into());
+        }
+
+        // Fit the tranche to the pool ratio; leftovers stay in escrow
+        let sol_offer = std::cmp::min(schedule.sol_per_tranche, schedule.sol_remaining);
+        let token_offer = std::cmp::min(schedule.token_per_tranche, schedule.token_remaining);
+        let (Lamports(sol_deposit), TokenAmount(token_deposit)) =
+            Self::fit_to_pool_ratio(&pool_state, Lamports(sol_offer), TokenAmount(token_offer))?;
+
+        // Move the tranche's tokens from escrow into the pool first and mint
+        // LP tokens for what the pool received
+        let token_received = Self::collect_token_input(&pool_state, pool_token_account, token_deposit, || {
+            invoke_signed(
+                &token_interface::transfer(
+                    token_program.key,
+                    escrow_token_account.key,
+                    pool_token_account.key,
+                    schedule_account.key,
+                    &[],
+                    token_deposit,
+                )?,
+                &[
+                    escrow_token_account.clone(),
+                    pool_token_account.clone(),
+                    schedule_account.clone(),
+                ],
+                &[&[
+                    LIQUIDITY_SCHEDULE_SEED,
+                    schedule.pool.as_ref(),
+                    schedule.owner.as_ref(),
+                    &schedule.schedule_id.to_le_bytes(),
+                    &[schedule.bump],
+                ]],
+            )
+        })?;
+        let total_supply = LpAmount(token_interface::unpack_mint(&lp_mint.data.borrow())?.supply);
+        let LpAmount(lp_tokens) = curve::for_pool(&pool_state).deposit_lp(
+            &pool_state,
+            Lamports(sol_deposit),
+            TokenAmount(token_received),
+            total_supply,
+        )?;
+
+        // Update pool and schedule state
+        pool_state.sol_reserve = pool_state.sol_reserve.checked_add(sol_deposit)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.token_reserve = pool_state.token_reserve.checked_add(token_received)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.lp_supply = total_supply.get().checked_add(lp_tokens)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        schedule.sol_remaining -= sol_deposit;
+        schedule.token_remaining -= token_deposit;
+        schedule.tranches_remaining -= 1;
+        schedule.next_tranche_slot = current_slot
+            .checked_add(schedule.interval_slots)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        schedule.serialize(&mut *schedule_account.data.borrow_mut())?;
+
+        // Move the tranche's SOL from escrow into the pool
+        **schedule_account.try_borrow_mut_lamports()? -= sol_deposit;
+        Self::credit_sol_vault(&pool_state, sol_vault, token_program, sol_deposit)?;
+
+        // Mint LP tokens to the owner
+        Self::invoke_signed_by_pool(
+            &token_interface::mint_to(
+                lp_mint.owner,
+                lp_mint.key,
+                owner_lp_account.key,
+                pool_account.key,
+                &[],
+                lp_tokens,
+            )?,
+            &[
+                lp_mint.clone(),
+                owner_lp_account.clone(),
+                pool_account.clone(),
+            ],
+            &pool_state,
+        )?;
+
+        Ok(())
+    }
+
+    /// Closes a liquidity schedule and refunds its escrow
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for closing the schedule
+    /// * `program_id` - The program's public key
+    fn process_close_liquidity_schedule(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let schedule_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the schedule
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if schedule_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let schedule = LiquiditySchedule::try_from_slice(&schedule_account.data.borrow())?;
+        if schedule.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidLiquiditySchedule.into());
+        }
+
+        let signer_seeds: &[&[u8]] = &[
+            LIQUIDITY_SCHEDULE_SEED,
+            schedule.pool.as_ref(),
+            schedule.owner.as_ref(),
+            &schedule.schedule_id.to_le_bytes(),
+            &[schedule.bump],
+        ];
+
+        // Refund remaining tokens and close the escrow token account
+        if schedule.token_remaining > 0 {
+            invoke_signed(
+                &token_interface::transfer(
+                    token_program.key,
+                    escrow_token_account.key,
+                    owner_token_account.key,
+                    schedule_account.key,
+                    &[],
+                    schedule.token_remaining,
+                )?,
+                &[
+                    escrow_token_account.clone(),
+                    owner_token_account.clone(),
+                    schedule_account.clone(),
+                ],
+                &[signer_seeds],
+            )?;
+        }
+        invoke_signed(
+            &token_interface::close_account(
+                token_program.key,
+                escrow_token_account.key,
+                owner.key,
+                schedule_account.key,
+                &[],
+            )?,
+            &[
+                escrow_token_account.clone(),
+                owner.clone(),
+                schedule_account.clone(),
+            ],
+            &[signer_seeds],
+        )?;
+
+        // Remaining SOL escrow and rent both return to the owner
+        Self::close_program_account(schedule_account, owner)
+    }
+
+    /// Grants an operator key a bounded trading allowance
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for granting the allowance
+    /// * `program_id` - The program's public key
+    /// * `max_notional_per_window` - SOL-side volume cap per window, in lamports
+    /// * `window_slots` - Length of a spending window in slots
+    fn process_grant_trading_allowance(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        max_notional_per_window: u64,
+        window_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let operator = next_account_info(account_info_iter)?;
+        let allowance_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if max_notional_per_window == 0 || window_slots == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // Verify the allowance account is the canonical PDA
+        let (expected_address, bump) =
+            TradingAllowance::find_address(owner.key, operator.key, program_id);
+        if *allowance_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidTradingAllowance.into());
+        }
+
+        // Every remaining account must be an initialized pool of this program
+        let mut allowed_pools = Vec::new();
+        for pool_account in account_info_iter {
+            if !validation::load_pool(pool_account, program_id)?.initialized {
+                return Err(TokenExchangeError::PoolNotInitialized.into());
+            }
+            if !allowed_pools.contains(pool_account.key) {
+                allowed_pools.push(*pool_account.key);
+            }
+        }
+        if allowed_pools.is_empty() || allowed_pools.len() > TradingAllowance::MAX_POOLS {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::create_pda_account(
+            owner,
+            allowance_account,
+            system_program,
+            program_id,
+            TradingAllowance::space(allowed_pools.len()),
+            &[
+                TRADING_ALLOWANCE_SEED,
+                owner.key.as_ref(),
+                operator.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let allowance = TradingAllowance {
+            owner: *owner.key,
+            operator: *operator.key,
+            max_notional_per_window,
+            window_slots,
+            window_start_slot: Clock::get()?.slot,
+            window_notional: 0,
+            bump,
+            allowed_pools,
+        };
+        allowance.serialize(&mut *allowance_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Revokes a trading allowance, refunding its rent and SOL balance
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for revoking the allowance
+    /// * `program_id` - The program's public key
+    fn process_revoke_trading_allowance(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let allowance_account = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the allowance
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if allowance_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let allowance = TradingAllowance::try_from_slice(&allowance_account.data.borrow())?;
+        if allowance.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidTradingAllowance.into());
+        }
+
+        Self::close_program_account(allowance_account, owner)
+    }
+
+    /// Executes a swap on the owner's behalf within a trading allowance
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the swap
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - Input token amount
+    /// * `minimum_amount_out` - Minimum acceptable output amount
+    /// * `is_sol_input` - Whether SOL is the input token
+    fn process_operator_swap(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        is_sol_input: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let operator = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let allowance_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+        let companions = Self::companion_accounts(account_info_iter, program_id, pool_account.key)?;
+
+        // Verify operator is a signer
+        if !operator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and allowance state
+        if pool_account.owner != program_id || allowance_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let mut allowance = TradingAllowance::try_from_slice(&allowance_account.data.borrow())?;
+        if allowance.operator != *operator.key
+            || allowance.owner != Self::sol_recipient(&pool_state, owner_sol_account)?
+        {
+            return Err(TokenExchangeError::InvalidTradingAllowance.into());
+        }
+        if !allowance.allowed_pools.contains(pool_account.key) {
+            return Err(TokenExchangeError::PoolNotAllowed.into());
+        }
+        let owner_token = token_interface::unpack_token_account(&owner_token_account.data.borrow())?;
+        if owner_token.owner != allowance.owner || owner_token.mint != pool_state.token_mint {
+            return Err(TokenExchangeError::InvalidTradingAllowance.into());
+        }
+
+        // Collect a token input first and price what the pool received;
+        // tokens move under the allowance PDA's delegate approval
+        let amount_in = if is_sol_input {
+            amount_in
+        } else {
+            Self::collect_token_input(&pool_state, pool_token_account, amount_in, || {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        owner_token_account.key,
+                        pool_token_account.key,
+                        allowance_account.key,
+                        &[],
+                        amount_in,
+                    )?,
+                    &[
+                        owner_token_account.clone(),
+                        pool_token_account.clone(),
+                        allowance_account.clone(),
+                    ],
+                    &[&[
+                        TRADING_ALLOWANCE_SEED,
+                        allowance.owner.as_ref(),
+                        allowance.operator.as_ref(),
+                        &[allowance.bump],
+                    ]],
+                )
+            })?
+        };
+
+        // Price the swap and check slippage
+        let swap_input = SwapAmount::new(amount_in, is_sol_input);
+        let swap_output = Self::calculate_swap_output(&pool_state, swap_input)?;
+        let amount_out = swap_output.get();
+        if amount_out < minimum_amount_out {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Charge the SOL leg against the current window
+        let notional = if is_sol_input { amount_in } else { amount_out };
+        if !allowance.consume(Clock::get()?.slot, notional) {
+            return Err(TokenExchangeError::AllowanceExceeded.into());
+        }
+        allowance.serialize(&mut *allowance_account.data.borrow_mut())?;
+
+        // Update price observations and protocol totals
+        Self::record_swap(&companions, &pool_state, swap_input, swap_output)?;
+
+        // Update pool state with new reserves
+        Self::apply_swap_reserves(&mut pool_state, swap_input, swap_output, 0, None)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        if is_sol_input {
+            // SOL comes from the allowance's balance above its rent-exempt minimum
+            let rent_floor = Rent::get()?.minimum_balance(allowance_account.data_len());
+            if allowance_account.lamports().saturating_sub(rent_floor) < amount_in {
+                return Err(ProgramError::InsufficientFunds);
+            }
+            **allowance_account.try_borrow_mut_lamports()? -= amount_in;
+            Self::credit_sol_vault(&pool_state, sol_vault, token_program, amount_in)?;
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        } else {
+            // The tokens were collected above, SOL goes to the owner
+            Self::pay_from_sol_vault(
+                &pool_state,
+                pool_account,
+                sol_vault,
+                owner_sol_account,
+                token_program,
+                amount_out,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes a swap order signed off-chain by its owner
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the order
+    /// * `program_id` - The program's public key
+    /// * `order` - The signed order
+    fn process_execute_signed_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        order: SignedOrder,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let relayer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let authority_account = next_account_info(account_info_iter)?;
+        let user_stats_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let instructions_sysvar = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify relayer is a signer and the owner signed the order
+        if !relayer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        verify_ed25519_signature(instructions_sysvar, &order.owner, &order.message(program_id)?)?;
+        if order.is_expired(Clock::get()?.slot) {
+            return Err(TokenExchangeError::OrderExpired.into());
+        }
+
+        // Load and verify pool state and the owner's accounts
+        if pool_account.owner != program_id || *pool_account.key != order.pool {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_system_program(system_program)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let (expected_authority, authority_bump) = SignedOrder::find_authority(&order.owner, program_id);
+        if *authority_account.key != expected_authority
+            || Self::sol_recipient(&pool_state, owner_sol_account)? != order.owner
+        {
+            return Err(TokenExchangeError::InvalidSignature.into());
+        }
+
+        // Token-denominated relayer fees (and SOL fees in wSOL mode) need a
+        // destination token account
+        let relayer_token_account = if (order.is_sol_input || pool_state.wsol) && order.relayer_fee > 0 {
+            Some(next_account_info(account_info_iter)?)
+        } else {
+            None
+        };
+        let companions = Self::companion_accounts(account_info_iter, program_id, pool_account.key)?;
+        let owner_token = token_interface::unpack_token_account(&owner_token_account.data.borrow())?;
+        if owner_token.owner != order.owner || owner_token.mint != pool_state.token_mint {
+            return Err(TokenExchangeError::InvalidTokenMint.into());
+        }
+
+        // Consume the owner's next nonce so the signature cannot be replayed
+        if user_stats_account.owner != program_id
+            || *user_stats_account.key != UserStats::find_address(&order.owner, program_id).0
+        {
+            return Err(TokenExchangeError::InvalidUserStats.into());
+        }
+        let mut user_stats = UserStats::try_from_slice(&user_stats_account.data.borrow())?;
+        if order.nonce != user_stats.signed_order_nonce {
+            return Err(TokenExchangeError::InvalidNonce.into());
+        }
+        user_stats.signed_order_nonce = user_stats.signed_order_nonce
+            .checked_add(1)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        user_stats.serialize(&mut *user_stats_account.data.borrow_mut())?;
+
+        // Collect a token input first and price what the pool received;
+        // tokens move under the order authority's delegate approval
+        let authority_seeds: &[&[u8]] = &[ORDER_AUTHORITY_SEED, order.owner.as_ref(), &[authority_bump]];
+        let amount_in = if order.is_sol_input {
+            order.amount_in
+        } else {
+            Self::collect_token_input(&pool_state, pool_token_account, order.amount_in, || {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        owner_token_account.key,
+                        pool_token_account.key,
+                        authority_account.key,
+                        &[],
+                        order.amount_in,
+                    )?,
+                    &[
+                        owner_token_account.clone(),
+                        pool_token_account.clone(),
+                        authority_account.clone(),
+                    ],
+                    &[authority_seeds],
+                )
+            })?
+        };
+
+        // Price the swap and check the owner's slippage bound
+        let swap_input = SwapAmount::new(amount_in, order.is_sol_input);
+        let swap_output = Self::calculate_swap_output(&pool_state, swap_input)?;
+        let amount_out = swap_output.get();
+
+        // Split the output between the owner and the relayer
+        let owner_amount_out = amount_out
+            .checked_sub(order.relayer_fee)
+            .ok_or(TokenExchangeError::SlippageExceeded)?;
+        if owner_amount_out < order.minimum_amount_out {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Update price observations, protocol totals and reserves
+        Self::record_swap(&companions, &pool_state, swap_input, swap_output)?;
+        Self::apply_swap_reserves(&mut pool_state, swap_input, swap_output, 0, None)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        if order.is_sol_input {
+            // SOL comes from the owner's order authority balance, or in wSOL
+            // mode from their wSOL account under the authority's delegate approval
+            if pool_state.wsol {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        owner_sol_account.key,
+                        sol_vault.key,
+                        authority_account.key,
+                        &[],
+                        order.amount_in,
+                    )?,
+                    &[owner_sol_account.clone(), sol_vault.clone(), authority_account.clone()],
+                    &[authority_seeds],
+                )?;
+            } else {
+                invoke_signed(
+                    &system_instruction::transfer(authority_account.key, sol_vault.key, order.amount_in),
+                    &[authority_account.clone(), sol_vault.clone(), system_program.clone()],
+                    &[authority_seeds],
+                )?;
+            }
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    owner_amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+
+            // Reimburse the relayer in tokens
+            if let Some(relayer_token_account) = relayer_token_account {
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        pool_token_account.key,
+                        relayer_token_account.key,
+                        pool_account.key,
+                        &[],
+                        order.relayer_fee,
+                    )?,
+                    &[
+                        pool_token_account.clone(),
+                        relayer_token_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_state,
+                )?;
+            }
+        } else {
+            // The tokens were collected above; SOL goes to the owner, with the relayer's reimbursement alongside
+            Self::pay_from_sol_vault(
+                &pool_state,
+                pool_account,
+                sol_vault,
+                owner_sol_account,
+                token_program,
+                owner_amount_out,
+            )?;
+            if order.relayer_fee > 0 {
+                Self::pay_from_sol_vault(
+                    &pool_state,
+                    pool_account,
+                    sol_vault,
+                    relayer_token_account.unwrap_or(relayer),
+                    token_program,
+                    order.relayer_fee,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Registers a keeper with a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the registration
+    /// * `program_id` - The program's public key
+    fn process_register_keeper(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let keeper = next_account_info(account_info_iter)?;
+        let registration_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        // Verify the registration account is the canonical PDA
+        let (expected_address, bump) =
+            KeeperRegistration::find_address(pool_account.key, keeper.key, program_id);
+        if *registration_account.key != expected_address {
+            return Err(TokenExchangeError::KeeperNotRegistered.into());
+        }
+
+        Self::create_pda_account(
+            authority,
+            registration_account,
+            system_program,
+            program_id,
+            KeeperRegistration::LEN,
+            &[
+                KEEPER_REGISTRATION_SEED,
+                pool_account.key.as_ref(),
+                keeper.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let registration = KeeperRegistration {
+            pool: *pool_account.key,
+            keeper: *keeper.key,
+            bump,
+        };
+        registration.serialize(&mut *registration_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Removes a keeper's registration with a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the deregistration
+    /// * `program_id` - The program's public key
+    fn process_deregister_keeper(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let registration_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and registration state
+        if pool_account.owner != program_id || registration_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let registration = KeeperRegistration::try_from_slice(&registration_account.data.borrow())?;
+        if registration.pool != *pool_account.key {
+            return Err(TokenExchangeError::KeeperNotRegistered.into());
+        }
+
+        Self::close_program_account(registration_account, authority)
+    }
+
+    /// Restricts or reopens liquidity deposits into a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the update
+    /// * `program_id` - The program's public key
+    /// * `enabled` - Whether only allowlisted providers may add liquidity
+    fn process_set_deposit_permissioned(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        enabled: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        pool_state.deposit_permissioned = enabled;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Allowlists a liquidity provider on a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the allowlist entry
+    /// * `program_id` - The program's public key
+    fn process_allow_liquidity_provider(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let provider = next_account_info(account_info_iter)?;
+        let entry_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        // Verify the allowlist account is the canonical PDA
+        let (expected_address, bump) =
+            LpAllowlistEntry::find_address(pool_account.key, provider.key, program_id);
+        if *entry_account.key != expected_address {
+            return Err(TokenExchangeError::LiquidityProviderNotAllowed.into());
+        }
+
+        Self::create_pda_account(
+            authority,
+            entry_account,
+            system_program,
+            program_id,
+            LpAllowlistEntry::LEN,
+            &[
+                LP_ALLOWLIST_SEED,
+                pool_account.key.as_ref(),
+                provider.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let entry = LpAllowlistEntry {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            bump,
+        };
+        entry.serialize(&mut *entry_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Removes a liquidity provider from a pool's allowlist
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the removal
+    /// * `program_id` - The program's public key
+    fn process_disallow_liquidity_provider(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let entry_account = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and allowlist state
+        if pool_account.owner != program_id || entry_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let entry = LpAllowlistEntry::try_from_slice(&entry_account.data.borrow())?;
+        if entry.pool != *pool_account.key {
+            return Err(TokenExchangeError::LiquidityProviderNotAllowed.into());
+        }
+
+        Self::close_program_account(entry_account, authority)
+    }
+
+    /// Sets a pool's display name and metadata URI
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the metadata
+    /// * `program_id` - The program's public key
+    /// * `name` - Short display name
+    /// * `uri` - URI of off-chain pool details
+    fn process_set_pool_metadata(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        name: &str,
+        uri: &str,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let metadata_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        // Verify the metadata account is the canonical PDA
+        let (expected_address, bump) = PoolMetadata::find_address(pool_account.key, program_id);
+        if *metadata_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidPoolMetadata.into());
+        }
+        let metadata = PoolMetadata::new(*pool_account.key, name, uri, bump)
+            .ok_or(TokenExchangeError::InvalidPoolMetadata)?;
+
+        // Create the metadata account on first use
+        if metadata_account.owner != program_id {
+            Self::create_pda_account(
+                authority,
+                metadata_account,
+                system_program,
+                program_id,
+                PoolMetadata::LEN,
+                &[POOL_METADATA_SEED, pool_account.key.as_ref(), &[bump]],
+            )?;
+        }
+        metadata.serialize(&mut *metadata_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Runs a keeper's closed arbitrage loop across the program's pools
+    /// 
+    /// # Arguments
+    /// * `accounts` - Keeper, token program, and per-pool accounts
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - SOL the keeper puts into the loop
+    /// * `minimum_profit` - Minimum SOL returned on top of `amount_in`
+    fn process_arbitrage_pools(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_profit: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Remaining accounts come in groups of four per pool, followed in
+        // wSOL mode by the keeper's wSOL account
+        let pool_accounts = account_info_iter.as_slice();
+        let (pool_accounts, keeper_wsol_account) = match pool_accounts.split_last() {
+            Some((last, rest)) if rest.len() % 8 == 0 => (rest, Some(last)),
+            _ => (pool_accounts, None),
+        };
+        let groups = Self::arbitrage_groups(pool_accounts, 4)?;
+        let mut pool_states = Self::load_arbitrage_pools(&groups, program_id)?;
+        for group in &groups {
+            validation::check_token_program(token_program, &group[1])?;
+        }
+        let keeper_sol_account = if pool_states[0].wsol {
+            keeper_wsol_account.ok_or(ProgramError::NotEnoughAccountKeys)?
+        } else {
+            keeper
+        };
+
+        // The keeper must be registered with every pool in the loop
+        for group in &groups {
+            let (pool_account, registration_account) = (&group[0], &group[3]);
+            if registration_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let registration = KeeperRegistration::try_from_slice(&registration_account.data.borrow())?;
+            if registration.pool != *pool_account.key || registration.keeper != *keeper.key {
+                return Err(TokenExchangeError::KeeperNotRegistered.into());
+            }
+        }
+
+        let hop_outputs = Self::price_arbitrage_loop(&mut pool_states, amount_in, minimum_profit)?;
+        let amount_out = *hop_outputs.last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        for (group, pool_state) in groups.iter().zip(&pool_states) {
+            pool_state.serialize(&mut *group[0].data.borrow_mut())?;
+        }
+
+        // The keeper's SOL enters the first pool and the proceeds return to the keeper
+        let (first, last) = (groups[0], groups[groups.len() - 1]);
+        Self::deposit_to_sol_vault(&pool_states[0], keeper, keeper_sol_account, &first[2], token_program, amount_in)?;
+        Self::settle_arbitrage_hops(&groups, &pool_states, &hop_outputs, token_program)?;
+        Self::pay_from_sol_vault(
+            &pool_states[pool_states.len() - 1],
+            &last[0],
+            &last[2],
+            keeper_sol_account,
+            token_program,
+            amount_out,
+        )?;
+
+        msg!("Arbitrage profit: {} lamports", amount_out - amount_in);
+
+        Ok(())
+    }
+
+    /// Settles a closed arbitrage loop internally, crediting the profit to LPs
+    /// 
+    /// The last pool's SOL proceeds fund the first pool's input, so no outside
+    /// capital is needed, and the profit is split evenly across the pools'
+    /// SOL reserves. Anyone may crank it, since nothing leaves the pools.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Token program and per-pool accounts
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - SOL routed through the loop
+    /// * `minimum_profit` - Minimum SOL the loop must return on top of `amount_in`
+    fn process_capture_arbitrage(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_profit: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Remaining accounts come in groups of three per pool
+        let groups = Self::arbitrage_groups(account_info_iter.as_slice(), 3)?;
+        let mut pool_states = Self::load_arbitrage_pools(&groups, program_id)?;
+        for group in &groups {
+            validation::check_token_program(token_program, &group[1])?;
+        }
+
+        let hop_outputs = Self::price_arbitrage_loop(&mut pool_states, amount_in, minimum_profit)?;
+        let amount_out = *hop_outputs.last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        // Split the profit evenly, the first pool taking any remainder
+        let profit = amount_out - amount_in;
+        let share = profit / pool_states.len() as u64;
+        let mut shares = vec![share; pool_states.len()];
+        shares[0] += profit % pool_states.len() as u64;
+        for ((group, pool_state), share) in groups.iter().zip(&mut pool_states).zip(&shares) {
+            pool_state.sol_reserve = pool_state.sol_reserve.checked_add(*share)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.serialize(&mut *group[0].data.borrow_mut())?;
+        }
+
+        // The last pool funds the first pool's input and every other pool's share
+        let (first, last) = (groups[0], groups[groups.len() - 1]);
+        let last_state = &pool_states[pool_states.len() - 1];
+        Self::pay_from_sol_vault(last_state, &last[0], &last[2], &first[2], token_program, amount_in)?;
+        Self::settle_arbitrage_hops(&groups, &pool_states, &hop_outputs, token_program)?;
+        for (group, share) in groups.iter().zip(shares) {
+            if group[2].key != last[2].key && share > 0 {
+                Self::pay_from_sol_vault(last_state, &last[0], &last[2], &group[2], token_program, share)?;
+            }
+        }
+
+        msg!("Captured arbitrage for LPs: {} lamports", profit);
+
+        Ok(())
+    }
+
+    /// Swaps through a chain of pools, checking slippage only on the final output
+    /// 
+    /// Each hop is priced on what its pool's vault actually received from
+    /// the previous one, so a Token-2022 transfer fee between pools is borne
+    /// by the trader rather than the next pool.
+    /// 
+    /// # Arguments
+    /// * `accounts` - User accounts, token program, and per-pool accounts
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - Input amount for the first pool
+    /// * `minimum_amount_out` - Minimum output of the last pool
+    /// * `is_sol_input` - Whether the first hop sells SOL
+    fn process_route_swap(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        is_sol_input: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let user = next_account_info(account_info_iter)?;
+        let user_source_account = next_account_info(account_info_iter)?;
+        let user_destination_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify user is a signer
+        if !user.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Remaining accounts come in groups of three per pool
+        let pool_accounts = account_info_iter.as_slice();
+        if pool_accounts.is_empty() || !pool_accounts.len().is_multiple_of(3) {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let groups: Vec<_> = pool_accounts.chunks_exact(3).collect();
+        let mut pool_states = Self::load_route_pools(&groups, program_id)?;
+        for group in &groups {
+            validation::check_token_program(token_program, &group[1])?;
+        }
+
+        // Hops alternate direction; tokens bought must be sold into a pool of the same mint
+        let sells_sol = |hop: usize| is_sol_input == hop.is_multiple_of(2);
+        for hop in 1..pool_states.len() {
+            if sells_sol(hop - 1) && pool_states[hop].token_mint != pool_states[hop - 1].token_mint {
+                return Err(TokenExchangeError::InvalidTokenMint.into());
+            }
+        }
+
+        // Collect the input into the first pool
+        let first = groups[0];
+        let mut amount = if is_sol_input {
+            Self::deposit_to_sol_vault(&pool_states[0], user, user_source_account, &first[2], token_program, amount_in)?;
+            amount_in
+        } else {
+            Self::transfer_into_vault(&first[1], || {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        user_source_account.key,
+                        first[1].key,
+                        user.key,
+                        &[],
+                        amount_in,
+                    )?,
+                    &[user_source_account.clone(), first[1].clone(), user.clone()],
+                )
+            })?
+        };
+
+        // Price each hop on what its pool received and pass the output along
+        for (hop, (group, pool_state)) in groups.iter().zip(&mut pool_states).enumerate() {
+            let (pool_account, pool_token_account, sol_vault) = (&group[0], &group[1], &group[2]);
+            let is_sol_hop = sells_sol(hop);
+            let swap_input = SwapAmount::new(amount, is_sol_hop);
+            let swap_output = Self::calculate_swap_output(pool_state, swap_input)?;
+            Self::apply_swap_reserves(pool_state, swap_input, swap_output, 0, None)?;
+            let amount_out = swap_output.get();
+            pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+            // Check slippage tolerance on the route's final output
+            let next = groups.get(hop + 1);
+            if next.is_none() && amount_out < minimum_amount_out {
+                return Err(TokenExchangeError::SlippageExceeded.into());
+            }
+
+            amount = if is_sol_hop {
+                // Tokens go to the next pool's vault, or to the user
+                let destination = next.map_or(user_destination_account, |next| &next[1]);
+                let transfer = || {
+                    Self::invoke_signed_by_pool(
+                        &token_interface::transfer(
+                            token_program.key,
+                            pool_token_account.key,
+                            destination.key,
+                            pool_account.key,
+                            &[],
+                            amount_out,
+                        )?,
+                        &[pool_token_account.clone(), destination.clone(), pool_account.clone()],
+                        pool_state,
+                    )
+                };
+                match next {
+                    Some(_) => Self::transfer_into_vault(destination, transfer)?,
+                    None => {
+                        transfer()?;
+                        amount_out
+                    }
+                }
+            } else {
+                // SOL goes to the next pool's vault, or to the user
+                let destination = next.map_or(user_destination_account, |next| &next[2]);
+                Self::pay_from_sol_vault(pool_state, pool_account, sol_vault, destination, token_program, amount_out)?;
+                amount_out
+            };
+        }
+
+        msg!("Routed swap: {} in, {} out across {} pools", amount_in, amount, groups.len());
+
+        Ok(())
+    }
+
+    /// Splits arbitrage accounts into per-pool groups
+    /// 
+    /// Pools trade in pairs, so the number of groups must be even and non-zero.
+    fn arbitrage_groups<'a, 'b>(
+        pool_accounts: &'a [AccountInfo<'b>],
+        group_len: usize,
+    ) -> Result<Vec<&'a [AccountInfo<'b>]>, ProgramError> {
+        if pool_accounts.is_empty() || !pool_accounts.len().is_multiple_of(group_len * 2) {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        Ok(pool_accounts.chunks_exact(group_len).collect())
+    }
+
+    /// Loads the pools of an arbitrage loop
+    /// 
+    /// Loads the pools like `load_route_pools`, and each pair of consecutive
+    /// pools must also share a token mint.
+    fn load_arbitrage_pools(
+        groups: &[&[AccountInfo]],
+        program_id: &Pubkey,
+    ) -> Result<Vec<PoolState>, ProgramError> {
+        let pool_states = Self::load_route_pools(groups, program_id)?;
+
+        // Each pair trades through a single token
+        if pool_states.chunks_exact(2).any(|pair| pair[0].token_mint != pair[1].token_mint) {
+            return Err(TokenExchangeError::InvalidTokenMint.into());
+        }
+
+        Ok(pool_states)
+    }
+
+    /// Loads the pools a multi-pool instruction trades through
+    /// 
+    /// Each group starts with a distinct, initialized native SOL pool followed
+    /// by its token account and SOL vault, and all pools must hold SOL the
+    /// same way.
+    fn load_route_pools(
+        groups: &[&[AccountInfo]],
+        program_id: &Pubkey,
+    ) -> Result<Vec<PoolState>, ProgramError> {
+        let mut pool_states = Vec::with_capacity(groups.len());
+        for (index, group) in groups.iter().enumerate() {
+            let pool_account = &group[0];
+            if pool_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            if groups[..index].iter().any(|earlier| earlier[0].key == pool_account.key) {
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            let mut pool_state = validation::load_pool(pool_account, program_id)?;
+            if !pool_state.initialized {
+                return Err(TokenExchangeError::PoolNotInitialized.into());
+            }
+            if pool_state.deprecated {
+                return Err(TokenExchangeError::PoolDeprecated.into());
+            }
+            if pool_state.paused {
+                return Err(TokenExchangeError::PoolPaused.into());
+            }
+            Self::require_native_sol_pool(&pool_state)?;
+            validation::load_pool_vault(&mut pool_state, pool_account.key, &group[1])?;
+            validation::check_sol_vault(&pool_state, &group[2])?;
+            if let Some(first) = pool_states.first() {
+                Self::require_matching_sol_mode(first, &pool_state)?;
+            }
+            pool_states.push(pool_state);
+        }
+
+        Ok(pool_states)
+    }
+
+    /// Prices every hop of an arbitrage loop and applies it to the pool reserves
+    /// 
+    /// Hops alternate SOL → token and token → SOL. Fails unless the loop
+    /// returns more than `amount_in` plus `minimum_profit`.
+    /// 
+    /// # Returns
+    /// * Output amount of each hop, the last being the SOL returned
+    fn price_arbitrage_loop(
+        pool_states: &mut [PoolState],
+        amount_in: u64,
+        minimum_profit: u64,
+    ) -> Result<Vec<u64>, ProgramError> {
+        let mut hop_outputs = Vec::with_capacity(pool_states.len());
+        // Each hop's output is the next hop's input, so the hops alternate sides
+        let mut amount = SwapAmount::Sol(Lamports(amount_in));
+        for pool_state in pool_states.iter_mut() {
+            let amount_out = Self::calculate_swap_output(pool_state, amount)?;
+            Self::apply_swap_reserves(pool_state, amount, amount_out, 0, None)?;
+            hop_outputs.push(amount_out.get());
+            amount = amount_out;
+        }
+
+        // The loop must return more SOL than it took
+        let required = amount_in
+            .checked_add(minimum_profit)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        if amount.get() <= amount_in || amount.get() < required {
+            return Err(TokenExchangeError::ArbitrageUnprofitable.into());
+        }
+
+        Ok(hop_outputs)
+    }
+
+    /// Moves the intermediate assets of an arbitrage loop between its pools
+    /// 
+    /// Tokens bought in the first pool of each pair go to the second; SOL
+    /// from each pair goes to the next pair. The last pool's SOL proceeds
+    /// are left for the caller to pay out.
+    fn settle_arbitrage_hops<'a>(
+        groups: &[&[AccountInfo<'a>]],
+        pool_states: &[PoolState],
+        hop_outputs: &[u64],
+        token_program: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        for (index, (group, amount_out)) in groups.iter().zip(hop_outputs).enumerate() {
+            let (pool_account, pool_token_account) = (&group[0], &group[1]);
+            if index % 2 == 0 {
+                // Tokens go straight to the paired pool
+                let paired_token_account = &groups[index + 1][1];
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        pool_token_account.key,
+                        paired_token_account.key,
+                        pool_account.key,
+                        &[],
+                        *amount_out,
+                    )?,
+                    &[
+                        pool_token_account.clone(),
+                        paired_token_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_states[index],
+                )?;
+            } else if let Some(next) = groups.get(index + 1) {
+                // SOL moves on to the next pair
+                Self::pay_from_sol_vault(
+                    &pool_states[index],
+                    pool_account,
+                    &group[2],
+                    &next[2],
+                    token_program,
+                    *amount_out,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opts a pool in to sealed orders by creating its committee
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for configuring the committee
+    /// * `program_id` - The program's public key
+    /// * `threshold` - Member signatures required to execute an order
+    /// * `reveal_delay_slots` - Slots between submission and earliest execution
+    fn process_configure_sealed_orders(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        threshold: u8,
+        reveal_delay_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let committee_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        // Verify the committee account is the canonical PDA
+        let (expected_address, bump) = SealedOrderCommittee::find_address(pool_account.key, program_id);
+        if *committee_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+
+        // Remaining accounts are the distinct committee members
+        let mut members = Vec::new();
+        for member in account_info_iter {
+            if !members.contains(member.key) {
+                members.push(*member.key);
+            }
+        }
+        if members.is_empty()
+            || members.len() > SealedOrderCommittee::MAX_MEMBERS
+            || threshold == 0
+            || threshold as usize > members.len()
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::create_pda_account(
+            authority,
+            committee_account,
+            system_program,
+            program_id,
+            SealedOrderCommittee::space(members.len()),
+            &[SEALED_COMMITTEE_SEED, pool_account.key.as_ref(), &[bump]],
+        )?;
+
+        let committee = SealedOrderCommittee {
+            pool: *pool_account.key,
+            threshold,
+            reveal_delay_slots,
+            bump,
+            members,
+        };
+        committee.serialize(&mut *committee_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Submits a sealed order and escrows its input
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for submitting the order
+    /// * `program_id` - The program's public key
+    /// * `order_id` - Owner-chosen order identifier
+    /// * `escrow_amount` - Input to escrow
+    /// * `is_sol_input` - Whether the order sells SOL
+    /// * `commitment` - Commitment to the order's terms
+    /// * `ciphertext` - Order terms encrypted to the committee
+    #[allow(clippy::too_many_arguments)]
+    fn process_submit_sealed_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        order_id: u64,
+        escrow_amount: u64,
+        is_sol_input: bool,
+        commitment: [u8; 32],
+        ciphertext: Vec<u8>,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let committee_account = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and committee state
+        if pool_account.owner != program_id || committee_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        let committee = SealedOrderCommittee::try_from_slice(&committee_account.data.borrow())?;
+        if committee.pool != *pool_account.key {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+
+        // Verify the order account is the canonical PDA
+        let (expected_address, bump) =
+            SealedOrder::find_address(pool_account.key, owner.key, order_id, program_id);
+        if *order_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+        if escrow_amount == 0 || ciphertext.len() > SealedOrder::MAX_CIPHERTEXT_LEN {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::create_pda_account(
+            owner,
+            order_account,
+            system_program,
+            program_id,
+            SealedOrder::space(ciphertext.len()),
+            &[
+                SEALED_ORDER_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &order_id.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        // Escrow the input asset
+        if is_sol_input {
+            invoke(
+                &system_instruction::transfer(owner.key, order_account.key, escrow_amount),
+                &[owner.clone(), order_account.clone(), system_program.clone()],
+            )?;
+        } else {
+            let escrow = token_interface::unpack_token_account(&escrow_token_account.data.borrow())?;
+            if escrow.owner != *order_account.key || escrow.mint != pool_state.token_mint {
+                return Err(TokenExchangeError::InvalidSealedOrder.into());
+            }
+
+            invoke(
+                &token_interface::transfer(
+                    token_program.key,
+                    owner_token_account.key,
+                    escrow_token_account.key,
+                    owner.key,
+                    &[],
+                    escrow_amount,
+                )?,
+                &[
+                    owner_token_account.clone(),
+                    escrow_token_account.clone(),
+                    owner.clone(),
+                ],
+            )?;
+        }
+
+        let order = SealedOrder {
+            owner: *owner.key,
+            pool: *pool_account.key,
+            order_id,
+            is_sol_input,
+            escrow_amount,
+            commitment,
+            reveal_slot: Clock::get()?
+                .slot
+                .checked_add(committee.reveal_delay_slots)
+                .ok_or(TokenExchangeError::MathOverflow)?,
+            bump,
+            ciphertext,
+        };
+        order.serialize(&mut *order_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Reveals and executes a sealed order on behalf of its committee
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for executing the order
+    /// * `program_id` - The program's public key
+    /// * `terms` - The revealed order terms
+    fn process_execute_sealed_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        terms: SealedOrderTerms,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let pool_account = next_account_info(account_info_iter)?;
+        let committee_account = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Load and verify pool, committee, and order state
+        if pool_account.owner != program_id
+            || committee_account.owner != program_id
+            || order_account.owner != program_id
+        {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let committee = SealedOrderCommittee::try_from_slice(&committee_account.data.borrow())?;
+        let order = SealedOrder::try_from_slice(&order_account.data.borrow())?;
+        if committee.pool != *pool_account.key || order.pool != *pool_account.key {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+        if Self::sol_recipient(&pool_state, owner_sol_account)? != order.owner {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+        let owner_token = token_interface::unpack_token_account(&owner_token_account.data.borrow())?;
+        if owner_token.owner != order.owner || owner_token.mint != pool_state.token_mint {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+
+        // Remaining accounts are signing committee members
+        let mut signers: Vec<&Pubkey> = Vec::new();
+        for member in account_info_iter {
+            if !member.is_signer || !committee.members.contains(member.key) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if !signers.contains(&member.key) {
+                signers.push(member.key);
+            }
+        }
+        if signers.len() < committee.threshold as usize {
+            return Err(TokenExchangeError::CommitteeThresholdNotMet.into());
+        }
+
+        // The revealed terms must match the commitment, after the delay
+        if Clock::get()?.slot < order.reveal_slot {
+            return Err(TokenExchangeError::SealedOrderNotReady.into());
+        }
+        if terms.commitment() != order.commitment {
+            return Err(TokenExchangeError::CommitmentMismatch.into());
+        }
+        if terms.amount_in == 0 || terms.amount_in > order.escrow_amount {
+            return Err(TokenExchangeError::InvalidFillAmount.into());
+        }
+
+        // Price the order; one the pool cannot satisfy is refunded in full
+        let swap_input = SwapAmount::new(terms.amount_in, order.is_sol_input);
+        let swap_output = Self::calculate_swap_output(&pool_state, swap_input)?;
+        let amount_out = swap_output.get();
+        if amount_out < terms.minimum_amount_out {
+            msg!("Sealed order refunded: output {} below minimum", amount_out);
+            return Self::close_sealed_order(
+                &order,
+                order_account,
+                escrow_token_account,
+                owner_sol_account,
+                owner_token_account,
+                token_program,
+                order.escrow_amount,
+            );
+        }
+
+        Self::apply_swap_reserves(&mut pool_state, swap_input, swap_output, 0, None)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        if order.is_sol_input {
+            // Escrowed SOL moves into the pool, tokens go to the owner
+            **order_account.try_borrow_mut_lamports()? -= terms.amount_in;
+            Self::credit_sol_vault(&pool_state, sol_vault, token_program, terms.amount_in)?;
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        } else {
+            // Escrowed tokens move into the pool, SOL goes to the owner
+            Self::collect_exact_token_input(&pool_state, pool_token_account, terms.amount_in, || {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        pool_token_account.key,
+                        order_account.key,
+                        &[],
+                        terms.amount_in,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        pool_token_account.clone(),
+                        order_account.clone(),
+                    ],
+                    &[&[
+                        SEALED_ORDER_SEED,
+                        order.pool.as_ref(),
+                        order.owner.as_ref(),
+                        &order.order_id.to_le_bytes(),
+                        &[order.bump],
+                    ]],
+                )
+            })?;
+
+            Self::pay_from_sol_vault(
+                &pool_state,
+                pool_account,
+                sol_vault,
+                owner_sol_account,
+                token_program,
+                amount_out,
+            )?;
+        }
+
+        // Unused escrow and rent return to the owner
+        Self::close_sealed_order(
+            &order,
+            order_account,
+            escrow_token_account,
+            owner_sol_account,
+            owner_token_account,
+            token_program,
+            order.escrow_amount - terms.amount_in,
+        )
+    }
+
+    /// Cancels a sealed order the committee failed to execute in time
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for cancelling the order
+    /// * `program_id` - The program's public key
+    fn process_cancel_sealed_order(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let order_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the order
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if order_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let order = SealedOrder::try_from_slice(&order_account.data.borrow())?;
+        if order.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidSealedOrder.into());
+        }
+
+        // Owners may only back out once the committee's window has lapsed
+        let cancel_slot = order.reveal_slot.saturating_add(SEALED_ORDER_EXECUTION_WINDOW_SLOTS);
+        if Clock::get()?.slot <= cancel_slot {
+            return Err(TokenExchangeError::SealedOrderNotReady.into());
+        }
+
+        Self::close_sealed_order(
+            &order,
+            order_account,
+            escrow_token_account,
+            owner,
+            owner_token_account,
+            token_program,
+            order.escrow_amount,
+        )
+    }
+
+    /// Refunds a sealed order's unused escrow and closes its accounts
+    /// 
+    /// `refund` of the escrowed input goes back to the owner; the order PDA's
+    /// rent (and the escrow token account's rent, for token orders) goes to
+    /// the owner's SOL account.
+    fn close_sealed_order<'a>(
+        order: &SealedOrder,
+        order_account: &AccountInfo<'a>,
+        escrow_token_account: &AccountInfo<'a>,
+        owner_sol_account: &AccountInfo<'a>,
+        owner_token_account: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        refund: u64,
+    ) -> ProgramResult {
+        let signer_seeds: &[&[u8]] = &[
+            SEALED_ORDER_SEED,
+            order.pool.as_ref(),
+            order.owner.as_ref(),
+            &order.order_id.to_le_bytes(),
+            &[order.bump],
+        ];
+
+        // SOL escrow is part of the order PDA's lamports and leaves with its rent
+        if !order.is_sol_input {
+            if refund > 0 {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        owner_token_account.key,
+                        order_account.key,
+                        &[],
+                        refund,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        owner_token_account.clone(),
+                        order_account.clone(),
+                    ],
+                    &[signer_seeds],
+                )?;
+            }
+            invoke_signed(
+                &token_interface::close_account(
+                    token_program.key,
+                    escrow_token_account.key,
+                    owner_sol_account.key,
+                    order_account.key,
+                    &[],
+                )?,
+                &[
+                    escrow_token_account.clone(),
+                    owner_sol_account.clone(),
+                    order_account.clone(),
+                ],
+                &[signer_seeds],
+            )?;
+        }
+
+        Self::close_program_account(order_account, owner_sol_account)
+    }
+
+    /// Current pool price on its curve as output per input, scaled by `LIMIT_PRICE_SCALE`
+    /// 
+    /// Saturates at `u64::MAX`; a pool without a price holds no tokens, so
+    /// selling SOL into it reads zero.
+    fn spot_price(pool_state: &PoolState, is_sol_input: bool) -> u64 {
+        let price = match (pool_state.spot_price(), is_sol_input) {
+            (None, true) => return 0,
+            (None, false) => return u64::MAX,
+            (Some(price), true) => math::mul_div_wide(Q64, LIMIT_PRICE_SCALE as u128, price, Rounding::Down),
+            (Some(price), false) => math::mul_div_wide(price, LIMIT_PRICE_SCALE as u128, Q64, Rounding::Down),
+        };
+        price.map_or(u64::MAX, |price| price.min(u64::MAX as u128) as u64)
+    }
+
+    /// Largest input of a limit order the pool can currently fill at its limit price
+    /// 
+    /// Solves `out(x) >= x * limit_price` for the constant product curve with
+    /// fees, capped at the order's remaining size. Other curves are solved by
+    /// bisection on the curve's output.
+    fn max_limit_order_fill(pool_state: &PoolState, order: &LimitOrder) -> Result<u64, ProgramError> {
+        if pool_state.curve_type != CurveType::ConstantProduct {
+            return Self::bisect_limit_order_fill(pool_state, order);
+        }
+
+        let (reserve_in, reserve_out) = if order.is_sol_input {
+            (pool_state.sol_reserve as u128, pool_state.token_reserve as u128)
+        } else {
+            (pool_state.token_reserve as u128, pool_state.sol_reserve as u128)
+        };
+        let fee_factor = 10000u128.saturating_sub(pool_state.fee_rate as u128);
+        let limit_price = order.limit_price as u128;
+
+        // x <= (R_out * g * S - p * 10000 * R_in) / (p * g)
+        let capacity = reserve_out
+            .checked_mul(fee_factor)
+            .and_then(|value| value.checked_mul(LIMIT_PRICE_SCALE as u128))
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let demand = limit_price
+            .checked_mul(10000)
+            .and_then(|value| value.checked_mul(reserve_in))
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let max_fill = capacity
+            .saturating_sub(demand)
+            .checked_div(limit_price * fee_factor)
+            .unwrap_or(0);
+
+        Ok(std::cmp::min(max_fill, order.remaining_amount as u128) as u64)
+    }
+
+    /// `max_limit_order_fill` for curves without a closed form
+    /// 
+    /// The output per unit of input only falls as the input grows, so the
+    /// fills meeting the limit price form a prefix of `0..=remaining_amount`.
+    fn bisect_limit_order_fill(pool_state: &PoolState, order: &LimitOrder) -> Result<u64, ProgramError> {
+        let meets_limit = |amount: u64| -> Result<bool, ProgramError> {
+            let out = Self::calculate_swap_output(pool_state, SwapAmount::new(amount, order.is_sol_input))?.get();
+            Ok(out as u128 * LIMIT_PRICE_SCALE as u128 >= amount as u128 * order.limit_price as u128)
+        };
+
+        let (mut low, mut high) = (0, order.remaining_amount);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if meets_limit(mid)? {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Creates the program-wide statistics PDA
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer)
+    ///   - Protocol stats PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    fn process_initialize_protocol_stats(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let stats_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Verify the stats account is the canonical PDA
+        let (expected_address, bump) = ProtocolStats::find_address(program_id);
+        if *stats_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidProtocolStats.into());
+        }
+
+        Self::create_pda_account(
+            payer,
+            stats_account,
+            system_program,
+            program_id,
+            ProtocolStats::LEN,
+            &[PROTOCOL_STATS_SEED, &[bump]],
+        )?;
+
+        let stats = ProtocolStats {
+            total_pools: 0,
+            total_volume_sol: 0,
+            total_fees_sol: 0,
+            last_update_slot: Clock::get()?.slot,
+            bump,
+        };
+        stats.serialize(&mut *stats_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Creates the protocol config PDA naming the protocol fee authority
+    /// 
+    /// Only the program's upgrade authority may create it, so the first
+    /// caller cannot squat the protocol fees.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Upgrade authority (signer, payer)
+    ///   - Protocol config PDA
+    ///   - Program data account
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `authority` - Protocol fee authority to record
+    /// * `parameters` - Bounds and defaults for new pools, or the built-in ones
+    fn process_initialize_protocol_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        authority: Pubkey,
+        parameters: Option<ProtocolParameters>,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let upgrade_authority = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let program_data = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify upgrade authority is a signer
+        if !upgrade_authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Verify the config account is the canonical PDA
+        let (expected_address, bump) = ProtocolConfig::find_address(program_id);
+        if *config_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+
+        // Verify the signer is the program's upgrade authority
+        let (program_data_address, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &solana_program::bpf_loader_upgradeable::id());
+        if *program_data.key != program_data_address {
+            return Err(TokenExchangeError::InvalidProtocolAuthority.into());
+        }
+        if Self::program_upgrade_authority(&program_data.data.borrow()) != Some(*upgrade_authority.key) {
+            return Err(TokenExchangeError::InvalidProtocolAuthority.into());
+        }
+
+        Self::create_pda_account(
+            upgrade_authority,
+            config_account,
+            system_program,
+            program_id,
+            ProtocolConfig::LEN,
+            &[PROTOCOL_CONFIG_SEED, &[bump]],
+        )?;
+
+        let parameters = parameters.unwrap_or_else(Self::builtin_protocol_parameters);
+        Self::check_protocol_parameters(&parameters)?;
+        let config = ProtocolConfig { authority, bump, parameters };
+        config.serialize(&mut *config_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Replaces the protocol authority and the parameters new pools are checked against
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Protocol authority (signer)
+    ///   - Protocol config PDA (writable)
+    /// * `program_id` - The program's public key
+    /// * `authority` - Protocol authority from now on
+    /// * `parameters` - Bounds and defaults for new pools
+    fn process_update_protocol_config(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        authority: Pubkey,
+        parameters: ProtocolParameters,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let current_authority = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
+        Self::check_protocol_authority(current_authority, config_account, program_id)?;
+        Self::check_protocol_parameters(&parameters)?;
+
+        let mut config = ProtocolConfig::try_from_slice(&config_account.data.borrow())?;
+        config.authority = authority;
+        config.parameters = parameters;
+        config.serialize(&mut *config_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Parameters new pools are checked against before the protocol config exists
+    fn builtin_protocol_parameters() -> ProtocolParameters {
+        ProtocolParameters {
+            default_fee_rate_bps: DEFAULT_FEE_RATE_BPS.get(),
+            max_fee_rate_bps: MAX_FEE_RATE_BPS.get(),
+            protocol_fee_share_bps: 0,
+            pool_creation_fee: 0,
+            treasury: Pubkey::default(),
+            pool_creation_paused: false,
+            fee_tier_count: DEFAULT_FEE_TIERS_BPS.len() as u8,
+            fee_tiers: {
+                let mut fee_tiers = [0; MAX_FEE_TIERS];
+                fee_tiers[..DEFAULT_FEE_TIERS_BPS.len()].copy_from_slice(&DEFAULT_FEE_TIERS_BPS);
+                fee_tiers
+            },
+            max_referral_fee_share_bps: 0,
+            max_variable_fee_bps: 0,
+            max_keeper_bounty_bps: MAX_KEEPER_BOUNTY_BPS.get(),
+            max_keeper_tip_bps: MAX_DCA_KEEPER_TIP_BPS.get(),
+            skim_excess_reserves: false,
+            max_platform_fee_bps: MAX_PLATFORM_FEE_BPS.get(),
+        }
+    }
+
+    /// Verifies protocol parameters stay within the program's hard limits
+    /// 
+    /// The configured maximum cannot exceed `MAX_FEE_RATE_BPS`, the fee tiers
+    /// must be strictly ascending up to that maximum with unused slots zeroed,
+    /// the default fee rate must be one of them, the keeper caps stay within
+    /// `MAX_KEEPER_BOUNTY_BPS` and `MAX_DCA_KEEPER_TIP_BPS`, and a creation fee
+    /// needs a treasury.
+    fn check_protocol_parameters(parameters: &ProtocolParameters) -> ProgramResult {
+        let fee_tier_count = parameters.fee_tier_count as usize;
+        if fee_tier_count > MAX_FEE_TIERS {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+        let (offered, unused) = parameters.fee_tiers.split_at(fee_tier_count);
+        if parameters.max_fee_rate_bps > MAX_FEE_RATE_BPS.get()
+            || !offered.windows(2).all(|pair| pair[0] < pair[1])
+            || offered.last().is_some_and(|&highest| highest > parameters.max_fee_rate_bps)
+            || unused.iter().any(|&fee_tier| fee_tier != 0)
+            || !parameters.allows_fee_tier(parameters.default_fee_rate_bps)
+            || parameters.protocol_fee_share_bps > MAX_PROTOCOL_FEE_SHARE_BPS.get()
+            || parameters.max_referral_fee_share_bps > MAX_REFERRAL_FEE_SHARE_BPS.get()
+            || parameters.max_variable_fee_bps > MAX_VARIABLE_FEE_BPS.get()
+        {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+        if parameters.max_keeper_bounty_bps > MAX_KEEPER_BOUNTY_BPS.get()
+            || parameters.max_keeper_tip_bps > MAX_DCA_KEEPER_TIP_BPS.get()
+        {
+            return Err(TokenExchangeError::InvalidKeeperBounty.into());
+        }
+        if parameters.max_platform_fee_bps > MAX_PLATFORM_FEE_BPS.get() {
+            return Err(TokenExchangeError::InvalidPlatformFee.into());
+        }
+        if parameters.pool_creation_fee > 0 && parameters.treasury == Pubkey::default() {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Reads the parameters a new pool is checked against
+    /// 
+    /// The account must be the protocol config PDA; until it is created, the
+    /// built-in parameters apply.
+    fn load_protocol_parameters(
+        config_account: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<ProtocolParameters, ProgramError> {
+        if *config_account.key != ProtocolConfig::find_address(program_id).0 {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+        if config_account.owner != program_id {
+            return Ok(Self::builtin_protocol_parameters());
+        }
+        Ok(ProtocolConfig::try_from_slice(&config_account.data.borrow())?.parameters)
+    }
+
+    /// Sets the share of a pool's trading fee accrued to the protocol
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Protocol authority (signer)
+    ///   - Protocol config PDA
+    ///   - Pool account (writable)
+    /// * `program_id` - The program's public key
+    /// * `protocol_fee_share_bps` - Share of the fee, at most `MAX_PROTOCOL_FEE_SHARE_BPS`
+    fn process_set_protocol_fee_share(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        protocol_fee_share_bps: Bps,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        Self::check_protocol_authority(authority, config_account, program_id)?;
+
+        if protocol_fee_share_bps > MAX_PROTOCOL_FEE_SHARE_BPS {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        pool_state.protocol_fee_share_bps = protocol_fee_share_bps.get();
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Sweeps a pool's accrued protocol fees to treasury accounts
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Protocol authority (signer)
+    ///   - Protocol config PDA
+    ///   - Pool account (writable)
+    ///   - Pool token account (writable)
+    ///   - Treasury token account (writable)
+    ///   - Pool side A vault: SOL vault, or mint A vault for token pairs (writable)
+    ///   - Treasury SOL account, or treasury mint A token account for token pairs (writable)
+    ///   - Token program
+    /// * `program_id` - The program's public key
+    fn process_collect_protocol_fees(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let treasury_side_a_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        Self::check_protocol_authority(authority, config_account, program_id)?;
+
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+
+        let (protocol_fee_sol, protocol_fee_token) = (pool_state.protocol_fee_sol, pool_state.protocol_fee_token);
+        pool_state.protocol_fee_sol = 0;
+        pool_state.protocol_fee_token = 0;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        // Sweep side A fees
+        if protocol_fee_sol > 0 {
+            if pool_state.is_token_pair() {
+                validation::check_pool_vault_a(&pool_state, pool_account.key, side_a_vault, token_program.key)?;
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        side_a_vault.key,
+                        treasury_side_a_account.key,
+                        pool_account.key,
+                        &[],
+                        protocol_fee_sol,
+                    )?,
+                    &[
+                        side_a_vault.clone(),
+                        treasury_side_a_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_state,
+                )?;
+            } else {
+                validation::check_sol_vault(&pool_state, side_a_vault)?;
+                Self::pay_from_sol_vault(
+                    &pool_state,
+                    pool_account,
+                    side_a_vault,
+                    treasury_side_a_account,
+                    token_program,
+                    protocol_fee_sol,
+                )?;
+            }
+        }
+
+        // Sweep token fees
+        if protocol_fee_token > 0 {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    treasury_token_account.key,
+                    pool_account.key,
+                    &[],
+                    protocol_fee_token,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    treasury_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        msg!("Collected protocol fees: {} side A, {} token", protocol_fee_sol, protocol_fee_token);
+
+        Ok(())
+    }
+
+    /// Pays a position the trading fees its liquidity has earned
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Position owner (signer)
+    ///   - Pool account (writable)
+    ///   - Position PDA (writable)
+    ///   - Pool token account (writable)
+    ///   - Owner token account (writable)
+    ///   - Pool side A vault: SOL vault, or mint A vault for token pairs (writable)
+    ///   - Owner SOL account, or owner mint A token account for token pairs (writable)
+    ///   - Token program
+    /// * `program_id` - The program's public key
+    fn process_claim_fees(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let owner_side_a_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+
+        let mut position = validation::load_user_position(position_account, pool_account.key, owner.key, program_id)?;
+
+        // Settle fee growth and take everything owed out of the set-aside fees
+        position.settle(&pool_state)?;
+        let (fees_sol, fees_token) = (position.fees_owed_sol, position.fees_owed_token);
+        position.fees_owed_sol = 0;
+        position.fees_owed_token = 0;
+        pool_state.lp_fees_sol = pool_state.lp_fees_sol.checked_sub(fees_sol)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.lp_fees_token = pool_state.lp_fees_token.checked_sub(fees_token)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        // Pay side A fees
+        if fees_sol > 0 {
+            if pool_state.is_token_pair() {
+                validation::check_pool_vault_a(&pool_state, pool_account.key, side_a_vault, token_program.key)?;
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        side_a_vault.key,
+                        owner_side_a_account.key,
+                        pool_account.key,
+                        &[],
+                        fees_sol,
+                    )?,
+                    &[
+                        side_a_vault.clone(),
+                        owner_side_a_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_state,
+                )?;
+            } else {
+                validation::check_sol_vault(&pool_state, side_a_vault)?;
+                Self::pay_from_sol_vault(
+                    &pool_state,
+                    pool_account,
+                    side_a_vault,
+                    owner_side_a_account,
+                    token_program,
+                    fees_sol,
+                )?;
+            }
+        }
+
+        // Pay token fees
+        if fees_token > 0 {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    fees_token,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        // Report the claim to indexers
+        FeesClaimedEvent {
+            pool: *pool_account.key,
+            owner: *owner.key,
+            fees_sol,
+            fees_token,
+        }
+        .emit()?;
+
+        msg!("Claimed fees: {} side A, {} token", fees_sol, fees_token);
+
+        Ok(())
+    }
+
+    /// Opens a liquidity provider's position PDA in a pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Position owner (signer, pays rent)
+    ///   - Pool state account
+    ///   - Position PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    fn process_initialize_user_position(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+
+        // Verify the position account is the canonical PDA
+        let (expected_address, bump) = UserPosition::find_address(pool_account.key, owner.key, program_id);
+        if *position_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidUserPosition.into());
+        }
+
+        Self::create_pda_account(
+            owner,
+            position_account,
+            system_program,
+            program_id,
+            UserPosition::LEN,
+            &[POSITION_SEED, pool_account.key.as_ref(), owner.key.as_ref(), &[bump]],
+        )?;
+
+        // Fees earned before the position opened are not its to claim
+        let position = UserPosition {
+            discriminator: UserPosition::DISCRIMINATOR,
+            owner: *owner.key,
+            pool: *pool_account.key,
+            lp_tokens: 0,
+            fee_growth_checkpoint_sol: pool_state.fee_growth_sol,
+            fee_growth_checkpoint_token: pool_state.fee_growth_token,
+            fees_owed_sol: 0,
+            fees_owed_token: 0,
+            bump,
+        };
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Closes a position PDA once its fees are claimed, refunding its rent
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Position owner (signer, receives rent)
+    ///   - Pool state account (writable)
+    ///   - Position PDA (writable)
+    /// * `program_id` - The program's public key
+    fn process_close_user_position(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        let mut position = validation::load_user_position(position_account, pool_account.key, owner.key, program_id)?;
+
+        // Everything the position earned must have been claimed
+        position.settle(&pool_state)?;
+        if position.fees_owed_sol > 0 || position.fees_owed_token > 0 {
+            return Err(TokenExchangeError::PositionHasUnclaimedFees.into());
+        }
+
+        // Its LP tokens no longer share in position fees
+        pool_state.position_lp_supply = pool_state.position_lp_supply.checked_sub(position.lp_tokens)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        Self::close_program_account(position_account, owner)
+    }
+
+    /// Creates a pool's reward farm and funds its emission
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Pool authority (signer, pays rent and rewards)
+    ///   - Pool state account
+    ///   - Farm PDA
+    ///   - Farm reward vault
+    ///   - Farm LP vault
+    ///   - Authority reward token account
+    ///   - Reward token program
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `emission_rate` - Reward tokens released per slot
+    /// * `duration_slots` - Number of slots the emission lasts
+    fn process_initialize_farm(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        emission_rate: u64,
+        duration_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let reward_vault = next_account_info(account_info_iter)?;
+        let lp_vault = next_account_info(account_info_iter)?;
+        let authority_reward_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+
+        let total_emission = farm::emission_total(emission_rate, duration_slots)?;
+
+        // Verify the farm account is the canonical PDA
+        let (expected_address, bump) = FarmState::find_address(pool_account.key, program_id);
+        if *farm_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+
+        // Both vaults must be held by the farm: rewards in the reward token's
+        // program, LP tokens of this pool's mint
+        token_interface::check_token_program(token_program.key)?;
+        if reward_vault.owner != token_program.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let reward = token_interface::unpack_token_account(&reward_vault.data.borrow())?;
+        let staked = token_interface::unpack_token_account(&lp_vault.data.borrow())?;
+        if reward.owner != expected_address || staked.owner != expected_address || staked.mint != pool_state.lp_mint {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+
+        Self::create_pda_account(
+            authority,
+            farm_account,
+            system_program,
+            program_id,
+            FarmState::LEN,
+            &[FARM_SEED, pool_account.key.as_ref(), &[bump]],
+        )?;
+
+        let start_slot = Clock::get()?.slot;
+        let mut rewards = [FarmReward::default(); MAX_FARM_REWARDS];
+        rewards[0] = FarmReward {
+            mint: reward.mint,
+            vault: *reward_vault.key,
+            emission_rate,
+            emission_end_slot: start_slot.saturating_add(duration_slots),
+            reward_per_share: 0,
+            last_update_slot: start_slot,
+        };
+        let farm = FarmState {
+            pool: *pool_account.key,
+            authority: *authority.key,
+            lp_vault: *lp_vault.key,
+            rewards,
+            total_staked: 0,
+            bump,
+        };
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+
+        // Fund the emission
+        invoke(
+            &token_interface::transfer(
+                token_program.key,
+                authority_reward_account.key,
+                reward_vault.key,
+                authority.key,
+                &[],
+                total_emission,
+            )?,
+            &[
+                authority_reward_account.clone(),
+                reward_vault.clone(),
+                authority.clone(),
+            ],
+        )?;
+
+        msg!("Farm created: {} per slot until slot {}", emission_rate, farm.rewards[0].emission_end_slot);
+
+        Ok(())
+    }
+
+    /// Stakes LP tokens in a farm, creating the staker's stake PDA on first use
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Staker (signer, pays rent)
+    ///   - Farm PDA (writable)
+    ///   - Stake PDA (writable)
+    ///   - Staker LP token account (writable)
+    ///   - Farm LP vault (writable)
+    ///   - LP token program
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `amount` - LP tokens to stake
+    fn process_stake_lp(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount: LpAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+        let owner_lp_account = next_account_info(account_info_iter)?;
+        let lp_vault = next_account_info(account_info_iter)?;
+        let lp_token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut farm = validation::load_farm(farm_account, program_id)?;
+        if *lp_vault.key != farm.lp_vault {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+
+        // Load the stake, or open it at the farm's current reward per share
+        let mut stake = if stake_account.owner == program_id {
+            validation::load_farm_stake(stake_account, farm_account.key, owner.key, program_id)?
+        } else {
+            let (expected_address, bump) = FarmStake::find_address(farm_account.key, owner.key, program_id);
+            if *stake_account.key != expected_address {
+                return Err(TokenExchangeError::InvalidFarm.into());
+            }
+            Self::create_pda_account(
+                owner,
+                stake_account,
+                system_program,
+                program_id,
+                FarmStake::LEN,
+                &[FARM_STAKE_SEED, farm_account.key.as_ref(), owner.key.as_ref(), &[bump]],
+            )?;
+            FarmStake {
+                farm: *farm_account.key,
+                owner: *owner.key,
+                amount: 0,
+                reward_per_share_checkpoints: [0; MAX_FARM_REWARDS],
+                rewards_owed: [0; MAX_FARM_REWARDS],
+                bump,
+            }
+        };
+
+        // Settle rewards at the old stake before adding to it
+        farm.accrue(Clock::get()?.slot)?;
+        stake.settle(&farm)?;
+        stake.amount = stake.amount.checked_add(amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        farm.total_staked = farm.total_staked.checked_add(amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+        stake.serialize(&mut *stake_account.data.borrow_mut())?;
+
+        // Move the LP tokens into the farm's custody
+        invoke(
+            &token_interface::transfer(
+                lp_token_program.key,
+                owner_lp_account.key,
+                lp_vault.key,
+                owner.key,
+                &[],
+                amount.get(),
+            )?,
+            &[
+                owner_lp_account.clone(),
+                lp_vault.clone(),
+                owner.clone(),
+            ],
+        )?;
+
+        msg!("Staked {} LP tokens", amount.get());
+
+        Ok(())
+    }
+
+    /// Withdraws staked LP tokens from a farm
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Staker (signer)
+    ///   - Farm PDA (writable)
+    ///   - Stake PDA (writable)
+    ///   - Farm LP vault (writable)
+    ///   - Staker LP token account (writable)
+    ///   - LP token program
+    /// * `program_id` - The program's public key
+    /// * `amount` - LP tokens to unstake
+    fn process_unstake_lp(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount: LpAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+        let lp_vault = next_account_info(account_info_iter)?;
+        let owner_lp_account = next_account_info(account_info_iter)?;
+        let lp_token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut farm = validation::load_farm(farm_account, program_id)?;
+        if *lp_vault.key != farm.lp_vault {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+        let mut stake = validation::load_farm_stake(stake_account, farm_account.key, owner.key, program_id)?;
+        if amount.get() > stake.amount {
+            return Err(TokenExchangeError::InsufficientStake.into());
+        }
+
+        // Settle rewards at the old stake before reducing it
+        farm.accrue(Clock::get()?.slot)?;
+        stake.settle(&farm)?;
+        stake.amount -= amount.get();
+        farm.total_staked = farm.total_staked.checked_sub(amount.get())
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+        stake.serialize(&mut *stake_account.data.borrow_mut())?;
+
+        // Return the LP tokens
+        Self::invoke_signed_by_farm(
+            &token_interface::transfer(
+                lp_token_program.key,
+                lp_vault.key,
+                owner_lp_account.key,
+                farm_account.key,
+                &[],
+                amount.get(),
+            )?,
+            &[
+                lp_vault.clone(),
+                owner_lp_account.clone(),
+                farm_account.clone(),
+            ],
+            &farm,
+        )?;
+
+        msg!("Unstaked {} LP tokens", amount.get());
+
+        Ok(())
+    }
+
+    /// Pays a staker the farm rewards they have earned, in every reward token
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Staker (signer)
+    ///   - Farm PDA (writable)
+    ///   - Stake PDA (writable)
+    ///   - Per active reward, in order: reward vault (writable), staker reward
+    ///     token account (writable) and reward token program
+    /// * `program_id` - The program's public key
+    fn process_harvest_rewards(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let stake_account = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut farm = validation::load_farm(farm_account, program_id)?;
+        let mut stake = validation::load_farm_stake(stake_account, farm_account.key, owner.key, program_id)?;
+
+        // Settle and take everything owed
+        farm.accrue(Clock::get()?.slot)?;
+        stake.settle(&farm)?;
+        let rewards = stake.rewards_owed;
+        stake.rewards_owed = [0; MAX_FARM_REWARDS];
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+        stake.serialize(&mut *stake_account.data.borrow_mut())?;
+
+        for (reward, amount) in farm.rewards.iter().zip(rewards).filter(|(reward, _)| reward.is_active()) {
+            let reward_vault = next_account_info(account_info_iter)?;
+            let owner_reward_account = next_account_info(account_info_iter)?;
+            let token_program = next_account_info(account_info_iter)?;
+            if *reward_vault.key != reward.vault {
+                return Err(TokenExchangeError::InvalidFarm.into());
+            }
+            if amount == 0 {
+                continue;
+            }
+            Self::invoke_signed_by_farm(
+                &token_interface::transfer(
+                    token_program.key,
+                    reward_vault.key,
+                    owner_reward_account.key,
+                    farm_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    reward_vault.clone(),
+                    owner_reward_account.clone(),
+                    farm_account.clone(),
+                ],
+                &farm,
+            )?;
+            msg!("Harvested {} of reward mint {}", amount, reward.mint);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a reward token to a farm and funds its emission
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Farm authority (signer, funds the rewards)
+    ///   - Farm PDA (writable)
+    ///   - New reward vault (writable, owned by the farm PDA)
+    ///   - Authority reward token account (writable)
+    ///   - Reward token program
+    /// * `program_id` - The program's public key
+    /// * `emission_rate` - Reward tokens released per slot
+    /// * `duration_slots` - Number of slots the emission lasts
+    fn process_add_reward_token(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        emission_rate: u64,
+        duration_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let reward_vault = next_account_info(account_info_iter)?;
+        let authority_reward_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut farm = validation::load_farm(farm_account, program_id)?;
+        if farm.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let total_emission = farm::emission_total(emission_rate, duration_slots)?;
+
+        // The vault must be held by the farm and hold a mint the farm does not emit yet
+        token_interface::check_token_program(token_program.key)?;
+        if reward_vault.owner != token_program.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let reward = token_interface::unpack_token_account(&reward_vault.data.borrow())?;
+        if reward.owner != *farm_account.key || farm.rewards.iter().any(|existing| existing.mint == reward.mint) {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+        let index = farm.rewards.iter().position(|existing| !existing.is_active())
+            .ok_or(TokenExchangeError::FarmRewardsFull)?;
+
+        // Stakers' checkpoints of an unused slot are zero, matching the new reward's
+        let start_slot = Clock::get()?.slot;
+        farm.accrue(start_slot)?;
+        farm.rewards[index] = FarmReward {
+            mint: reward.mint,
+            vault: *reward_vault.key,
+            emission_rate,
+            emission_end_slot: start_slot.saturating_add(duration_slots),
+            reward_per_share: 0,
+            last_update_slot: start_slot,
+        };
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+
+        // Fund the emission
+        invoke(
+            &token_interface::transfer(
+                token_program.key,
+                authority_reward_account.key,
+                reward_vault.key,
+                authority.key,
+                &[],
+                total_emission,
+            )?,
+            &[
+                authority_reward_account.clone(),
+                reward_vault.clone(),
+                authority.clone(),
+            ],
+        )?;
+
+        msg!(
+            "Farm reward {} added: {} per slot until slot {}",
+            index,
+            emission_rate,
+            farm.rewards[index].emission_end_slot
+        );
+
+        Ok(())
+    }
+
+    /// Extends one of a farm's emissions at its current rate and funds the extension
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Farm authority (signer, funds the rewards)
+    ///   - Farm PDA (writable)
+    ///   - The reward's vault (writable)
+    ///   - Authority reward token account (writable)
+    ///   - Reward token program
+    /// * `program_id` - The program's public key
+    /// * `reward_index` - Which of the farm's rewards to extend
+    /// * `duration_slots` - Slots to add to the emission
+    fn process_extend_emission(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        reward_index: u8,
+        duration_slots: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let farm_account = next_account_info(account_info_iter)?;
+        let reward_vault = next_account_info(account_info_iter)?;
+        let authority_reward_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify authority is a signer
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut farm = validation::load_farm(farm_account, program_id)?;
+        if farm.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidPoolAuthority.into());
+        }
+        let index = reward_index as usize;
+        let reward = farm.rewards.get(index).filter(|reward| reward.is_active())
+            .ok_or(TokenExchangeError::InvalidFarm)?;
+        if *reward_vault.key != reward.vault {
+            return Err(TokenExchangeError::InvalidFarm.into());
+        }
+        let total_emission = farm::emission_total(reward.emission_rate, duration_slots)?;
+
+        // Accrue first so the extension only releases from now on; an ended
+        // emission restarts at the current slot instead of back-paying the gap
+        let slot = Clock::get()?.slot;
+        farm.accrue(slot)?;
+        let reward = &mut farm.rewards[index];
+        if reward.emission_end_slot <= slot {
+            reward.emission_end_slot = slot;
+            reward.last_update_slot = slot;
+        }
+        reward.emission_end_slot = reward.emission_end_slot.checked_add(duration_slots)
+            .ok_or(TokenExchangeError::InvalidEmissionSchedule)?;
+        let emission_end_slot = reward.emission_end_slot;
+        farm.serialize(&mut *farm_account.data.borrow_mut())?;
+
+        // Fund the extension
+        invoke(
+            &token_interface::transfer(
+                token_program.key,
+                authority_reward_account.key,
+                reward_vault.key,
+                authority.key,
+                &[],
+                total_emission,
+            )?,
+            &[
+                authority_reward_account.clone(),
+                reward_vault.clone(),
+                authority.clone(),
+            ],
+        )?;
+
+        msg!("Farm reward {} extended until slot {}", index, emission_end_slot);
+
+        Ok(())
+    }
+
+    /// Creates a concentrated liquidity pool at its PDA, with no liquidity yet
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Creator (signer, pays rent)
+    ///   - Pool PDA (writable)
+    ///   - Token A vault
+    ///   - Token B vault
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `fee_rate` - Trading fee in basis points
+    /// * `tick_spacing` - Spacing of the ticks positions may use
+    /// * `initial_sqrt_price` - Initial Q64.64 square root price of token A in token B
+    fn process_initialize_concentrated_pool(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        fee_rate: Bps,
+        tick_spacing: u16,
+        initial_sqrt_price: u128,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify creator is a signer
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if fee_rate > MAX_FEE_RATE_BPS {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+        if tick_spacing == 0 || tick_spacing > MAX_TICK_SPACING {
+            return Err(TokenExchangeError::InvalidTick.into());
+        }
+        let min_sqrt_price = concentrated::sqrt_price_at_tick(MIN_TICK)?;
+        if initial_sqrt_price < min_sqrt_price || initial_sqrt_price >= concentrated::sqrt_price_at_tick(MAX_TICK)? {
+            return Err(TokenExchangeError::InvalidTick.into());
+        }
+
+        // Both vaults are held by the pool under one token program, and
+        // their mints are the pair in canonical order
+        token_interface::check_token_program(vault_a.owner)?;
+        if vault_b.owner != vault_a.owner {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let vault_a_state = token_interface::unpack_token_account(&vault_a.data.borrow())?;
+        let vault_b_state = token_interface::unpack_token_account(&vault_b.data.borrow())?;
+        if vault_a_state.mint >= vault_b_state.mint {
+            return Err(TokenExchangeError::InvalidConcentratedPool.into());
+        }
+        let (expected_address, bump) =
+            ConcentratedPool::find_address(&vault_a_state.mint, &vault_b_state.mint, tick_spacing, program_id);
+        if *pool_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidConcentratedPool.into());
+        }
+        if vault_a_state.owner != expected_address || vault_b_state.owner != expected_address {
+            return Err(TokenExchangeError::InvalidTokenVault.into());
+        }
+
+        Self::create_pda_account(
+            creator,
+            pool_account,
+            system_program,
+            program_id,
+            ConcentratedPool::LEN,
+            &[
+                CONCENTRATED_POOL_SEED,
+                vault_a_state.mint.as_ref(),
+                vault_b_state.mint.as_ref(),
+                &tick_spacing.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        let pool = ConcentratedPool {
+            authority: *creator.key,
+            mint_a: vault_a_state.mint,
+            mint_b: vault_b_state.mint,
+            vault_a: *vault_a.key,
+            vault_b: *vault_b.key,
+            fee_rate: fee_rate.get(),
+            tick_spacing,
+            sqrt_price: initial_sqrt_price,
+            current_tick: concentrated::tick_at_sqrt_price(initial_sqrt_price, MIN_TICK, MAX_TICK - 1)?,
+            liquidity: 0,
+            fee_growth_global_a: 0,
+            fee_growth_global_b: 0,
+            tick_below: MIN_TICK,
+            tick_above: MAX_TICK,
+            bump,
+        };
+        pool.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        msg!("Concentrated pool created at tick {}", pool.current_tick);
+
+        Ok(())
+    }
+
+    /// Creates a tick PDA and links it into the pool's list of initialized ticks
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer, pays rent)
+    ///   - Pool PDA (writable)
+    ///   - Tick PDA (writable)
+    ///   - System program
+    ///   - Previous tick PDA (writable, unless `prev_tick` is `MIN_TICK`)
+    ///   - Next tick PDA (writable, unless `next_tick` is `MAX_TICK`)
+    /// * `program_id` - The program's public key
+    /// * `tick_index` - The tick to initialize
+    /// * `prev_tick` - Nearest initialized tick below it
+    /// * `next_tick` - Nearest initialized tick above it
+    fn process_initialize_tick(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        tick_index: i32,
+        prev_tick: i32,
+        next_tick: i32,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let tick_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut pool = validation::load_concentrated_pool(pool_account, program_id)?;
+        pool.check_tick(tick_index)?;
+        if !(prev_tick < tick_index && tick_index < next_tick) {
+            return Err(TokenExchangeError::InvalidTickList.into());
+        }
+
+        // The neighbours must be adjacent in the list, so the new tick slots
+        // in between them; with neither, the list must be empty
+        let mut prev = if prev_tick == MIN_TICK {
+            None
+        } else {
+            let prev_account = next_account_info(account_info_iter)?;
+            Some((prev_account, validation::load_tick(prev_account, pool_account.key, prev_tick, program_id)?))
+        };
+        let mut next = if next_tick == MAX_TICK {
+            None
+        } else {
+            let next_account = next_account_info(account_info_iter)?;
+            Some((next_account, validation::load_tick(next_account, pool_account.key, next_tick, program_id)?))
+        };
+        let linked = match (&prev, &next) {
+            (Some((_, prev)), Some((_, next))) => prev.next_tick == next_tick && next.prev_tick == prev_tick,
+            (Some((_, prev)), None) => prev.next_tick == MAX_TICK,
+            (None, Some((_, next))) => next.prev_tick == MIN_TICK,
+            (None, None) => pool.tick_below == MIN_TICK && pool.tick_above == MAX_TICK,
+        };
+        if !linked {
+            return Err(TokenExchangeError::InvalidTickList.into());
+        }
+
+        let (expected_address, bump) = TickState::find_address(pool_account.key, tick_index, program_id);
+        if *tick_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidTickList.into());
+        }
+        Self::create_pda_account(
+            payer,
+            tick_account,
+            system_program,
+            program_id,
+            TickState::LEN,
+            &[TICK_SEED, pool_account.key.as_ref(), &tick_index.to_le_bytes(), &[bump]],
+        )?;
+
+        // Fee growth so far counts as below the tick when the price is at or
+        // above it, and nothing has been earned above it otherwise
+        let (fee_growth_outside_a, fee_growth_outside_b) = if pool.current_tick >= tick_index {
+            (pool.fee_growth_global_a, pool.fee_growth_global_b)
+        } else {
+            (0, 0)
+        };
+        let tick = TickState {
+            pool: *pool_account.key,
+            index: tick_index,
+            liquidity_net: 0,
+            liquidity_gross: 0,
+            fee_growth_outside_a,
+            fee_growth_outside_b,
+            prev_tick,
+            next_tick,
+            bump,
+        };
+        tick.serialize(&mut *tick_account.data.borrow_mut())?;
+
+        if let Some((prev_account, prev)) = prev.as_mut() {
+            prev.next_tick = tick_index;
+            prev.serialize(&mut *prev_account.data.borrow_mut())?;
+        }
+        if let Some((next_account, next)) = next.as_mut() {
+            next.prev_tick = tick_index;
+            next.serialize(&mut *next_account.data.borrow_mut())?;
+        }
+
+        // A tick in the gap around the current price becomes its new bound
+        if pool.tick_below == prev_tick && pool.tick_above == next_tick {
+            if tick_index <= pool.current_tick {
+                pool.tick_below = tick_index;
+            } else {
+                pool.tick_above = tick_index;
+            }
+            pool.serialize(&mut *pool_account.data.borrow_mut())?;
+        }
+
+        msg!("Tick {} initialized", tick_index);
+
+        Ok(())
+    }
+
+    /// Opens an empty position over a tick range of a concentrated liquidity pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Owner (signer, pays rent)
+    ///   - Pool PDA
+    ///   - Position PDA (writable)
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `tick_lower` - Lower tick of the range
+    /// * `tick_upper` - Upper tick of the range
+    fn process_open_position(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pool = validation::load_concentrated_pool(pool_account, program_id)?;
+        pool.check_tick(tick_lower)?;
+        pool.check_tick(tick_upper)?;
+        if tick_lower >= tick_upper {
+            return Err(TokenExchangeError::InvalidTick.into());
+        }
+
+        let (expected_address, bump) =
+            ConcentratedPosition::find_address(pool_account.key, owner.key, tick_lower, tick_upper, program_id);
+        if *position_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidConcentratedPool.into());
+        }
+        Self::create_pda_account(
+            owner,
+            position_account,
+            system_program,
+            program_id,
+            ConcentratedPosition::LEN,
+            &[
+                CONCENTRATED_POSITION_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &tick_lower.to_le_bytes(),
+                &tick_upper.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        let position = ConcentratedPosition {
+            pool: *pool_account.key,
+            owner: *owner.key,
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_a: 0,
+            fee_growth_inside_b: 0,
+            fees_owed_a: 0,
+            fees_owed_b: 0,
+            bump,
+        };
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        msg!("Position opened over ticks {} to {}", tick_lower, tick_upper);
+
+        Ok(())
+    }
+
+    /// Adds liquidity to a concentrated liquidity position, depositing the tokens it holds
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Owner (signer)
+    ///   - Pool PDA (writable)
+    ///   - Position PDA (writable)
+    ///   - Lower and upper tick PDAs (writable)
+    ///   - Owner token A and B accounts (writable)
+    ///   - Pool token A and B vaults (writable)
+    ///   - Token program
+    /// * `program_id` - The program's public key
+    /// * `liquidity` - Liquidity to add
+    /// * `max_amount_a` - Maximum token A to deposit
+    /// * `max_amount_b` - Maximum token B to deposit
+    fn process_increase_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        liquidity: u128,
+        max_amount_a: u64,
+        max_amount_b: u64,
+    ) -> ProgramResult {
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| TokenExchangeError::MathOverflow)?;
+        Self::modify_concentrated_liquidity(accounts, program_id, liquidity_delta, max_amount_a, max_amount_b)
+    }
+
+    /// Removes liquidity from a concentrated liquidity position, paying out its tokens and fees
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts, as for `process_increase_liquidity`
+    /// * `program_id` - The program's public key
+    /// * `liquidity` - Liquidity to remove, 0 to only collect fees
+    /// * `min_amount_a` - Minimum token A to receive, excluding fees
+    /// * `min_amount_b` - Minimum token B to receive, excluding fees
+    fn process_decrease_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        liquidity: u128,
+        min_amount_a: u64,
+        min_amount_b: u64,
+    ) -> ProgramResult {
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| TokenExchangeError::MathOverflow)?;
+        Self::modify_concentrated_liquidity(accounts, program_id, -liquidity_delta, min_amount_a, min_amount_b)
+    }
+
+    /// Changes a concentrated liquidity position's liquidity and moves the tokens it holds
+    /// 
+    /// Fees earned at the old liquidity are settled first. Deposits round
+    /// up and must stay within `limit_a` and `limit_b`; withdrawals round
+    /// down, must reach them, and also pay out the fees owed.
+    fn modify_concentrated_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        liquidity_delta: i128,
+        limit_a: u64,
+        limit_b: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let lower_tick_account = next_account_info(account_info_iter)?;
+        let upper_tick_account = next_account_info(account_info_iter)?;
+        let owner_account_a = next_account_info(account_info_iter)?;
+        let owner_account_b = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut pool = validation::load_concentrated_pool(pool_account, program_id)?;
+        validation::check_pair_vaults(&pool.vault_a, &pool.vault_b, vault_a, vault_b, token_program)?;
+        let mut position =
+            validation::load_concentrated_position(position_account, pool_account.key, owner.key, program_id)?;
+        let mut lower = validation::load_tick(lower_tick_account, pool_account.key, position.tick_lower, program_id)?;
+        let mut upper = validation::load_tick(upper_tick_account, pool_account.key, position.tick_upper, program_id)?;
+
+        // Settle fees at the old liquidity before changing it
+        let (fee_growth_inside_a, fee_growth_inside_b) = pool.fee_growth_inside(&lower, &upper);
+        position.settle(fee_growth_inside_a, fee_growth_inside_b)?;
+        position.liquidity = position.liquidity.checked_add_signed(liquidity_delta)
+            .ok_or(TokenExchangeError::InsufficientLiquidity)?;
+        lower.update_liquidity(liquidity_delta, false)?;
+        upper.update_liquidity(liquidity_delta, true)?;
+        if pool.in_range(position.tick_lower, position.tick_upper) {
+            pool.liquidity = pool.liquidity.checked_add_signed(liquidity_delta)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+        }
+
+        let deposit = liquidity_delta > 0;
+        let rounding = if deposit { Rounding::Up } else { Rounding::Down };
+        let (amount_a, amount_b) = pool.amounts_for_liquidity(
+            position.tick_lower,
+            position.tick_upper,
+            liquidity_delta.unsigned_abs(),
+            rounding,
+        )?;
+        let within_limits = if deposit {
+            amount_a <= limit_a && amount_b <= limit_b
+        } else {
+            amount_a >= limit_a && amount_b >= limit_b
+        };
+        if !within_limits {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+
+        // Withdrawals also pay out every fee owed
+        let (amount_a, amount_b) = if deposit {
+            (amount_a, amount_b)
+        } else {
+            let payout_a = amount_a.checked_add(position.fees_owed_a).ok_or(TokenExchangeError::MathOverflow)?;
+            let payout_b = amount_b.checked_add(position.fees_owed_b).ok_or(TokenExchangeError::MathOverflow)?;
+            position.fees_owed_a = 0;
+            position.fees_owed_b = 0;
+            (payout_a, payout_b)
+        };
+
+        pool.serialize(&mut *pool_account.data.borrow_mut())?;
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+        lower.serialize(&mut *lower_tick_account.data.borrow_mut())?;
+        upper.serialize(&mut *upper_tick_account.data.borrow_mut())?;
+
+        let transfers = [(amount_a, owner_account_a, vault_a), (amount_b, owner_account_b, vault_b)];
+        for (amount, owner_account, vault) in transfers {
+            if amount == 0 {
+                continue;
+            }
+            if deposit {
+                invoke(
+                    &token_interface::transfer(
+                        token_program.key,
+                        owner_account.key,
+                        vault.key,
+                        owner.key,
+                        &[],
+                        amount,
+                    )?,
+                    &[owner_account.clone(), vault.clone(), owner.clone()],
+                )?;
+            } else {
+                Self::invoke_signed_by_concentrated_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        vault.key,
+                        owner_account.key,
+                        pool_account.key,
+                        &[],
+                        amount,
+                    )?,
+                    &[vault.clone(), owner_account.clone(), pool_account.clone()],
+                    &pool,
+                )?;
+            }
+        }
+
+        msg!("Position liquidity now {}: moved {} token A and {} token B", position.liquidity, amount_a, amount_b);
+
+        Ok(())
+    }
+
+    /// Swaps against a concentrated liquidity pool, crossing initialized ticks as the price moves
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - User (signer)
+    ///   - Pool PDA (writable)
+    ///   - User token A and B accounts (writable)
+    ///   - Pool token A and B vaults (writable)
+    ///   - Token program
+    ///   - Each tick PDA crossed, in order (writable)
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - Input to swap, including the fee
+    /// * `minimum_amount_out` - Minimum output to receive
+    /// * `a_to_b` - Whether token A is sold for token B
+    fn process_swap_concentrated(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let user = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let user_account_a = next_account_info(account_info_iter)?;
+        let user_account_b = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify user is a signer
+        if !user.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if amount_in == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let mut pool = validation::load_concentrated_pool(pool_account, program_id)?;
+        validation::check_pair_vaults(&pool.vault_a, &pool.vault_b, vault_a, vault_b, token_program)?;
+
+        // Step to the next initialized tick in the swap's direction, crossing
+        // it if the input carries the price that far, until the input runs
+        // out or no initialized tick is left
+        let mut amount_remaining = amount_in;
+        let mut amount_out: u64 = 0;
+        let mut at_crossed_tick = false;
+        while amount_remaining > 0 {
+            let next_tick = if a_to_b { pool.tick_below } else { pool.tick_above };
+            let sqrt_price_target = concentrated::sqrt_price_at_tick(next_tick)?;
+            let step = concentrated::swap_step(
+                pool.sqrt_price,
+                sqrt_price_target,
+                pool.liquidity,
+                amount_remaining,
+                pool.fee_rate,
+                a_to_b,
+            )?;
+            amount_remaining -= step.amount_in + step.fee;
+            amount_out = amount_out.checked_add(step.amount_out)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool.accrue_fee(step.fee, a_to_b);
+            pool.sqrt_price = step.sqrt_price_next;
+            if !step.reached(sqrt_price_target) || next_tick == MIN_TICK || next_tick == MAX_TICK {
+                at_crossed_tick = false;
+                break;
+            }
+            let tick_account = next_account_info(account_info_iter)?;
+            let mut tick = validation::load_tick(tick_account, pool_account.key, next_tick, program_id)?;
+            pool.cross(&mut tick, a_to_b)?;
+            tick.serialize(&mut *tick_account.data.borrow_mut())?;
+            at_crossed_tick = true;
+        }
+
+        // Crossing sets the tick; otherwise it follows the price within the range
+        if !at_crossed_tick {
+            pool.current_tick =
+                concentrated::tick_at_sqrt_price(pool.sqrt_price, pool.tick_below, pool.tick_above - 1)?;
+        }
+
+        if amount_out < minimum_amount_out {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        let amount_used = amount_in - amount_remaining;
+        pool.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        let (user_source, user_destination, vault_in, vault_out) = if a_to_b {
+            (user_account_a, user_account_b, vault_a, vault_b)
+        } else {
+            (user_account_b, user_account_a, vault_b, vault_a)
+        };
+        invoke(
+            &token_interface::transfer(token_program.key, user_source.key, vault_in.key, user.key, &[], amount_used)?,
+            &[user_source.clone(), vault_in.clone(), user.clone()],
+        )?;
+        if amount_out > 0 {
+            Self::invoke_signed_by_concentrated_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    vault_out.key,
+                    user_destination.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[vault_out.clone(), user_destination.clone(), pool_account.clone()],
+                &pool,
+            )?;
+        }
+
+        msg!("Concentrated swap: {} in, {} out, now at tick {}", amount_used, amount_out, pool.current_tick);
+
+        Ok(())
+    }
+
+    /// Creates a bin pool at its PDA, with no liquidity yet
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Creator (signer, pays rent)
+    ///   - Pool PDA (writable)
+    ///   - Token A vault
+    ///   - Token B vault
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `fee_rate` - Trading fee in basis points
+    /// * `bin_step` - Price step between neighbouring bins in basis points
+    /// * `active_bin` - Bin the pool starts trading in
+    fn process_initialize_bin_pool(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        fee_rate: Bps,
+        bin_step: u16,
+        active_bin: i32,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let creator = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify creator is a signer
+        if !creator.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if fee_rate > MAX_FEE_RATE_BPS {
+            return Err(TokenExchangeError::InvalidFeeRate.into());
+        }
+        if bin_step == 0 || bin_step > MAX_BIN_STEP {
+            return Err(TokenExchangeError::InvalidBin.into());
+        }
+        bin_pool::bin_price(bin_step, active_bin)?;
+
+        // Both vaults are held by the pool under one token program, and
+        // their mints are the pair in canonical order
+        token_interface::check_token_program(vault_a.owner)?;
+        if vault_b.owner != vault_a.owner {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let vault_a_state = token_interface::unpack_token_account(&vault_a.data.borrow())?;
+        let vault_b_state = token_interface::unpack_token_account(&vault_b.data.borrow())?;
+        if vault_a_state.mint >= vault_b_state.mint {
+            return Err(TokenExchangeError::InvalidBinPool.into());
+        }
+        let (expected_address, bump) =
+            BinPool::find_address(&vault_a_state.mint, &vault_b_state.mint, bin_step, program_id);
+        if *pool_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidBinPool.into());
+        }
+        if vault_a_state.owner != expected_address || vault_b_state.owner != expected_address {
+            return Err(TokenExchangeError::InvalidTokenVault.into());
+        }
+
+        Self::create_pda_account(
+            creator,
+            pool_account,
+            system_program,
+            program_id,
+            BinPool::LEN,
+            &[
+                BIN_POOL_SEED,
+                vault_a_state.mint.as_ref(),
+                vault_b_state.mint.as_ref(),
+                &bin_step.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        let pool = BinPool {
+            authority: *creator.key,
+            mint_a: vault_a_state.mint,
+            mint_b: vault_b_state.mint,
+            vault_a: *vault_a.key,
+            vault_b: *vault_b.key,
+            fee_rate: fee_rate.get(),
+            bin_step,
+            active_bin,
+            bump,
+        };
+        pool.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        msg!("Bin pool created at bin {}", active_bin);
+
+        Ok(())
+    }
+
+    /// Creates an empty bin PDA of a bin pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer, pays rent)
+    ///   - Pool PDA
+    ///   - Bin PDA (writable)
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `bin_id` - The bin to initialize
+    fn process_initialize_bin(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        bin_id: i32,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let bin_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pool = validation::load_bin_pool(pool_account, program_id)?;
+        bin_pool::bin_price(pool.bin_step, bin_id)?;
+
+        let (expected_address, bump) = BinState::find_address(pool_account.key, bin_id, program_id);
+        if *bin_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidBin.into());
+        }
+        Self::create_pda_account(
+            payer,
+            bin_account,
+            system_program,
+            program_id,
+            BinState::LEN,
+            &[BIN_SEED, pool_account.key.as_ref(), &bin_id.to_le_bytes(), &[bump]],
+        )?;
+
+        let bin = BinState {
+            pool: *pool_account.key,
+            id: bin_id,
+            amount_a: 0,
+            amount_b: 0,
+            liquidity_supply: 0,
+            bump,
+        };
+        bin.serialize(&mut *bin_account.data.borrow_mut())?;
+
+        msg!("Bin {} initialized", bin_id);
+
+        Ok(())
+    }
+
+    /// Opens an empty position over a range of bins of a bin pool
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Owner (signer, pays rent)
+    ///   - Pool PDA
+    ///   - Position PDA (writable)
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `lower_bin` - Lowest bin of the range
+    /// * `width` - Number of bins in the range
+    fn process_open_bin_position(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lower_bin: i32,
+        width: u16,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pool = validation::load_bin_pool(pool_account, program_id)?;
+        if width == 0 || width as usize > MAX_BIN_POSITION_WIDTH {
+            return Err(TokenExchangeError::InvalidBin.into());
+        }
+        bin_pool::bin_price(pool.bin_step, lower_bin)?;
+        bin_pool::bin_price(pool.bin_step, lower_bin.saturating_add(width as i32 - 1))?;
+
+        let (expected_address, bump) =
+            BinPosition::find_address(pool_account.key, owner.key, lower_bin, width, program_id);
+        if *position_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidBinPool.into());
+        }
+        Self::create_pda_account(
+            owner,
+            position_account,
+            system_program,
+            program_id,
+            BinPosition::LEN,
+            &[
+                BIN_POSITION_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &lower_bin.to_le_bytes(),
+                &width.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        let position = BinPosition {
+            pool: *pool_account.key,
+            owner: *owner.key,
+            lower_bin,
+            width,
+            shares: [0; MAX_BIN_POSITION_WIDTH],
+            bump,
+        };
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        msg!("Bin position opened over {} bins from bin {}", width, lower_bin);
+
+        Ok(())
+    }
+
+    /// Adds liquidity across a bin position's range, split evenly by side of the active bin
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Owner (signer)
+    ///   - Pool PDA
+    ///   - Position PDA (writable)
+    ///   - Owner token A and B accounts (writable)
+    ///   - Pool token A and B vaults (writable)
+    ///   - Token program
+    ///   - Each bin PDA of the range, from the lowest (writable)
+    /// * `program_id` - The program's public key
+    /// * `amount_a` - Most token A to deposit
+    /// * `amount_b` - Most token B to deposit
+    /// * `active_bin` - Active bin the split was planned for
+    fn process_add_bin_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_a: u64,
+        amount_b: u64,
+        active_bin: i32,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let owner_account_a = next_account_info(account_info_iter)?;
+        let owner_account_b = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pool = validation::load_bin_pool(pool_account, program_id)?;
+        validation::check_pair_vaults(&pool.vault_a, &pool.vault_b, vault_a, vault_b, token_program)?;
+        if pool.active_bin != active_bin {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        let mut position = validation::load_bin_position(position_account, pool_account.key, owner.key, program_id)?;
+
+        // Token A goes to the bins at and above the active bin, token B to
+        // those at and below it
+        let bins_a = position.bins().filter(|bin_id| *bin_id >= active_bin).count() as u64;
+        let bins_b = position.bins().filter(|bin_id| *bin_id <= active_bin).count() as u64;
+        let per_bin_a = amount_a.checked_div(bins_a).unwrap_or(0);
+        let per_bin_b = amount_b.checked_div(bins_b).unwrap_or(0);
+
+        let (mut deposited_a, mut deposited_b) = (0u64, 0u64);
+        for (index, bin_id) in position.bins().enumerate() {
+            let bin_account = next_account_info(account_info_iter)?;
+            let mut bin = validation::load_bin(bin_account, pool_account.key, bin_id, program_id)?;
+            let bin_a = if bin_id >= active_bin { per_bin_a } else { 0 };
+            let bin_b = if bin_id <= active_bin { per_bin_b } else { 0 };
+            let shares = bin.deposit(bin_pool::bin_price(pool.bin_step, bin_id)?, bin_a, bin_b)?;
+            position.shares[index] = position.shares[index].checked_add(shares)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            bin.serialize(&mut *bin_account.data.borrow_mut())?;
+            deposited_a += bin_a;
+            deposited_b += bin_b;
+        }
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        let transfers = [(deposited_a, owner_account_a, vault_a), (deposited_b, owner_account_b, vault_b)];
+        for (amount, owner_account, vault) in transfers.into_iter().filter(|(amount, _, _)| *amount > 0) {
+            invoke(
+                &token_interface::transfer(token_program.key, owner_account.key, vault.key, owner.key, &[], amount)?,
+                &[owner_account.clone(), vault.clone(), owner.clone()],
+            )?;
+        }
+
+        msg!("Added {} token A and {} token B across {} bins", deposited_a, deposited_b, position.width);
+
+        Ok(())
+    }
+
+    /// Redeems part of a bin position's shares in every bin of its range
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts, as for `process_add_bin_liquidity`
+    /// * `program_id` - The program's public key
+    /// * `share_bps` - Part of the shares in each bin to redeem, in basis points
+    /// * `min_amount_a` - Minimum token A to receive
+    /// * `min_amount_b` - Minimum token B to receive
+    fn process_remove_bin_liquidity(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        share_bps: Bps,
+        min_amount_a: u64,
+        min_amount_b: u64,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let owner_account_a = next_account_info(account_info_iter)?;
+        let owner_account_b = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if share_bps.get() == 0 || share_bps.get() > 10000 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let pool = validation::load_bin_pool(pool_account, program_id)?;
+        validation::check_pair_vaults(&pool.vault_a, &pool.vault_b, vault_a, vault_b, token_program)?;
+        let mut position = validation::load_bin_position(position_account, pool_account.key, owner.key, program_id)?;
+
+        let (mut amount_a, mut amount_b) = (0u64, 0u64);
+        for (index, bin_id) in position.bins().enumerate() {
+            let bin_account = next_account_info(account_info_iter)?;
+            let mut bin = validation::load_bin(bin_account, pool_account.key, bin_id, program_id)?;
+            let shares = math::mul_div(position.shares[index], share_bps.get() as u128, 10000, Rounding::Down)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let (bin_a, bin_b) = bin.withdraw(shares)?;
+            position.shares[index] -= shares;
+            bin.serialize(&mut *bin_account.data.borrow_mut())?;
+            amount_a = amount_a.checked_add(bin_a).ok_or(TokenExchangeError::MathOverflow)?;
+            amount_b = amount_b.checked_add(bin_b).ok_or(TokenExchangeError::MathOverflow)?;
+        }
+        if amount_a < min_amount_a || amount_b < min_amount_b {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        let transfers = [(amount_a, owner_account_a, vault_a), (amount_b, owner_account_b, vault_b)];
+        for (amount, owner_account, vault) in transfers.into_iter().filter(|(amount, _, _)| *amount > 0) {
+            Self::invoke_signed_by_bin_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    vault.key,
+                    owner_account.key,
+                    pool_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[vault.clone(), owner_account.clone(), pool_account.clone()],
+                &pool,
+            )?;
+        }
+
+        msg!("Removed {} token A and {} token B across {} bins", amount_a, amount_b, position.width);
+
+        Ok(())
+    }
+
+    /// Swaps against a bin pool, trading through bins from the active one on
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - User (signer)
+    ///   - Pool PDA (writable)
+    ///   - User token A and B accounts (writable)
+    ///   - Pool token A and B vaults (writable)
+    ///   - Token program
+    ///   - Each bin PDA traded in, from the active bin (writable)
+    /// * `program_id` - The program's public key
+    /// * `amount_in` - Input to swap, including the fee
+    /// * `minimum_amount_out` - Minimum output to receive
+    /// * `a_to_b` - Whether token A is sold for token B
+    fn process_swap_bins(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        a_to_b: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let user = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let user_account_a = next_account_info(account_info_iter)?;
+        let user_account_b = next_account_info(account_info_iter)?;
+        let vault_a = next_account_info(account_info_iter)?;
+        let vault_b = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify user is a signer
+        if !user.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if amount_in == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let mut pool = validation::load_bin_pool(pool_account, program_id)?;
+        validation::check_pair_vaults(&pool.vault_a, &pool.vault_b, vault_a, vault_b, token_program)?;
+
+        // Trade in the active bin, and move on to the next bin only once its
+        // account is passed, so the active bin is always an initialized one
+        let mut amount_remaining = amount_in;
+        let mut amount_out: u64 = 0;
+        let mut drained = false;
+        while amount_remaining > 0 {
+            let Some(bin_account) = account_info_iter.next() else {
+                break;
+            };
+            if drained {
+                pool.active_bin = if a_to_b { pool.active_bin - 1 } else { pool.active_bin + 1 };
+            }
+            let mut bin = validation::load_bin(bin_account, pool_account.key, pool.active_bin, program_id)?;
+            let price = bin_pool::bin_price(pool.bin_step, pool.active_bin)?;
+            let swap = bin.swap(price, amount_remaining, pool.fee_rate, a_to_b)?;
+            bin.serialize(&mut *bin_account.data.borrow_mut())?;
+            amount_remaining -= swap.amount_in + swap.fee;
+            amount_out = amount_out.checked_add(swap.amount_out)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            drained = true;
+        }
+
+        if amount_out < minimum_amount_out {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        let amount_used = amount_in - amount_remaining;
+        pool.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        let (user_source, user_destination, vault_in, vault_out) = if a_to_b {
+            (user_account_a, user_account_b, vault_a, vault_b)
+        } else {
+            (user_account_b, user_account_a, vault_b, vault_a)
+        };
+        invoke(
+            &token_interface::transfer(token_program.key, user_source.key, vault_in.key, user.key, &[], amount_used)?,
+            &[user_source.clone(), vault_in.clone(), user.clone()],
+        )?;
+        if amount_out > 0 {
+            Self::invoke_signed_by_bin_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    vault_out.key,
+                    user_destination.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[vault_out.clone(), user_destination.clone(), pool_account.clone()],
+                &pool,
+            )?;
+        }
+
+        msg!("Bin swap: {} in, {} out, now at bin {}", amount_used, amount_out, pool.active_bin);
+
+        Ok(())
+    }
+
+    /// Graduates a bonding curve at its threshold into a constant product pool
+    /// 
+    /// Burns the tokens the curve's last price no longer backs, drops the
+    /// virtual reserves and locks LP tokens for the whole pool in a token
+    /// account the pool owns.
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the graduation
+    /// * `program_id` - The program's public key
+    fn process_graduate(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let caller = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+        let locked_lp_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify caller is a signer
+        if !caller.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify the pool and its accounts
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.graduation_threshold == 0 || pool_state.sol_reserve < pool_state.graduation_threshold {
+            return Err(TokenExchangeError::GraduationNotReached.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        if *token_mint.key != pool_state.token_mint {
+            return Err(TokenExchangeError::InvalidTokenMint.into());
+        }
+        let locked_lp = token_interface::unpack_token_account(&locked_lp_account.data.borrow())?;
+        if locked_lp.owner != *pool_account.key || locked_lp.mint != pool_state.lp_mint {
+            return Err(TokenExchangeError::InvalidLpMint.into());
+        }
+
+        // A bonding curve takes no deposits, so the locked LP tokens are the whole supply
+        if token_interface::unpack_mint(&lp_mint.data.borrow())?.supply != 0 {
+            return Err(TokenExchangeError::InvalidLpMint.into());
+        }
+
+        // Keep the tokens that hold the curve's last price, and lock LP tokens for the pool
+        let token_reserve = pool_state.graduated_token_reserve()?;
+        if token_reserve == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        let tokens_burned = pool_state.token_reserve - token_reserve;
+        let lp_tokens = math::geometric_mean(pool_state.sol_reserve, token_reserve);
+
+        // Reopen as a constant product pool
+        pool_state.token_reserve = token_reserve;
+        pool_state.curve_type = CurveType::ConstantProduct;
+        pool_state.sol_offset = 0;
+        pool_state.token_offset = 0;
+        pool_state.graduation_threshold = 0;
+        pool_state.lp_supply = lp_tokens;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        // Burn the tokens the constant product pool does not keep
+        if tokens_burned > 0 {
+            Self::invoke_signed_by_pool(
+                &token_interface::burn(
+                    token_program.key,
+                    pool_token_account.key,
+                    token_mint.key,
+                    pool_account.key,
+                    &[],
+                    tokens_burned,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    token_mint.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        // Mint the LP tokens to the pool's own account, where they stay
+        Self::invoke_signed_by_pool(
+            &token_interface::mint_to(
+                lp_mint.owner,
+                lp_mint.key,
+                locked_lp_account.key,
+                pool_account.key,
+                &[],
+                lp_tokens,
+            )?,
+            &[
+                lp_mint.clone(),
+                locked_lp_account.clone(),
+                pool_account.clone(),
+            ],
+            &pool_state,
+        )?;
+
+        PoolGraduatedEvent {
+            pool: *pool_account.key,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve,
+            tokens_burned,
+            lp_tokens_locked: lp_tokens,
+        }
+        .emit()?;
+        msg!(
+            "Graduated: {} SOL, {} tokens, {} burned, {} LP locked",
+            pool_state.sol_reserve,
+            token_reserve,
+            tokens_burned,
+            lp_tokens
+        );
+
+        Ok(())
+    }
+
+    /// Opens a DCA position and escrows its input
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for opening the position
+    /// * `program_id` - The program's public key
+    /// * `terms` - The position's size, cadence, direction, tip, and floor price
+    fn process_open_dca(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        terms: DcaTerms,
+    ) -> ProgramResult {
+        let DcaTerms {
+            dca_id,
+            amount,
+            amount_per_fill,
+            interval_slots,
+            is_sol_input,
+            keeper_tip_bps,
+            limit_price,
+        } = terms;
+
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+
+        // Verify the position account is the canonical PDA
+        let (expected_address, bump) =
+            DcaPosition::find_address(pool_account.key, owner.key, dca_id, program_id);
+        if *position_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidDcaPosition.into());
+        }
+        if amount == 0 || amount_per_fill == 0 || amount_per_fill > amount {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let parameters = Self::load_protocol_parameters(config_account, program_id)?;
+        if keeper_tip_bps.get() > parameters.max_keeper_tip_bps {
+            return Err(TokenExchangeError::InvalidKeeperBounty.into());
+        }
+
+        Self::create_pda_account(
+            owner,
+            position_account,
+            system_program,
+            program_id,
+            DcaPosition::LEN,
+            &[
+                DCA_POSITION_SEED,
+                pool_account.key.as_ref(),
+                owner.key.as_ref(),
+                &dca_id.to_le_bytes(),
+                &[bump],
+            ],
+        )?;
+
+        // Escrow the whole input up front
+        if is_sol_input {
+            invoke(
+                &system_instruction::transfer(owner.key, position_account.key, amount),
+                &[owner.clone(), position_account.clone(), system_program.clone()],
+            )?;
+        } else {
+            let escrow = token_interface::unpack_token_account(&escrow_token_account.data.borrow())?;
+            if escrow.owner != *position_account.key || escrow.mint != pool_state.token_mint {
+                return Err(TokenExchangeError::InvalidDcaPosition.into());
+            }
+
+            invoke(
+                &token_interface::transfer(
+                    token_program.key,
+                    owner_token_account.key,
+                    escrow_token_account.key,
+                    owner.key,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    owner_token_account.clone(),
+                    escrow_token_account.clone(),
+                    owner.clone(),
+                ],
+            )?;
+        }
+
+        let position = DcaPosition {
+            owner: *owner.key,
+            pool: *pool_account.key,
+            dca_id,
+            is_sol_input,
+            amount_per_fill,
+            interval_slots,
+            next_fill_slot: Clock::get()?.slot,
+            remaining_amount: amount,
+            filled_output: 0,
+            limit_price,
+            keeper_tip_bps: keeper_tip_bps.get(),
+            bump,
+        };
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Swaps the next due tranche of a DCA position and tips the keeper
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for the tranche
+    /// * `program_id` - The program's public key
+    fn process_execute_dca(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let keeper = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let owner_sol_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let keeper_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify keeper is a signer
+        if !keeper.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool and position state
+        if pool_account.owner != program_id || position_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if pool_state.deprecated {
+            return Err(TokenExchangeError::PoolDeprecated.into());
+        }
+        if pool_state.paused {
+            return Err(TokenExchangeError::PoolPaused.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+        let mut position = DcaPosition::try_from_slice(&position_account.data.borrow())?;
+        if position.pool != *pool_account.key {
+            return Err(TokenExchangeError::InvalidDcaPosition.into());
+        }
+
+        // Output must reach the position owner
+        if position.is_sol_input {
+            let destination = token_interface::unpack_token_account(&owner_token_account.data.borrow())?;
+            if destination.owner != position.owner || destination.mint != pool_state.token_mint {
+                return Err(TokenExchangeError::InvalidDcaPosition.into());
+            }
+        } else if Self::sol_recipient(&pool_state, owner_sol_account)? != position.owner {
+            return Err(TokenExchangeError::InvalidDcaPosition.into());
+        }
+
+        // Only one tranche per interval
+        let current_slot = Clock::get()?.slot;
+        if position.remaining_amount == 0 || current_slot < position.next_fill_slot {
+            return Err(TokenExchangeError::TrancheNotDue.into());
+        }
+
+        // The keeper's tip comes out of the tranche; the rest is swapped and
+        // must clear the floor price for the whole tranche
+        let fill_amount = position.next_fill_amount();
+        let keeper_tip = position.keeper_tip(fill_amount);
+        let swap_amount = fill_amount - keeper_tip;
+        let swap_input = SwapAmount::new(swap_amount, position.is_sol_input);
+        let swap_output = Self::calculate_swap_output(&pool_state, swap_input)?;
+        let amount_out = swap_output.get();
+        let required_output = position
+            .required_output(fill_amount)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        if amount_out < required_output {
+            return Err(TokenExchangeError::LimitPriceNotReached.into());
+        }
+
+        // Update pool and position state
+        Self::apply_swap_reserves(&mut pool_state, swap_input, swap_output, 0, None)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        position.remaining_amount -= fill_amount;
+        position.filled_output = position.filled_output.checked_add(amount_out)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        position.next_fill_slot = current_slot
+            .checked_add(position.interval_slots)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        position.serialize(&mut *position_account.data.borrow_mut())?;
+
+        let signer_seeds: &[&[u8]] = &[
+            DCA_POSITION_SEED,
+            position.pool.as_ref(),
+            position.owner.as_ref(),
+            &position.dca_id.to_le_bytes(),
+            &[position.bump],
+        ];
+
+        if position.is_sol_input {
+            // Escrowed SOL moves into the pool and to the keeper, tokens go to the owner
+            **position_account.try_borrow_mut_lamports()? -= fill_amount;
+            **keeper.try_borrow_mut_lamports()? += keeper_tip;
+            Self::credit_sol_vault(&pool_state, sol_vault, token_program, swap_amount)?;
+
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    owner_token_account.key,
+                    pool_account.key,
+                    &[],
+                    amount_out,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    owner_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        } else {
+            // Escrowed tokens move into the pool and to the keeper, SOL goes to the owner
+            Self::collect_exact_token_input(&pool_state, pool_token_account, swap_amount, || {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        pool_token_account.key,
+                        position_account.key,
+                        &[],
+                        swap_amount,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        pool_token_account.clone(),
+                        position_account.clone(),
+                    ],
+                    &[signer_seeds],
+                )
+            })?;
+
+            if keeper_tip > 0 {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        keeper_token_account.key,
+                        position_account.key,
+                        &[],
+                        keeper_tip,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        keeper_token_account.clone(),
+                        position_account.clone(),
+                    ],
+                    &[signer_seeds],
+                )?;
+            }
+
+            Self::pay_from_sol_vault(
+                &pool_state,
+                pool_account,
+                sol_vault,
+                owner_sol_account,
+                token_program,
+                amount_out,
+            )?;
+        }
+
+        if keeper_tip > 0 {
+            KeeperBountyPaidEvent {
+                pool: position.pool,
+                keeper: *keeper.key,
+                amount: keeper_tip,
+                is_sol: position.is_sol_input,
+            }
+            .emit()?;
+        }
+        msg!(
+            "DCA tranche: {} in, {} out, {} keeper tip, {} remaining",
+            swap_amount,
+            amount_out,
+            keeper_tip,
+            position.remaining_amount
+        );
+
+        Ok(())
+    }
+
+    /// Closes a DCA position and refunds its escrow
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts for closing the position
+    /// * `program_id` - The program's public key
+    fn process_close_dca(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let owner = next_account_info(account_info_iter)?;
+        let position_account = next_account_info(account_info_iter)?;
+        let owner_token_account = next_account_info(account_info_iter)?;
+        let escrow_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Verify owner is a signer and owns the position
+        if !owner.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if position_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let position = DcaPosition::try_from_slice(&position_account.data.borrow())?;
+        if position.owner != *owner.key {
+            return Err(TokenExchangeError::InvalidDcaPosition.into());
+        }
+
+        // Refund remaining tokens and close the escrow token account
+        if !position.is_sol_input {
+            let signer_seeds: &[&[u8]] = &[
+                DCA_POSITION_SEED,
+                position.pool.as_ref(),
+                position.owner.as_ref(),
+                &position.dca_id.to_le_bytes(),
+                &[position.bump],
+            ];
+
+            if position.remaining_amount > 0 {
+                invoke_signed(
+                    &token_interface::transfer(
+                        token_program.key,
+                        escrow_token_account.key,
+                        owner_token_account.key,
+                        position_account.key,
+                        &[],
+                        position.remaining_amount,
+                    )?,
+                    &[
+                        escrow_token_account.clone(),
+                        owner_token_account.clone(),
+                        position_account.clone(),
+                    ],
+                    &[signer_seeds],
+                )?;
+            }
+            invoke_signed(
+                &token_interface::close_account(
+                    token_program.key,
+                    escrow_token_account.key,
+                    owner.key,
+                    position_account.key,
+                    &[],
+                )?,
+                &[
+                    escrow_token_account.clone(),
+                    owner.clone(),
+                    position_account.clone(),
+                ],
+                &[signer_seeds],
+            )?;
+        }
+
+        // Remaining SOL escrow and rent both return to the owner
+        Self::close_program_account(position_account, owner)
+    }
+
+    /// Puts a pool in emergency mode, pausing it, or takes it out again
+    /// 
+    /// # Arguments
+    /// * `accounts` - Protocol authority, protocol config PDA and pool state account
+    /// * `program_id` - The program's public key
+    /// * `emergency` - Whether the pool should be in emergency mode
+    fn process_set_emergency_mode(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        emergency: bool,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+
+        Self::check_protocol_authority(authority, config_account, program_id)?;
+
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+
+        // Leaving emergency mode keeps the pool paused until its authority resumes it
+        pool_state.emergency = emergency;
+        pool_state.paused |= emergency;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        msg!("Emergency mode {}", if emergency { "entered" } else { "lifted" });
+        Ok(())
+    }
+
+    /// Burns LP tokens for a pro-rata share of the vault balances of a pool in emergency mode
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts, as for `process_remove_liquidity`
+    /// * `program_id` - The program's public key
+    /// * `lp_tokens` - Amount of LP tokens to burn
+    fn process_emergency_withdraw(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        lp_tokens: LpAmount,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let provider = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let provider_sol_account = next_account_info(account_info_iter)?;
+        let provider_token_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let provider_lp_account = next_account_info(account_info_iter)?;
+        let lp_mint = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+
+        // Verify provider is a signer
+        if !provider.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        if !pool_state.emergency {
+            return Err(TokenExchangeError::PoolNotInEmergency.into());
+        }
+        Self::require_native_sol_pool(&pool_state)?;
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+        validation::check_lp_mint(&pool_state, lp_mint)?;
+        validation::check_sol_vault(&pool_state, sol_vault)?;
+
+        // Keep the provider's position, if passed, in step with the LP tokens
+        let mut position =
+            Self::find_user_position(account_info_iter.as_slice(), pool_account.key, provider.key, program_id)?;
+
+        // Price the share off what the vaults hold, not the stored reserves
+        let sol_balance = if pool_state.wsol {
+            token_interface::unpack_token_account(&sol_vault.data.borrow())?.amount
+        } else {
+            sol_vault.lamports().saturating_sub(Rent::get()?.minimum_balance(sol_vault.data_len()))
+        };
+        let token_balance = token_interface::unpack_token_account(&pool_token_account.data.borrow())?.amount;
+        let total_supply = token_interface::unpack_mint(&lp_mint.data.borrow())?.supply;
+        let sol_amount = Self::emergency_withdraw_amount(
+            sol_balance,
+            pool_state.protocol_fee_sol.saturating_add(pool_state.lp_fees_sol),
+            lp_tokens.get(),
+            total_supply,
+        )?;
+        let token_amount = Self::emergency_withdraw_amount(
+            token_balance,
+            pool_state.protocol_fee_token.saturating_add(pool_state.lp_fees_token),
+            lp_tokens.get(),
+            total_supply,
+        )?;
+
+        // Update pool state
+        pool_state.sol_reserve = pool_state.sol_reserve.saturating_sub(sol_amount);
+        pool_state.token_reserve = pool_state.token_reserve.saturating_sub(token_amount);
+        if let Some((_, position)) = position.as_mut() {
+            Self::update_position(&mut pool_state, position, lp_tokens.get(), false)?;
+        }
+        pool_state.lp_supply = total_supply.saturating_sub(lp_tokens.get());
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+        if let Some((position_account, position)) = &position {
+            position.serialize(&mut *position_account.data.borrow_mut())?;
+        }
+
+        // Burn LP tokens
+        invoke(
+            &token_interface::burn(
+                lp_mint.owner,
+                provider_lp_account.key,
+                lp_mint.key,
+                provider.key,
+                &[],
+                lp_tokens.get(),
+            )?,
+            &[
+                provider_lp_account.clone(),
+                lp_mint.clone(),
+                provider.clone(),
+            ],
+        )?;
+
+        // Transfer assets back to provider
+        Self::pay_from_sol_vault(
+            &pool_state,
+            pool_account,
+            sol_vault,
+            provider_sol_account,
+            token_program,
+            sol_amount,
+        )?;
+
+        Self::invoke_signed_by_pool(
+            &token_interface::transfer(
+                token_program.key,
+                pool_token_account.key,
+                provider_token_account.key,
+                pool_account.key,
+                &[],
+                token_amount,
+            )?,
+            &[
+                pool_token_account.clone(),
+                provider_token_account.clone(),
+                pool_account.clone(),
+            ],
+            &pool_state,
+        )?;
+
+        // Report the execution to CPI callers, simulations and indexers
+        LiquidityRemovedEvent {
+            pool: *pool_account.key,
+            provider: *provider.key,
+            sol_amount,
+            token_amount,
+            lp_tokens: lp_tokens.get(),
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+        Self::set_result(&LiquidityResult {
+            sol_amount: Lamports(sol_amount),
+            token_amount: TokenAmount(token_amount),
+            lp_tokens,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        })?;
+
+        msg!("Emergency withdrawal: {} lamports, {} tokens", sol_amount, token_amount);
+        Ok(())
+    }
+
+    /// An LP's emergency share of one vault
+    /// 
+    /// `lp_tokens` out of `total_supply` of the vault balance net of the fees
+    /// set aside in it, rounded down so the last LP out can always be paid.
+    pub fn emergency_withdraw_amount(
+        vault_balance: u64,
+        set_aside: u64,
+        lp_tokens: u64,
+        total_supply: u64,
+    ) -> Result<u64, ProgramError> {
+        if lp_tokens == 0 || lp_tokens > total_supply {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let available = vault_balance.saturating_sub(set_aside);
+        Ok(math::mul_div(available as u128, lp_tokens as u128, total_supply as u128, Rounding::Down)
+            .ok_or(TokenExchangeError::MathOverflow)? as u64)
+    }
+
+    /// Folds vault balances beyond a pool's accounting into its reserves or protocol fees
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool state account, pool token account, SOL (or mint A)
+    ///   vault and protocol config PDA
+    /// * `program_id` - The program's public key
+    fn process_sync_reserves(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        let parameters = Self::load_protocol_parameters(config_account, program_id)?;
+        let (sol_balance, token_balance) =
+            Self::vault_balances(&pool_state, pool_account, pool_token_account, side_a_vault)?;
+
+        // Advance the price accumulator at the pre-sync price before the reserves move
+        pool_state.accumulate_price(Clock::get()?.slot);
+        let skim = parameters.skim_excess_reserves;
+        let [sol_excess, token_excess] = pool_state.sync_reserves(sol_balance, token_balance, skim)?;
+        pool_state.serialize(&mut *pool_account.data.borrow_mut())?;
+
+        // Report the execution to CPI callers, simulations and indexers
+        ReservesSyncedEvent {
+            pool: *pool_account.key,
+            sol_excess,
+            token_excess,
+            skimmed: skim,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+
+        msg!("Synced reserves: {} side A, {} token excess", sol_excess, token_excess);
+        Ok(())
+    }
+
+    /// Pays a pool's vault excess to the protocol treasury
+    /// 
+    /// # Arguments
+    /// * `accounts` - Pool state account, pool token account, SOL (or mint A)
+    ///   vault, protocol config PDA, treasury token and side A accounts and
+    ///   token program
+    /// * `program_id` - The program's public key
+    fn process_skim(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let pool_account = next_account_info(account_info_iter)?;
+        let pool_token_account = next_account_info(account_info_iter)?;
+        let side_a_vault = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let treasury_token_account = next_account_info(account_info_iter)?;
+        let treasury_side_a_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+
+        // Load and verify pool state
+        let mut pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+        validation::load_pool_vault(&mut pool_state, pool_account.key, pool_token_account)?;
+        validation::check_token_program(token_program, pool_token_account)?;
+
+        // The excess may only go to the configured treasury
+        let treasury = Self::load_protocol_parameters(config_account, program_id)?.treasury;
+        if treasury == Pubkey::default() {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+        let treasury_side_a_owner = if pool_state.is_token_pair() {
+            token_interface::unpack_token_account(&treasury_side_a_account.data.borrow())?.owner
+        } else {
+            Self::sol_recipient(&pool_state, treasury_side_a_account)?
+        };
+        if token_interface::unpack_token_account(&treasury_token_account.data.borrow())?.owner != treasury
+            || treasury_side_a_owner != treasury
+        {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+
+        let (sol_balance, token_balance) =
+            Self::vault_balances(&pool_state, pool_account, pool_token_account, side_a_vault)?;
+        let [sol_excess, token_excess] = pool_state.vault_excess(sol_balance, token_balance);
+
+        // Sweep side A excess
+        if sol_excess > 0 {
+            if pool_state.is_token_pair() {
+                Self::invoke_signed_by_pool(
+                    &token_interface::transfer(
+                        token_program.key,
+                        side_a_vault.key,
+                        treasury_side_a_account.key,
+                        pool_account.key,
+                        &[],
+                        sol_excess,
+                    )?,
+                    &[
+                        side_a_vault.clone(),
+                        treasury_side_a_account.clone(),
+                        pool_account.clone(),
+                    ],
+                    &pool_state,
+                )?;
+            } else {
+                Self::pay_from_sol_vault(
+                    &pool_state,
+                    pool_account,
+                    side_a_vault,
+                    treasury_side_a_account,
+                    token_program,
+                    sol_excess,
+                )?;
+            }
+        }
+
+        // Sweep token excess
+        if token_excess > 0 {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    pool_token_account.key,
+                    treasury_token_account.key,
+                    pool_account.key,
+                    &[],
+                    token_excess,
+                )?,
+                &[
+                    pool_token_account.clone(),
+                    treasury_token_account.clone(),
+                    pool_account.clone(),
+                ],
+                &pool_state,
+            )?;
+        }
+
+        // Report the execution to CPI callers, simulations and indexers
+        ReservesSyncedEvent {
+            pool: *pool_account.key,
+            sol_excess,
+            token_excess,
+            skimmed: true,
+            sol_reserve: pool_state.sol_reserve,
+            token_reserve: pool_state.token_reserve,
+        }
+        .emit()?;
+
+        msg!("Skimmed to treasury: {} side A, {} token", sol_excess, token_excess);
+        Ok(())
+    }
+
+    /// What a pool's SOL (or mint A) and token vaults actually hold
+    /// 
+    /// A raw-lamport SOL vault's rent-exempt minimum is not counted.
+    fn vault_balances(
+        pool_state: &PoolState,
+        pool_account: &AccountInfo,
+        pool_token_account: &AccountInfo,
+        side_a_vault: &AccountInfo,
+    ) -> Result<(u64, u64), ProgramError> {
+        let sol_balance = if pool_state.is_token_pair() {
+            validation::check_pool_vault_a(pool_state, pool_account.key, side_a_vault, pool_token_account.owner)?;
+            token_interface::unpack_token_account(&side_a_vault.data.borrow())?.amount
+        } else if pool_state.wsol {
+            validation::check_sol_vault(pool_state, side_a_vault)?;
+            token_interface::unpack_token_account(&side_a_vault.data.borrow())?.amount
+        } else {
+            validation::check_sol_vault(pool_state, side_a_vault)?;
+            side_a_vault.lamports().saturating_sub(Rent::get()?.minimum_balance(side_a_vault.data_len()))
+        };
+        let token_balance = token_interface::unpack_token_account(&pool_token_account.data.borrow())?.amount;
+        Ok((sol_balance, token_balance))
+    }
+
+    /// SOL (or mint A) that seeds a constant product pool at `initial_price`
+    /// 
+    /// `initial_price` is in SOL base units per token base unit, scaled by
+    /// `LIMIT_PRICE_SCALE`. Rounded up, so the pool opens at or just above
+    /// the price asked for.
+    pub fn sol_amount_for_price(initial_price: u64, token_amount: TokenAmount) -> Result<Lamports, ProgramError> {
+        let sol_amount = math::mul_div(
+            token_amount.get() as u128,
+            initial_price as u128,
+            LIMIT_PRICE_SCALE as u128,
+            Rounding::Up,
+        )
+        .and_then(|sol_amount| u64::try_from(sol_amount).ok())
+        .ok_or(TokenExchangeError::MathOverflow)?;
+        if sol_amount == 0 {
+            return Err(TokenExchangeError::InsufficientLiquidity.into());
+        }
+        Ok(Lamports(sol_amount))
+    }
+
+    /// Verifies the signer is the protocol authority recorded in the protocol config PDA
+    fn check_protocol_authority(
+        authority: &AccountInfo,
+        config_account: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if config_account.owner != program_id || *config_account.key != ProtocolConfig::find_address(program_id).0 {
+            return Err(TokenExchangeError::InvalidProtocolConfig.into());
+        }
+        let config = ProtocolConfig::try_from_slice(&config_account.data.borrow())?;
+        if config.authority != *authority.key {
+            return Err(TokenExchangeError::InvalidProtocolAuthority.into());
+        }
+        Ok(())
+    }
+
+    /// Reads the upgrade authority out of an upgradeable loader program data account
+    /// 
+    /// Layout: u32 `ProgramData` tag (3), u64 slot, then `Option<Pubkey>`.
+    fn program_upgrade_authority(data: &[u8]) -> Option<Pubkey> {
+        if data.len() < 45 || data[0..4] != 3u32.to_le_bytes() || data[12] != 1 {
+            return None;
+        }
+        Pubkey::try_from(&data[13..45]).ok()
+    }
+
+    /// Creates a user's stats PDA
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer)
+    ///   - User the stats belong to
+    ///   - User stats PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    fn process_initialize_user_stats(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let owner = next_account_info(account_info_iter)?;
+        let user_stats_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Verify the stats account is the canonical PDA
+        let (expected_address, bump) = UserStats::find_address(owner.key, program_id);
+        if *user_stats_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidUserStats.into());
+        }
+
+        Self::create_pda_account(
+            payer,
+            user_stats_account,
+            system_program,
+            program_id,
+            UserStats::LEN,
+            &[USER_STATS_SEED, owner.key.as_ref(), &[bump]],
+        )?;
+
+        let user_stats = UserStats {
+            owner: *owner.key,
+            signed_order_nonce: 0,
+            bump,
+        };
+        user_stats.serialize(&mut *user_stats_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Creates a pool's price observations ring buffer
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer)
+    ///   - Pool state account
+    ///   - Observations PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `cardinality` - Initial number of observation slots
+    fn process_initialize_observations(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        cardinality: u16,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+
+        // Verify the observations account is the pool's canonical PDA
+        let (expected_address, bump) = ObservationState::find_address(pool_account.key, program_id);
+        if *observations_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidObservations.into());
+        }
+        if cardinality == 0 || cardinality > ObservationState::MAX_CARDINALITY {
+            return Err(TokenExchangeError::InvalidObservationCardinality.into());
+        }
+
+        Self::create_pda_account(
+            payer,
+            observations_account,
+            system_program,
+            program_id,
+            ObservationState::space(cardinality),
+            &[OBSERVATIONS_SEED, pool_account.key.as_ref(), &[bump]],
+        )?;
+
+        // Seed the buffer with an observation at the current slot
+        let mut observations = ObservationState {
+            pool: *pool_account.key,
+            index: 0,
+            bump,
+            observations: vec![Observation::default(); cardinality as usize],
+        };
+        observations.observations[0] = Observation {
+            slot: Clock::get()?.slot,
+            price_cumulative: 0,
+            initialized: true,
+            ..Observation::default()
+        };
+        observations.serialize(&mut *observations_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Creates a pool's volume stats ring buffer
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer)
+    ///   - Pool state account
+    ///   - Volume stats PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    fn process_initialize_volume_stats(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let volume_stats_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load and verify pool state
+        let pool_state = validation::load_pool(pool_account, program_id)?;
+        if !pool_state.initialized {
+            return Err(TokenExchangeError::PoolNotInitialized.into());
+        }
+
+        // Verify the volume stats account is the pool's canonical PDA
+        let (expected_address, bump) = VolumeStats::find_address(pool_account.key, program_id);
+        if *volume_stats_account.key != expected_address {
+            return Err(TokenExchangeError::InvalidVolumeStats.into());
+        }
+
+        Self::create_pda_account(
+            payer,
+            volume_stats_account,
+            system_program,
+            program_id,
+            VolumeStats::LEN,
+            &[VOLUME_STATS_SEED, pool_account.key.as_ref(), &[bump]],
+        )?;
+
+        let volume_stats = VolumeStats {
+            pool: *pool_account.key,
+            bump,
+            buckets: [VolumeBucket::default(); VOLUME_BUCKET_COUNT],
+        };
+        volume_stats.serialize(&mut *volume_stats_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Grows a pool's price observations ring buffer
+    /// 
+    /// # Arguments
+    /// * `accounts` - Required accounts:
+    ///   - Payer (signer)
+    ///   - Pool state account
+    ///   - Observations PDA
+    ///   - System program
+    /// * `program_id` - The program's public key
+    /// * `new_cardinality` - New number of observation slots
+    fn process_increase_observation_cardinality(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+        new_cardinality: u16,
+    ) -> ProgramResult {
+        // Get account references
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let pool_account = next_account_info(account_info_iter)?;
+        let observations_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+
+        // Verify payer is a signer
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Verify the observations account belongs to this pool
+        let (expected_address, _) = ObservationState::find_address(pool_account.key, program_id);
+        if *observations_account.key != expected_address || observations_account.owner != program_id {
+            return Err(TokenExchangeError::InvalidObservations.into());
+        }
+
+        let mut observations = ObservationState::try_from_slice(&observations_account.data.borrow())?;
+        if new_cardinality as usize <= observations.observations.len()
+            || new_cardinality > ObservationState::MAX_CARDINALITY
+        {
+            return Err(TokenExchangeError::InvalidObservationCardinality.into());
+        }
+
+        // Top up rent for the larger account, then resize it
+        let new_space = ObservationState::space(new_cardinality);
+        let required_lamports = Rent::get()?
+            .minimum_balance(new_space)
+            .saturating_sub(observations_account.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, observations_account.key, required_lamports),
+                &[payer.clone(), observations_account.clone(), system_program.clone()],
+            )?;
+        }
+        observations_account.realloc(new_space, false)?;
+
+        // New slots stay uninitialized until the ring buffer wraps into them
+        observations
+            .observations
+            .resize(new_cardinality as usize, Observation::default());
+        observations.serialize(&mut *observations_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Records a swap in the optional companion accounts
+    /// 
+    /// Writes a pre-trade price observation and aggregates the swap's SOL-side
+    /// volume and fee into the pool's volume buckets and the protocol totals.
+    /// 
+    /// Interest-bearing pools must pass their token mint as a companion when
+    /// recording observations, which then price the token's UI amount at the
+    /// current scale. Swaps and deposits need no scaling: their curves are
+    /// restricted at initialization to ones where scaling the token reserve
+    /// and every token amount alike leaves outputs and LP shares unchanged.
+    /// 
+    /// # Arguments
+    /// * `companions` - Companion accounts passed to the swap
+    /// * `pool_state` - Pool state before the swap
+    /// * `amount_in` - Input amount
+    /// * `amount_out` - Output amount before any integrator fee
+    fn record_swap(
+        companions: &CompanionAccounts,
+        pool_state: &PoolState,
+        amount_in: SwapAmount,
+        amount_out: SwapAmount,
+    ) -> ProgramResult {
+        // Record the pre-trade price for TWAP consumers
+        if let Some(observations_account) = companions.observations {
+            let clock = Clock::get()?;
+            let (price, token_reserve) = if pool_state.interest_bearing {
+                let token_mint = companions.token_mint
+                    .filter(|mint| *mint.key == pool_state.token_mint)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)?;
+                let config = InterestBearingConfig::load(&token_mint.data.borrow())
+                    .ok_or(ProgramError::InvalidAccountData)?;
+                let scale = config.scale(clock.unix_timestamp).ok_or(TokenExchangeError::MathOverflow)?;
+                let ui_price = pool_state.spot_price()
+                    .and_then(|price| math::mul_div_wide(price, Q64, scale, Rounding::Down));
+                let ui_token_reserve = config.ui_amount(pool_state.token_reserve, clock.unix_timestamp)
+                    .ok_or(TokenExchangeError::MathOverflow)?;
+                (ui_price, ui_token_reserve)
+            } else {
+                (pool_state.spot_price(), pool_state.token_reserve)
+            };
+
+            let mut observations = ObservationState::try_from_slice(&observations_account.data.borrow())?;
+            let liquidity = math::geometric_mean(pool_state.sol_reserve, token_reserve);
+            observations.write(clock.slot, price, liquidity);
+            observations.serialize(&mut *observations_account.data.borrow_mut())?;
+        }
+
+        // Value the swap's volume and fee in SOL
+        let fee_rate = Self::swap_fee_rate(pool_state)?;
+        let (sol_volume, fee_sol) = Self::swap_volume_in_sol(pool_state, amount_in, amount_out, fee_rate);
+
+        // Add to the pool's current volume bucket
+        if let Some(volume_stats_account) = companions.volume_stats {
+            let mut volume_stats = VolumeStats::try_from_slice(&volume_stats_account.data.borrow())?;
+            volume_stats.record(
+                Clock::get()?.slot,
+                sol_volume.min(u64::MAX as u128) as u64,
+                fee_sol.min(u64::MAX as u128) as u64,
+            );
+            volume_stats.serialize(&mut *volume_stats_account.data.borrow_mut())?;
+        }
+
+        // Aggregate volume and fees into the protocol totals
+        if let Some(stats_account) = companions.stats {
+            let mut stats = ProtocolStats::try_from_slice(&stats_account.data.borrow())?;
+            stats.total_volume_sol = stats.total_volume_sol
+                .checked_add(sol_volume)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            stats.total_fees_sol = stats.total_fees_sol
+                .checked_add(fee_sol)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            stats.last_update_slot = Clock::get()?.slot;
+            stats.serialize(&mut *stats_account.data.borrow_mut())?;
+        }
+
+        Ok(())
+    }
+
+    /// Verifies a provider may add liquidity to a pool
+    /// 
+    /// Deposit-permissioned pools require the provider's allowlist PDA among
+    /// `remaining_accounts`; other pools accept any provider. A bonding curve
+    /// takes no liquidity until it graduates.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - The pool being deposited into
+    /// * `pool` - The pool state account's address
+    /// * `provider` - The account receiving the LP tokens
+    /// * `remaining_accounts` - Trailing accounts of the instruction
+    /// * `program_id` - The program's public key
+    fn check_deposit_allowed(
+        pool_state: &PoolState,
+        pool: &Pubkey,
+        provider: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if pool_state.graduation_threshold > 0 {
+            return Err(TokenExchangeError::BondingCurveActive.into());
+        }
+        if !pool_state.deposit_permissioned {
+            return Ok(());
+        }
+
+        let (expected_address, _) = LpAllowlistEntry::find_address(pool, provider, program_id);
+        let entry_account = remaining_accounts
+            .iter()
+            .find(|account| *account.key == expected_address && account.owner == program_id)
+            .ok_or(TokenExchangeError::LiquidityProviderNotAllowed)?;
+        let entry = LpAllowlistEntry::try_from_slice(&entry_account.data.borrow())?;
+        if entry.pool != *pool || entry.provider != *provider {
+            return Err(TokenExchangeError::LiquidityProviderNotAllowed.into());
+        }
+        Ok(())
+    }
+
+    /// Loads the provider's position if it is among an instruction's trailing accounts
+    fn find_user_position<'a, 'b>(
+        remaining_accounts: &'a [AccountInfo<'b>],
+        pool: &Pubkey,
+        provider: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Option<(&'a AccountInfo<'b>, UserPosition)>, ProgramError> {
+        let (expected_address, _) = UserPosition::find_address(pool, provider, program_id);
+        remaining_accounts
+            .iter()
+            .find(|account| *account.key == expected_address)
+            .map(|account| Ok((account, validation::load_user_position(account, pool, provider, program_id)?)))
+            .transpose()
+    }
+
+    /// Settles a position's fees, then credits or debits its LP tokens
+    /// 
+    /// The pool's `position_lp_supply` moves with the position. A debit is
+    /// capped at the position's balance, since the LP tokens burned may have
+    /// been acquired outside it.
+    fn update_position(
+        pool_state: &mut PoolState,
+        position: &mut UserPosition,
+        lp_tokens: u64,
+        credit: bool,
+    ) -> ProgramResult {
+        position.settle(pool_state)?;
+        if credit {
+            position.lp_tokens = position.lp_tokens.checked_add(lp_tokens)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.position_lp_supply = pool_state.position_lp_supply.checked_add(lp_tokens)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+        } else {
+            let debit = lp_tokens.min(position.lp_tokens);
+            position.lp_tokens -= debit;
+            pool_state.position_lp_supply = pool_state.position_lp_supply.checked_sub(debit)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Invokes a CPI with a farm PDA signing, to move tokens out of its vaults
+    fn invoke_signed_by_farm(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        farm: &FarmState,
+    ) -> ProgramResult {
+        invoke_signed(instruction, account_infos, &[&[FARM_SEED, farm.pool.as_ref(), &[farm.bump]]])
+    }
+
+    /// Invokes a CPI with a concentrated liquidity pool PDA signing, to pay out of its vaults
+    fn invoke_signed_by_concentrated_pool(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        pool: &ConcentratedPool,
+    ) -> ProgramResult {
+        invoke_signed(
+            instruction,
+            account_infos,
+            &[&[
+                CONCENTRATED_POOL_SEED,
+                pool.mint_a.as_ref(),
+                pool.mint_b.as_ref(),
+                &pool.tick_spacing.to_le_bytes(),
+                &[pool.bump],
+            ]],
+        )
+    }
+
+    /// Invokes a CPI with a bin pool PDA signing, to pay out of its vaults
+    fn invoke_signed_by_bin_pool(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        pool: &BinPool,
+    ) -> ProgramResult {
+        invoke_signed(
+            instruction,
+            account_infos,
+            &[&[BIN_POOL_SEED, pool.mint_a.as_ref(), pool.mint_b.as_ref(), &pool.bin_step.to_le_bytes(), &[pool.bump]]],
+        )
+    }
+
+    /// Invokes a CPI with the pool PDA signing, e.g. to pay out of its vaults or mint LP tokens
+    fn invoke_signed_by_pool(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        pool_state: &PoolState,
+    ) -> ProgramResult {
+        invoke_signed(
+            instruction,
+            account_infos,
+            &[&[
+                POOL_SEED,
+                pool_state.mint_a.as_ref(),
+                pool_state.token_mint.as_ref(),
+                &pool_state.fee_tier.to_le_bytes(),
+                &[pool_state.bump],
+            ]],
+        )
+    }
+
+    /// Runs a transfer into a pool token vault and returns what the vault actually received
+    /// 
+    /// Token-2022 transfer-fee mints withhold part of every transfer, so the
+    /// nominal amount would overstate the pool's new balance.
+    fn transfer_into_vault(vault: &AccountInfo, transfer: impl FnOnce() -> ProgramResult) -> Result<u64, ProgramError> {
+        let balance_before = token_interface::unpack_token_account(&vault.data.borrow())?.amount;
+        transfer()?;
+        let balance_after = token_interface::unpack_token_account(&vault.data.borrow())?.amount;
+        balance_after
+            .checked_sub(balance_before)
+            .ok_or_else(|| TokenExchangeError::MathOverflow.into())
+    }
+
+    /// Runs a token input transfer into a pool vault and returns the amount to account
+    /// 
+    /// In fee-on-transfer mode this is what the vault actually received;
+    /// otherwise the pool trusts the instruction amount.
+    fn collect_token_input(
+        pool_state: &PoolState,
+        vault: &AccountInfo,
+        amount: u64,
+        transfer: impl FnOnce() -> ProgramResult,
+    ) -> Result<u64, ProgramError> {
+        if pool_state.fee_on_transfer {
+            Self::transfer_into_vault(vault, transfer)
+        } else {
+            transfer()?;
+            Ok(amount)
+        }
+    }
+
+    /// Runs a token input transfer whose amount the price already fixed
+    /// 
+    /// In fee-on-transfer mode the vault must receive the full amount, since
+    /// the output was paid against it.
+    fn collect_exact_token_input(
+        pool_state: &PoolState,
+        vault: &AccountInfo,
+        amount: u64,
+        transfer: impl FnOnce() -> ProgramResult,
+    ) -> ProgramResult {
+        if Self::collect_token_input(pool_state, vault, amount, transfer)? < amount {
+            return Err(TokenExchangeError::TransferFeeNotCovered.into());
+        }
+        Ok(())
+    }
+
+    /// Verifies the vault a flash loan moves through
+    /// 
+    /// SOL loans use the SOL vault, or the mint A vault for token pair pools;
+    /// token loans use the pool token account, whose reserve is re-read in
+    /// rebasing mode. Every vault but a native SOL vault must be a token
+    /// account under the passed token program.
+    fn check_flash_loan_vault(
+        pool_state: &mut PoolState,
+        pool: &Pubkey,
+        vault: &AccountInfo,
+        token_program: &AccountInfo,
+        is_sol: bool,
+    ) -> ProgramResult {
+        if !is_sol {
+            validation::load_pool_vault(pool_state, pool, vault)?;
+        } else if pool_state.is_token_pair() {
+            validation::check_pool_vault_a(pool_state, pool, vault, token_program.key)?;
+        } else {
+            validation::check_sol_vault(pool_state, vault)?;
+            if !pool_state.wsol {
+                return Ok(());
+            }
+        }
+        validation::check_token_program(token_program, vault)
+    }
+
+    /// Rejects token pair pools from operations that move native SOL
+    fn require_native_sol_pool(pool_state: &PoolState) -> ProgramResult {
+        if pool_state.is_token_pair() {
+            return Err(TokenExchangeError::UnsupportedTokenPair.into());
+        }
+        Ok(())
+    }
+
+    /// Rejects moving SOL between pools whose SOL vaults hold it differently
+    fn require_matching_sol_mode(first: &PoolState, second: &PoolState) -> ProgramResult {
+        if first.wsol != second.wsol {
+            return Err(TokenExchangeError::UnsupportedWsolMode.into());
+        }
+        Ok(())
+    }
+
+    /// Pays SOL into a pool's SOL vault from a signing wallet
+    /// 
+    /// In wSOL mode `source` is the depositor's wrapped SOL token account and
+    /// the depositor signs a token transfer; otherwise the system program
+    /// moves lamports straight from the depositor.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - State of the pool receiving the SOL
+    /// * `depositor` - Signer paying the SOL
+    /// * `source` - Depositor's wSOL token account (ignored outside wSOL mode)
+    /// * `sol_vault` - The pool's SOL vault
+    /// * `token_program` - The token program
+    /// * `amount` - Lamports to deposit
+    fn deposit_to_sol_vault<'a>(
+        pool_state: &PoolState,
+        depositor: &AccountInfo<'a>,
+        source: &AccountInfo<'a>,
+        sol_vault: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        if pool_state.wsol {
+            invoke(
+                &token_interface::transfer(token_program.key, source.key, sol_vault.key, depositor.key, &[], amount)?,
+                &[source.clone(), sol_vault.clone(), depositor.clone()],
+            )
+        } else {
+            invoke(
+                &system_instruction::transfer(depositor.key, sol_vault.key, amount),
+                &[depositor.clone(), sol_vault.clone()],
+            )
+        }
+    }
+
+    /// Credits a pool's SOL vault with lamports already debited from a program-owned account
+    /// 
+    /// In wSOL mode the vault's token balance is then synced to its lamports.
+    fn credit_sol_vault<'a>(
+        pool_state: &PoolState,
+        sol_vault: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        **sol_vault.try_borrow_mut_lamports()? += amount;
+        if pool_state.wsol {
+            invoke(
+                &spl_token::instruction::sync_native(token_program.key, sol_vault.key)?,
+                std::slice::from_ref(sol_vault),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Pays SOL out of a pool's SOL vault
+    /// 
+    /// In wSOL mode the pool signs a token transfer, so `destination` must be
+    /// a wrapped SOL token account (or another wSOL vault); otherwise
+    /// lamports move directly.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - State of the pool paying the SOL
+    /// * `pool_account` - The pool state account
+    /// * `sol_vault` - The pool's SOL vault
+    /// * `destination` - Account receiving the SOL
+    /// * `token_program` - The token program
+    /// * `amount` - Lamports to pay
+    fn pay_from_sol_vault<'a>(
+        pool_state: &PoolState,
+        pool_account: &AccountInfo<'a>,
+        sol_vault: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        if pool_state.wsol {
+            Self::invoke_signed_by_pool(
+                &token_interface::transfer(
+                    token_program.key,
+                    sol_vault.key,
+                    destination.key,
+                    pool_account.key,
+                    &[],
+                    amount,
+                )?,
+                &[sol_vault.clone(), destination.clone(), pool_account.clone()],
+                pool_state,
+            )
+        } else {
+            **sol_vault.try_borrow_mut_lamports()? -= amount;
+            **destination.try_borrow_mut_lamports()? += amount;
+            Ok(())
+        }
+    }
+
+    /// Verifies the side A vault of a pool: its SOL vault, or its mint A vault for token pair pools
+    fn check_side_a_vault(
+        pool_state: &PoolState,
+        pool: &Pubkey,
+        vault: &AccountInfo,
+        token_program: &AccountInfo,
+    ) -> ProgramResult {
+        if pool_state.is_token_pair() {
+            validation::check_pool_vault_a(pool_state, pool, vault, token_program.key)
+        } else {
+            validation::check_sol_vault(pool_state, vault)
+        }
+    }
+
+    /// Pays side A into a pool from a signing depositor and returns the amount to account
+    /// 
+    /// Token pair pools take mint A from the depositor's `source` token
+    /// account and count what the vault actually received; other pools take
+    /// SOL as `deposit_to_sol_vault` does.
+    fn deposit_side_a<'a>(
+        pool_state: &PoolState,
+        depositor: &AccountInfo<'a>,
+        source: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> Result<u64, ProgramError> {
+        if !pool_state.is_token_pair() {
+            Self::deposit_to_sol_vault(pool_state, depositor, source, vault, token_program, amount)?;
+            return Ok(amount);
+        }
+        Self::transfer_into_vault(vault, || {
+            invoke(
+                &token_interface::transfer(token_program.key, source.key, vault.key, depositor.key, &[], amount)?,
+                &[source.clone(), vault.clone(), depositor.clone()],
+            )
+        })
+    }
+
+    /// Pays side A out of a pool
+    /// 
+    /// Token pair pools pay mint A through a pool-signed token transfer;
+    /// other pools pay SOL as `pay_from_sol_vault` does.
+    fn pay_side_a<'a>(
+        pool_state: &PoolState,
+        pool_account: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        token_program: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        if !pool_state.is_token_pair() {
+            return Self::pay_from_sol_vault(pool_state, pool_account, vault, destination, token_program, amount);
+        }
+        Self::invoke_signed_by_pool(
+            &token_interface::transfer(token_program.key, vault.key, destination.key, pool_account.key, &[], amount)?,
+            &[vault.clone(), destination.clone(), pool_account.clone()],
+            pool_state,
+        )
+    }
+
+    /// Returns the wallet that a SOL payout account pays out to
+    /// 
+    /// Outside wSOL mode SOL is paid to the wallet itself; in wSOL mode the
+    /// payout account must be a wrapped SOL token account, paying its owner.
+    fn sol_recipient(pool_state: &PoolState, account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+        if !pool_state.wsol {
+            return Ok(*account.key);
+        }
+        let token_account = token_interface::unpack_token_account(&account.data.borrow())?;
+        if token_account.mint != spl_token::native_mint::id() {
+            return Err(TokenExchangeError::UnsupportedWsolMode.into());
+        }
+        Ok(token_account.owner)
+    }
+
+    /// Trading fee a swap of `amount_in` pays, as the curves charge it
+    /// 
+    /// Curves price on the input net of the fee rounded down, so the fee is
+    /// the remainder, rounded up.
+    fn trading_fee(amount_in: u64, fee_rate: Bps) -> Result<u64, ProgramError> {
+        math::mul_div(amount_in as u128, fee_rate.get() as u128, 10000, Rounding::Up)
+            .and_then(|fee| u64::try_from(fee).ok())
+            .ok_or(TokenExchangeError::MathOverflow.into())
+    }
+
+    /// The part of an LP fee earned by position liquidity, and the fee growth it adds
+    /// 
+    /// Positions earn the fee in proportion to the LP supply credited to them,
+    /// capped at the whole supply; the growth spreads that part over the
+    /// credited supply, rounded down so claims never exceed what is set aside.
+    /// The rest of the fee compounds into the reserves.
+    pub fn position_fee_share(pool_state: &PoolState, lp_fee: u64) -> Result<(u64, u128), ProgramError> {
+        if pool_state.position_lp_supply == 0 || pool_state.lp_supply == 0 {
+            return Ok((0, 0));
+        }
+        let credited_supply = pool_state.position_lp_supply.min(pool_state.lp_supply);
+        let position_fee = math::mul_div(lp_fee as u128, credited_supply as u128, pool_state.lp_supply as u128, Rounding::Down)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let fee_growth = math::mul_div(position_fee, 1 << 64, pool_state.position_lp_supply as u128, Rounding::Down)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        Ok((position_fee as u64, fee_growth))
+    }
+
+    /// Splits the trading fee on a swap's input between its recipients
+    /// 
+    /// Every share is taken on the whole input. The protocol's share comes
+    /// first and the carved fee comes out of the LP part that remains, so
+    /// neither can eat into the reserves; positions then earn their share of
+    /// what is left to LPs.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool state before the swap
+    /// * `amount_in` - Input amount the pool received, fee included
+    /// * `fee_rate` - Trading fee charged on the swap
+    /// * `carved_fee` - Part of the fee paid out of the input to a referrer or keeper
+    pub fn split_swap_fee(
+        pool_state: &PoolState,
+        amount_in: u64,
+        fee_rate: Bps,
+        carved_fee: u64,
+    ) -> Result<SwapFees, ProgramError> {
+        let protocol_fee = Self::calculate_protocol_fee(amount_in, fee_rate, Bps(pool_state.protocol_fee_share_bps))?;
+        let lp_fee = Self::trading_fee(amount_in, fee_rate)?
+            .saturating_sub(protocol_fee)
+            .checked_sub(carved_fee)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        let (position_fee, fee_growth) = Self::position_fee_share(pool_state, lp_fee)?;
+        Ok(SwapFees { protocol_fee, carved_fee, position_fee, fee_growth })
+    }
+
+    /// Writes an instruction's Borsh-encoded result to return data
+    fn set_result<T: BorshSerialize>(result: &T) -> ProgramResult {
+        set_return_data(&result.try_to_vec()?);
+        Ok(())
+    }
+
+    /// A swap's SOL leg and trading fee, both in lamports
+    /// 
+    /// Token-denominated fees are valued at the pre-swap pool price, so
+    /// `pool_state` must not yet reflect the swap.
+    fn swap_volume_in_sol(
+        pool_state: &PoolState,
+        amount_in: SwapAmount,
+        amount_out: SwapAmount,
+        fee_rate: Bps,
+    ) -> (u128, u128) {
+        let fee = amount_in.get() as u128 * fee_rate.get() as u128 / 10000;
+        if amount_in.is_sol() {
+            (amount_in.get() as u128, fee)
+        } else {
+            (
+                amount_out.get() as u128,
+                (fee * pool_state.sol_reserve as u128)
+                    .checked_div(pool_state.token_reserve as u128)
+                    .unwrap_or(0),
+            )
+        }
+    }
+
+    /// Applies a priced swap to the in-memory pool reserves
+    /// 
+    /// The input is split by `split_swap_fee`: the protocol's share of the
+    /// fee is set aside in the pool's protocol fee balance instead of the
+    /// input reserve, the carved fee already paid out leaves the pool, and
+    /// the part of the rest earned by position liquidity goes to its LP fees,
+    /// advancing fee growth. The pool's price accumulator and moving average price are
+    /// advanced first, at the pre-trade price. Pools with an oracle only swap through paths that
+    /// pass its price. The swap's price move is checked against the pool's
+    /// price impact limit and added to the volatility accumulator last, so it
+    /// only raises the fee of later swaps.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool state to update
+    /// * `amount_in` - Input amount the pool received, fee included
+    /// * `amount_out` - Output amount removed from the output reserve, on the other side
+    /// * `carved_fee` - Part of the fee paid out of the input to a referrer or keeper, in the input's unit
+    /// * `oracle_price` - The pool's oracle price from `pool_oracle_price`, for pools with an oracle
+    fn apply_swap_reserves(
+        pool_state: &mut PoolState,
+        amount_in: SwapAmount,
+        amount_out: SwapAmount,
+        carved_fee: u64,
+        oracle_price: Option<u64>,
+    ) -> ProgramResult {
+        let (sol_amount, token_amount) = amount_in.with_output(amount_out).ok_or(ProgramError::InvalidArgument)?;
+
+        // Every swap path honors the pool's launch protection, bonding curve and oracle price band
+        let clock = Clock::get()?;
+        pool_state.check_launch_protection(clock.unix_timestamp, amount_in.is_sol(), amount_out.get())?;
+        pool_state.check_bonding_curve()?;
+        pool_state.check_oracle_band(oracle_price, sol_amount.get(), token_amount.get())?;
+
+        // Accumulate the pre-trade price for TWAP and moving average readers
+        pool_state.accumulate_price(clock.slot);
+        pool_state.update_ema_price(clock.slot);
+
+        // Add to the pool's lifetime totals, valuing the fee at the pre-trade price
+        let fee_rate = Self::swap_fee_rate(pool_state)?;
+        let (sol_volume, fee_sol) = Self::swap_volume_in_sol(pool_state, amount_in, amount_out, fee_rate);
+        pool_state.cumulative_sol_volume = pool_state.cumulative_sol_volume.checked_add(sol_volume)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.cumulative_token_volume = pool_state.cumulative_token_volume.checked_add(token_amount.get() as u128)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        pool_state.cumulative_fees = pool_state.cumulative_fees.checked_add(fee_sol)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+
+        let fees = Self::split_swap_fee(pool_state, amount_in.get(), fee_rate, carved_fee)?;
+        let reserve_amount_in = fees.reserve_amount_in(amount_in.get()).ok_or(TokenExchangeError::MathOverflow)?;
+        let SwapFees { protocol_fee, position_fee, fee_growth, .. } = fees;
+        let (sol_reserve_before, token_reserve_before) = (pool_state.sol_reserve, pool_state.token_reserve);
+        let (sol_outflow, token_outflow) = match amount_out {
+            SwapAmount::Sol(amount_out) => (amount_out.get(), 0),
+            SwapAmount::Token(amount_out) => (0, amount_out.get()),
+        };
+        pool_state.record_outflow(clock.slot, sol_outflow, token_outflow)?;
+
+        if amount_in.is_sol() {
+            pool_state.sol_reserve = pool_state.sol_reserve.checked_add(reserve_amount_in)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.token_reserve = pool_state.token_reserve.checked_sub(token_outflow)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.protocol_fee_sol = pool_state.protocol_fee_sol.checked_add(protocol_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.lp_fees_sol = pool_state.lp_fees_sol.checked_add(position_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.fee_growth_sol = pool_state.fee_growth_sol.wrapping_add(fee_growth);
+        } else {
+            pool_state.token_reserve = pool_state.token_reserve.checked_add(reserve_amount_in)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.sol_reserve = pool_state.sol_reserve.checked_sub(sol_outflow)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.protocol_fee_token = pool_state.protocol_fee_token.checked_add(protocol_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.lp_fees_token = pool_state.lp_fees_token.checked_add(position_fee)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            pool_state.fee_growth_token = pool_state.fee_growth_token.wrapping_add(fee_growth);
+        }
+
+        // Bound the swap's price move, and record it for the variable fee of later swaps
+        let movement_bps = pool_state.price_movement_bps(sol_reserve_before, token_reserve_before);
+        pool_state.check_price_impact(movement_bps)?;
+        pool_state.accumulate_volatility(clock.slot, movement_bps);
+        Ok(())
+    }
+
+    /// Reads the price a swap against the pool is checked against, if it has an oracle
+    /// 
+    /// The pool's Pyth price account is the next account. Its price must be
+    /// fresh, and is returned scaled by LIMIT_PRICE_SCALE.
+    fn pool_oracle_price(
+        pool_state: &PoolState,
+        account_info_iter: &mut std::slice::Iter<AccountInfo>,
+    ) -> Result<Option<u64>, ProgramError> {
+        if pool_state.price_oracle == Pubkey::default() {
+            return Ok(None);
+        }
+        let oracle_account = next_account_info(account_info_iter)?;
+        if *oracle_account.key != pool_state.price_oracle {
+            return Err(TokenExchangeError::InvalidOracle.into());
+        }
+        let oracle_price = OraclePrice::load_pyth(oracle_account)?;
+        oracle_price.check_fresh(Clock::get()?.slot, MAX_ORACLE_STALENESS_SLOTS)?;
+        let scaled_price = oracle_price
+            .to_fixed(LIMIT_PRICE_DECIMALS)
+            .ok_or(TokenExchangeError::InvalidOracle)?;
+        Ok(Some(scaled_price))
+    }
+
+    /// Collects the optional companion accounts trailing an instruction
+    /// 
+    /// Each remaining account must be the protocol stats PDA, the pool's
+    /// observations or volume stats PDA, or a Token-2022 mint; anything else
+    /// is rejected.
+    /// The mint is checked against the pool by whoever reads it.
+    fn companion_accounts<'a, 'b>(
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+        program_id: &Pubkey,
+        pool: &Pubkey,
+    ) -> Result<CompanionAccounts<'a, 'b>, ProgramError> {
+        let mut companions = CompanionAccounts {
+            stats: None,
+            observations: None,
+            token_mint: None,
+            volume_stats: None,
+        };
+
+        for account in account_info_iter {
+            if *account.owner == TOKEN_2022_PROGRAM_ID {
+                companions.token_mint = Some(account);
+                continue;
+            }
+            if account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            if *account.key == ProtocolStats::find_address(program_id).0 {
+                companions.stats = Some(account);
+            } else if *account.key == ObservationState::find_address(pool, program_id).0 {
+                companions.observations = Some(account);
+            } else if *account.key == VolumeStats::find_address(pool, program_id).0 {
+                companions.volume_stats = Some(account);
+            } else {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        Ok(companions)
+    }
+
+    /// Closes a program-owned account, sending its lamports to `destination`
+    fn close_program_account<'a>(
+        account: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let lamports = account.lamports();
+        **account.try_borrow_mut_lamports()? = 0;
+        **destination.try_borrow_mut_lamports()? = destination
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        account.data.borrow_mut().fill(0);
+        Ok(())
+    }
+
+    /// Creates a program-owned PDA account sized and funded for rent exemption
+    /// 
+    /// `create_account` refuses an address that already holds lamports, so a
+    /// PDA someone pre-funded is instead topped up to the rent-exempt minimum,
+    /// then allocated and assigned.
+    /// 
+    /// # Arguments
+    /// * `payer` - Account funding the rent
+    /// * `new_account` - The PDA to create
+    /// * `system_program` - The system program
+    /// * `program_id` - The program that will own the account
+    /// * `space` - Account data size in bytes
+    /// * `signer_seeds` - Seeds (including bump) used to sign for the PDA
+    fn create_pda_account<'a>(
+        payer: &AccountInfo<'a>,
+        new_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        program_id: &Pubkey,
+        space: usize,
+        signer_seeds: &[&[u8]],
+    ) -> ProgramResult {
+        validation::check_system_program(system_program)?;
+        let lamports = Rent::get()?.minimum_balance(space);
+
+        if new_account.lamports() == 0 {
+            return invoke_signed(
+                &system_instruction::create_account(
+                    payer.key,
+                    new_account.key,
+                    lamports,
+                    space as u64,
+                    program_id,
+                ),
+                &[payer.clone(), new_account.clone(), system_program.clone()],
+                &[signer_seeds],
+            );
+        }
+
+        let required_lamports = lamports.saturating_sub(new_account.lamports());
+        if required_lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, new_account.key, required_lamports),
+                &[payer.clone(), new_account.clone(), system_program.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(new_account.key, space as u64),
+            &[new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(new_account.key, program_id),
+            &[new_account.clone(), system_program.clone()],
+            &[signer_seeds],
+        )
+    }
+
+    /// Verifies a pool state account that already belongs to the program can be initialized
+    /// 
+    /// It must be exactly `PoolState::LEN` bytes, rent-exempt, and still
+    /// zeroed: data carrying the pool discriminator is an initialized pool,
+    /// which initialization would otherwise overwrite.
+    fn check_existing_pool_account(pool_account: &AccountInfo) -> ProgramResult {
+        if pool_account.data_len() != PoolState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !Rent::get()?.is_exempt(pool_account.lamports(), pool_account.data_len()) {
+            return Err(ProgramError::AccountNotRentExempt);
+        }
+        let discriminator = &pool_account.data.borrow()[..PoolState::DISCRIMINATOR.len()];
+        if discriminator == PoolState::DISCRIMINATOR {
+            return Err(TokenExchangeError::PoolAlreadyInitialized.into());
+        }
+        if discriminator != [0; 8] {
+            return Err(TokenExchangeError::InvalidAccountDiscriminator.into());
+        }
+        Ok(())
+    }
+
+    /// Largest deposit within the given amounts that matches the pool's ratio
+    /// 
+    /// Empty pools accept both amounts as-is; otherwise one side is used in
+    /// full and the other is scaled down to the current reserve ratio.
+    fn fit_to_pool_ratio(
+        pool_state: &PoolState,
+        sol_amount: Lamports,
+        token_amount: TokenAmount,
+    ) -> Result<(Lamports, TokenAmount), ProgramError> {
+        curve::fit_to_ratio(
+            Lamports(pool_state.sol_reserve),
+            TokenAmount(pool_state.token_reserve),
+            sol_amount,
+            token_amount,
+        )
+    }
+
+    /// Calculates a swap's output on the pool's curve
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool being swapped against
+    /// * `amount_in` - Input amount, SOL (mint A for token pair pools) or token
+    pub fn calculate_swap_output(pool_state: &PoolState, amount_in: SwapAmount) -> Result<SwapAmount, ProgramError> {
+        let fee_rate = Self::swap_fee_rate(pool_state)?;
+        let (sol_reserve, token_reserve) = (Lamports(pool_state.sol_reserve), TokenAmount(pool_state.token_reserve));
+        curve::for_pool(pool_state).swap_out(amount_in, sol_reserve, token_reserve, fee_rate)
+    }
+
+    /// Calculates the input a swap on the pool's curve needs to yield an exact output
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool being swapped against
+    /// * `amount_out` - Desired output amount, SOL (mint A for token pair pools) or token
+    pub fn calculate_swap_input(pool_state: &PoolState, amount_out: SwapAmount) -> Result<SwapAmount, ProgramError> {
+        let fee_rate = Self::swap_fee_rate(pool_state)?;
+        let (sol_reserve, token_reserve) = (Lamports(pool_state.sol_reserve), TokenAmount(pool_state.token_reserve));
+        curve::for_pool(pool_state).swap_in(amount_out, sol_reserve, token_reserve, fee_rate)
+    }
+
+    /// The trading fee a swap pays now, in basis points
+    /// 
+    /// A pool with a launch fee or a variable fee reads the clock for its
+    /// decayed launch rate plus the variable fee of its recent volatility;
+    /// others charge their base `fee_rate` without touching the clock.
+    pub fn swap_fee_rate(pool_state: &PoolState) -> Result<Bps, ProgramError> {
+        if pool_state.launch_fee_bps == 0 && pool_state.variable_fee_control == 0 {
+            return Ok(Bps(pool_state.fee_rate));
+        }
+        let clock = Clock::get()?;
+        let fee_rate = pool_state.fee_rate_at(clock.unix_timestamp).get() + pool_state.variable_fee_at(clock.slot);
+        Ok(Bps(fee_rate))
+    }
+
+    /// Calculates the protocol's share of the fee charged on a swap input
+    /// 
+    /// Rounds down, so the protocol never takes more than its share and the
+    /// remainder of the fee stays with LPs.
+    /// 
+    /// # Arguments
+    /// * `amount_in` - Input token amount
+    /// * `fee_rate` - Fee rate in basis points
+    /// * `protocol_fee_share` - Protocol's share of the fee in basis points
+    pub fn calculate_protocol_fee(
+        amount_in: u64,
+        fee_rate: Bps,
+        protocol_fee_share: Bps,
+    ) -> Result<u64, ProgramError> {
+        math::mul_div(
+            amount_in as u128 * fee_rate.get() as u128,
+            protocol_fee_share.get() as u128,
+            10000 * 10000,
+            Rounding::Down,
+        )
+        .and_then(|protocol_fee| u64::try_from(protocol_fee).ok())
+        .ok_or(TokenExchangeError::MathOverflow.into())
+    }
+
+    /// Trims desired deposit amounts to the pool's reserve ratio
+    /// 
+    /// Keeps one side at its desired amount and scales the other down to the
+    /// ratio, rounding down, as Uniswap's `addLiquidity` does. An empty pool
+    /// takes the desired amounts as given.
+    /// 
+    /// # Arguments
+    /// * `pool_state` - Pool receiving the deposit
+    /// * `sol_desired` - Most SOL the provider will deposit
+    /// * `token_desired` - Most tokens the provider will deposit
+    /// * `minimum_sol` - Least SOL the provider will deposit
+    /// * `minimum_token` - Least tokens the provider will deposit
+    pub fn calculate_optimal_deposit(
+        pool_state: &PoolState,
+        sol_desired: Lamports,
+        token_desired: TokenAmount,
+        minimum_sol: Lamports,
+        minimum_token: TokenAmount,
+    ) -> Result<(Lamports, TokenAmount), ProgramError> {
+        let (sol_amount, token_amount) = Self::fit_to_pool_ratio(pool_state, sol_desired, token_desired)?;
+        if sol_amount < minimum_sol || token_amount < minimum_token {
+            return Err(TokenExchangeError::SlippageExceeded.into());
+        }
+        Ok((sol_amount, token_amount))
     }
 } 
\ No newline at end of file
@@ -0,0 +1,201 @@
+// Quote Helpers
+// This module prices pools off-chain for market makers, risk systems and frontends
+
+use crate::{
+    amount::{Bps, Lamports, LpAmount, SwapAmount, TokenAmount}, // Typed amounts
+    curve::{self, ConstantProduct, SwapCurve},                 // On-chain swap pricing and deposit fitting
+    math::{self, Rounding, Q64},                               // Rounded division and Q64.64 prices
+    state::LIMIT_PRICE_SCALE,                                  // Fixed-point price scale
+    token_interface::InterestBearingConfig,                    // Interest-bearing UI scaling
+};
+
+/// Price moves reported by `market_depth`
+pub const DEPTH_LEVELS_BPS: [Bps; 2] = [Bps(100), Bps(200)];
+
+/// Fixed-point scale used for square roots of price ratios (8 decimals)
+const SQRT_SCALE: u128 = 100_000_000;
+
+/// Input sizes needed to move a pool's price by each of `DEPTH_LEVELS_BPS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketDepth {
+    pub marginal_price: u64,                              // SOL per token at the margin, scaled by LIMIT_PRICE_SCALE
+    pub sol_in_to_raise: [Lamports; DEPTH_LEVELS_BPS.len()],      // SOL to sell into the pool to raise the price by each level
+    pub token_in_to_lower: [TokenAmount; DEPTH_LEVELS_BPS.len()], // Tokens to sell into the pool to lower the price by each level
+}
+
+/// Expected result of a swap against a constant product pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_out: SwapAmount,                           // Output the program pays, before any platform fee
+    pub fee_paid: SwapAmount,                             // Part of the input kept as the pool fee
+    pub price_impact_bps: u64,                            // Shortfall of the execution price against the spot price, fee excluded
+}
+
+/// Expected result of a deposit matched to a pool's reserve ratio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub sol_amount: Lamports,                             // SOL the deposit takes
+    pub token_amount: TokenAmount,                        // Tokens the deposit takes
+    pub lp_tokens: LpAmount,                              // LP tokens minted
+    pub pool_share_bps: u64,                              // Share of the LP supply held by the minted tokens afterwards
+}
+
+impl SwapQuote {
+    /// Least output to accept when tolerating `slippage` below the quote
+    /// 
+    /// Pass this as a swap's `minimum_amount_out`.
+    pub fn minimum_amount_out(&self, slippage: Bps) -> SwapAmount {
+        let remaining = slippage.complement().unwrap_or(0);
+        self.amount_out.same_side((self.amount_out.get() as u128 * remaining as u128 / 10000) as u64)
+    }
+}
+
+/// Quotes a swap of `amount_in` against a constant product pool
+/// 
+/// The output is computed exactly as the program prices it. On this curve a
+/// trade's price impact is its net input's share of the new input reserve,
+/// `dx' / (x + dx')`, reported rounded up. Returns `None` when the swap
+/// cannot execute. Pools on other curves are priced by
+/// `Processor::calculate_swap_output`.
+/// 
+/// # Arguments
+/// * `sol_reserve` - Current SOL reserve
+/// * `token_reserve` - Current token reserve
+/// * `amount_in` - Input amount, including the fee
+/// * `fee_rate` - Pool fee
+pub fn quote_swap(
+    sol_reserve: Lamports,
+    token_reserve: TokenAmount,
+    amount_in: SwapAmount,
+    fee_rate: Bps,
+) -> Option<SwapQuote> {
+    let amount_out = ConstantProduct.swap_out(amount_in, sol_reserve, token_reserve, fee_rate).ok()?;
+    let (reserve_in, _) = amount_in.reserves(sol_reserve, token_reserve);
+    let net_input = math::mul_div(amount_in.get() as u128, fee_rate.complement()? as u128, 10000, Rounding::Down)?;
+    let price_impact_bps = math::mul_div(net_input, 10000, reserve_in as u128 + net_input, Rounding::Up)?;
+    Some(SwapQuote {
+        amount_out,
+        fee_paid: amount_in.same_side(amount_in.get() - net_input as u64),
+        price_impact_bps: price_impact_bps as u64,
+    })
+}
+
+/// Quotes a deposit of at most the desired amounts, matched to the pool ratio
+/// 
+/// Mirrors `AddLiquidity` with `match_pool_ratio` set: one side is kept and
+/// the other scaled down to the reserve ratio, then LP tokens are minted pro
+/// rata, all rounded down. An empty pool takes both amounts and mints their
+/// geometric mean. Returns `None` on overflow.
+/// 
+/// # Arguments
+/// * `sol_reserve` - Current SOL reserve
+/// * `token_reserve` - Current token reserve
+/// * `lp_supply` - Current LP token supply
+/// * `sol_desired` - Most SOL to deposit
+/// * `token_desired` - Most tokens to deposit
+pub fn quote_add_liquidity(
+    sol_reserve: Lamports,
+    token_reserve: TokenAmount,
+    lp_supply: LpAmount,
+    sol_desired: Lamports,
+    token_desired: TokenAmount,
+) -> Option<LiquidityQuote> {
+    let (sol_amount, token_amount) = curve::fit_to_ratio(sol_reserve, token_reserve, sol_desired, token_desired).ok()?;
+    let (sol_reserve, token_reserve, supply) =
+        (sol_reserve.get() as u128, token_reserve.get() as u128, lp_supply.get() as u128);
+    let (sol_amount, token_amount) = (sol_amount.get() as u128, token_amount.get() as u128);
+    let lp_tokens = if sol_reserve == 0 || token_reserve == 0 {
+        math::geometric_mean(sol_amount as u64, token_amount as u64) as u128
+    } else {
+        let sol_share = math::mul_div(sol_amount, supply, sol_reserve, Rounding::Down)?;
+        let token_share = math::mul_div(token_amount, supply, token_reserve, Rounding::Down)?;
+        sol_share.min(token_share)
+    };
+    let new_supply = if sol_reserve == 0 { lp_tokens } else { supply + lp_tokens };
+    let pool_share_bps = math::mul_div(lp_tokens, 10000, new_supply, Rounding::Down).unwrap_or(0);
+    Some(LiquidityQuote {
+        sol_amount: Lamports(u64::try_from(sol_amount).ok()?),
+        token_amount: TokenAmount(u64::try_from(token_amount).ok()?),
+        lp_tokens: LpAmount(u64::try_from(lp_tokens).ok()?),
+        pool_share_bps: pool_share_bps as u64,
+    })
+}
+
+/// Marginal price of the pool's token in SOL on `curve`, scaled by `LIMIT_PRICE_SCALE`
+/// 
+/// This is the price an infinitesimal trade executes at before fees, the
+/// curve's `spot_price`. Returns `None` for an empty pool.
+pub fn marginal_price(curve: &dyn SwapCurve, sol_reserve: Lamports, token_reserve: TokenAmount) -> Option<u64> {
+    let price = curve.spot_price(sol_reserve, token_reserve)?;
+    u64::try_from(math::mul_div_wide(price, LIMIT_PRICE_SCALE as u128, Q64, Rounding::Down)?).ok()
+}
+
+/// Marginal price of one UI unit of an interest-bearing token in SOL, scaled
+/// by `LIMIT_PRICE_SCALE`
+/// 
+/// Values the token reserve at its UI amount at `unix_timestamp`, as the
+/// pool's price observations do. Returns `None` for an empty pool or when
+/// the scale overflows.
+pub fn ui_marginal_price(
+    curve: &dyn SwapCurve,
+    sol_reserve: Lamports,
+    token_reserve: TokenAmount,
+    config: &InterestBearingConfig,
+    unix_timestamp: i64,
+) -> Option<u64> {
+    marginal_price(curve, sol_reserve, TokenAmount(config.ui_amount(token_reserve.get(), unix_timestamp)?))
+}
+
+/// SOL a swap must put in to raise the token's price by `move_bps`
+/// 
+/// On the constant product curve the price grows with the square of the SOL
+/// reserve, so the reserve must grow by `sqrt(1 + move)`. The result includes
+/// the pool fee, which does not reach the reserves.
+/// 
+/// # Arguments
+/// * `sol_reserve` - Current SOL reserve
+/// * `fee_rate` - Pool fee
+/// * `move_bps` - Price increase
+pub fn sol_input_to_raise_price(sol_reserve: Lamports, fee_rate: Bps, move_bps: Bps) -> Option<Lamports> {
+    let growth = ((10000 + move_bps.get() as u128) * SQRT_SCALE * SQRT_SCALE / 10000).isqrt();
+    let net_input = (sol_reserve.get() as u128 * (growth - SQRT_SCALE)).div_ceil(SQRT_SCALE);
+    gross_input(net_input, fee_rate).map(Lamports)
+}
+
+/// Tokens a swap must put in to lower the token's price by `move_bps`
+/// 
+/// The price falls as the token reserve grows squared, so the reserve must
+/// grow by `1 / sqrt(1 - move)`. The result includes the pool fee.
+/// 
+/// # Arguments
+/// * `token_reserve` - Current token reserve
+/// * `fee_rate` - Pool fee
+/// * `move_bps` - Price decrease (below 100%)
+pub fn token_input_to_lower_price(token_reserve: TokenAmount, fee_rate: Bps, move_bps: Bps) -> Option<TokenAmount> {
+    let remaining = move_bps.complement().filter(|value| *value > 0)? as u128;
+    let shrink = (remaining * SQRT_SCALE * SQRT_SCALE / 10000).isqrt();
+    let new_reserve = (token_reserve.get() as u128 * SQRT_SCALE).div_ceil(shrink);
+    gross_input(new_reserve - token_reserve.get() as u128, fee_rate).map(TokenAmount)
+}
+
+/// Marginal price and the inputs needed to move it by each of `DEPTH_LEVELS_BPS`
+/// 
+/// Returns `None` for an empty pool or an invalid fee.
+pub fn market_depth(sol_reserve: Lamports, token_reserve: TokenAmount, fee_rate: Bps) -> Option<MarketDepth> {
+    let mut depth = MarketDepth {
+        marginal_price: marginal_price(&ConstantProduct, sol_reserve, token_reserve)?,
+        sol_in_to_raise: [Lamports::ZERO; DEPTH_LEVELS_BPS.len()],
+        token_in_to_lower: [TokenAmount::ZERO; DEPTH_LEVELS_BPS.len()],
+    };
+    for (level, move_bps) in DEPTH_LEVELS_BPS.iter().enumerate() {
+        depth.sol_in_to_raise[level] = sol_input_to_raise_price(sol_reserve, fee_rate, *move_bps)?;
+        depth.token_in_to_lower[level] = token_input_to_lower_price(token_reserve, fee_rate, *move_bps)?;
+    }
+    Some(depth)
+}
+
+/// Grosses a net reserve increase up by the pool fee, rounding up
+fn gross_input(net_input: u128, fee_rate: Bps) -> Option<u64> {
+    let fee_factor = fee_rate.complement().filter(|value| *value > 0)? as u128;
+    u64::try_from((net_input * 10000).div_ceil(fee_factor)).ok()
+}
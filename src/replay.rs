@@ -0,0 +1,416 @@
+// Transaction Replay
+// This module re-executes a fetched transaction's instructions off-chain for incident forensics
+
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    clock::Clock,                                              // Slot and timestamp seen by the handlers
+    entrypoint::{ProgramResult, SUCCESS},                      // Result types
+    instruction::{AccountMeta, Instruction},                   // Cross-program invocations
+    message::Message,                                          // Fetched transaction message
+    program_error::ProgramError,                               // Error handling
+    program_pack::Pack,                                        // Token state packing
+    program_stubs::{self, SyscallStubs},                       // Host-side syscall emulation
+    pubkey::Pubkey,                                            // Public key type
+    rent::Rent,                                                // Rent sysvar
+    system_program,                                            // System program ID
+};
+use spl_token::{
+    instruction::TokenInstruction,                             // Token instruction decoding
+    state::{Account as TokenAccount, Mint},                    // Token account layouts
+};
+use std::sync::{Arc, Mutex};                                   // Shared recorder state
+
+use crate::{
+    instruction::TokenExchangeInstruction,                     // Instruction decoding
+    processor::Processor,                                      // Handlers under replay
+    token_interface::TOKEN_2022_PROGRAM_ID,                    // Token-2022 program ID
+};
+
+/// System program `Transfer` discriminant
+const SYSTEM_TRANSFER_TAG: u32 = 2;
+
+/// An account's state before or after the transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub key: Pubkey,            // Account address
+    pub owner: Pubkey,          // Owning program
+    pub lamports: u64,          // Lamport balance
+    pub data: Vec<u8>,          // Account data
+    pub executable: bool,       // Whether the account is a program
+}
+
+impl AccountSnapshot {
+    /// An account that does not exist yet
+    pub fn empty(key: Pubkey) -> Self {
+        Self {
+            key,
+            owner: system_program::id(),
+            lamports: 0,
+            data: Vec::new(),
+            executable: false,
+        }
+    }
+}
+
+/// A cross-program invocation made by a replayed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpiCall {
+    pub instruction: Instruction, // The invoked instruction
+    pub emulated: bool,           // Whether its effects were applied to the replayed accounts
+}
+
+/// What happened to one instruction of the transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The instruction targets another program and was not replayed
+    Skipped,
+    /// A system or token instruction whose effects were applied to the replayed accounts
+    Emulated,
+    /// The instruction data does not decode as a `TokenExchangeInstruction`
+    Undecodable(ProgramError),
+    /// The instruction ran; an error means the transaction would have failed
+    Executed(Result<(), ProgramError>),
+}
+
+/// The replay of one instruction
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    pub index: usize,                                   // Position in the transaction
+    pub instruction: Option<TokenExchangeInstruction>,  // Decoded instruction, if it targets this program
+    pub outcome: StepOutcome,                           // Result of the replay
+    pub cpis: Vec<CpiCall>,                             // Invocations made by the handler
+    pub logs: Vec<String>,                              // Messages logged by the handler
+}
+
+/// A field of a program account that differs from the observed post-state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The account was observed after the transaction but never replayed
+    Missing { key: Pubkey },
+    /// Lamport balances differ
+    Lamports { key: Pubkey, observed: u64, replayed: u64 },
+    /// Owning programs differ
+    Owner { key: Pubkey, observed: Pubkey, replayed: Pubkey },
+    /// Data differs, starting at `offset` (lengths are reported when they differ)
+    Data { key: Pubkey, offset: usize, observed_len: usize, replayed_len: usize },
+}
+
+/// The result of replaying a transaction
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub steps: Vec<ReplayStep>,             // One entry per transaction instruction
+    pub divergences: Vec<Divergence>,       // Program accounts whose replayed state differs
+}
+
+impl ReplayReport {
+    /// Whether the replay reproduced the observed program state without gaps
+    /// 
+    /// Invocations that could not be emulated (such as account creation) make
+    /// the replay incomplete even when no divergence is reported.
+    pub fn is_faithful(&self) -> bool {
+        self.divergences.is_empty()
+            && self.steps.iter().all(|step| {
+                !matches!(step.outcome, StepOutcome::Undecodable(_))
+                    && step.cpis.iter().all(|cpi| cpi.emulated)
+            })
+    }
+}
+
+/// Syscall stubs that serve the transaction's clock and emulate the
+/// system and token invocations the processor makes
+struct ReplayStubs {
+    clock: Clock,
+    cpis: Arc<Mutex<Vec<CpiCall>>>,
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl SyscallStubs for ReplayStubs {
+    fn sol_log(&self, message: &str) {
+        self.logs.lock().unwrap().push(message.to_string());
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let emulated = emulate_cpi(instruction, account_infos)?;
+        self.cpis.lock().unwrap().push(CpiCall {
+            instruction: instruction.clone(),
+            emulated,
+        });
+        Ok(())
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = self.clock.clone() };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+}
+
+/// Replays a fetched transaction against reconstructed account state
+/// 
+/// Instructions for this program run through `Processor::process` with the
+/// transaction's clock. System transfers and token transfers, mints, burns
+/// and closes, whether top-level or invoked, are applied to the replayed
+/// accounts; other programs' instructions are skipped. If any
+/// instruction fails, the accounts roll back as they would on-chain. The
+/// replayed state of every account `program_id` owns after the transaction
+/// is then compared with `post_accounts`.
+/// 
+/// Replays install process-wide syscall stubs, so they must not run
+/// concurrently with each other or with other users of the stubs.
+/// 
+/// # Arguments
+/// * `program_id` - This program's deployed address
+/// * `message` - The transaction message, with address lookup tables resolved
+/// * `pre_accounts` - Account state before the transaction; absent accounts are treated as nonexistent
+/// * `post_accounts` - Account state observed after the transaction
+/// * `clock` - The clock at the transaction's slot
+pub fn replay_transaction(
+    program_id: &Pubkey,
+    message: &Message,
+    pre_accounts: &[AccountSnapshot],
+    post_accounts: &[AccountSnapshot],
+    clock: Clock,
+) -> ReplayReport {
+    let pre_state: Vec<AccountSnapshot> = message
+        .account_keys
+        .iter()
+        .map(|key| {
+            pre_accounts
+                .iter()
+                .find(|account| account.key == *key)
+                .cloned()
+                .unwrap_or_else(|| AccountSnapshot::empty(*key))
+        })
+        .collect();
+    let mut state = pre_state.clone();
+
+    let cpis = Arc::new(Mutex::new(Vec::new()));
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let previous_stubs = program_stubs::set_syscall_stubs(Box::new(ReplayStubs {
+        clock,
+        cpis: cpis.clone(),
+        logs: logs.clone(),
+    }));
+
+    let mut report = ReplayReport::default();
+    let mut failed = false;
+    {
+        let account_infos: Vec<AccountInfo> = state
+            .iter_mut()
+            .enumerate()
+            .map(|(index, account)| {
+                AccountInfo::new(
+                    &account.key,
+                    message.is_signer(index),
+                    message.is_writable(index),
+                    &mut account.lamports,
+                    &mut account.data,
+                    &account.owner,
+                    account.executable,
+                    0,
+                )
+            })
+            .collect();
+
+        for (index, compiled) in message.instructions.iter().enumerate() {
+            let mut step = ReplayStep {
+                index,
+                instruction: None,
+                outcome: StepOutcome::Skipped,
+                cpis: Vec::new(),
+                logs: Vec::new(),
+            };
+            let invoked_program = message.account_keys[compiled.program_id_index as usize];
+            if invoked_program != *program_id {
+                let instruction = Instruction::new_with_bytes(
+                    invoked_program,
+                    &compiled.data,
+                    compiled
+                        .accounts
+                        .iter()
+                        .map(|index| AccountMeta {
+                            pubkey: message.account_keys[*index as usize],
+                            is_signer: message.is_signer(*index as usize),
+                            is_writable: message.is_writable(*index as usize),
+                        })
+                        .collect(),
+                );
+                match emulate_cpi(&instruction, &account_infos) {
+                    Ok(true) => step.outcome = StepOutcome::Emulated,
+                    Ok(false) => {}
+                    Err(error) => {
+                        step.outcome = StepOutcome::Executed(Err(error));
+                        failed = true;
+                    }
+                }
+                report.steps.push(step);
+                if failed {
+                    break;
+                }
+                continue;
+            }
+
+            match TokenExchangeInstruction::unpack(&compiled.data) {
+                Ok(instruction) => step.instruction = Some(instruction),
+                Err(error) => {
+                    step.outcome = StepOutcome::Undecodable(error);
+                    report.steps.push(step);
+                    continue;
+                }
+            }
+
+            let accounts: Vec<AccountInfo> = compiled
+                .accounts
+                .iter()
+                .map(|index| account_infos[*index as usize].clone())
+                .collect();
+            let result = Processor::process(program_id, &accounts, &compiled.data);
+            failed |= result.is_err();
+            step.outcome = StepOutcome::Executed(result);
+            step.cpis = std::mem::take(&mut *cpis.lock().unwrap());
+            step.logs = std::mem::take(&mut *logs.lock().unwrap());
+            report.steps.push(step);
+            if failed {
+                break;
+            }
+        }
+    }
+    program_stubs::set_syscall_stubs(previous_stubs);
+
+    // A failed transaction persists nothing
+    if failed {
+        state = pre_state;
+    }
+
+    for observed in post_accounts.iter().filter(|account| account.owner == *program_id) {
+        let Some(replayed) = state.iter().find(|account| account.key == observed.key) else {
+            report.divergences.push(Divergence::Missing { key: observed.key });
+            continue;
+        };
+        if replayed.lamports != observed.lamports {
+            report.divergences.push(Divergence::Lamports {
+                key: observed.key,
+                observed: observed.lamports,
+                replayed: replayed.lamports,
+            });
+        }
+        if replayed.owner != observed.owner {
+            report.divergences.push(Divergence::Owner {
+                key: observed.key,
+                observed: observed.owner,
+                replayed: replayed.owner,
+            });
+        }
+        if replayed.data != observed.data {
+            let offset = replayed
+                .data
+                .iter()
+                .zip(&observed.data)
+                .position(|(replayed, observed)| replayed != observed)
+                .unwrap_or_else(|| replayed.data.len().min(observed.data.len()));
+            report.divergences.push(Divergence::Data {
+                key: observed.key,
+                offset,
+                observed_len: observed.data.len(),
+                replayed_len: replayed.data.len(),
+            });
+        }
+    }
+    report
+}
+
+/// Applies a system or token invocation to the replayed accounts
+/// 
+/// Returns whether the invocation was emulated; anything else (such as
+/// account creation, which cannot resize the replayed buffers) is left as a
+/// no-op for the report to flag.
+fn emulate_cpi(instruction: &Instruction, account_infos: &[AccountInfo]) -> Result<bool, ProgramError> {
+    let account = |position: usize| -> Result<&AccountInfo, ProgramError> {
+        let key = instruction
+            .accounts
+            .get(position)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?
+            .pubkey;
+        account_infos
+            .iter()
+            .find(|info| *info.key == key)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+
+    if instruction.program_id == system_program::id() {
+        if instruction.data.len() != 12
+            || u32::from_le_bytes(instruction.data[..4].try_into().unwrap()) != SYSTEM_TRANSFER_TAG
+        {
+            return Ok(false);
+        }
+        let lamports = u64::from_le_bytes(instruction.data[4..].try_into().unwrap());
+        move_lamports(account(0)?, account(1)?, lamports)?;
+        return Ok(true);
+    }
+
+    if instruction.program_id != spl_token::id() && instruction.program_id != TOKEN_2022_PROGRAM_ID {
+        return Ok(false);
+    }
+    match TokenInstruction::unpack(&instruction.data)? {
+        TokenInstruction::Transfer { amount } => {
+            update_token_amount(account(0)?, |balance| balance.checked_sub(amount))?;
+            update_token_amount(account(1)?, |balance| balance.checked_add(amount))?;
+        }
+        TokenInstruction::MintTo { amount } => {
+            update_mint_supply(account(0)?, |supply| supply.checked_add(amount))?;
+            update_token_amount(account(1)?, |balance| balance.checked_add(amount))?;
+        }
+        TokenInstruction::Burn { amount } => {
+            update_token_amount(account(0)?, |balance| balance.checked_sub(amount))?;
+            update_mint_supply(account(1)?, |supply| supply.checked_sub(amount))?;
+        }
+        TokenInstruction::CloseAccount => {
+            let closed = account(0)?;
+            move_lamports(closed, account(1)?, closed.lamports())?;
+            closed.data.borrow_mut().fill(0);
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Moves lamports between two replayed accounts
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if from.key == to.key {
+        return Ok(());
+    }
+    let debited = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+    let credited = to.lamports().checked_add(lamports).ok_or(ProgramError::InvalidArgument)?;
+    **from.lamports.borrow_mut() = debited;
+    **to.lamports.borrow_mut() = credited;
+    Ok(())
+}
+
+/// Rewrites the amount of a token account's base layout
+fn update_token_amount(account: &AccountInfo, update: impl Fn(u64) -> Option<u64>) -> ProgramResult {
+    let mut data = account.data.borrow_mut();
+    let base = data.get_mut(..TokenAccount::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    let mut token_account = TokenAccount::unpack_from_slice(base)?;
+    token_account.amount = update(token_account.amount).ok_or(ProgramError::InsufficientFunds)?;
+    token_account.pack_into_slice(base);
+    Ok(())
+}
+
+/// Rewrites the supply of a mint's base layout
+fn update_mint_supply(mint: &AccountInfo, update: impl Fn(u64) -> Option<u64>) -> ProgramResult {
+    let mut data = mint.data.borrow_mut();
+    let base = data.get_mut(..Mint::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    let mut state = Mint::unpack_from_slice(base)?;
+    state.supply = update(state.supply).ok_or(ProgramError::InsufficientFunds)?;
+    state.pack_into_slice(base);
+    Ok(())
+}
@@ -0,0 +1,34 @@
+// Sandwich Protection
+// This module checks, by instruction introspection, that no other instruction in a transaction touches a pool
+
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked}, // Instruction introspection
+};
+
+use crate::error::TokenExchangeError;                          // Custom errors
+
+/// Checks that the current instruction is the only one in the transaction referencing `pool`
+/// 
+/// A same-transaction sandwich needs a trade against the pool before and
+/// after the victim's, so rejecting any other top-level instruction that
+/// lists the pool account, from this or any other program, rules it out.
+/// Instructions are matched by their accounts, which also catches wrapper
+/// programs that reach the pool by CPI.
+/// 
+/// # Arguments
+/// * `instructions_sysvar` - The instructions sysvar account
+/// * `pool` - The pool being traded against
+pub fn verify_pool_isolated(instructions_sysvar: &AccountInfo, pool: &Pubkey) -> Result<(), ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    let mut index = 0;
+    while let Ok(instruction) = load_instruction_at_checked(index, instructions_sysvar) {
+        if index != current_index && instruction.accounts.iter().any(|meta| meta.pubkey == *pool) {
+            return Err(TokenExchangeError::PoolSandwiched.into());
+        }
+        index += 1;
+    }
+    Ok(())
+}
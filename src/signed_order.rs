@@ -0,0 +1,138 @@
+// Pre-Signed Orders
+// This module verifies off-chain signed swap orders submitted by relayers
+
+use borsh::{BorshDeserialize, BorshSerialize};                 // For order serialization
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    ed25519_program,                                           // Native signature verification program
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked}, // Instruction introspection
+};
+
+use crate::error::TokenExchangeError;                          // Custom errors
+
+/// Seed used to derive an owner's order authority PDA
+/// 
+/// Owners approve this PDA as delegate on their token accounts, and fund it
+/// with lamports, so relayers can execute their signed orders.
+pub const ORDER_AUTHORITY_SEED: &[u8] = b"order_authority";
+
+/// Prefix of every signed order message, separating it from other signed payloads
+const SIGNED_ORDER_DOMAIN: &[u8] = b"simple-token-exchange:signed-order";
+
+/// Size of the ed25519 instruction header (signature count and padding)
+const ED25519_HEADER_LEN: usize = 2;
+
+/// Size of one ed25519 signature offsets entry
+const ED25519_OFFSETS_LEN: usize = 14;
+
+/// Size of an ed25519 public key
+const ED25519_PUBKEY_LEN: usize = 32;
+
+/// A swap order signed off-chain by its owner
+/// 
+/// The owner signs `message(program_id)`; any relayer may then submit the
+/// order alongside an ed25519 program instruction carrying that signature.
+/// A non-zero `relayer_fee` is carved out of the output, so owners holding
+/// no SOL can still trade while the relayer covers transaction costs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedOrder {
+    pub owner: Pubkey,            // The order owner, whose key signed the message
+    pub pool: Pubkey,             // The pool the order executes against
+    pub amount_in: u64,           // Input amount to swap
+    pub minimum_amount_out: u64,  // Minimum output the owner accepts
+    pub is_sol_input: bool,       // Direction (true = SOL→Token, false = Token→SOL)
+    pub expiry_slot: u64,         // Last slot the order may execute in
+    pub nonce: u64,               // Must equal the owner's next signed-order nonce in UserStats
+    pub relayer_fee: u64,         // Output paid to the relayer as reimbursement (0 = none)
+}
+
+impl SignedOrder {
+    /// Serialized size of a signed order
+    /// 
+    /// Breakdown:
+    /// - owner, pool (Pubkey): 64 bytes
+    /// - amount_in, minimum_amount_out (u64): 16 bytes
+    /// - is_sol_input (bool): 1 byte
+    /// - expiry_slot, nonce, relayer_fee (u64): 24 bytes
+    pub const LEN: usize = 64 + 16 + 1 + 24;
+
+    /// Bytes the owner signs for this order on the given program
+    pub fn message(&self, program_id: &Pubkey) -> Result<Vec<u8>, ProgramError> {
+        let mut message = Vec::with_capacity(SIGNED_ORDER_DOMAIN.len() + 32 + Self::LEN);
+        message.extend_from_slice(SIGNED_ORDER_DOMAIN);
+        message.extend_from_slice(program_id.as_ref());
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+
+    /// Whether the order has passed its expiry slot
+    pub fn is_expired(&self, current_slot: u64) -> bool {
+        current_slot > self.expiry_slot
+    }
+
+    /// Derives the order authority PDA for an owner
+    pub fn find_authority(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[ORDER_AUTHORITY_SEED, owner.as_ref()], program_id)
+    }
+}
+
+/// Checks that the instruction preceding the current one verifies `message` signed by `signer`
+/// 
+/// The preceding instruction must be a single-signature ed25519 program
+/// instruction whose key, signature and message all live in its own data.
+/// 
+/// # Arguments
+/// * `instructions_sysvar` - The instructions sysvar account
+/// * `signer` - The expected signing key
+/// * `message` - The expected signed message
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<(), ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    if current_index == 0 {
+        return Err(TokenExchangeError::InvalidSignature.into());
+    }
+    let instruction = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    if instruction.program_id != ed25519_program::id() {
+        return Err(TokenExchangeError::InvalidSignature.into());
+    }
+
+    let data = &instruction.data;
+    if data.len() < ED25519_HEADER_LEN + ED25519_OFFSETS_LEN || data[0] != 1 {
+        return Err(TokenExchangeError::InvalidSignature.into());
+    }
+
+    // Offsets entry: signature, key and message, each with an instruction index
+    let offsets = &data[ED25519_HEADER_LEN..ED25519_HEADER_LEN + ED25519_OFFSETS_LEN];
+    let signature_instruction_index = read_u16(offsets, 2);
+    let pubkey_offset = read_u16(offsets, 4) as usize;
+    let pubkey_instruction_index = read_u16(offsets, 6);
+    let message_offset = read_u16(offsets, 8) as usize;
+    let message_size = read_u16(offsets, 10) as usize;
+    let message_instruction_index = read_u16(offsets, 12);
+
+    // Data referenced from other instructions could be swapped out by the submitter
+    if signature_instruction_index != u16::MAX
+        || pubkey_instruction_index != u16::MAX
+        || message_instruction_index != u16::MAX
+    {
+        return Err(TokenExchangeError::InvalidSignature.into());
+    }
+
+    let signed_pubkey = data.get(pubkey_offset..pubkey_offset + ED25519_PUBKEY_LEN);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    if signed_pubkey != Some(signer.as_ref()) || signed_message != Some(message) {
+        return Err(TokenExchangeError::InvalidSignature.into());
+    }
+
+    Ok(())
+}
+
+/// Reads a little-endian u16 at `offset`
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
@@ -2,7 +2,16 @@
 // This module defines the state structures used by the token exchange program
 
 use borsh::{BorshDeserialize, BorshSerialize}; // For account data serialization
-use solana_program::pubkey::Pubkey;            // For handling Solana public keys
+use bytemuck::{Pod, Zeroable}; // For zero-copy account views
+use solana_program::{hash::hashv, program_error::ProgramError, pubkey::Pubkey}; // For commitments, errors and Solana public keys
+
+use crate::{
+    amount::{Bps, Lamports, TokenAmount}, // Fee rates and typed reserves
+    curve,                                // Curve spot prices
+    error::TokenExchangeError,            // Custom errors
+    math,                                 // Roots and rounded division
+    oracle,                               // TWAP arithmetic
+};
 
 /// Represents the state of a liquidity pool in the token exchange
 /// 
@@ -10,6 +19,8 @@ use solana_program::pubkey::Pubkey;            // For handling Solana public key
 /// including reserves, fee configuration, and authority information.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct PoolState {
+    pub discriminator: [u8; 8], // Account type tag, always `PoolState::DISCRIMINATOR`
+    pub version: u8,            // Layout revision, always `StateVersion::CURRENT` once loaded
     pub authority: Pubkey,      // The pool administrator's public key
     pub sol_reserve: u64,       // Current SOL balance in the pool
     pub token_reserve: u64,     // Current SPL token balance in the pool
@@ -17,22 +28,552 @@ pub struct PoolState {
     pub fee_rate: u64,          // Trading fee in basis points (1 bp = 0.01%, e.g., 30 = 0.3%)
     pub token_mint: Pubkey,     // Mint address of the SPL token in the pool
     pub initialized: bool,      // Pool initialization status flag
+    pub keeper_bounty_bps: u64, // Share of the trading fee paid to keepers filling limit orders, in basis points
+    pub interest_bearing: bool, // Whether the token mint is a Token-2022 interest-bearing mint
+    pub rebasing: bool,         // Whether the token reserve is re-read from the vault before each operation
+    pub token_vault: Pubkey,    // Pool token account read in rebasing mode (default when unset)
+    pub non_transferable_lp: bool, // Whether LP tokens are soulbound (Token-2022 non-transferable mint)
+    pub deposit_permissioned: bool, // Whether only allowlisted providers may add liquidity
+    pub creator: Pubkey,        // The account that initialized the pool (never changes)
+    pub created_at_slot: u64,   // Slot in which the pool was initialized
+    pub deprecated: bool,       // Withdraw-only: swaps and deposits are disabled
+    pub mint_a: Pubkey,         // Mint paired against token_mint, its reserve in sol_reserve (default for native SOL)
+    pub bump: u8,               // PDA bump seed
+    pub sol_vault: Pubkey,      // SOL vault PDA holding the SOL reserve's lamports (default for token pairs)
+    pub protocol_fee_share_bps: u64, // Share of the trading fee accrued to the protocol, in basis points
+    pub protocol_fee_sol: u64,  // Uncollected protocol fees held in the SOL (or mint A) vault
+    pub protocol_fee_token: u64, // Uncollected protocol fees held in the token vault
+    pub wsol: bool,             // Whether the SOL vault is a wrapped SOL token account instead of raw lamports
+    pub fee_tier: u64,          // Fee rate the pool PDA was derived with (fee_rate may be updated later)
+    pub paused: bool,           // Whether swaps and deposits are halted
+    pub curve_type: CurveType,  // Pricing curve chosen at creation
+    pub amplification: u64,     // StableSwap amplification coefficient (0 for other curves)
+    pub token_offset: u64,      // Virtual tokens added to the token reserve by offset pools (0 for other curves)
+    pub price_cumulative_last: u128, // Running sum of the Q64.64 token price (in SOL) times elapsed slots
+    pub last_update_slot: u64,  // Slot at which price_cumulative_last was last advanced
+    pub flash_loan_amount: u64, // Principal of the outstanding flash loan (0 when none)
+    pub flash_loan_sol: bool,   // Whether the outstanding flash loan is of SOL (or mint A) rather than tokens
+    pub fee_on_transfer: bool,  // Whether every token input is accounted at the vault's measured balance change
+    pub cumulative_sol_volume: u128, // SOL (or mint A) leg of every swap since creation
+    pub cumulative_token_volume: u128, // Token leg of every swap since creation
+    pub cumulative_fees: u128,  // Trading fees charged since creation, valued in lamports at each swap's pre-trade price
+    pub lp_supply: u64,         // LP token supply as of the last liquidity operation
+    pub position_lp_supply: u64, // LP tokens credited to user positions, across all positions
+    pub fee_growth_sol: u128,   // Position fees per position LP token in SOL (or mint A), Q64.64, wrapping
+    pub fee_growth_token: u128, // Position fees per position LP token in tokens, Q64.64, wrapping
+    pub lp_fees_sol: u64,       // Position fees set aside in the SOL (or mint A) vault, unclaimed
+    pub lp_fees_token: u64,     // Position fees set aside in the token vault, unclaimed
+    pub trade_start_timestamp: i64, // Unix time before which no swap executes
+    pub launch_window_seconds: u64, // Seconds after trade start during which buys are capped
+    pub max_swap_per_wallet_during_launch: u64, // Most tokens one buy may take in the launch window, 0 for no cap
+    pub launch_fee_bps: u64,    // Trading fee at trade start, decaying to fee_rate; 0 for none
+    pub launch_fee_decay_seconds: u64, // Seconds over which the launch fee decays to fee_rate
+    pub volatility_accumulator: u64, // Recent price movement in basis points, halving every VOLATILITY_HALF_LIFE_SLOTS
+    pub volatility_update_slot: u64, // Slot at which volatility_accumulator was last updated
+    pub variable_fee_control: u64, // Variable fee in basis points per 10000 bps of accumulated volatility
+    pub max_variable_fee_bps: u64, // Cap on the variable fee added to the trading fee; 0 for none
+    pub max_price_impact_bps: u64, // Largest price move one swap may cause, in basis points; 0 for no limit
+    pub price_oracle: Pubkey,   // Pyth price account swaps are checked against (default for none)
+    pub oracle_max_deviation_bps: u64, // Furthest a swap may execute from the oracle price, in basis points
+    pub ema_price: u128,        // Exponential moving average of the Q64.64 token price in SOL (0 until the first swap)
+    pub ema_update_slot: u64,   // Slot at which ema_price was last updated
+    pub weights: [u64; 2],      // SOL (or mint A) and token weights in basis points (zero unless weighted)
+    pub sol_offset: u64,        // Virtual SOL (or mint A) added to the SOL reserve by offset pools (0 for other curves)
+    pub graduation_threshold: u64, // SOL reserve at which a bonding curve stops trading until it graduates; 0 for none
+    pub max_outflow_bps: u64,   // Most of either reserve that may leave in one slot, in basis points; 0 for no limit
+    pub outflow_slot: u64,      // Slot that outflow_this_slot was counted in
+    pub outflow_this_slot: [u64; 2], // SOL (or mint A) and tokens that left the reserves during outflow_slot
+    pub emergency: bool,        // Reserves are untrusted: the pool is paused and LPs exit pro rata to vault balances
+}
+
+/// Pricing curve of a pool
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveType {
+    /// Constant product, `x * y = k`
+    ConstantProduct,
+    /// StableSwap invariant, flattened around the 1:1 price by the pool's amplification
+    Stable,
+    /// Constant product over the token reserve plus the pool's virtual token offset
+    Offset,
+    /// Weighted constant mean, `x^w_x * y^w_y = k`, over the pool's weights
+    Weighted,
+}
+
+/// Layout revision of a pool state account
+/// 
+/// Adding a field to `PoolState` changes its serialized length, so pools
+/// created under an older layout no longer deserialize. Each new layout gets
+/// a variant here and an upgrade step from the one before it, which
+/// `MigratePool` applies after growing the account. Pools created before the
+/// discriminator and version byte were introduced carry neither and cannot
+/// be migrated; they must be drained and recreated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StateVersion {
+    /// First versioned layout
+    V1 = 1,
+}
+
+impl StateVersion {
+    /// Layout that `PoolState` serializes to
+    pub const CURRENT: Self = Self::V1;
+
+    /// Serialized length of a pool state in this layout
+    pub const fn account_len(self) -> usize {
+        match self {
+            Self::V1 => PoolState::LEN,
+        }
+    }
+
+    /// Identifies the layout of pool state data from the version byte after
+    /// its discriminator
+    pub fn detect(pool_data: &[u8]) -> Result<Self, ProgramError> {
+        if pool_data.get(..PoolState::DISCRIMINATOR.len()) != Some(&PoolState::DISCRIMINATOR[..]) {
+            return Err(TokenExchangeError::InvalidAccountDiscriminator.into());
+        }
+        match pool_data.get(PoolState::DISCRIMINATOR.len()) {
+            Some(1) => Ok(Self::V1),
+            _ => Err(TokenExchangeError::InvalidStateVersion.into()),
+        }
+    }
+
+    /// Rewrites pool state data from this layout to `CURRENT`, in place
+    /// 
+    /// `pool_data` must already be `CURRENT.account_len()` bytes, holding
+    /// the old layout's bytes at its start. A later layout adds a step here
+    /// that fills its new fields and bumps the version byte.
+    pub fn upgrade(self, pool_data: &mut [u8]) -> Result<(), ProgramError> {
+        if pool_data.len() != Self::CURRENT.account_len() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        match self {
+            Self::V1 => Ok(()),
+        }
+    }
+}
+
+/// Seed used to derive a pool state PDA
+pub const POOL_SEED: &[u8] = b"pool";
+
+/// Seed used to derive a pool's SOL vault PDA
+pub const SOL_VAULT_SEED: &[u8] = b"sol_vault";
+
+/// Slots over which a pool's moving average price closes half its gap to the spot price (about one minute)
+pub const EMA_HALF_LIFE_SLOTS: u64 = 150;
+
+/// Slots over which a pool's volatility accumulator halves (about one minute)
+pub const VOLATILITY_HALF_LIFE_SLOTS: u64 = 150;
+
+/// Seed used to derive the program-wide statistics PDA
+pub const PROTOCOL_STATS_SEED: &[u8] = b"protocol_stats";
+
+/// Program-wide statistics aggregated across every pool
+/// 
+/// Lives in a single PDA and is updated lazily by pool operations that
+/// pass it along, so dashboards can read protocol totals without
+/// scanning every pool account.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ProtocolStats {
+    pub total_pools: u64,       // Number of pools initialized while tracking stats
+    pub total_volume_sol: u128, // Cumulative SOL leg of every swap, in lamports
+    pub total_fees_sol: u128,   // Cumulative trading fees, valued in lamports at execution price
+    pub last_update_slot: u64,  // Slot of the most recent update
+    pub bump: u8,               // PDA bump seed
+}
+
+/// Seed used to derive the program-wide protocol configuration PDA
+pub const PROTOCOL_CONFIG_SEED: &[u8] = b"protocol_config";
+
+/// Program-wide protocol configuration
+/// 
+/// Names the protocol authority, which sets each pool's protocol fee share
+/// and collects the accrued protocol fees, and holds the parameters new
+/// pools are checked against. Created once by the program's upgrade
+/// authority.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ProtocolConfig {
+    pub authority: Pubkey,      // The protocol authority's public key
+    pub bump: u8,               // PDA bump seed
+    pub parameters: ProtocolParameters, // Bounds and defaults for new pools
+}
+
+/// Number of fee tiers the protocol config can offer
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// Program-wide bounds and defaults applied when a pool is created
+/// 
+/// A pair may have one pool per fee tier, since the tier is part of the
+/// pool PDA seeds. Only the first `fee_tier_count` entries of `fee_tiers`
+/// are offered; the rest are zero.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolParameters {
+    pub default_fee_rate_bps: u64,   // Fee tier clients offer new pools unless told otherwise
+    pub max_fee_rate_bps: u64,       // Highest fee rate a new pool may charge
+    pub protocol_fee_share_bps: u64, // Protocol fee share new pools start with
+    pub pool_creation_fee: u64,      // Lamports a pool creator pays the treasury
+    pub treasury: Pubkey,            // Receives pool-creation fees (default when there is no fee)
+    pub pool_creation_paused: bool,  // Whether new pools are refused
+    pub fee_tier_count: u8,          // Number of fee tiers offered
+    pub fee_tiers: [u64; MAX_FEE_TIERS], // Fee rates new pools may pick, ascending, in basis points
+    pub max_referral_fee_share_bps: u64, // Highest share of a swap's trading fee a referrer may receive
+    pub max_variable_fee_bps: u64,   // Highest variable fee a pool may add to its trading fee
+    pub max_keeper_bounty_bps: u64,  // Highest share of the trading fee a pool may pay keepers cranking its orders
+    pub max_keeper_tip_bps: u64,     // Highest share of each tranche a DCA position may tip its keeper
+    pub skim_excess_reserves: bool,  // Whether SyncReserves credits vault excess to protocol fees, not reserves
+    pub max_platform_fee_bps: u64,   // Highest integrator fee a swap may take from its output
+}
+
+/// Seed used to derive a pool's price observations PDA
+pub const OBSERVATIONS_SEED: &[u8] = b"observations";
+
+/// A single price observation recorded by a swap
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct Observation {
+    pub slot: u64,              // Slot at which the observation was written
+    pub price_cumulative: u128, // Running sum of the Q64.64 token price (in SOL) times elapsed slots
+    pub initialized: bool,      // Whether this entry has been written yet
+    pub sqrt_price: u64,        // Square root of the pre-trade token price (in SOL), Q32.32
+    pub liquidity: u64,         // Geometric mean of the pre-trade reserves, sqrt(sol * token)
+}
+
+/// Ring buffer of a pool's historical price observations
+/// 
+/// Each swap writes at most one observation per slot, holding the price
+/// accumulator, square-root price and liquidity before the trade. The
+/// buffer holds `observations.len()` entries (its cardinality), which
+/// anyone may grow to support longer TWAP windows.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ObservationState {
+    pub pool: Pubkey,                   // The pool these observations belong to
+    pub index: u16,                     // Position of the most recent observation
+    pub bump: u8,                       // PDA bump seed
+    pub observations: Vec<Observation>, // Ring buffer entries
+}
+
+/// Seed used to derive a pool's volume stats PDA
+pub const VOLUME_STATS_SEED: &[u8] = b"volume_stats";
+
+/// Slots covered by one volume bucket (about one hour at 400ms slots)
+pub const VOLUME_BUCKET_SLOTS: u64 = 9_000;
+
+/// Number of volume buckets kept per pool (about 24 hours)
+pub const VOLUME_BUCKET_COUNT: usize = 24;
+
+/// Volume and fees a pool traded within one bucket of slots
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct VolumeBucket {
+    pub bucket: u64,            // Bucket number (slot / VOLUME_BUCKET_SLOTS) the totals belong to
+    pub volume_sol: u64,        // SOL leg of the bucket's swaps, in lamports
+    pub fees_sol: u64,          // Trading fees, valued in lamports at execution price
+}
+
+/// Ring buffer of a pool's recent per-bucket volume and fees
+/// 
+/// Swaps that pass this account add to the current bucket, overwriting the
+/// bucket from `VOLUME_BUCKET_COUNT` buckets ago, so 24h volume and fee
+/// APR can be read straight from chain.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct VolumeStats {
+    pub pool: Pubkey,                                 // The pool these totals belong to
+    pub bump: u8,                                     // PDA bump seed
+    pub buckets: [VolumeBucket; VOLUME_BUCKET_COUNT], // Ring buffer indexed by bucket number
+}
+
+/// Seed used to derive a limit order PDA
+pub const LIMIT_ORDER_SEED: &[u8] = b"limit_order";
+
+/// Number of decimal places in limit prices
+pub const LIMIT_PRICE_DECIMALS: u32 = 9;
+
+/// Fixed-point scale for limit prices (output units per input unit)
+pub const LIMIT_PRICE_SCALE: u64 = 10u64.pow(LIMIT_PRICE_DECIMALS);
+
+/// Kind of resting order
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    /// Fills whenever the pool price reaches the limit price
+    Limit,
+    /// Fills at market, bounded by the limit price, once the pool price falls to the trigger price
+    StopLoss,
+    /// Fills at market, bounded by the limit price, once an external oracle price crosses the trigger price
+    OracleConditioned,
+}
+
+/// A resting limit order escrowed against a pool
+/// 
+/// The order PDA escrows the input asset (lamports directly, or tokens in an
+/// escrow token account it owns) and is filled against the pool, in one or
+/// more chunks, whenever the pool price reaches `limit_price`. Stop-loss
+/// orders additionally wait for the pool price to fall to `trigger_price`,
+/// with `limit_price` acting as their slippage floor.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct LimitOrder {
+    pub owner: Pubkey,          // The order owner's public key
+    pub pool: Pubkey,           // The pool the order executes against
+    pub order_id: u64,          // Owner-chosen identifier used in the PDA seeds
+    pub is_sol_input: bool,     // Direction (true = sells SOL for tokens, false = sells tokens for SOL)
+    pub limit_price: u64,       // Minimum output per input, scaled by LIMIT_PRICE_SCALE
+    pub original_amount: u64,   // Input size when the order was placed
+    pub remaining_amount: u64,  // Input still waiting to be filled
+    pub filled_output: u64,     // Total output delivered to the owner so far
+    pub expiry_slot: u64,       // Slot after which the order can no longer fill (0 = good-till-cancelled)
+    pub order_type: OrderType,  // Limit or stop-loss
+    pub trigger_price: u64,     // Stop-loss or oracle trigger, scaled by LIMIT_PRICE_SCALE (0 for limits)
+    pub oracle: Pubkey,         // Oracle price account for oracle-conditioned orders
+    pub trigger_below: bool,    // Oracle orders trigger when the price is at or below (true) or above (false) the trigger
+    pub bump: u8,               // PDA bump seed
+}
+
+/// Seed used to derive a liquidity schedule PDA
+pub const LIQUIDITY_SCHEDULE_SEED: &[u8] = b"liquidity_schedule";
+
+/// A recurring liquidity provision plan
+/// 
+/// The schedule PDA escrows SOL (as its own lamports) and tokens (in an
+/// escrow token account it owns) and deposits one tranche into the pool
+/// every `interval_slots`, cranked by anyone.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct LiquiditySchedule {
+    pub owner: Pubkey,             // The schedule owner's public key (receives LP tokens)
+    pub pool: Pubkey,              // The pool receiving the liquidity
+    pub schedule_id: u64,          // Owner-chosen identifier used in the PDA seeds
+    pub sol_per_tranche: u64,      // SOL offered to the pool per tranche
+    pub token_per_tranche: u64,    // Tokens offered to the pool per tranche
+    pub interval_slots: u64,       // Slots between tranches
+    pub next_tranche_slot: u64,    // Earliest slot the next tranche may execute
+    pub tranches_remaining: u64,   // Tranches still to execute
+    pub sol_remaining: u64,        // SOL still held in escrow
+    pub token_remaining: u64,      // Tokens still held in escrow
+    pub bump: u8,                  // PDA bump seed
+}
+
+/// Seed used to derive a DCA position PDA
+pub const DCA_POSITION_SEED: &[u8] = b"dca_position";
+
+/// A dollar-cost averaging plan that sells its escrowed input in tranches
+/// 
+/// The position PDA escrows the input (SOL as its own lamports, tokens in an
+/// escrow token account it owns) and swaps `amount_per_fill` of it every
+/// `interval_slots`, cranked by anyone. The cranking keeper earns
+/// `keeper_tip_bps` of each tranche.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DcaPosition {
+    pub owner: Pubkey,             // The position owner's public key (receives the output)
+    pub pool: Pubkey,              // The pool the tranches swap through
+    pub dca_id: u64,               // Owner-chosen identifier used in the PDA seeds
+    pub is_sol_input: bool,        // Direction (true = SOL→Token, false = Token→SOL)
+    pub amount_per_fill: u64,      // Input sold per tranche, keeper tip included
+    pub interval_slots: u64,       // Slots between tranches
+    pub next_fill_slot: u64,       // Earliest slot the next tranche may execute
+    pub remaining_amount: u64,     // Input still held in escrow
+    pub filled_output: u64,        // Total output delivered to the owner so far
+    pub limit_price: u64,          // Minimum output per input, scaled by LIMIT_PRICE_SCALE (0 = no floor)
+    pub keeper_tip_bps: u64,       // Share of each tranche paid to the keeper, in basis points
+    pub bump: u8,                  // PDA bump seed
+}
+
+/// Seed used to derive a trading allowance PDA
+pub const TRADING_ALLOWANCE_SEED: &[u8] = b"trading_allowance";
+
+/// A bounded trading permission granted by a wallet to an operator key
+/// 
+/// The operator may swap on the owner's behalf in the listed pools, up to
+/// `max_notional_per_window` lamports of SOL-side volume per window. Token
+/// inputs are spent through an SPL delegate approval the owner grants the
+/// allowance PDA; SOL inputs come from lamports the owner deposits into the
+/// PDA above its rent-exempt minimum.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct TradingAllowance {
+    pub owner: Pubkey,                   // The wallet granting the allowance
+    pub operator: Pubkey,                // The key allowed to trade on the owner's behalf
+    pub max_notional_per_window: u64,    // SOL-side volume cap per window, in lamports
+    pub window_slots: u64,               // Length of a spending window in slots
+    pub window_start_slot: u64,          // Slot at which the current window began
+    pub window_notional: u64,            // SOL-side volume traded in the current window
+    pub bump: u8,                        // PDA bump seed
+    pub allowed_pools: Vec<Pubkey>,      // Pools the operator may trade in
+}
+
+/// Seed used to derive a keeper registration PDA
+pub const KEEPER_REGISTRATION_SEED: &[u8] = b"keeper_registration";
+
+/// A keeper registered by a pool's authority
+/// 
+/// Registered keepers may run multi-pool arbitrage through the pool.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct KeeperRegistration {
+    pub pool: Pubkey,    // The pool the keeper is registered with
+    pub keeper: Pubkey,  // The registered keeper's public key
+    pub bump: u8,        // PDA bump seed
+}
+
+/// Seed used to derive a liquidity provider allowlist PDA
+pub const LP_ALLOWLIST_SEED: &[u8] = b"lp_allowlist";
+
+/// A liquidity provider allowlisted by a pool's authority
+/// 
+/// Only allowlisted providers may add liquidity to deposit-permissioned pools.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct LpAllowlistEntry {
+    pub pool: Pubkey,      // The pool the provider may deposit into
+    pub provider: Pubkey,  // The allowlisted provider's public key
+    pub bump: u8,          // PDA bump seed
 }
 
+/// Seed used to derive a pool's metadata PDA
+pub const POOL_METADATA_SEED: &[u8] = b"pool_metadata";
+
+/// Maximum length of a pool's short name, in bytes
+pub const POOL_NAME_MAX_LEN: usize = 32;
+
+/// Maximum length of a pool's metadata URI, in bytes
+pub const POOL_URI_MAX_LEN: usize = 200;
+
+/// Display metadata a pool's authority publishes for frontends
+/// 
+/// The name and URI are stored zero-padded in fixed-size fields, so
+/// updates never resize the account.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PoolMetadata {
+    pub pool: Pubkey,                   // The pool the metadata describes
+    pub name: [u8; POOL_NAME_MAX_LEN],  // UTF-8 short name, zero-padded
+    pub uri: [u8; POOL_URI_MAX_LEN],    // UTF-8 URI of off-chain details, zero-padded
+    pub bump: u8,                       // PDA bump seed
+}
+
+/// Seed used to derive the program-wide pool registry PDA
+pub const POOL_REGISTRY_SEED: &[u8] = b"pool_registry";
+
+/// Seed used to derive a pool registry entry PDA
+pub const POOL_REGISTRY_ENTRY_SEED: &[u8] = b"pool_registry_entry";
+
+/// Counter of every pool ever created
+/// 
+/// Each pool is recorded at creation in the entry PDA derived from its
+/// index, so clients enumerate pools by deriving entries `0..pool_count`
+/// instead of scanning every program account.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PoolRegistry {
+    pub pool_count: u64,          // Number of pools registered, and the index of the next one
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// A pool's record in the pool registry
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct PoolRegistryEntry {
+    pub index: u64,               // Position of the pool in creation order
+    pub pool: Pubkey,             // The pool state PDA
+    pub mint_a: Pubkey,           // Side A mint (default for native SOL pools)
+    pub token_mint: Pubkey,       // Side B mint
+    pub fee_tier: u64,            // Fee rate the pool PDA was derived with, in basis points
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// Seed used to derive a user's stats PDA
+pub const USER_STATS_SEED: &[u8] = b"user_stats";
+
+/// Per-user statistics and sequencing
+/// 
+/// Holds the sequence number that pre-signed orders must reference, so each
+/// signed order executes at most once and strictly in order.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct UserStats {
+    pub owner: Pubkey,            // The user these stats belong to
+    pub signed_order_nonce: u64,  // Nonce the owner's next signed order must carry
+    pub bump: u8,                 // PDA bump seed
+}
+
+/// Seed used to derive a pool's sealed order committee PDA
+pub const SEALED_COMMITTEE_SEED: &[u8] = b"sealed_committee";
+
+/// Seed used to derive a sealed order PDA
+pub const SEALED_ORDER_SEED: &[u8] = b"sealed_order";
+
+/// Slots after its reveal slot during which only the committee may settle a sealed order
+pub const SEALED_ORDER_EXECUTION_WINDOW_SLOTS: u64 = 150;
+
+/// The committee that decrypts and executes a pool's sealed orders
+/// 
+/// Pools opt in to sealed orders by creating a committee. Members hold
+/// shares of the decryption key off-chain; executing an order requires
+/// `threshold` of them to co-sign the reveal.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SealedOrderCommittee {
+    pub pool: Pubkey,             // The pool accepting sealed orders
+    pub threshold: u8,            // Member signatures required to execute an order
+    pub reveal_delay_slots: u64,  // Slots between submission and earliest execution
+    pub bump: u8,                 // PDA bump seed
+    pub members: Vec<Pubkey>,     // Committee member keys
+}
+
+/// An order submitted as a ciphertext and a commitment to its terms
+/// 
+/// Only the direction and an upper bound on the size (the escrow) are
+/// public. The committee decrypts `ciphertext` off-chain and reveals terms
+/// matching `commitment` once `reveal_slot` has passed.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SealedOrder {
+    pub owner: Pubkey,            // The order owner's public key
+    pub pool: Pubkey,             // The pool the order executes against
+    pub order_id: u64,            // Owner-chosen identifier used in the PDA seeds
+    pub is_sol_input: bool,       // Direction (true = sells SOL for tokens, false = sells tokens for SOL)
+    pub escrow_amount: u64,       // Input escrowed, bounding the hidden order size
+    pub commitment: [u8; 32],     // Hash of the order's SealedOrderTerms
+    pub reveal_slot: u64,         // Earliest slot the committee may execute the order
+    pub bump: u8,                 // PDA bump seed
+    pub ciphertext: Vec<u8>,      // Order terms encrypted to the committee
+}
+
+/// The hidden terms of a sealed order
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SealedOrderTerms {
+    pub amount_in: u64,           // Input to swap, at most the escrow amount
+    pub minimum_amount_out: u64,  // Minimum output the owner accepts
+    pub salt: [u8; 32],           // Random salt hiding the terms from guessing
+}
+
+/// Seed used to derive a user position PDA
+pub const POSITION_SEED: &[u8] = b"position";
+
 /// Represents a liquidity provider's position in the pool
 /// 
 /// Tracks an individual user's liquidity provision and their share of the pool
 /// through LP tokens.
+///
+/// Trading fees earned by the LP tokens credited to positions are set aside
+/// in the pool's `lp_fees_*` instead of compounding into the reserves, and
+/// accrue to each position through the pool's fee growth since its last
+/// checkpoint. `ClaimFees` pays them out without burning LP tokens.
+///
+/// Positions are PDAs derived from `["position", pool, owner]`, opened with
+/// `InitializeUserPosition` and closed with `CloseUserPosition`. Liquidity
+/// instructions passed the provider's position credit it the LP tokens they
+/// mint and debit the ones they burn; LP tokens moved any other way are not
+/// tracked.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct UserPosition {
+    pub discriminator: [u8; 8], // Account type tag, always `UserPosition::DISCRIMINATOR`
     pub owner: Pubkey,          // The liquidity provider's public key
+    pub pool: Pubkey,           // The pool the position provides liquidity to
     pub lp_tokens: u64,         // Amount of LP tokens owned by this user
+    pub fee_growth_checkpoint_sol: u128, // Pool's fee_growth_sol when fees were last settled
+    pub fee_growth_checkpoint_token: u128, // Pool's fee_growth_token when fees were last settled
+    pub fees_owed_sol: u64,     // Settled SOL (or mint A) fees not yet claimed
+    pub fees_owed_token: u64,   // Settled token fees not yet claimed
+    pub bump: u8,               // PDA bump seed
 }
 
 impl PoolState {
+    /// Tag leading every pool state account's data
+    /// 
+    /// Distinct from every other account type's tag, so clients can select
+    /// pools with a `getProgramAccounts` memcmp filter at offset 0.
+    pub const DISCRIMINATOR: [u8; 8] = *b"poolstat";
+
     /// Total size of the PoolState structure when serialized
     /// 
     /// Breakdown:
+    /// - discriminator ([u8; 8]): 8 bytes
+    /// - version (u8): 1 byte
     /// - authority (Pubkey): 32 bytes
     /// - sol_reserve (u64): 8 bytes
     /// - token_reserve (u64): 8 bytes
@@ -40,14 +581,1320 @@ impl PoolState {
     /// - fee_rate (u64): 8 bytes
     /// - token_mint (Pubkey): 32 bytes
     /// - initialized (bool): 1 byte
-    pub const LEN: usize = 32 + 8 + 8 + 32 + 8 + 32 + 1;
+    /// - keeper_bounty_bps (u64): 8 bytes
+    /// - interest_bearing (bool): 1 byte
+    /// - rebasing (bool): 1 byte
+    /// - token_vault (Pubkey): 32 bytes
+    /// - non_transferable_lp (bool): 1 byte
+    /// - deposit_permissioned (bool): 1 byte
+    /// - creator (Pubkey): 32 bytes
+    /// - created_at_slot (u64): 8 bytes
+    /// - deprecated (bool): 1 byte
+    /// - mint_a (Pubkey): 32 bytes
+    /// - bump (u8): 1 byte
+    /// - sol_vault (Pubkey): 32 bytes
+    /// - protocol_fee_share_bps (u64): 8 bytes
+    /// - protocol_fee_sol (u64): 8 bytes
+    /// - protocol_fee_token (u64): 8 bytes
+    /// - wsol (bool): 1 byte
+    /// - fee_tier (u64): 8 bytes
+    /// - paused (bool): 1 byte
+    /// - curve_type (CurveType): 1 byte
+    /// - amplification (u64): 8 bytes
+    /// - token_offset (u64): 8 bytes
+    /// - price_cumulative_last (u128): 16 bytes
+    /// - last_update_slot (u64): 8 bytes
+    /// - flash_loan_amount (u64): 8 bytes
+    /// - flash_loan_sol (bool): 1 byte
+    /// - fee_on_transfer (bool): 1 byte
+    /// - cumulative_sol_volume (u128): 16 bytes
+    /// - cumulative_token_volume (u128): 16 bytes
+    /// - cumulative_fees (u128): 16 bytes
+    /// - lp_supply (u64): 8 bytes
+    /// - position_lp_supply (u64): 8 bytes
+    /// - fee_growth_sol (u128): 16 bytes
+    /// - fee_growth_token (u128): 16 bytes
+    /// - lp_fees_sol (u64): 8 bytes
+    /// - lp_fees_token (u64): 8 bytes
+    /// - trade_start_timestamp (i64): 8 bytes
+    /// - launch_window_seconds (u64): 8 bytes
+    /// - max_swap_per_wallet_during_launch (u64): 8 bytes
+    /// - launch_fee_bps (u64): 8 bytes
+    /// - launch_fee_decay_seconds (u64): 8 bytes
+    /// - volatility_accumulator (u64): 8 bytes
+    /// - volatility_update_slot (u64): 8 bytes
+    /// - variable_fee_control (u64): 8 bytes
+    /// - max_variable_fee_bps (u64): 8 bytes
+    /// - max_price_impact_bps (u64): 8 bytes
+    /// - price_oracle (Pubkey): 32 bytes
+    /// - oracle_max_deviation_bps (u64): 8 bytes
+    /// - ema_price (u128): 16 bytes
+    /// - ema_update_slot (u64): 8 bytes
+    /// - weights ([u64; 2]): 16 bytes
+    /// - sol_offset (u64): 8 bytes
+    /// - graduation_threshold (u64): 8 bytes
+    /// - max_outflow_bps (u64): 8 bytes
+    /// - outflow_slot (u64): 8 bytes
+    /// - outflow_this_slot ([u64; 2]): 16 bytes
+    /// - emergency (bool): 1 byte
+    pub const LEN: usize =
+        8 + 1 + 32 + 8 + 8 + 32 + 8 + 32 + 1 + 8 + 1 + 1 + 32 + 1 + 1 + 32 + 8 + 1 + 32 + 1 + 32 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + 8 + 8
+            + 16 + 8 + 8 + 1 + 1 + 16 + 16 + 16 + 8 + 8 + 16 + 16 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8
+            + 32 + 8 + 16 + 8 + 8 * 2 + 8 + 8 + 8 + 8 + 8 * 2 + 1;
+
+    /// Derives the PDA of the pool for a pair and fee tier
+    /// 
+    /// `mint_a` is the default key for native SOL pools, so each SOL/token
+    /// pair and each token pair gets one pool per fee rate. The fee rate here
+    /// is the pool's creation-time `fee_tier`, which later fee updates keep.
+    pub fn find_address(
+        mint_a: &Pubkey,
+        token_mint: &Pubkey,
+        fee_rate: u64,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[POOL_SEED, mint_a.as_ref(), token_mint.as_ref(), &fee_rate.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Derives the pool PDA of a pair at each of `fee_tiers`
+    /// 
+    /// Pass `ProtocolParameters::offered_fee_tiers` to find every pool the
+    /// pair may have; the returned pairs are `(fee_tier, pool)`.
+    pub fn find_tier_addresses(
+        mint_a: &Pubkey,
+        token_mint: &Pubkey,
+        fee_tiers: &[u64],
+        program_id: &Pubkey,
+    ) -> Vec<(u64, Pubkey)> {
+        fee_tiers
+            .iter()
+            .map(|&fee_tier| (fee_tier, Self::find_address(mint_a, token_mint, fee_tier, program_id).0))
+            .collect()
+    }
+
+    /// Derives the PDA of a native SOL pool's SOL vault
+    /// 
+    /// The vault is a data-less program-owned account whose lamports above
+    /// its rent-exempt minimum back `sol_reserve`, keeping swap lamports off
+    /// the pool state account. In wSOL mode the same address instead holds a
+    /// wrapped SOL token account owned by the pool.
+    pub fn find_sol_vault_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[SOL_VAULT_SEED, pool.as_ref()], program_id)
+    }
+
+    /// Recomputes the pool's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[
+                POOL_SEED,
+                self.mint_a.as_ref(),
+                self.token_mint.as_ref(),
+                &self.fee_tier.to_le_bytes(),
+                &[self.bump],
+            ],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Whether the pool pairs two SPL mints instead of native SOL and a token
+    /// 
+    /// Side A of a token pair pool is held in an SPL vault rather than as
+    /// lamports on the pool account; `sol_reserve` tracks its reserve.
+    pub fn is_token_pair(&self) -> bool {
+        self.mint_a != Pubkey::default()
+    }
+
+    /// Spot price of the token in SOL on the pool's curve, Q64.64, or `None`
+    /// for a pool without a price
+    pub fn spot_price(&self) -> Option<u128> {
+        self.spot_price_at(self.sol_reserve, self.token_reserve)
+    }
+
+    /// Spot price on the pool's curve had it the given reserves
+    fn spot_price_at(&self, sol_reserve: u64, token_reserve: u64) -> Option<u128> {
+        curve::for_pool(self).spot_price(Lamports(sol_reserve), TokenAmount(token_reserve))
+    }
+
+    /// The price accumulator as it would read at `slot`
+    /// 
+    /// Extends `price_cumulative_last` by the current Q64.64 token price (in
+    /// SOL) for the slots since `last_update_slot`, so readers get an
+    /// up-to-date value even when the pool has not traded recently. Wraps on
+    /// overflow; only differences between two readings are meaningful.
+    pub fn price_cumulative_at(&self, slot: u64) -> u128 {
+        let elapsed = slot.saturating_sub(self.last_update_slot) as u128;
+        match self.spot_price() {
+            Some(price) if elapsed > 0 => self.price_cumulative_last.wrapping_add(price.wrapping_mul(elapsed)),
+            _ => self.price_cumulative_last,
+        }
+    }
+
+    /// Advances the price accumulator to `slot`
+    /// 
+    /// Called before a swap moves the reserves, so a price pushed within a
+    /// slot only weighs in once later slots pass.
+    pub fn accumulate_price(&mut self, slot: u64) {
+        if slot > self.last_update_slot {
+            self.price_cumulative_last = self.price_cumulative_at(slot);
+            self.last_update_slot = slot;
+        }
+    }
+
+    /// The moving average token price as it would read at `slot`, Q64.64 in SOL
+    /// 
+    /// The average closes half its distance to the current spot price every
+    /// `EMA_HALF_LIFE_SLOTS`, interpolated linearly within a half-life. It
+    /// only moves as slots pass, so a price pushed and reverted within one
+    /// slot leaves it untouched. Before the first swap it reads the spot
+    /// price.
+    pub fn ema_price_at(&self, slot: u64) -> u128 {
+        let Some(price) = self.spot_price() else {
+            return self.ema_price;
+        };
+        let elapsed = slot.saturating_sub(self.ema_update_slot);
+        let half_lives = elapsed / EMA_HALF_LIFE_SLOTS;
+        if self.ema_price == 0 || half_lives >= u128::BITS as u64 {
+            return price;
+        }
+        let gap = self.ema_price.abs_diff(price) >> half_lives;
+        let gap = gap - gap / (2 * EMA_HALF_LIFE_SLOTS as u128) * (elapsed % EMA_HALF_LIFE_SLOTS) as u128;
+        if self.ema_price > price {
+            price + gap
+        } else {
+            price - gap
+        }
+    }
+
+    /// Advances the moving average price to `slot`
+    /// 
+    /// Called before a swap moves the reserves, alongside `accumulate_price`.
+    pub fn update_ema_price(&mut self, slot: u64) {
+        if slot > self.ema_update_slot || self.ema_price == 0 {
+            self.ema_price = self.ema_price_at(slot);
+            self.ema_update_slot = slot;
+        }
+    }
+
+    /// Rejects a swap the pool's launch protection forbids at unix time `now`
+    /// 
+    /// Nothing trades before `trade_start_timestamp`. For the
+    /// `launch_window_seconds` after it, each buy of the token (a swap with
+    /// SOL or mint A in) may take at most `max_swap_per_wallet_during_launch`
+    /// tokens, so a sniper has to spread a large buy over many swaps.
+    pub fn check_launch_protection(&self, now: i64, is_sol_input: bool, amount_out: u64) -> Result<(), ProgramError> {
+        if now < self.trade_start_timestamp {
+            return Err(TokenExchangeError::TradingNotStarted.into());
+        }
+        let launch_end = self.trade_start_timestamp.saturating_add(self.launch_window_seconds as i64);
+        if is_sol_input
+            && now < launch_end
+            && self.max_swap_per_wallet_during_launch > 0
+            && amount_out > self.max_swap_per_wallet_during_launch
+        {
+            return Err(TokenExchangeError::LaunchBuyCapExceeded.into());
+        }
+        Ok(())
+    }
+
+    /// Trading fee at unix time `now`, including any launch fee
+    /// 
+    /// The launch fee applies in full until `trade_start_timestamp` and
+    /// decays linearly to `fee_rate` over the following
+    /// `launch_fee_decay_seconds`, rounded down.
+    pub fn fee_rate_at(&self, now: i64) -> Bps {
+        let elapsed = now.saturating_sub(self.trade_start_timestamp).max(0) as u64;
+        if self.launch_fee_bps <= self.fee_rate || elapsed >= self.launch_fee_decay_seconds {
+            return Bps(self.fee_rate);
+        }
+        let remaining = (self.launch_fee_decay_seconds - elapsed) as u128;
+        let surcharge = (self.launch_fee_bps - self.fee_rate) as u128 * remaining;
+        Bps(self.fee_rate + (surcharge / self.launch_fee_decay_seconds as u128) as u64)
+    }
+
+    /// The volatility accumulator as it would read at `slot`
+    /// 
+    /// Halves for every `VOLATILITY_HALF_LIFE_SLOTS` since the last swap, so
+    /// volatility from an old burst of trading fades away.
+    pub fn volatility_at(&self, slot: u64) -> u64 {
+        let half_lives = slot.saturating_sub(self.volatility_update_slot) / VOLATILITY_HALF_LIFE_SLOTS;
+        if half_lives >= u64::BITS as u64 {
+            return 0;
+        }
+        self.volatility_accumulator >> half_lives
+    }
+
+    /// Keeper's share of the trading fee on `amount_in` of input (rounded down)
+    /// 
+    /// Paid to whoever cranks an order fill, out of the fee portion of the
+    /// input, so it never exceeds the fee the pool would have kept.
+    pub fn keeper_bounty(&self, amount_in: u64) -> u64 {
+        (amount_in as u128 * self.fee_rate as u128 * self.keeper_bounty_bps as u128 / (10000 * 10000)) as u64
+    }
+
+    /// Fee added to the trading fee at `slot` for recent volatility, in basis points
+    /// 
+    /// Grows with the accumulator by `variable_fee_control` up to
+    /// `max_variable_fee_bps`.
+    pub fn variable_fee_at(&self, slot: u64) -> u64 {
+        let variable_fee = self.volatility_at(slot) as u128 * self.variable_fee_control as u128 / 10000;
+        variable_fee.min(self.max_variable_fee_bps as u128) as u64
+    }
+
+    /// How far the token price moved from the given reserves to the current
+    /// ones, in basis points of the price before, saturating
+    /// 
+    /// Called after a swap moves the reserves, with the reserves before it.
+    /// Prices are read off the pool's curve; a move from or to a pool
+    /// without a price counts as no move.
+    pub fn price_movement_bps(&self, sol_reserve_before: u64, token_reserve_before: u64) -> u64 {
+        match (self.spot_price_at(sol_reserve_before, token_reserve_before), self.spot_price()) {
+            (Some(before), Some(after)) if before > 0 => {
+                math::mul_div(before.abs_diff(after), 10000, before, math::Rounding::Down)
+                    .and_then(|movement| u64::try_from(movement).ok())
+                    .unwrap_or(u64::MAX)
+            }
+            _ => 0,
+        }
+    }
+
+    /// SOL (or mint A) and tokens the vaults hold beyond the reserves and set-aside fees
+    /// 
+    /// Tokens or lamports sent straight to a vault are otherwise stranded.
+    pub fn vault_excess(&self, sol_balance: u64, token_balance: u64) -> [u64; 2] {
+        let sol_owed = self.sol_reserve as u128 + self.protocol_fee_sol as u128 + self.lp_fees_sol as u128;
+        let token_owed = self.token_reserve as u128 + self.protocol_fee_token as u128 + self.lp_fees_token as u128;
+        [
+            (sol_balance as u128).saturating_sub(sol_owed) as u64,
+            (token_balance as u128).saturating_sub(token_owed) as u64,
+        ]
+    }
+
+    /// Accounts for the vault excess
+    /// 
+    /// The excess is added to the reserves, or to the uncollected protocol
+    /// fees when `skim` is set, and returned as SOL (or mint A) and token
+    /// amounts.
+    pub fn sync_reserves(
+        &mut self,
+        sol_balance: u64,
+        token_balance: u64,
+        skim: bool,
+    ) -> Result<[u64; 2], ProgramError> {
+        let excess = self.vault_excess(sol_balance, token_balance);
+        let (sol_bucket, token_bucket) = if skim {
+            (&mut self.protocol_fee_sol, &mut self.protocol_fee_token)
+        } else {
+            (&mut self.sol_reserve, &mut self.token_reserve)
+        };
+        *sol_bucket = sol_bucket.checked_add(excess[0]).ok_or(TokenExchangeError::MathOverflow)?;
+        *token_bucket = token_bucket.checked_add(excess[1]).ok_or(TokenExchangeError::MathOverflow)?;
+        Ok(excess)
+    }
+
+    /// Counts `sol_amount` and `token_amount` leaving the reserves at `slot`
+    /// against the per-slot outflow limit
+    /// 
+    /// Call before the amounts are taken out of the reserves. Each reserve may
+    /// lose at most `max_outflow_bps` of what it held before the slot's first
+    /// outflow (its current balance plus what already left this slot), so a
+    /// drain has to spread over many slots while an exploit is triaged.
+    pub fn record_outflow(&mut self, slot: u64, sol_amount: u64, token_amount: u64) -> Result<(), ProgramError> {
+        if self.outflow_slot != slot {
+            self.outflow_slot = slot;
+            self.outflow_this_slot = [0; 2];
+        }
+        let reserves = [self.sol_reserve, self.token_reserve];
+        for (side, amount) in [sol_amount, token_amount].into_iter().enumerate() {
+            let outflow = self.outflow_this_slot[side]
+                .checked_add(amount)
+                .ok_or(TokenExchangeError::MathOverflow)?;
+            let limit = (reserves[side] as u128 + self.outflow_this_slot[side] as u128)
+                * self.max_outflow_bps as u128
+                / 10000;
+            if self.max_outflow_bps > 0 && outflow as u128 > limit {
+                return Err(TokenExchangeError::OutflowLimitExceeded.into());
+            }
+            self.outflow_this_slot[side] = outflow;
+        }
+        Ok(())
+    }
+
+    /// Rejects a swap that moved the price by more than `max_price_impact_bps`
+    pub fn check_price_impact(&self, movement_bps: u64) -> Result<(), ProgramError> {
+        if self.max_price_impact_bps > 0 && movement_bps > self.max_price_impact_bps {
+            return Err(TokenExchangeError::PriceImpactTooHigh.into());
+        }
+        Ok(())
+    }
+
+    /// Rejects a swap executing more than `oracle_max_deviation_bps` from the oracle price
+    /// 
+    /// `oracle_price` is one token's price in SOL (or mint A) base units,
+    /// scaled by LIMIT_PRICE_SCALE; the swap executes at its SOL leg over its
+    /// token leg in the same units. Pools without an oracle accept any swap,
+    /// while a pool with one refuses swaps that come without its price.
+    pub fn check_oracle_band(
+        &self,
+        oracle_price: Option<u64>,
+        sol_amount: u64,
+        token_amount: u64,
+    ) -> Result<(), ProgramError> {
+        if self.price_oracle == Pubkey::default() {
+            return Ok(());
+        }
+        let oracle_price = oracle_price.ok_or(TokenExchangeError::InvalidOracle)? as u128;
+        let execution_price =
+            math::mul_div(sol_amount as u128, LIMIT_PRICE_SCALE as u128, token_amount as u128, math::Rounding::Down)
+                .ok_or(TokenExchangeError::OraclePriceDeviation)?;
+        if execution_price.abs_diff(oracle_price) * 10000 > oracle_price * self.oracle_max_deviation_bps as u128 {
+            return Err(TokenExchangeError::OraclePriceDeviation.into());
+        }
+        Ok(())
+    }
+
+    /// Rejects a swap on a bonding curve that has reached its graduation threshold
+    /// 
+    /// The swap that carries the SOL reserve past the threshold still
+    /// executes; after it the pool only trades again once `Graduate` has
+    /// turned it into a constant product pool.
+    pub fn check_bonding_curve(&self) -> Result<(), ProgramError> {
+        if self.graduation_threshold > 0 && self.sol_reserve >= self.graduation_threshold {
+            return Err(TokenExchangeError::BondingCurveComplete.into());
+        }
+        Ok(())
+    }
+
+    /// Token reserve a graduating bonding curve keeps, the rest being burned
+    /// 
+    /// Dropping the virtual reserves would move the price, so the constant
+    /// product pool keeps just the tokens that hold its price `S / T'` at the
+    /// curve's last price `(S + sol_offset) / (T + token_offset)`, rounded up
+    /// and at most the real token reserve.
+    pub fn graduated_token_reserve(&self) -> Result<u64, ProgramError> {
+        let sol_reserve = self.sol_reserve as u128;
+        let token_reserve = math::mul_div_wide(
+            sol_reserve,
+            self.token_reserve as u128 + self.token_offset as u128,
+            sol_reserve + self.sol_offset as u128,
+            math::Rounding::Up,
+        )
+        .ok_or(TokenExchangeError::MathOverflow)?;
+        Ok(token_reserve.min(self.token_reserve as u128) as u64)
+    }
+
+    /// Adds a swap's price movement, from `price_movement_bps`, to the
+    /// volatility accumulator after decaying it to `slot`
+    pub fn accumulate_volatility(&mut self, slot: u64, movement_bps: u64) {
+        self.volatility_accumulator = self.volatility_at(slot).saturating_add(movement_bps);
+        self.volatility_update_slot = slot;
+    }
+
+    /// Deserializes a pool state, rejecting data that does not start with
+    /// `DISCRIMINATOR` or is laid out in an older `StateVersion`
+    pub fn unpack(pool_data: &[u8]) -> Result<Self, ProgramError> {
+        if StateVersion::detect(pool_data)? != StateVersion::CURRENT {
+            return Err(TokenExchangeError::InvalidStateVersion.into());
+        }
+        let pool_state = Self::try_from_slice(pool_data)?;
+        if pool_state.discriminator != Self::DISCRIMINATOR {
+            return Err(TokenExchangeError::InvalidAccountDiscriminator.into());
+        }
+        Ok(pool_state)
+    }
+
+    /// Reads a pool's creator and creation slot from its account data
+    /// 
+    /// Intended for clients such as explorers and launch verification tools
+    /// that only need the pool's provenance.
+    pub fn read_creation(pool_data: &[u8]) -> Result<(Pubkey, u64), ProgramError> {
+        let pool_state = Self::unpack(pool_data)?;
+        if !pool_state.initialized {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok((pool_state.creator, pool_state.created_at_slot))
+    }
+}
+
+/// Zero-copy view of a pool state account's data
+/// 
+/// Mirrors the Borsh layout of `PoolState` byte for byte: packed, with bools
+/// and the curve type as single bytes and integers little-endian. Swaps read
+/// the pool through it and write back only the fields a swap changes,
+/// instead of deserializing and re-serializing the whole state.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C, packed)]
+pub struct PoolStatePod {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    pub authority: Pubkey,
+    pub sol_reserve: u64,
+    pub token_reserve: u64,
+    pub lp_mint: Pubkey,
+    pub fee_rate: u64,
+    pub token_mint: Pubkey,
+    pub initialized: u8,
+    pub keeper_bounty_bps: u64,
+    pub interest_bearing: u8,
+    pub rebasing: u8,
+    pub token_vault: Pubkey,
+    pub non_transferable_lp: u8,
+    pub deposit_permissioned: u8,
+    pub creator: Pubkey,
+    pub created_at_slot: u64,
+    pub deprecated: u8,
+    pub mint_a: Pubkey,
+    pub bump: u8,
+    pub sol_vault: Pubkey,
+    pub protocol_fee_share_bps: u64,
+    pub protocol_fee_sol: u64,
+    pub protocol_fee_token: u64,
+    pub wsol: u8,
+    pub fee_tier: u64,
+    pub paused: u8,
+    pub curve_type: u8,
+    pub amplification: u64,
+    pub token_offset: u64,
+    pub price_cumulative_last: u128,
+    pub last_update_slot: u64,
+    pub flash_loan_amount: u64,
+    pub flash_loan_sol: u8,
+    pub fee_on_transfer: u8,
+    pub cumulative_sol_volume: u128,
+    pub cumulative_token_volume: u128,
+    pub cumulative_fees: u128,
+    pub lp_supply: u64,
+    pub position_lp_supply: u64,
+    pub fee_growth_sol: u128,
+    pub fee_growth_token: u128,
+    pub lp_fees_sol: u64,
+    pub lp_fees_token: u64,
+    pub trade_start_timestamp: i64,
+    pub launch_window_seconds: u64,
+    pub max_swap_per_wallet_during_launch: u64,
+    pub launch_fee_bps: u64,
+    pub launch_fee_decay_seconds: u64,
+    pub volatility_accumulator: u64,
+    pub volatility_update_slot: u64,
+    pub variable_fee_control: u64,
+    pub max_variable_fee_bps: u64,
+    pub max_price_impact_bps: u64,
+    pub price_oracle: Pubkey,
+    pub oracle_max_deviation_bps: u64,
+    pub ema_price: u128,
+    pub ema_update_slot: u64,
+    pub weights: [u64; 2],
+    pub sol_offset: u64,
+    pub graduation_threshold: u64,
+    pub max_outflow_bps: u64,
+    pub outflow_slot: u64,
+    pub outflow_this_slot: [u64; 2],
+    pub emergency: u8,
+}
+
+impl PoolStatePod {
+    /// Views pool state data, rejecting data that does not start with
+    /// `PoolState::DISCRIMINATOR` or is laid out in an older `StateVersion`
+    pub fn load(pool_data: &[u8]) -> Result<&Self, ProgramError> {
+        if StateVersion::detect(pool_data)? != StateVersion::CURRENT {
+            return Err(TokenExchangeError::InvalidStateVersion.into());
+        }
+        bytemuck::try_from_bytes(pool_data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Mutably views pool state data, with the same checks as `load`
+    pub fn load_mut(pool_data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if StateVersion::detect(pool_data)? != StateVersion::CURRENT {
+            return Err(TokenExchangeError::InvalidStateVersion.into());
+        }
+        bytemuck::try_from_bytes_mut(pool_data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Copies the viewed state out, validating bools and the curve type as Borsh would
+    pub fn to_state(&self) -> Result<PoolState, ProgramError> {
+        let flag = |byte: u8| match byte {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ProgramError::InvalidAccountData),
+        };
+        let curve_type = match self.curve_type {
+            0 => CurveType::ConstantProduct,
+            1 => CurveType::Stable,
+            2 => CurveType::Offset,
+            3 => CurveType::Weighted,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(PoolState {
+            discriminator: self.discriminator,
+            version: self.version,
+            authority: self.authority,
+            sol_reserve: self.sol_reserve,
+            token_reserve: self.token_reserve,
+            lp_mint: self.lp_mint,
+            fee_rate: self.fee_rate,
+            token_mint: self.token_mint,
+            initialized: flag(self.initialized)?,
+            keeper_bounty_bps: self.keeper_bounty_bps,
+            interest_bearing: flag(self.interest_bearing)?,
+            rebasing: flag(self.rebasing)?,
+            token_vault: self.token_vault,
+            non_transferable_lp: flag(self.non_transferable_lp)?,
+            deposit_permissioned: flag(self.deposit_permissioned)?,
+            creator: self.creator,
+            created_at_slot: self.created_at_slot,
+            deprecated: flag(self.deprecated)?,
+            mint_a: self.mint_a,
+            bump: self.bump,
+            sol_vault: self.sol_vault,
+            protocol_fee_share_bps: self.protocol_fee_share_bps,
+            protocol_fee_sol: self.protocol_fee_sol,
+            protocol_fee_token: self.protocol_fee_token,
+            wsol: flag(self.wsol)?,
+            fee_tier: self.fee_tier,
+            paused: flag(self.paused)?,
+            curve_type,
+            amplification: self.amplification,
+            token_offset: self.token_offset,
+            price_cumulative_last: self.price_cumulative_last,
+            last_update_slot: self.last_update_slot,
+            flash_loan_amount: self.flash_loan_amount,
+            flash_loan_sol: flag(self.flash_loan_sol)?,
+            fee_on_transfer: flag(self.fee_on_transfer)?,
+            cumulative_sol_volume: self.cumulative_sol_volume,
+            cumulative_token_volume: self.cumulative_token_volume,
+            cumulative_fees: self.cumulative_fees,
+            lp_supply: self.lp_supply,
+            position_lp_supply: self.position_lp_supply,
+            fee_growth_sol: self.fee_growth_sol,
+            fee_growth_token: self.fee_growth_token,
+            lp_fees_sol: self.lp_fees_sol,
+            lp_fees_token: self.lp_fees_token,
+            trade_start_timestamp: self.trade_start_timestamp,
+            launch_window_seconds: self.launch_window_seconds,
+            max_swap_per_wallet_during_launch: self.max_swap_per_wallet_during_launch,
+            launch_fee_bps: self.launch_fee_bps,
+            launch_fee_decay_seconds: self.launch_fee_decay_seconds,
+            volatility_accumulator: self.volatility_accumulator,
+            volatility_update_slot: self.volatility_update_slot,
+            variable_fee_control: self.variable_fee_control,
+            max_variable_fee_bps: self.max_variable_fee_bps,
+            max_price_impact_bps: self.max_price_impact_bps,
+            price_oracle: self.price_oracle,
+            oracle_max_deviation_bps: self.oracle_max_deviation_bps,
+            ema_price: self.ema_price,
+            ema_update_slot: self.ema_update_slot,
+            weights: self.weights,
+            sol_offset: self.sol_offset,
+            graduation_threshold: self.graduation_threshold,
+            max_outflow_bps: self.max_outflow_bps,
+            outflow_slot: self.outflow_slot,
+            outflow_this_slot: self.outflow_this_slot,
+            emergency: flag(self.emergency)?,
+        })
+    }
+
+    /// Writes back the fields a swap changes: reserves, protocol fees, the
+    /// price accumulator, the cumulative totals, the position fee accounting
+    /// and the slot's outflow
+    pub fn store_swap(&mut self, pool_state: &PoolState) {
+        self.sol_reserve = pool_state.sol_reserve;
+        self.token_reserve = pool_state.token_reserve;
+        self.protocol_fee_sol = pool_state.protocol_fee_sol;
+        self.protocol_fee_token = pool_state.protocol_fee_token;
+        self.price_cumulative_last = pool_state.price_cumulative_last;
+        self.last_update_slot = pool_state.last_update_slot;
+        self.cumulative_sol_volume = pool_state.cumulative_sol_volume;
+        self.cumulative_token_volume = pool_state.cumulative_token_volume;
+        self.cumulative_fees = pool_state.cumulative_fees;
+        self.fee_growth_sol = pool_state.fee_growth_sol;
+        self.fee_growth_token = pool_state.fee_growth_token;
+        self.lp_fees_sol = pool_state.lp_fees_sol;
+        self.lp_fees_token = pool_state.lp_fees_token;
+        self.volatility_accumulator = pool_state.volatility_accumulator;
+        self.volatility_update_slot = pool_state.volatility_update_slot;
+        self.ema_price = pool_state.ema_price;
+        self.ema_update_slot = pool_state.ema_update_slot;
+        self.outflow_slot = pool_state.outflow_slot;
+        self.outflow_this_slot = pool_state.outflow_this_slot;
+    }
+}
+
+impl ProtocolStats {
+    /// Total size of the ProtocolStats structure when serialized
+    /// 
+    /// Breakdown:
+    /// - total_pools (u64): 8 bytes
+    /// - total_volume_sol (u128): 16 bytes
+    /// - total_fees_sol (u128): 16 bytes
+    /// - last_update_slot (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 8 + 16 + 16 + 8 + 1;
+
+    /// Derives the address of the program-wide statistics PDA
+    pub fn find_address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PROTOCOL_STATS_SEED], program_id)
+    }
+}
+
+impl ProtocolConfig {
+    /// Total size of the ProtocolConfig structure when serialized
+    /// 
+    /// Breakdown:
+    /// - authority (Pubkey): 32 bytes
+    /// - bump (u8): 1 byte
+    /// - parameters (ProtocolParameters): `ProtocolParameters::LEN` bytes
+    pub const LEN: usize = 32 + 1 + ProtocolParameters::LEN;
+
+    /// Derives the address of the program-wide protocol configuration PDA
+    pub fn find_address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[PROTOCOL_CONFIG_SEED], program_id)
+    }
+}
+
+impl ProtocolParameters {
+    /// Total size of the ProtocolParameters structure when serialized
+    /// 
+    /// Breakdown:
+    /// - default_fee_rate_bps (u64): 8 bytes
+    /// - max_fee_rate_bps (u64): 8 bytes
+    /// - protocol_fee_share_bps (u64): 8 bytes
+    /// - pool_creation_fee (u64): 8 bytes
+    /// - treasury (Pubkey): 32 bytes
+    /// - pool_creation_paused (bool): 1 byte
+    /// - fee_tier_count (u8): 1 byte
+    /// - fee_tiers ([u64; MAX_FEE_TIERS]): 64 bytes
+    /// - max_referral_fee_share_bps (u64): 8 bytes
+    /// - max_variable_fee_bps (u64): 8 bytes
+    /// - max_keeper_bounty_bps, max_keeper_tip_bps (u64): 16 bytes
+    /// - skim_excess_reserves (bool): 1 byte
+    /// - max_platform_fee_bps (u64): 8 bytes
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 32 + 1 + 1 + 8 * MAX_FEE_TIERS + 8 + 8 + 16 + 1 + 8;
+
+    /// The fee tiers new pools may be created with
+    pub fn offered_fee_tiers(&self) -> &[u64] {
+        &self.fee_tiers[..(self.fee_tier_count as usize).min(MAX_FEE_TIERS)]
+    }
+
+    /// Whether a new pool may charge `fee_rate`
+    pub fn allows_fee_tier(&self, fee_rate: u64) -> bool {
+        self.offered_fee_tiers().contains(&fee_rate)
+    }
+}
+
+impl Observation {
+    /// Serialized size of a single observation
+    /// 
+    /// Breakdown:
+    /// - slot (u64): 8 bytes
+    /// - price_cumulative (u128): 16 bytes
+    /// - initialized (bool): 1 byte
+    /// - sqrt_price (u64): 8 bytes
+    /// - liquidity (u64): 8 bytes
+    pub const LEN: usize = 8 + 16 + 1 + 8 + 8;
+
+    /// Computes the time-weighted average price between two observations
+    /// 
+    /// Returns the Q64.64 token price in SOL, or `None` if `newer` is not
+    /// strictly after `older`.
+    pub fn twap(older: &Observation, newer: &Observation) -> Option<u128> {
+        oracle::twap(older.price_cumulative, older.slot, newer.price_cumulative, newer.slot)
+    }
+}
+
+impl ObservationState {
+    /// Maximum number of observations a single account can hold
+    pub const MAX_CARDINALITY: u16 = 1024;
+
+    /// Size of the ObservationState account for a given cardinality
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - index (u16): 2 bytes
+    /// - bump (u8): 1 byte
+    /// - observations (Vec): 4-byte length prefix + cardinality * Observation::LEN
+    pub fn space(cardinality: u16) -> usize {
+        32 + 2 + 1 + 4 + cardinality as usize * Observation::LEN
+    }
+
+    /// Derives the observations PDA for a pool
+    pub fn find_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[OBSERVATIONS_SEED, pool.as_ref()], program_id)
+    }
+
+    /// Returns the most recently written observation
+    pub fn latest(&self) -> &Observation {
+        &self.observations[self.index as usize]
+    }
+
+    /// Returns the newest observation written at or before `slot`
+    /// 
+    /// Walks the ring buffer backwards from the latest entry, so callers can
+    /// pick the two endpoints of any TWAP window still held in the buffer.
+    pub fn observation_at_or_before(&self, slot: u64) -> Option<&Observation> {
+        let cardinality = self.observations.len();
+        (0..cardinality)
+            .map(|offset| &self.observations[(self.index as usize + cardinality - offset) % cardinality])
+            .filter(|observation| observation.initialized)
+            .find(|observation| observation.slot <= slot)
+    }
+
+    /// Time-weighted average price over the `window_slots` before the latest observation
+    /// 
+    /// Uses the newest observation at or before the window start, so the
+    /// window actually covered can be slightly longer. Returns the Q64.64
+    /// token price in SOL, or `None` if the buffer does not reach back that far.
+    pub fn twap_over(&self, window_slots: u64) -> Option<u128> {
+        let newer = self.latest();
+        let older = self.observation_at_or_before(newer.slot.checked_sub(window_slots)?)?;
+        Observation::twap(older, newer)
+    }
+
+    /// Records a new observation for the given slot from the pre-trade Q64.64
+    /// spot price and liquidity
+    /// 
+    /// Does nothing if an observation was already written in this slot or the
+    /// pool has no price.
+    pub fn write(&mut self, slot: u64, price: Option<u128>, liquidity: u64) {
+        let last = *self.latest();
+        let Some(price) = price.filter(|_| last.slot < slot) else {
+            return;
+        };
+
+        let elapsed = (slot - last.slot) as u128;
+        self.index = ((self.index as usize + 1) % self.observations.len()) as u16;
+        self.observations[self.index as usize] = Observation {
+            slot,
+            price_cumulative: last.price_cumulative.wrapping_add(price.wrapping_mul(elapsed)),
+            initialized: true,
+            sqrt_price: math::sqrt(price) as u64,
+            liquidity,
+        };
+    }
+}
+
+impl VolumeBucket {
+    /// Total size of a VolumeBucket when serialized
+    /// 
+    /// Breakdown:
+    /// - bucket (u64): 8 bytes
+    /// - volume_sol (u64): 8 bytes
+    /// - fees_sol (u64): 8 bytes
+    pub const LEN: usize = 8 + 8 + 8;
+}
+
+impl VolumeStats {
+    /// Total size of the VolumeStats structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - bump (u8): 1 byte
+    /// - buckets ([VolumeBucket; VOLUME_BUCKET_COUNT]): 24 * VolumeBucket::LEN bytes
+    pub const LEN: usize = 32 + 1 + VOLUME_BUCKET_COUNT * VolumeBucket::LEN;
+
+    /// Derives the volume stats PDA for a pool
+    pub fn find_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VOLUME_STATS_SEED, pool.as_ref()], program_id)
+    }
+
+    /// Adds a swap's volume and fee to the bucket containing `slot`
+    pub fn record(&mut self, slot: u64, volume_sol: u64, fees_sol: u64) {
+        let bucket = slot / VOLUME_BUCKET_SLOTS;
+        let entry = &mut self.buckets[(bucket % VOLUME_BUCKET_COUNT as u64) as usize];
+        if entry.bucket != bucket {
+            *entry = VolumeBucket {
+                bucket,
+                ..VolumeBucket::default()
+            };
+        }
+        entry.volume_sol = entry.volume_sol.saturating_add(volume_sol);
+        entry.fees_sol = entry.fees_sol.saturating_add(fees_sol);
+    }
+
+    /// Volume and fees over the last `VOLUME_BUCKET_COUNT` buckets up to `current_slot`
+    /// 
+    /// Dividing the fees by the pool's SOL-valued liquidity and scaling by
+    /// 365 gives the pool's trailing fee APR.
+    pub fn trailing_totals(&self, current_slot: u64) -> (u128, u128) {
+        let current_bucket = current_slot / VOLUME_BUCKET_SLOTS;
+        self.buckets
+            .iter()
+            .filter(|entry| {
+                entry.bucket <= current_bucket
+                    && current_bucket - entry.bucket < VOLUME_BUCKET_COUNT as u64
+                    && (entry.volume_sol > 0 || entry.fees_sol > 0)
+            })
+            .fold((0, 0), |(volume, fees), entry| {
+                (volume + entry.volume_sol as u128, fees + entry.fees_sol as u128)
+            })
+    }
+}
+
+impl LimitOrder {
+    /// Total size of the LimitOrder structure when serialized
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - pool (Pubkey): 32 bytes
+    /// - order_id (u64): 8 bytes
+    /// - is_sol_input (bool): 1 byte
+    /// - limit_price (u64): 8 bytes
+    /// - original_amount (u64): 8 bytes
+    /// - remaining_amount (u64): 8 bytes
+    /// - filled_output (u64): 8 bytes
+    /// - expiry_slot (u64): 8 bytes
+    /// - order_type (OrderType): 1 byte
+    /// - trigger_price (u64): 8 bytes
+    /// - oracle (Pubkey): 32 bytes
+    /// - trigger_below (bool): 1 byte
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 32 + 1 + 1;
+
+    /// Derives the PDA of an owner's limit order on a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        order_id: u64,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[LIMIT_ORDER_SEED, pool.as_ref(), owner.as_ref(), &order_id.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Whether the order may execute at the given spot price (output per input, scaled)
+    /// 
+    /// Limit orders are always armed; stop-loss orders arm once the spot
+    /// price falls to their trigger price. Oracle-conditioned orders never
+    /// arm from the pool price alone.
+    pub fn is_triggered(&self, spot_price: u64) -> bool {
+        match self.order_type {
+            OrderType::Limit => true,
+            OrderType::StopLoss => spot_price <= self.trigger_price,
+            OrderType::OracleConditioned => false,
+        }
+    }
+
+    /// Whether an oracle-conditioned order may execute at the given oracle price
+    /// 
+    /// `oracle_price` uses the same LIMIT_PRICE_SCALE fixed point as `trigger_price`.
+    pub fn is_oracle_triggered(&self, oracle_price: u64) -> bool {
+        self.order_type == OrderType::OracleConditioned
+            && if self.trigger_below {
+                oracle_price <= self.trigger_price
+            } else {
+                oracle_price >= self.trigger_price
+            }
+    }
+
+    /// Whether the order has passed its expiry slot
+    pub fn is_expired(&self, current_slot: u64) -> bool {
+        self.expiry_slot != 0 && current_slot > self.expiry_slot
+    }
+
+    /// Minimum output required to fill `input_amount` at the limit price (rounded up)
+    pub fn required_output(&self, input_amount: u64) -> Option<u64> {
+        let required = (input_amount as u128)
+            .checked_mul(self.limit_price as u128)?
+            .checked_add(LIMIT_PRICE_SCALE as u128 - 1)?
+            / LIMIT_PRICE_SCALE as u128;
+        u64::try_from(required).ok()
+    }
+}
+
+impl LiquiditySchedule {
+    /// Total size of the LiquiditySchedule structure when serialized
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - pool (Pubkey): 32 bytes
+    /// - schedule_id, sol_per_tranche, token_per_tranche (u64): 24 bytes
+    /// - interval_slots, next_tranche_slot, tranches_remaining (u64): 24 bytes
+    /// - sol_remaining, token_remaining (u64): 16 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 32 + 24 + 24 + 16 + 1;
+
+    /// Derives the PDA of an owner's liquidity schedule on a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        schedule_id: u64,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[LIQUIDITY_SCHEDULE_SEED, pool.as_ref(), owner.as_ref(), &schedule_id.to_le_bytes()],
+            program_id,
+        )
+    }
+}
+
+impl DcaPosition {
+    /// Total size of the DcaPosition structure when serialized
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - pool (Pubkey): 32 bytes
+    /// - dca_id (u64): 8 bytes
+    /// - is_sol_input (bool): 1 byte
+    /// - amount_per_fill, interval_slots, next_fill_slot (u64): 24 bytes
+    /// - remaining_amount, filled_output (u64): 16 bytes
+    /// - limit_price, keeper_tip_bps (u64): 16 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 24 + 16 + 16 + 1;
+
+    /// Derives the PDA of an owner's DCA position on a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        dca_id: u64,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[DCA_POSITION_SEED, pool.as_ref(), owner.as_ref(), &dca_id.to_le_bytes()],
+            program_id,
+        )
+    }
+
+    /// Input the next tranche sells, keeper tip included
+    /// 
+    /// The last tranche takes whatever is left when the escrow does not
+    /// divide evenly.
+    pub fn next_fill_amount(&self) -> u64 {
+        std::cmp::min(self.amount_per_fill, self.remaining_amount)
+    }
+
+    /// Keeper's share of a tranche of `fill_amount` input (rounded down)
+    pub fn keeper_tip(&self, fill_amount: u64) -> u64 {
+        (fill_amount as u128 * self.keeper_tip_bps as u128 / 10000) as u64
+    }
+
+    /// Minimum output required for `input_amount` at the floor price (rounded up)
+    pub fn required_output(&self, input_amount: u64) -> Option<u64> {
+        let required = (input_amount as u128)
+            .checked_mul(self.limit_price as u128)?
+            .checked_add(LIMIT_PRICE_SCALE as u128 - 1)?
+            / LIMIT_PRICE_SCALE as u128;
+        u64::try_from(required).ok()
+    }
+}
+
+impl TradingAllowance {
+    /// Maximum number of pools a single allowance can list
+    pub const MAX_POOLS: usize = 8;
+
+    /// Size of the TradingAllowance account for a given number of pools
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - operator (Pubkey): 32 bytes
+    /// - max_notional_per_window, window_slots (u64): 16 bytes
+    /// - window_start_slot, window_notional (u64): 16 bytes
+    /// - bump (u8): 1 byte
+    /// - allowed_pools (Vec): 4-byte length prefix + pool_count * 32 bytes
+    pub fn space(pool_count: usize) -> usize {
+        32 + 32 + 16 + 16 + 1 + 4 + pool_count * 32
+    }
+
+    /// Derives the PDA of an owner's allowance for an operator
+    pub fn find_address(owner: &Pubkey, operator: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[TRADING_ALLOWANCE_SEED, owner.as_ref(), operator.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Charges `notional` lamports against the current window
+    /// 
+    /// Starts a fresh window once `window_slots` have elapsed. Returns
+    /// `false`, leaving the allowance untouched, if the charge would exceed
+    /// the window's cap.
+    pub fn consume(&mut self, current_slot: u64, notional: u64) -> bool {
+        let (window_start_slot, spent) =
+            if current_slot.saturating_sub(self.window_start_slot) >= self.window_slots {
+                (current_slot, 0)
+            } else {
+                (self.window_start_slot, self.window_notional)
+            };
+
+        match spent.checked_add(notional) {
+            Some(total) if total <= self.max_notional_per_window => {
+                self.window_start_slot = window_start_slot;
+                self.window_notional = total;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl KeeperRegistration {
+    /// Total size of the KeeperRegistration structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - keeper (Pubkey): 32 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 32 + 1;
+
+    /// Derives the PDA registering a keeper with a pool
+    pub fn find_address(pool: &Pubkey, keeper: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[KEEPER_REGISTRATION_SEED, pool.as_ref(), keeper.as_ref()],
+            program_id,
+        )
+    }
+}
+
+impl LpAllowlistEntry {
+    /// Total size of the LpAllowlistEntry structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - provider (Pubkey): 32 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 32 + 1;
+
+    /// Derives the PDA allowlisting a provider on a pool
+    pub fn find_address(pool: &Pubkey, provider: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[LP_ALLOWLIST_SEED, pool.as_ref(), provider.as_ref()],
+            program_id,
+        )
+    }
+}
+
+impl PoolMetadata {
+    /// Total size of the PoolMetadata structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - name ([u8; POOL_NAME_MAX_LEN]): 32 bytes
+    /// - uri ([u8; POOL_URI_MAX_LEN]): 200 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + POOL_NAME_MAX_LEN + POOL_URI_MAX_LEN + 1;
+
+    /// Derives the metadata PDA for a pool
+    pub fn find_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[POOL_METADATA_SEED, pool.as_ref()], program_id)
+    }
+
+    /// Builds metadata for a pool, or `None` if the name or URI is too long
+    pub fn new(pool: Pubkey, name: &str, uri: &str, bump: u8) -> Option<Self> {
+        Some(Self {
+            pool,
+            name: Self::pad(name)?,
+            uri: Self::pad(uri)?,
+            bump,
+        })
+    }
+
+    /// The pool's short name
+    pub fn name(&self) -> &str {
+        Self::unpad(&self.name)
+    }
+
+    /// The URI of the pool's off-chain details
+    pub fn uri(&self) -> &str {
+        Self::unpad(&self.uri)
+    }
+
+    /// Copies `value` into a zero-padded field, if it fits
+    fn pad<const N: usize>(value: &str) -> Option<[u8; N]> {
+        let bytes = value.as_bytes();
+        if bytes.len() > N {
+            return None;
+        }
+        let mut field = [0u8; N];
+        field[..bytes.len()].copy_from_slice(bytes);
+        Some(field)
+    }
+
+    /// Reads a zero-padded field back as a string
+    fn unpad(field: &[u8]) -> &str {
+        let len = field.iter().position(|byte| *byte == 0).unwrap_or(field.len());
+        std::str::from_utf8(&field[..len]).unwrap_or_default()
+    }
+}
+
+impl PoolRegistry {
+    /// Total size of the PoolRegistry structure when serialized
+    /// 
+    /// Breakdown:
+    /// - pool_count (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 8 + 1;
+
+    /// Derives the address of the program-wide pool registry PDA
+    pub fn find_address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[POOL_REGISTRY_SEED], program_id)
+    }
+}
+
+impl PoolRegistryEntry {
+    /// Total size of the PoolRegistryEntry structure when serialized
+    /// 
+    /// Breakdown:
+    /// - index (u64): 8 bytes
+    /// - pool (Pubkey): 32 bytes
+    /// - mint_a (Pubkey): 32 bytes
+    /// - token_mint (Pubkey): 32 bytes
+    /// - fee_tier (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 1;
+
+    /// Derives the registry entry PDA of the pool created `index`-th
+    pub fn find_address(index: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[POOL_REGISTRY_ENTRY_SEED, &index.to_le_bytes()], program_id)
+    }
+}
+
+impl UserStats {
+    /// Total size of the UserStats structure when serialized
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - signed_order_nonce (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 32 + 8 + 1;
+
+    /// Derives the stats PDA for a user
+    pub fn find_address(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[USER_STATS_SEED, owner.as_ref()], program_id)
+    }
+}
+
+impl SealedOrderCommittee {
+    /// Maximum number of members a committee can have
+    pub const MAX_MEMBERS: usize = 10;
+
+    /// Size of the SealedOrderCommittee account for a given number of members
+    /// 
+    /// Breakdown:
+    /// - pool (Pubkey): 32 bytes
+    /// - threshold (u8): 1 byte
+    /// - reveal_delay_slots (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    /// - members (Vec): 4-byte length prefix + member_count * 32 bytes
+    pub fn space(member_count: usize) -> usize {
+        32 + 1 + 8 + 1 + 4 + member_count * 32
+    }
+
+    /// Derives the sealed order committee PDA for a pool
+    pub fn find_address(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[SEALED_COMMITTEE_SEED, pool.as_ref()], program_id)
+    }
+}
+
+impl SealedOrder {
+    /// Maximum ciphertext size a sealed order can carry
+    pub const MAX_CIPHERTEXT_LEN: usize = 512;
+
+    /// Size of the SealedOrder account for a given ciphertext length
+    /// 
+    /// Breakdown:
+    /// - owner (Pubkey): 32 bytes
+    /// - pool (Pubkey): 32 bytes
+    /// - order_id (u64): 8 bytes
+    /// - is_sol_input (bool): 1 byte
+    /// - escrow_amount (u64): 8 bytes
+    /// - commitment ([u8; 32]): 32 bytes
+    /// - reveal_slot (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    /// - ciphertext (Vec): 4-byte length prefix + ciphertext_len bytes
+    pub fn space(ciphertext_len: usize) -> usize {
+        32 + 32 + 8 + 1 + 8 + 32 + 8 + 1 + 4 + ciphertext_len
+    }
+
+    /// Derives the PDA of an owner's sealed order on a pool
+    pub fn find_address(
+        pool: &Pubkey,
+        owner: &Pubkey,
+        order_id: u64,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[SEALED_ORDER_SEED, pool.as_ref(), owner.as_ref(), &order_id.to_le_bytes()],
+            program_id,
+        )
+    }
+}
+
+impl SealedOrderTerms {
+    /// Commitment stored with the sealed order: SHA-256 over the terms
+    pub fn commitment(&self) -> [u8; 32] {
+        hashv(&[
+            &self.amount_in.to_le_bytes(),
+            &self.minimum_amount_out.to_le_bytes(),
+            &self.salt,
+        ])
+        .to_bytes()
+    }
 }
 
 impl UserPosition {
+    /// Tag leading every user position account's data
+    pub const DISCRIMINATOR: [u8; 8] = *b"userposn";
+
     /// Total size of the UserPosition structure when serialized
     /// 
     /// Breakdown:
+    /// - discriminator ([u8; 8]): 8 bytes
     /// - owner (Pubkey): 32 bytes
+    /// - pool (Pubkey): 32 bytes
     /// - lp_tokens (u64): 8 bytes
-    pub const LEN: usize = 32 + 8;
+    /// - fee_growth_checkpoint_sol (u128): 16 bytes
+    /// - fee_growth_checkpoint_token (u128): 16 bytes
+    /// - fees_owed_sol (u64): 8 bytes
+    /// - fees_owed_token (u64): 8 bytes
+    /// - bump (u8): 1 byte
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 16 + 16 + 8 + 8 + 1;
+
+    /// Derives the position PDA of an owner in a pool
+    pub fn find_address(pool: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[POSITION_SEED, pool.as_ref(), owner.as_ref()], program_id)
+    }
+
+    /// Recomputes the position's address from its seeds and stored bump
+    pub fn address(&self, program_id: &Pubkey) -> Result<Pubkey, ProgramError> {
+        Pubkey::create_program_address(
+            &[POSITION_SEED, self.pool.as_ref(), self.owner.as_ref(), &[self.bump]],
+            program_id,
+        )
+        .map_err(|_| ProgramError::InvalidSeeds)
+    }
+
+    /// Moves the fees earned since the last checkpoint into `fees_owed_*`
+    /// 
+    /// Each side earns `lp_tokens` times the growth since the checkpoint,
+    /// rounded down. Growth wraps, so the difference is taken wrapping as
+    /// well. Must run before `lp_tokens` changes, so the old balance earns
+    /// the fees up to now.
+    pub fn settle(&mut self, pool_state: &PoolState) -> Result<(), ProgramError> {
+        let earned = |growth: u128, checkpoint: u128| {
+            math::mul_div(self.lp_tokens as u128, growth.wrapping_sub(checkpoint), 1 << 64, math::Rounding::Down)
+                .and_then(|fees| u64::try_from(fees).ok())
+                .ok_or(TokenExchangeError::MathOverflow)
+        };
+        let earned_sol = earned(pool_state.fee_growth_sol, self.fee_growth_checkpoint_sol)?;
+        let earned_token = earned(pool_state.fee_growth_token, self.fee_growth_checkpoint_token)?;
+        self.fees_owed_sol = self.fees_owed_sol.checked_add(earned_sol)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.fees_owed_token = self.fees_owed_token.checked_add(earned_token)
+            .ok_or(TokenExchangeError::MathOverflow)?;
+        self.fee_growth_checkpoint_sol = pool_state.fee_growth_sol;
+        self.fee_growth_checkpoint_token = pool_state.fee_growth_token;
+        Ok(())
+    }
+
+    /// Deserializes a user position, rejecting data that does not start with `DISCRIMINATOR`
+    pub fn unpack(position_data: &[u8]) -> Result<Self, ProgramError> {
+        let position = Self::try_from_slice(position_data)?;
+        if position.discriminator != Self::DISCRIMINATOR {
+            return Err(TokenExchangeError::InvalidAccountDiscriminator.into());
+        }
+        Ok(position)
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,231 @@
+// Token Program Interface
+// This module builds token CPIs and reads token accounts for both SPL Token and Token-2022
+
+use solana_program::{
+    instruction::Instruction,                                  // CPI instruction type
+    program_error::ProgramError,                               // Error handling
+    program_pack::Pack,                                        // Account packing
+    pubkey,                                                    // Public key literal macro
+    pubkey::Pubkey,                                            // Public key type
+};
+use spl_token::state::{Account, Mint};                         // Base token account layouts
+
+use crate::math::{self, Rounding, Q64};                        // Fixed-point powers of two
+
+/// Program ID of the Token-2022 program
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Offset of the account type byte in Token-2022 accounts with extensions
+const ACCOUNT_TYPE_OFFSET: usize = Account::LEN;
+
+/// Token-2022 account type tag for mints
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+/// Token-2022 extension type marking a mint's tokens as non-transferable
+const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+
+/// Token-2022 extension type of the interest-bearing mint config
+const EXTENSION_INTEREST_BEARING_CONFIG: u16 = 10;
+
+/// Size of an interest-bearing config: rate authority, two timestamps and two rates
+const INTEREST_BEARING_CONFIG_LEN: usize = 32 + 8 + 2 + 8 + 2;
+
+/// Seconds per year used by Token-2022 interest accrual (365.24 days)
+const SECONDS_PER_YEAR: i128 = 60 * 60 * 24 * 36524 / 100;
+
+/// log2(e) in Q64.64, turning natural exponents into powers of two
+const LOG2_E: i128 = 0x1_7154_7652_B82F_E177;
+
+/// Rejects token programs other than SPL Token and Token-2022
+pub fn check_token_program(token_program_id: &Pubkey) -> Result<(), ProgramError> {
+    if *token_program_id != spl_token::id() && *token_program_id != TOKEN_2022_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Creates a `Transfer` instruction for either token program
+pub fn transfer(
+    token_program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_token_program(token_program_id)?;
+    let instruction = spl_token::instruction::transfer(
+        &spl_token::id(),
+        source_pubkey,
+        destination_pubkey,
+        authority_pubkey,
+        signer_pubkeys,
+        amount,
+    )?;
+    Ok(with_program_id(instruction, token_program_id))
+}
+
+/// Creates a `MintTo` instruction for either token program
+pub fn mint_to(
+    token_program_id: &Pubkey,
+    mint_pubkey: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_token_program(token_program_id)?;
+    let instruction = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint_pubkey,
+        account_pubkey,
+        owner_pubkey,
+        signer_pubkeys,
+        amount,
+    )?;
+    Ok(with_program_id(instruction, token_program_id))
+}
+
+/// Creates a `Burn` instruction for either token program
+pub fn burn(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    check_token_program(token_program_id)?;
+    let instruction = spl_token::instruction::burn(
+        &spl_token::id(),
+        account_pubkey,
+        mint_pubkey,
+        authority_pubkey,
+        signer_pubkeys,
+        amount,
+    )?;
+    Ok(with_program_id(instruction, token_program_id))
+}
+
+/// Creates a `CloseAccount` instruction for either token program
+pub fn close_account(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_token_program(token_program_id)?;
+    let instruction = spl_token::instruction::close_account(
+        &spl_token::id(),
+        account_pubkey,
+        destination_pubkey,
+        owner_pubkey,
+        signer_pubkeys,
+    )?;
+    Ok(with_program_id(instruction, token_program_id))
+}
+
+/// Points an SPL Token instruction at `token_program_id`
+/// 
+/// Token-2022 shares SPL Token's encoding for these instructions.
+fn with_program_id(mut instruction: Instruction, token_program_id: &Pubkey) -> Instruction {
+    instruction.program_id = *token_program_id;
+    instruction
+}
+
+/// Unpacks the base state of a token account, ignoring any Token-2022 extensions
+pub fn unpack_token_account(data: &[u8]) -> Result<Account, ProgramError> {
+    let base = data.get(..Account::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    Account::unpack(base)
+}
+
+/// Unpacks the base state of a mint, ignoring any Token-2022 extensions
+pub fn unpack_mint(data: &[u8]) -> Result<Mint, ProgramError> {
+    let base = data.get(..Mint::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    Mint::unpack(base)
+}
+
+/// Returns the value of a Token-2022 mint extension, if the mint carries it
+/// 
+/// Walks the type-length-value entries that follow the account type byte.
+fn mint_extension(data: &[u8], extension_type: u16) -> Option<&[u8]> {
+    if data.get(ACCOUNT_TYPE_OFFSET) != Some(&ACCOUNT_TYPE_MINT) {
+        return None;
+    }
+
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    while let Some(header) = data.get(offset..offset + 4) {
+        let entry_type = u16::from_le_bytes([header[0], header[1]]);
+        let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let value = data.get(offset + 4..offset + 4 + length)?;
+        if entry_type == extension_type {
+            return Some(value);
+        }
+        if entry_type == 0 {
+            return None;
+        }
+        offset += 4 + length;
+    }
+    None
+}
+
+/// Whether a Token-2022 mint carries the non-transferable extension
+/// 
+/// Holders of such a mint can only burn their tokens, never transfer them.
+pub fn is_non_transferable_mint(mint_data: &[u8]) -> bool {
+    mint_extension(mint_data, EXTENSION_NON_TRANSFERABLE).is_some()
+}
+
+/// Interest accrual parameters of a Token-2022 interest-bearing mint
+/// 
+/// Interest is purely presentational: raw balances never change, while the
+/// UI amount grows by `scale` through continuous compounding.
+#[derive(Debug, Clone, Copy)]
+pub struct InterestBearingConfig {
+    pub initialization_timestamp: i64,  // Unix time interest started accruing
+    pub pre_update_average_rate: i16,   // Average rate before the last update, in basis points
+    pub last_update_timestamp: i64,     // Unix time of the last rate update
+    pub current_rate: i16,              // Rate since the last update, in basis points
+}
+
+impl InterestBearingConfig {
+    /// Loads the interest-bearing config from a mint's data, if present
+    pub fn load(mint_data: &[u8]) -> Option<Self> {
+        let value = mint_extension(mint_data, EXTENSION_INTEREST_BEARING_CONFIG)?;
+        if value.len() != INTEREST_BEARING_CONFIG_LEN {
+            return None;
+        }
+
+        Some(Self {
+            initialization_timestamp: i64::from_le_bytes(value[32..40].try_into().ok()?),
+            pre_update_average_rate: i16::from_le_bytes(value[40..42].try_into().ok()?),
+            last_update_timestamp: i64::from_le_bytes(value[42..50].try_into().ok()?),
+            current_rate: i16::from_le_bytes(value[50..52].try_into().ok()?),
+        })
+    }
+
+    /// Factor converting raw amounts to UI amounts (before decimals) at
+    /// `unix_timestamp`, in Q64.64 rounded down, or `None` on overflow
+    /// 
+    /// The continuously compounded `e^(rate * t)` is taken as a power of two
+    /// through the integer `math::exp2`, so every validator agrees on it.
+    pub fn scale(&self, unix_timestamp: i64) -> Option<u128> {
+        // Accrued interest as a base-2 exponent in Q64.64
+        let accrued = |rate: i16, from: i64, to: i64| {
+            let rate_seconds = rate as i128 * to.saturating_sub(from) as i128;
+            Some(rate_seconds.checked_mul(LOG2_E)?.div_euclid(SECONDS_PER_YEAR * 10_000))
+        };
+        let before_update =
+            accrued(self.pre_update_average_rate, self.initialization_timestamp, self.last_update_timestamp)?;
+        let since_update = accrued(self.current_rate, self.last_update_timestamp, unix_timestamp)?;
+        math::exp2(before_update.checked_add(since_update)?)
+    }
+
+    /// UI amount (before decimals) of a raw `amount` at `unix_timestamp`,
+    /// rounded down, or `None` when it overflows a u64
+    pub fn ui_amount(&self, amount: u64, unix_timestamp: i64) -> Option<u64> {
+        let ui_amount = math::mul_div(amount as u128, self.scale(unix_timestamp)?, Q64, Rounding::Down)?;
+        u64::try_from(ui_amount).ok()
+    }
+}
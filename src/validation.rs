@@ -0,0 +1,302 @@
+// Account Validation
+// This module checks the accounts passed to every handler against the pool state before they are trusted
+
+use borsh::BorshDeserialize;                                   // Account deserialization
+use solana_program::{
+    account_info::AccountInfo,                                 // Account handling
+    entrypoint::ProgramResult,                                 // Program result type
+    program_error::ProgramError,                               // Error handling
+    pubkey::Pubkey,                                            // Public key type
+    system_program,                                            // System program ID
+};
+
+use crate::{
+    bin_pool::{BinPool, BinPosition, BinState},                // Bin liquidity pools
+    concentrated::{ConcentratedPool, ConcentratedPosition, TickState}, // Concentrated liquidity pools
+    error::TokenExchangeError,                                 // Custom errors
+    farm::{FarmStake, FarmState},                              // LP reward farms
+    state::{PoolState, PoolStatePod, UserPosition},            // Pool state, its zero-copy view and LP positions
+    token_interface,                                           // SPL Token and Token-2022
+};
+
+/// Loads a pool's state after verifying the account is a genuine pool PDA
+/// 
+/// The account must be owned by the program and sit at the address derived
+/// from its own mints, fee rate and stored bump, so a forged account
+/// carrying pool-shaped data is rejected. The state is read through the
+/// zero-copy `PoolStatePod` view rather than deserialized.
+/// 
+/// A pool with a flash loan outstanding is rejected with `FlashLoanActive`,
+/// so nothing can price against or withdraw from its lent-out reserves.
+pub fn load_pool(pool_account: &AccountInfo, program_id: &Pubkey) -> Result<PoolState, ProgramError> {
+    let pool_state = load_pool_during_flash_loan(pool_account, program_id)?;
+    if pool_state.flash_loan_amount > 0 {
+        return Err(TokenExchangeError::FlashLoanActive.into());
+    }
+    Ok(pool_state)
+}
+
+/// Loads a pool's state like `load_pool`, even while a flash loan is outstanding
+/// 
+/// Only `FlashRepay` may use this.
+pub fn load_pool_during_flash_loan(pool_account: &AccountInfo, program_id: &Pubkey) -> Result<PoolState, ProgramError> {
+    if pool_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let pool_state = PoolStatePod::load(&pool_account.data.borrow())?.to_state()?;
+    if pool_state.address(program_id)? != *pool_account.key {
+        return Err(TokenExchangeError::InvalidPoolAccount.into());
+    }
+    Ok(pool_state)
+}
+
+/// Verifies the pool token account passed to an operation is the pool's vault
+/// 
+/// The vault must hold the pool's token mint and be owned by the pool
+/// account; otherwise a swap could deliver its input to an account the
+/// trader controls while still being paid from the pool.
+/// 
+/// Rebasing tokens change balances out-of-band, so in rebasing mode the
+/// vault must also be the pinned one, and its balance replaces the stored
+/// reserve before any pricing.
+/// 
+/// # Arguments
+/// * `pool_state` - Pool state to update
+/// * `pool` - The pool state account's address
+/// * `pool_token_account` - The pool token account passed to the operation
+pub fn load_pool_vault(
+    pool_state: &mut PoolState,
+    pool: &Pubkey,
+    pool_token_account: &AccountInfo,
+) -> ProgramResult {
+    let vault = token_interface::unpack_token_account(&pool_token_account.data.borrow())?;
+    if vault.owner != *pool || vault.mint != pool_state.token_mint {
+        return Err(TokenExchangeError::InvalidTokenVault.into());
+    }
+
+    if pool_state.rebasing {
+        if *pool_token_account.key != pool_state.token_vault {
+            return Err(TokenExchangeError::InvalidTokenVault.into());
+        }
+        pool_state.token_reserve = vault.amount
+            .saturating_sub(pool_state.protocol_fee_token)
+            .saturating_sub(pool_state.lp_fees_token);
+    }
+    Ok(())
+}
+
+/// Verifies a token pair pool's side A vault
+/// 
+/// The vault must be a token account owned by the pool that holds mint A,
+/// under the same token program as the pool's token.
+pub fn check_pool_vault_a(
+    pool_state: &PoolState,
+    pool: &Pubkey,
+    pool_vault_a: &AccountInfo,
+    token_program_id: &Pubkey,
+) -> ProgramResult {
+    if pool_vault_a.owner != token_program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let vault = token_interface::unpack_token_account(&pool_vault_a.data.borrow())?;
+    if vault.owner != *pool || vault.mint != pool_state.mint_a {
+        return Err(TokenExchangeError::InvalidTokenVault.into());
+    }
+    Ok(())
+}
+
+/// Verifies the SOL vault passed to an operation is the pool's
+pub fn check_sol_vault(pool_state: &PoolState, sol_vault: &AccountInfo) -> ProgramResult {
+    if *sol_vault.key != pool_state.sol_vault {
+        return Err(TokenExchangeError::InvalidSolVault.into());
+    }
+    Ok(())
+}
+
+/// Loads an owner's position in a pool after verifying it is their position PDA
+/// 
+/// The account must be owned by the program, record the given pool and
+/// owner, and sit at the address derived from them and its stored bump.
+pub fn load_user_position(
+    position_account: &AccountInfo,
+    pool: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<UserPosition, ProgramError> {
+    if position_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidUserPosition.into());
+    }
+    let position = UserPosition::unpack(&position_account.data.borrow())?;
+    if position.pool != *pool || position.owner != *owner || position.address(program_id)? != *position_account.key {
+        return Err(TokenExchangeError::InvalidUserPosition.into());
+    }
+    Ok(position)
+}
+
+/// Loads a farm after verifying the account is a genuine farm PDA
+pub fn load_farm(farm_account: &AccountInfo, program_id: &Pubkey) -> Result<FarmState, ProgramError> {
+    if farm_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidFarm.into());
+    }
+    let farm = FarmState::try_from_slice(&farm_account.data.borrow())?;
+    if farm.address(program_id)? != *farm_account.key {
+        return Err(TokenExchangeError::InvalidFarm.into());
+    }
+    Ok(farm)
+}
+
+/// Loads an owner's stake in a farm after verifying it is their stake PDA
+pub fn load_farm_stake(
+    stake_account: &AccountInfo,
+    farm: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<FarmStake, ProgramError> {
+    if stake_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidFarm.into());
+    }
+    let stake = FarmStake::try_from_slice(&stake_account.data.borrow())?;
+    if stake.farm != *farm || stake.owner != *owner || stake.address(program_id)? != *stake_account.key {
+        return Err(TokenExchangeError::InvalidFarm.into());
+    }
+    Ok(stake)
+}
+
+/// Loads a concentrated liquidity pool after verifying the account is a genuine pool PDA
+pub fn load_concentrated_pool(
+    pool_account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<ConcentratedPool, ProgramError> {
+    if pool_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidConcentratedPool.into());
+    }
+    let pool = ConcentratedPool::try_from_slice(&pool_account.data.borrow())?;
+    if pool.address(program_id)? != *pool_account.key {
+        return Err(TokenExchangeError::InvalidConcentratedPool.into());
+    }
+    Ok(pool)
+}
+
+/// Loads a pool's tick after verifying it is the PDA of that tick
+pub fn load_tick(
+    tick_account: &AccountInfo,
+    pool: &Pubkey,
+    index: i32,
+    program_id: &Pubkey,
+) -> Result<TickState, ProgramError> {
+    if tick_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidTickList.into());
+    }
+    let tick = TickState::try_from_slice(&tick_account.data.borrow())?;
+    if tick.pool != *pool || tick.index != index || tick.address(program_id)? != *tick_account.key {
+        return Err(TokenExchangeError::InvalidTickList.into());
+    }
+    Ok(tick)
+}
+
+/// Loads an owner's concentrated liquidity position after verifying it is their position PDA
+pub fn load_concentrated_position(
+    position_account: &AccountInfo,
+    pool: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<ConcentratedPosition, ProgramError> {
+    if position_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidConcentratedPool.into());
+    }
+    let position = ConcentratedPosition::try_from_slice(&position_account.data.borrow())?;
+    if position.pool != *pool || position.owner != *owner || position.address(program_id)? != *position_account.key {
+        return Err(TokenExchangeError::InvalidConcentratedPool.into());
+    }
+    Ok(position)
+}
+
+/// Loads a bin pool after verifying the account is a genuine pool PDA
+pub fn load_bin_pool(pool_account: &AccountInfo, program_id: &Pubkey) -> Result<BinPool, ProgramError> {
+    if pool_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidBinPool.into());
+    }
+    let pool = BinPool::try_from_slice(&pool_account.data.borrow())?;
+    if pool.address(program_id)? != *pool_account.key {
+        return Err(TokenExchangeError::InvalidBinPool.into());
+    }
+    Ok(pool)
+}
+
+/// Loads a pool's bin after verifying it is the PDA of that bin
+pub fn load_bin(
+    bin_account: &AccountInfo,
+    pool: &Pubkey,
+    id: i32,
+    program_id: &Pubkey,
+) -> Result<BinState, ProgramError> {
+    if bin_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidBin.into());
+    }
+    let bin = BinState::try_from_slice(&bin_account.data.borrow())?;
+    if bin.pool != *pool || bin.id != id || bin.address(program_id)? != *bin_account.key {
+        return Err(TokenExchangeError::InvalidBin.into());
+    }
+    Ok(bin)
+}
+
+/// Loads an owner's bin pool position after verifying it is their position PDA
+pub fn load_bin_position(
+    position_account: &AccountInfo,
+    pool: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<BinPosition, ProgramError> {
+    if position_account.owner != program_id {
+        return Err(TokenExchangeError::InvalidBinPool.into());
+    }
+    let position = BinPosition::try_from_slice(&position_account.data.borrow())?;
+    if position.pool != *pool || position.owner != *owner || position.address(program_id)? != *position_account.key {
+        return Err(TokenExchangeError::InvalidBinPool.into());
+    }
+    Ok(position)
+}
+
+/// Verifies the vaults of a concentrated liquidity or bin pool and the token program that owns them
+pub fn check_pair_vaults(
+    expected_vault_a: &Pubkey,
+    expected_vault_b: &Pubkey,
+    vault_a: &AccountInfo,
+    vault_b: &AccountInfo,
+    token_program: &AccountInfo,
+) -> ProgramResult {
+    if vault_a.key != expected_vault_a || vault_b.key != expected_vault_b {
+        return Err(TokenExchangeError::InvalidTokenVault.into());
+    }
+    check_token_program(token_program, vault_a)?;
+    check_token_program(token_program, vault_b)
+}
+
+/// Verifies the LP mint passed to an operation is the pool's
+/// 
+/// A foreign mint would let a caller burn worthless LP tokens against a
+/// tiny supply and withdraw most of the pool.
+pub fn check_lp_mint(pool_state: &PoolState, lp_mint: &AccountInfo) -> ProgramResult {
+    if *lp_mint.key != pool_state.lp_mint {
+        return Err(TokenExchangeError::InvalidLpMint.into());
+    }
+    token_interface::check_token_program(lp_mint.owner)
+}
+
+/// Verifies the token program passed to an operation is SPL Token or
+/// Token-2022 and is the program that owns the pool's token vault
+pub fn check_token_program(token_program: &AccountInfo, pool_token_account: &AccountInfo) -> ProgramResult {
+    token_interface::check_token_program(token_program.key)?;
+    if pool_token_account.owner != token_program.key {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Verifies the system program passed to an operation is the real one
+pub fn check_system_program(system_program: &AccountInfo) -> ProgramResult {
+    if *system_program.key != system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
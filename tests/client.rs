@@ -0,0 +1,184 @@
+// Client tests
+// Checks that instruction builders and off-chain quotes agree with what the program decodes and prices
+
+mod common;
+
+use common::{amount, pool, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{
+    amount::{Bps, Lamports, LpAmount, SwapAmount, TokenAmount},
+    curve::{ConstantProduct, SwapCurve},
+    instruction::{self, TokenExchangeInstruction},
+    processor::Processor,
+    quote,
+    state::{CurveType, PoolState, LIMIT_PRICE_SCALE},
+};
+use solana_program::pubkey::Pubkey;
+
+/// Client builders encode data the program decodes back to the same fields
+#[test]
+fn instruction_builders_round_trip() {
+    let program_id = Pubkey::new_unique();
+    let [user, pool, token_account, vault, lp_account, lp_mint] = [(); 6].map(|_| Pubkey::new_unique());
+    let mut rng = StdRng::seed_from_u64(SEED + 18);
+    for _ in 0..1_000 {
+        let (amount_in, minimum_amount_out, is_sol_input) = (amount(&mut rng), amount(&mut rng), rng.gen());
+        let ix = instruction::swap(
+            &program_id,
+            &user,
+            &pool,
+            &token_account,
+            &vault,
+            &spl_token::id(),
+            amount_in,
+            minimum_amount_out,
+            is_sol_input,
+        );
+        assert_eq!(ix.accounts.len(), 7);
+        match TokenExchangeInstruction::unpack(&ix.data).unwrap() {
+            TokenExchangeInstruction::Swap {
+                amount_in: a,
+                minimum_amount_out: m,
+                is_sol_input: s,
+                platform_fee_bps,
+                referral_fee_share_bps,
+                sandwich_protection,
+            } => {
+                assert_eq!((a, m, s, platform_fee_bps), (amount_in, minimum_amount_out, is_sol_input, Bps(0)));
+                assert_eq!((referral_fee_share_bps, sandwich_protection), (Bps(0), false));
+            }
+            other => panic!("decoded {other:?}"),
+        }
+
+        let (sol, token, lp) = (Lamports(amount(&mut rng)), TokenAmount(amount(&mut rng)), LpAmount(amount(&mut rng)));
+        let ix = instruction::add_liquidity(
+            &program_id,
+            &user,
+            &pool,
+            &token_account,
+            &vault,
+            &lp_account,
+            &lp_mint,
+            &spl_token::id(),
+            sol,
+            token,
+            lp,
+        );
+        match TokenExchangeInstruction::unpack(&ix.data).unwrap() {
+            TokenExchangeInstruction::AddLiquidity { sol_amount, token_amount, minimum_lp_tokens, .. } => {
+                assert_eq!((sol_amount, token_amount, minimum_lp_tokens), (sol, token, lp));
+            }
+            other => panic!("decoded {other:?}"),
+        }
+
+        let ix = instruction::remove_liquidity(
+            &program_id,
+            &user,
+            &pool,
+            &token_account,
+            &vault,
+            &lp_account,
+            &lp_mint,
+            &spl_token::id(),
+            lp,
+            sol,
+            token,
+        );
+        match TokenExchangeInstruction::unpack(&ix.data).unwrap() {
+            TokenExchangeInstruction::RemoveLiquidity { lp_tokens, minimum_sol, minimum_token } => {
+                assert_eq!((lp_tokens, minimum_sol, minimum_token), (lp, sol, token));
+            }
+            other => panic!("decoded {other:?}"),
+        }
+    }
+
+    let fee_rate = Bps(30);
+    let user_token_account = Pubkey::new_unique();
+    let ix = instruction::initialize_pool(
+        &program_id,
+        &user,
+        &token_account,
+        &lp_mint,
+        None,
+        0,
+        &user_token_account,
+        &vault,
+        &lp_account,
+        &spl_token::id(),
+        Lamports(1),
+        TokenAmount(1),
+        fee_rate,
+    );
+    assert_eq!(ix.accounts[1].pubkey, PoolState::find_address(&Pubkey::default(), &token_account, 30, &program_id).0);
+    assert_eq!(ix.accounts.len(), 13);
+    let deposit_accounts: Vec<_> = ix.accounts[9..].iter().map(|account| account.pubkey).collect();
+    assert_eq!(deposit_accounts, [spl_token::id(), user_token_account, vault, lp_account]);
+    assert!(matches!(
+        TokenExchangeInstruction::unpack(&ix.data).unwrap(),
+        TokenExchangeInstruction::InitializePool { fee_rate: Bps(30), curve_type: CurveType::ConstantProduct, .. }
+    ));
+    let priced = instruction::initialize_pool_with_price(
+        &program_id,
+        &user,
+        &token_account,
+        &lp_mint,
+        None,
+        0,
+        &user_token_account,
+        &vault,
+        &lp_account,
+        &spl_token::id(),
+        LIMIT_PRICE_SCALE,
+        TokenAmount(1),
+        fee_rate,
+    );
+    assert_eq!(priced.accounts, ix.accounts);
+    assert!(matches!(
+        TokenExchangeInstruction::unpack(&priced.data).unwrap(),
+        TokenExchangeInstruction::InitializePoolWithPrice { initial_price: LIMIT_PRICE_SCALE, fee_rate: Bps(30), .. }
+    ));
+}
+
+/// Off-chain quotes match the program's pricing of the same swap and deposit
+#[test]
+fn quotes_match_program_pricing() {
+    let mut rng = StdRng::seed_from_u64(SEED + 19);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.fee_rate = rng.gen_range(0..=100);
+        let (amount_in, is_sol_input) = (amount(&mut rng), rng.gen());
+        let amount_in = SwapAmount::new(amount_in, is_sol_input);
+        let (sol_reserve, token_reserve) = (Lamports(state.sol_reserve), TokenAmount(state.token_reserve));
+        let quoted = quote::quote_swap(sol_reserve, token_reserve, amount_in, Bps(state.fee_rate));
+        let program = Processor::calculate_swap_output(&state, amount_in).ok();
+        assert_eq!(quoted.map(|quote| quote.amount_out), program);
+        if let Some(quote) = quoted {
+            assert!(quote.price_impact_bps <= 10000 && quote.fee_paid.get() <= amount_in.get());
+            assert_eq!(quote.fee_paid.is_sol(), is_sol_input);
+            assert!(quote.minimum_amount_out(Bps(50)).get() <= quote.amount_out.get());
+        }
+
+        let total_supply = LpAmount(amount(&mut rng));
+        let (sol_desired, token_desired) = (Lamports(amount(&mut rng)), TokenAmount(amount(&mut rng)));
+        let Ok((sol_amount, token_amount)) =
+            Processor::calculate_optimal_deposit(&state, sol_desired, token_desired, Lamports::ZERO, TokenAmount::ZERO)
+        else {
+            continue;
+        };
+        let program = ConstantProduct.deposit_lp(&state, sol_amount, token_amount, total_supply).ok();
+        let quoted = quote::quote_add_liquidity(
+            Lamports(state.sol_reserve),
+            TokenAmount(state.token_reserve),
+            total_supply,
+            sol_desired,
+            token_desired,
+        );
+        if let Some(lp_tokens) = program {
+            let quoted = quoted.expect("program accepted a deposit the quote refused");
+            assert_eq!(
+                (quoted.sol_amount, quoted.token_amount, quoted.lp_tokens),
+                (sol_amount, token_amount, lp_tokens)
+            );
+        }
+    }
+}
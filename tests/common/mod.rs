@@ -0,0 +1,97 @@
+// Shared test fixtures
+// Seeded randomness and a default pool state for the randomized property tests
+
+// Each test binary compiles this module and uses only some of it
+#![allow(dead_code)]
+
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng};
+use simple_token_exchange::state::{CurveType, PoolState, StateVersion};
+use solana_program::pubkey::Pubkey;
+
+/// Number of randomized cases per property
+pub const CASES: usize = 20_000;
+
+/// Fixed seed so failures reproduce
+pub const SEED: u64 = 0x5eed_c0de;
+
+/// Draws a log-uniform amount so tiny, typical and huge values are all covered
+pub fn amount(rng: &mut StdRng) -> u64 {
+    let bits = rng.gen_range(1..=64);
+    rng.gen_range(1..=u64::MAX >> (64 - bits))
+}
+
+pub fn big(value: u64) -> BigUint {
+    BigUint::from(value)
+}
+
+/// Pool with the given reserves and every other field defaulted
+pub fn pool(sol_reserve: u64, token_reserve: u64) -> PoolState {
+    PoolState {
+        discriminator: PoolState::DISCRIMINATOR,
+        version: StateVersion::CURRENT as u8,
+        authority: Pubkey::default(),
+        sol_reserve,
+        token_reserve,
+        lp_mint: Pubkey::default(),
+        fee_rate: 0,
+        token_mint: Pubkey::default(),
+        initialized: true,
+        keeper_bounty_bps: 0,
+        interest_bearing: false,
+        rebasing: false,
+        token_vault: Pubkey::default(),
+        non_transferable_lp: false,
+        deposit_permissioned: false,
+        creator: Pubkey::default(),
+        created_at_slot: 0,
+        deprecated: false,
+        mint_a: Pubkey::default(),
+        bump: 0,
+        sol_vault: Pubkey::default(),
+        protocol_fee_share_bps: 0,
+        protocol_fee_sol: 0,
+        protocol_fee_token: 0,
+        wsol: false,
+        fee_tier: 0,
+        paused: false,
+        curve_type: CurveType::ConstantProduct,
+        amplification: 0,
+        token_offset: 0,
+        price_cumulative_last: 0,
+        last_update_slot: 0,
+        flash_loan_amount: 0,
+        flash_loan_sol: false,
+        fee_on_transfer: false,
+        cumulative_sol_volume: 0,
+        cumulative_token_volume: 0,
+        cumulative_fees: 0,
+        lp_supply: 0,
+        position_lp_supply: 0,
+        fee_growth_sol: 0,
+        fee_growth_token: 0,
+        lp_fees_sol: 0,
+        lp_fees_token: 0,
+        trade_start_timestamp: 0,
+        launch_window_seconds: 0,
+        max_swap_per_wallet_during_launch: 0,
+        launch_fee_bps: 0,
+        launch_fee_decay_seconds: 0,
+        volatility_accumulator: 0,
+        volatility_update_slot: 0,
+        variable_fee_control: 0,
+        max_variable_fee_bps: 0,
+        max_price_impact_bps: 0,
+        price_oracle: Pubkey::default(),
+        oracle_max_deviation_bps: 0,
+        ema_price: 0,
+        ema_update_slot: 0,
+        weights: [0; 2],
+        sol_offset: 0,
+        graduation_threshold: 0,
+        max_outflow_bps: 0,
+        outflow_slot: 0,
+        outflow_this_slot: [0; 2],
+        emergency: false,
+    }
+}
@@ -0,0 +1,696 @@
+// Differential tests of the on-chain curve math
+// Compares the program's integer math against exact big-integer rationals across randomized inputs
+
+mod common;
+
+use common::{amount, big, pool, CASES, SEED};
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{
+    amount::{Bps, Lamports, LpAmount, SwapAmount, TokenAmount},
+    bin_pool::{self, BinState, MAX_BIN_STEP},
+    concentrated::{self, MAX_TICK, MIN_TICK},
+    curve::{ConstantProduct, Offset, Stable, SwapCurve, Weighted, MAX_AMPLIFICATION, MIN_WEIGHT_BPS},
+    math::{self, Rounding},
+    processor::{Processor, MAX_PROTOCOL_FEE_SHARE_BPS, MAX_REFERRAL_FEE_SHARE_BPS},
+    state::{CurveType, LIMIT_PRICE_SCALE},
+    token_interface::InterestBearingConfig,
+};
+use std::cmp::Ordering;
+
+/// Swap output never exceeds the exact output, and trails it by less than
+/// the value of one unit of input plus one unit of output
+#[test]
+fn swap_output_rounds_in_pool_favor() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    for _ in 0..CASES {
+        let (amount_in, reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng), amount(&mut rng));
+        let fee_rate = rng.gen_range(0..10000);
+        let (sol_reserve, token_reserve) = (Lamports(reserve_in), TokenAmount(reserve_out));
+        let out = ConstantProduct
+            .swap_out(SwapAmount::Sol(Lamports(amount_in)), sol_reserve, token_reserve, Bps(fee_rate))
+            .expect("u128 intermediates never overflow")
+            .get();
+
+        // exact = R_out * dx * (1 - f) / (R_in + dx * (1 - f)), as numerator / denominator
+        let fee_factor = big(10000 - fee_rate);
+        let numerator = big(reserve_out) * big(amount_in) * &fee_factor;
+        let denominator = big(reserve_in) * big(10000) + big(amount_in) * &fee_factor;
+        assert!(
+            big(out) * &denominator <= numerator,
+            "output above exact: in={amount_in} reserves=({reserve_in}, {reserve_out}) fee={fee_rate} out={out}"
+        );
+
+        let bound = 1 + reserve_out.div_ceil(reserve_in);
+        assert!(
+            (big(out) + big(bound)) * &denominator > numerator,
+            "rounding error too large: in={amount_in} reserves=({reserve_in}, {reserve_out}) fee={fee_rate} out={out}"
+        );
+    }
+}
+
+/// Swaps never shrink the invariant `sol_reserve * token_reserve`
+#[test]
+fn swap_never_decreases_invariant() {
+    let mut rng = StdRng::seed_from_u64(SEED + 1);
+    for _ in 0..CASES {
+        let (amount_in, reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng), amount(&mut rng));
+        let fee_rate = rng.gen_range(0..10000);
+        let (sol_reserve, token_reserve) = (Lamports(reserve_in), TokenAmount(reserve_out));
+        let out = ConstantProduct
+            .swap_out(SwapAmount::Sol(Lamports(amount_in)), sol_reserve, token_reserve, Bps(fee_rate))
+            .expect("u128 intermediates never overflow")
+            .get();
+        assert!(out < reserve_out || reserve_out == 0, "swap drained the pool");
+        assert!(
+            (big(reserve_in) + big(amount_in)) * (big(reserve_out) - big(out)) >= big(reserve_in) * big(reserve_out),
+            "invariant decreased: in={amount_in} reserves=({reserve_in}, {reserve_out}) fee={fee_rate} out={out}"
+        );
+    }
+}
+
+/// The exact-output input is the smallest input whose swap output covers the request
+#[test]
+fn exact_output_input_is_minimal() {
+    let mut rng = StdRng::seed_from_u64(SEED + 4);
+    for _ in 0..CASES {
+        let (reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng));
+        let amount_out = rng.gen_range(0..=reserve_out);
+        let fee_rate = rng.gen_range(0..10000);
+        let (sol_reserve, token_reserve) = (Lamports(reserve_in), TokenAmount(reserve_out));
+        let swap_out = |amount_in| {
+            ConstantProduct.swap_out(SwapAmount::Sol(Lamports(amount_in)), sol_reserve, token_reserve, Bps(fee_rate))
+        };
+        let desired_out = SwapAmount::Token(TokenAmount(amount_out));
+        let Ok(amount_in) = ConstantProduct.swap_in(desired_out, sol_reserve, token_reserve, Bps(fee_rate)) else {
+            continue; // The input needed exceeds a u64
+        };
+        let amount_in = amount_in.get();
+
+        let out = swap_out(amount_in).unwrap().get();
+        assert!(
+            out >= amount_out,
+            "input too small: out={amount_out} reserves=({reserve_in}, {reserve_out}) fee={fee_rate} in={amount_in}"
+        );
+        let short = swap_out(amount_in - 1);
+        assert!(
+            short.map_or(true, |short| short.get() < amount_out),
+            "input not minimal: out={amount_out} reserves=({reserve_in}, {reserve_out}) fee={fee_rate} in={amount_in}"
+        );
+    }
+}
+
+/// Stable swaps never shrink the StableSwap invariant at the pre-swap `D`
+#[test]
+fn stable_swap_never_decreases_invariant() {
+    let mut rng = StdRng::seed_from_u64(SEED + 6);
+    for _ in 0..CASES {
+        let (amount_in, reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng), amount(&mut rng));
+        let amplification = rng.gen_range(1..=MAX_AMPLIFICATION);
+        let fee_rate = rng.gen_range(0..10000);
+        let curve = Stable { amplification };
+        let Ok(d) = curve.invariant(reserve_in, reserve_out) else {
+            continue;
+        };
+        let input = SwapAmount::Sol(Lamports(amount_in));
+        let Ok(out) = curve.swap_out(input, Lamports(reserve_in), TokenAmount(reserve_out), Bps(fee_rate)) else {
+            continue;
+        };
+        let out = out.get();
+        assert!(out < reserve_out, "stable swap drained the pool");
+        if out == 0 {
+            continue; // Reserves only grew
+        }
+
+        // Sign of 4xy * (4A(x + y) + D - 4AD) - D^3, which falls as D grows and is zero at the exact invariant
+        let curve_side = |x: &BigUint, y: &BigUint, d: &BigUint| {
+            let a4 = big(4 * amplification);
+            (big(4) * x * y * (&a4 * (x + y) + d)).cmp(&(big(4) * x * y * &a4 * d + d.pow(3)))
+        };
+        let (x, y) = (big(reserve_in), big(reserve_out));
+        let upper = BigUint::from(d + 1);
+        assert!(curve_side(&x, &y, &upper) != Ordering::Greater, "invariant off by more than a unit");
+        assert!(
+            curve_side(&(&x + big(amount_in)), &(&y - big(out)), &upper) != Ordering::Less,
+            "invariant decreased: in={amount_in} reserves=({reserve_in}, {reserve_out}) amp={amplification} out={out}"
+        );
+    }
+}
+
+/// Offset swaps never shrink the shifted invariant nor pay out tokens the pool does not hold
+#[test]
+fn offset_swap_stays_within_real_reserves() {
+    let mut rng = StdRng::seed_from_u64(SEED + 7);
+    for _ in 0..CASES {
+        let (amount_in, sol_reserve, token_reserve) = (amount(&mut rng), amount(&mut rng), amount(&mut rng));
+        let (sol_offset, token_offset, is_sol_input) = (amount(&mut rng), amount(&mut rng), rng.gen_bool(0.5));
+        let sol_offset = if rng.gen() { sol_offset } else { 0 };
+        let fee_rate = rng.gen_range(0..10000);
+        let input = SwapAmount::new(amount_in, is_sol_input);
+        let (_, reserve_out) = input.reserves(Lamports(sol_reserve), TokenAmount(token_reserve));
+        let curve = Offset { sol_offset, token_offset };
+        let Ok(out) = curve.swap_out(input, Lamports(sol_reserve), TokenAmount(token_reserve), Bps(fee_rate)) else {
+            continue;
+        };
+        assert!(out.is_sol() != is_sol_input, "output on the input side");
+        let out = out.get();
+        assert!(out < reserve_out || out == 0, "offset swap drained the pool");
+
+        let (sol_after, token_after) = if is_sol_input {
+            (big(sol_reserve) + big(amount_in), big(token_reserve) - big(out))
+        } else {
+            (big(sol_reserve) - big(out), big(token_reserve) + big(amount_in))
+        };
+        assert!(
+            (sol_after + big(sol_offset)) * (token_after + big(token_offset))
+                >= (big(sol_reserve) + big(sol_offset)) * (big(token_reserve) + big(token_offset)),
+            "invariant decreased: in={amount_in} reserves=({sol_reserve}, {token_reserve}) \
+             offsets=({sol_offset}, {token_offset}) out={out}"
+        );
+    }
+}
+
+/// The integer square root is the exact root rounded down, including at the extremes
+#[test]
+fn sqrt_rounds_down() {
+    let mut rng = StdRng::seed_from_u64(SEED + 9);
+    let extremes = [0, 1, 2, 3, 4, u64::MAX as u128, u128::MAX - 1, u128::MAX];
+    let random = (0..CASES).map(|_| (amount(&mut rng) as u128) << rng.gen_range(0..64) | amount(&mut rng) as u128);
+    for value in extremes.into_iter().chain(random) {
+        let root = math::sqrt(value);
+        assert!(BigUint::from(root).pow(2) <= BigUint::from(value), "root above exact: value={value} root={root}");
+        assert!(BigUint::from(root + 1).pow(2) > BigUint::from(value), "root below floor: value={value} root={root}");
+    }
+}
+
+/// Rounded division lands on the exact quotient's floor or ceiling as asked
+#[test]
+fn mul_div_rounds_as_asked() {
+    let mut rng = StdRng::seed_from_u64(SEED + 10);
+    for _ in 0..CASES {
+        let (a, b, c) = (amount(&mut rng) as u128, amount(&mut rng) as u128, amount(&mut rng) as u128);
+        let exact = big(a as u64) * big(b as u64);
+        let down = math::mul_div(a, b, c, Rounding::Down).unwrap();
+        let up = math::mul_div(a, b, c, Rounding::Up).unwrap();
+        assert!(
+            BigUint::from(down) * c <= exact && (BigUint::from(down) + 1u32) * c > exact,
+            "floor off: {a} * {b} / {c}"
+        );
+        assert!(BigUint::from(up) * c >= exact && up - down <= 1, "ceiling off: {a} * {b} / {c}");
+        assert_eq!(up == down, &exact % c == BigUint::from(0u32), "ceiling of an exact quotient: {a} * {b} / {c}");
+    }
+}
+
+/// Swapping in and straight back out never returns more than was put in, on every curve
+#[test]
+fn swap_round_trip_never_profits() {
+    let mut rng = StdRng::seed_from_u64(SEED + 11);
+    for _ in 0..CASES {
+        let (amount_in, reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng), amount(&mut rng));
+        let fee_rate = Bps(rng.gen_range(0..10000));
+        let curve: Box<dyn SwapCurve> = match rng.gen_range(0..3) {
+            0 => Box::new(ConstantProduct),
+            1 => Box::new(Stable { amplification: rng.gen_range(1..=MAX_AMPLIFICATION) }),
+            _ => Box::new(Offset { sol_offset: 0, token_offset: amount(&mut rng) }),
+        };
+        let input = SwapAmount::Sol(Lamports(amount_in));
+        let Ok(out) = curve.swap_out(input, Lamports(reserve_in), TokenAmount(reserve_out), fee_rate) else {
+            continue;
+        };
+        let (Some(reserve_in), Some(reserve_out)) =
+            (reserve_in.checked_add(amount_in), reserve_out.checked_sub(out.get()))
+        else {
+            continue;
+        };
+        let Ok(back) = curve.swap_out(out, Lamports(reserve_in), TokenAmount(reserve_out), fee_rate) else {
+            continue;
+        };
+        let (out, back) = (out.get(), back.get());
+        assert!(back <= amount_in, "round trip profited: in={amount_in} out={out} back={back}");
+    }
+}
+
+/// Depositing and withdrawing the minted LP tokens never returns more than was deposited
+#[test]
+fn deposit_withdraw_round_trip_never_profits() {
+    let mut rng = StdRng::seed_from_u64(SEED + 12);
+    for _ in 0..CASES {
+        let state = pool(amount(&mut rng), amount(&mut rng));
+        let total_supply = amount(&mut rng);
+        let (sol_amount, token_amount) = (amount(&mut rng), amount(&mut rng));
+        let Ok(LpAmount(minted)) = ConstantProduct.deposit_lp(
+            &state,
+            Lamports(sol_amount),
+            TokenAmount(token_amount),
+            LpAmount(total_supply),
+        ) else {
+            continue;
+        };
+        let (Some(sol_reserve), Some(token_reserve), Some(supply)) = (
+            state.sol_reserve.checked_add(sol_amount),
+            state.token_reserve.checked_add(token_amount),
+            total_supply.checked_add(minted),
+        ) else {
+            continue;
+        };
+        let (Lamports(sol), TokenAmount(token)) = ConstantProduct
+            .withdraw_lp(&pool(sol_reserve, token_reserve), LpAmount(minted), LpAmount(supply))
+            .unwrap();
+        assert!(
+            sol <= sol_amount && token <= token_amount,
+            "round trip profited: deposit=({sol_amount}, {token_amount}) withdrew=({sol}, {token})"
+        );
+    }
+}
+
+/// The protocol's cut is the exact share of the fee rounded down
+#[test]
+fn protocol_fee_rounds_in_lp_favor() {
+    let mut rng = StdRng::seed_from_u64(SEED + 5);
+    for _ in 0..CASES {
+        let amount_in = amount(&mut rng);
+        let (fee_rate, share) = (rng.gen_range(0..10000), rng.gen_range(0..=10000));
+        let protocol_fee = Processor::calculate_protocol_fee(amount_in, Bps(fee_rate), Bps(share)).unwrap();
+
+        let exact = big(amount_in) * big(fee_rate) * big(share);
+        let scale = big(10000 * 10000);
+        assert!(big(protocol_fee) * &scale <= exact, "protocol fee above its share: in={amount_in}");
+        assert!((big(protocol_fee) + 1u32) * &scale > exact, "protocol fee rounding error too large: in={amount_in}");
+    }
+}
+
+/// Withdrawals never release more than the exact pro-rata share, and trail it by less than one unit
+#[test]
+fn withdrawal_rounds_in_pool_favor() {
+    let mut rng = StdRng::seed_from_u64(SEED + 2);
+    for _ in 0..CASES {
+        let state = pool(amount(&mut rng), amount(&mut rng));
+        let total_supply = amount(&mut rng);
+        let lp_tokens = rng.gen_range(1..=total_supply);
+        let (Lamports(sol), TokenAmount(token)) = ConstantProduct
+            .withdraw_lp(&state, LpAmount(lp_tokens), LpAmount(total_supply))
+            .expect("u128 intermediates never overflow");
+
+        for (released, reserve) in [(sol, state.sol_reserve), (token, state.token_reserve)] {
+            let exact = big(reserve) * big(lp_tokens);
+            assert!(big(released) * big(total_supply) <= exact, "withdrawal above pro-rata share");
+            assert!((big(released) + 1u32) * big(total_supply) > exact, "withdrawal rounding error too large");
+        }
+    }
+}
+
+/// Ratio-matched deposits never exceed the desired amounts, keep one side
+/// whole, and trim the other to the exact pool ratio rounded down
+#[test]
+fn optimal_deposit_matches_pool_ratio() {
+    let mut rng = StdRng::seed_from_u64(SEED + 8);
+    for _ in 0..CASES {
+        let state = pool(amount(&mut rng), amount(&mut rng));
+        let (sol_desired, token_desired) = (amount(&mut rng), amount(&mut rng));
+        let (Lamports(sol), TokenAmount(token)) = Processor::calculate_optimal_deposit(
+            &state,
+            Lamports(sol_desired),
+            TokenAmount(token_desired),
+            Lamports(0),
+            TokenAmount(0),
+        )
+        .unwrap();
+        assert!(sol <= sol_desired && token <= token_desired, "deposit above desired amounts");
+
+        // The trimmed side is floor(kept * R_trimmed / R_kept)
+        let (sol_reserve, token_reserve) = (big(state.sol_reserve), big(state.token_reserve));
+        let (kept, trimmed, kept_reserve, trimmed_reserve) = if sol == sol_desired {
+            (sol, token, sol_reserve, token_reserve)
+        } else {
+            assert_eq!(token, token_desired, "neither side kept whole");
+            (token, sol, token_reserve, sol_reserve)
+        };
+        let exact = big(kept) * trimmed_reserve;
+        assert!(big(trimmed) * &kept_reserve <= exact, "trimmed side above the pool ratio");
+        assert!((big(trimmed) + 1u32) * &kept_reserve > exact, "trimmed side rounded too far down");
+    }
+}
+
+/// Deposits never mint more than the exact share, and trail it by less than one unit
+#[test]
+fn deposit_rounds_in_pool_favor() {
+    let mut rng = StdRng::seed_from_u64(SEED + 3);
+    for _ in 0..CASES {
+        let state = pool(amount(&mut rng), amount(&mut rng));
+        let total_supply = amount(&mut rng);
+        let (sol_amount, token_amount) = (amount(&mut rng), amount(&mut rng));
+        let Ok(LpAmount(minted)) = ConstantProduct.deposit_lp(
+            &state,
+            Lamports(sol_amount),
+            TokenAmount(token_amount),
+            LpAmount(total_supply),
+        ) else {
+            continue; // The share exceeds a u64
+        };
+
+        // exact = min(sol / R_sol, token / R_token) * supply, compared as fractions over R_sol * R_token
+        let sol_share = big(sol_amount) * big(state.token_reserve);
+        let token_share = big(token_amount) * big(state.sol_reserve);
+        let exact = sol_share.min(token_share) * big(total_supply);
+        let scale = big(state.sol_reserve) * big(state.token_reserve);
+        assert!(
+            big(minted) * &scale <= exact,
+            "minted above exact share: deposit=({sol_amount}, {token_amount}) supply={total_supply} minted={minted}"
+        );
+
+        assert!(
+            (big(minted) + 1u32) * &scale > exact,
+            "mint rounding error too large: deposit=({sol_amount}, {token_amount}) supply={total_supply} minted={minted}"
+        );
+    }
+}
+
+/// The 256-bit product division matches exact big-integer division
+#[test]
+fn mul_div_wide_matches_bigint() {
+    let mut rng = StdRng::seed_from_u64(SEED + 29);
+    let wide = |rng: &mut StdRng| {
+        let bits = rng.gen_range(1..=128);
+        rng.gen_range(1..=u128::MAX >> (128 - bits))
+    };
+    for _ in 0..CASES {
+        let (a, b, c) = (wide(&mut rng), wide(&mut rng), wide(&mut rng));
+        let product = BigUint::from(a) * BigUint::from(b);
+        let (floor, remainder) = (&product / c, &product % c);
+        let ceil = if remainder == BigUint::from(0u8) { floor.clone() } else { &floor + 1u8 };
+        for (rounding, exact) in [(Rounding::Down, floor), (Rounding::Up, ceil)] {
+            let expected = u128::try_from(exact).ok();
+            assert_eq!(math::mul_div_wide(a, b, c, rounding), expected, "{a} * {b} / {c} {rounding:?}");
+        }
+    }
+    assert_eq!(math::mul_div_wide(1, 1, 0, Rounding::Down), None);
+}
+
+/// Tick square root prices track `sqrt(1.0001^tick)`, increase with the
+/// tick, and invert through `tick_at_sqrt_price`
+#[test]
+fn tick_sqrt_prices_track_exact_powers() {
+    let mut rng = StdRng::seed_from_u64(SEED + 30);
+    assert!(concentrated::sqrt_price_at_tick(MIN_TICK - 1).is_err());
+    assert!(concentrated::sqrt_price_at_tick(MAX_TICK + 1).is_err());
+    assert_eq!(concentrated::sqrt_price_at_tick(0).unwrap(), 1 << 64);
+    for _ in 0..CASES / 10 {
+        let tick = rng.gen_range(MIN_TICK..MAX_TICK);
+        let (sqrt_price, above) =
+            (concentrated::sqrt_price_at_tick(tick).unwrap(), concentrated::sqrt_price_at_tick(tick + 1).unwrap());
+        assert!(sqrt_price < above, "tick {tick}");
+        let exact = 1.0001f64.powf(tick as f64 / 2.0) * 2f64.powi(64);
+        assert!((sqrt_price as f64 / exact - 1.0).abs() < 1e-8, "tick {tick}: {sqrt_price} vs {exact}");
+
+        let within = sqrt_price + rng.gen_range(0..above - sqrt_price);
+        assert_eq!(concentrated::tick_at_sqrt_price(within, MIN_TICK, MAX_TICK - 1).unwrap(), tick);
+    }
+}
+
+/// Swap steps move the price toward the target without passing it, spend
+/// at most the input, and a fee-free round trip never returns more than it sold
+#[test]
+fn swap_steps_stay_within_input_and_target() {
+    let mut rng = StdRng::seed_from_u64(SEED + 31);
+    for _ in 0..CASES {
+        let (lower, upper) = (rng.gen_range(MIN_TICK / 4..0), rng.gen_range(1..MAX_TICK / 4));
+        let tick = rng.gen_range(lower..upper);
+        let sqrt_price = concentrated::sqrt_price_at_tick(tick).unwrap();
+        let liquidity = amount(&mut rng) as u128 * rng.gen_range(1..=1u128 << 20);
+        let amount_in = amount(&mut rng);
+        let fee_rate = rng.gen_range(0..=100);
+        let a_to_b = rng.gen();
+        let target = concentrated::sqrt_price_at_tick(if a_to_b { lower } else { upper }).unwrap();
+
+        let Ok(step) = concentrated::swap_step(sqrt_price, target, liquidity, amount_in, fee_rate, a_to_b) else {
+            continue;
+        };
+        assert!(step.amount_in as u128 + step.fee as u128 <= amount_in as u128);
+        if !step.reached(target) {
+            assert_eq!(step.amount_in + step.fee, amount_in, "an unfinished step consumes its input");
+        }
+        if a_to_b {
+            assert!(target <= step.sqrt_price_next && step.sqrt_price_next <= sqrt_price);
+        } else {
+            assert!(sqrt_price <= step.sqrt_price_next && step.sqrt_price_next <= target);
+        }
+        if step.amount_out == 0 {
+            continue;
+        }
+
+        // Sell the output straight back, fee free, toward the starting price
+        let back = concentrated::swap_step(step.sqrt_price_next, sqrt_price, liquidity, step.amount_out, 0, !a_to_b)
+            .unwrap();
+        assert!(back.amount_out <= step.amount_in, "round trip of {} returned {}", step.amount_in, back.amount_out);
+    }
+}
+
+/// Bin prices track `(1 + bin_step / 10000)^bin_id` and increase with the bin
+#[test]
+fn bin_prices_track_exact_powers() {
+    let mut rng = StdRng::seed_from_u64(SEED + 32);
+    for _ in 0..CASES / 10 {
+        let bin_step = rng.gen_range(1..=MAX_BIN_STEP);
+        let limit = (32.0 * 2f64.ln() / (1.0 + bin_step as f64 / 10000.0).ln()) as i32;
+        let bin_id = rng.gen_range(-limit + 1..limit - 1);
+        let (price, above) =
+            (bin_pool::bin_price(bin_step, bin_id).unwrap(), bin_pool::bin_price(bin_step, bin_id + 1).unwrap());
+        assert!(price < above, "bin {bin_id} at step {bin_step}");
+        let exact = (1.0 + bin_step as f64 / 10000.0).powi(bin_id) * 2f64.powi(64);
+        assert!((price as f64 / exact - 1.0).abs() < 1e-9, "bin {bin_id} at step {bin_step}: {price} vs {exact}");
+        assert!(bin_pool::bin_price(bin_step, limit + 1).is_err());
+        assert!(bin_pool::bin_price(bin_step, -limit - 1).is_err());
+    }
+}
+
+/// Swaps within a bin never lower its value at its price, and shares never
+/// redeem for more value than was deposited
+#[test]
+fn bin_swaps_and_shares_preserve_value() {
+    let mut rng = StdRng::seed_from_u64(SEED + 33);
+    for _ in 0..CASES {
+        let price = bin_pool::bin_price(rng.gen_range(1..=MAX_BIN_STEP), rng.gen_range(-200..=200)).unwrap();
+        let mut bin = BinState::default();
+        bin.deposit(price, amount(&mut rng) >> 2, amount(&mut rng) >> 2).unwrap();
+
+        let value = |bin: &BinState| bin.value(price, Rounding::Down).unwrap();
+        let value_before = value(&bin);
+        let (amount_a, amount_b) = (bin.amount_a, bin.amount_b);
+        let (amount_in, fee_rate, a_to_b) = (amount(&mut rng) >> 2, rng.gen_range(0..=100), rng.gen());
+        let swap = bin.swap(price, amount_in, fee_rate, a_to_b).unwrap();
+        assert!(swap.amount_in as u128 + swap.fee as u128 <= amount_in as u128);
+        assert!(value(&bin) >= value_before, "bin lost value");
+        assert!(swap.amount_in + swap.fee == amount_in || (if a_to_b { bin.amount_b } else { bin.amount_a }) == 0);
+        assert_eq!(bin.amount_a + bin.amount_b, amount_a + amount_b + swap.amount_in + swap.fee - swap.amount_out);
+
+        // A second provider's deposit redeems for at most what it added; a
+        // bin without shares belongs to whoever deposits next
+        if bin.liquidity_supply == 0 {
+            continue;
+        }
+        let (deposit_a, deposit_b) = (amount(&mut rng) >> 2, amount(&mut rng) >> 2);
+        let added = value(&BinState { amount_a: deposit_a, amount_b: deposit_b, ..Default::default() });
+        let shares = bin.deposit(price, deposit_a, deposit_b).unwrap();
+        let redeemed = bin.withdraw(shares).unwrap();
+        let redeemed = BinState { amount_a: redeemed.0, amount_b: redeemed.1, ..Default::default() };
+        assert!(value(&redeemed) <= added, "shares of {added} redeemed for {}", value(&redeemed));
+    }
+}
+
+/// Weighted swaps pay at most the exact weighted output and stay within the
+/// power margin of it, `swap_in` is the smallest input covering an output,
+/// and the initial LP mint tracks the exact weighted geometric mean
+#[test]
+fn weighted_swaps_track_exact_powers() {
+    let mut rng = StdRng::seed_from_u64(SEED + 34);
+    for _ in 0..CASES / 10 {
+        let sol_weight = rng.gen_range(MIN_WEIGHT_BPS..=10000 - MIN_WEIGHT_BPS);
+        let token_weight = 10000 - sol_weight;
+        let curve = Weighted { sol_weight, token_weight };
+        let (reserve_in, reserve_out) = (amount(&mut rng), amount(&mut rng));
+        let (amount_in, is_sol_input, fee_rate) = (amount(&mut rng) >> 1, rng.gen(), rng.gen_range(0..=100));
+        let (weight_in, weight_out) =
+            if is_sol_input { (sol_weight, token_weight) } else { (token_weight, sol_weight) };
+
+        // y * (1 - (x / (x + dx'))^(w_in / w_out)), without cancellation
+        let amount_in_with_fee = (amount_in as u128 * (10000 - fee_rate as u128) / 10000) as f64;
+        let exponent = weight_in as f64 / weight_out as f64;
+        let exact = -(reserve_out as f64) * (-exponent * (amount_in_with_fee / reserve_in as f64).ln_1p()).exp_m1();
+        let input = SwapAmount::new(amount_in, is_sol_input);
+        let (sol_reserve, token_reserve) = if is_sol_input {
+            (Lamports(reserve_in), TokenAmount(reserve_out))
+        } else {
+            (Lamports(reserve_out), TokenAmount(reserve_in))
+        };
+        let fee_rate = Bps(fee_rate);
+        let swap_out = |amount_in| curve.swap_out(input.same_side(amount_in), sol_reserve, token_reserve, fee_rate);
+        let out = swap_out(amount_in).unwrap().get();
+        assert!(out as f64 <= exact * (1.0 + 1e-12) + 1.0, "paid {out} over exact {exact}");
+        assert!(out as f64 >= exact - reserve_out as f64 / 2f64.powi(38) - 2.0, "paid {out} under exact {exact}");
+
+        if out > 0 {
+            let needed = curve.swap_in(input.other_side(out), sol_reserve, token_reserve, fee_rate).unwrap();
+            assert_eq!(needed.is_sol(), is_sol_input);
+            let needed = needed.get();
+            assert!(needed <= amount_in);
+            assert!(swap_out(needed).unwrap().get() >= out);
+            assert!(swap_out(needed - 1).unwrap().get() < out);
+        }
+
+        let (a, b) = (amount(&mut rng) >> 1, amount(&mut rng) >> 1);
+        let mean = math::weighted_geometric_mean(a, b, sol_weight, token_weight).unwrap();
+        let exact = ((a as f64).ln() * sol_weight as f64 + (b as f64).ln() * token_weight as f64) / 10000.0;
+        let exact = exact.exp();
+        assert!(mean as f64 <= exact * (1.0 + 1e-12), "mean {mean} over exact {exact}");
+        assert!(mean as f64 >= exact * (1.0 - 1e-11) - 1.0, "mean {mean} under exact {exact}");
+    }
+}
+
+/// A graduating bonding curve keeps the fewest tokens that hold its last
+/// price, never more than it holds, and stops trading at its threshold
+#[test]
+fn graduation_keeps_the_curve_price() {
+    let mut rng = StdRng::seed_from_u64(SEED + 35);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.curve_type = CurveType::Offset;
+        (state.sol_offset, state.token_offset) = (amount(&mut rng), amount(&mut rng) >> rng.gen_range(0..64));
+        state.graduation_threshold = amount(&mut rng);
+        assert_eq!(state.check_bonding_curve().is_ok(), state.sol_reserve < state.graduation_threshold);
+
+        let kept = state.graduated_token_reserve().unwrap();
+        assert!(kept <= state.token_reserve);
+        let (sol_reserve, curve_sol) = (big(state.sol_reserve), big(state.sol_reserve) + big(state.sol_offset));
+        let curve_token = big(state.token_reserve) + big(state.token_offset);
+        if kept < state.token_reserve {
+            assert!(big(kept) * &curve_sol >= &sol_reserve * &curve_token, "graduated below the curve price");
+        }
+        assert!(big(kept - 1) * &curve_sol < &sol_reserve * &curve_token, "kept more tokens than needed");
+    }
+}
+
+/// Seeding by price deposits the least SOL that opens the pool at or above
+/// the requested price
+#[test]
+fn initial_price_seeds_the_requested_ratio() {
+    let mut rng = StdRng::seed_from_u64(SEED + 43);
+    for _ in 0..CASES {
+        let (initial_price, token_amount) = (amount(&mut rng), amount(&mut rng));
+        let exact = big(token_amount) * big(initial_price);
+        match Processor::sol_amount_for_price(initial_price, TokenAmount(token_amount)) {
+            Ok(sol_amount) => {
+                let scaled = big(sol_amount.get()) * big(LIMIT_PRICE_SCALE);
+                assert!(scaled >= exact && scaled - big(LIMIT_PRICE_SCALE) < exact);
+            }
+            Err(_) => assert!(exact > big(u64::MAX) * big(LIMIT_PRICE_SCALE)),
+        }
+    }
+}
+
+/// The fixed-point interest scale tracks continuous compounding at both of
+/// the mint's rates
+#[test]
+fn interest_scale_tracks_continuous_compounding() {
+    const SECONDS_PER_YEAR: i64 = 31_556_736;
+    let mut rng = StdRng::seed_from_u64(SEED + 44);
+    for _ in 0..CASES {
+        let initialization_timestamp = rng.gen_range(0..1 << 32);
+        let last_update_timestamp = initialization_timestamp + rng.gen_range(0..=20 * SECONDS_PER_YEAR);
+        let now = last_update_timestamp + rng.gen_range(0..=20 * SECONDS_PER_YEAR);
+        let config = InterestBearingConfig {
+            initialization_timestamp,
+            pre_update_average_rate: rng.gen(),
+            last_update_timestamp,
+            current_rate: rng.gen(),
+        };
+
+        let before_update = last_update_timestamp - initialization_timestamp;
+        let since_update = now - last_update_timestamp;
+        let rate_seconds = config.pre_update_average_rate as f64 * before_update as f64
+            + config.current_rate as f64 * since_update as f64;
+        let exact = (rate_seconds / SECONDS_PER_YEAR as f64 / 10_000.0).exp() * 2f64.powi(64);
+        match config.scale(now) {
+            Some(scale) => assert!((scale as f64 - exact).abs() <= (exact * 1e-9).max(2.0), "{scale} vs {exact}"),
+            None => assert!(exact >= 2f64.powi(126)),
+        }
+    }
+}
+
+/// A referred swap with the protocol and referral shares at their maximums
+/// and position liquidity in the pool never shrinks the constant product
+#[test]
+fn carved_fees_never_shrink_the_product() {
+    let mut rng = StdRng::seed_from_u64(SEED + 45);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.fee_rate = rng.gen_range(0..=100);
+        state.protocol_fee_share_bps = MAX_PROTOCOL_FEE_SHARE_BPS.get();
+        state.lp_supply = amount(&mut rng);
+        state.position_lp_supply = rng.gen_range(0..=state.lp_supply);
+        let (amount_in, is_sol_input) = (amount(&mut rng), rng.gen());
+        let Ok(amount_out) = Processor::calculate_swap_output(&state, SwapAmount::new(amount_in, is_sol_input)) else {
+            continue;
+        };
+        let amount_out = amount_out.get();
+
+        let fee_rate = Bps(state.fee_rate);
+        let referral_fee = Processor::calculate_protocol_fee(amount_in, fee_rate, MAX_REFERRAL_FEE_SHARE_BPS).unwrap();
+        let fees = Processor::split_swap_fee(&state, amount_in, fee_rate, referral_fee).unwrap();
+        let trading_fee = math::mul_div(amount_in as u128, fee_rate.get() as u128, 10000, Rounding::Up).unwrap();
+        assert!(fees.protocol_fee as u128 + fees.carved_fee as u128 + fees.position_fee as u128 <= trading_fee);
+
+        let (reserve_in, reserve_out) = if is_sol_input {
+            (state.sol_reserve, state.token_reserve)
+        } else {
+            (state.token_reserve, state.sol_reserve)
+        };
+        let new_reserve_in = big(reserve_in) + big(fees.reserve_amount_in(amount_in).unwrap());
+        assert!(new_reserve_in * big(reserve_out - amount_out) >= big(reserve_in) * big(reserve_out));
+    }
+}
+
+/// Every curve's spot price lies between what buying and selling the token
+/// execute at, and a trade of a millionth of the reserve executes within 1%
+/// of it
+#[test]
+fn spot_price_brackets_execution_prices() {
+    let mut rng = StdRng::seed_from_u64(SEED + 46);
+    let q64 = BigUint::from(math::Q64);
+    for _ in 0..CASES {
+        let (sol_reserve, token_reserve) = (rng.gen_range(1 << 32..1 << 56), rng.gen_range(1 << 32..1 << 56));
+        let curve: Box<dyn SwapCurve> = match rng.gen_range(0..4) {
+            0 => Box::new(ConstantProduct),
+            1 => Box::new(Stable { amplification: rng.gen_range(1..=MAX_AMPLIFICATION) }),
+            2 => Box::new(Offset { sol_offset: rng.gen_range(0..1 << 56), token_offset: rng.gen_range(0..1 << 56) }),
+            _ => {
+                let sol_weight = rng.gen_range(MIN_WEIGHT_BPS..=10000 - MIN_WEIGHT_BPS);
+                Box::new(Weighted { sol_weight, token_weight: 10000 - sol_weight })
+            }
+        };
+        let Some(spot) = curve.spot_price(Lamports(sol_reserve), TokenAmount(token_reserve)) else {
+            continue;
+        };
+        let spot_lower = BigUint::from((spot - (spot >> 32)).saturating_sub(1));
+        let spot_upper = BigUint::from(spot + (spot >> 32) + 1);
+
+        // Buying tokens pays at least the spot price and selling them earns at most it
+        let sol_in = rng.gen_range(1..=sol_reserve);
+        let swap_out = |amount_in| curve.swap_out(amount_in, Lamports(sol_reserve), TokenAmount(token_reserve), Bps(0));
+        if let Ok(tokens_out) = swap_out(SwapAmount::Sol(Lamports(sol_in))) {
+            assert!(&spot_lower * big(tokens_out.get()) <= big(sol_in) * &q64, "bought below spot");
+        }
+        let token_in = rng.gen_range(1..=token_reserve);
+        if let Ok(sol_out) = swap_out(SwapAmount::Token(TokenAmount(token_in))) {
+            assert!(big(sol_out.get()) * &q64 <= &spot_upper * big(token_in), "sold above spot");
+        }
+
+        let sol_in = sol_reserve >> 20;
+        let Ok(tokens_out) = swap_out(SwapAmount::Sol(Lamports(sol_in))) else {
+            continue;
+        };
+        let tokens_out = tokens_out.get();
+        if tokens_out >= 1 << 12 {
+            assert!(big(sol_in) * &q64 * 100u32 <= &spot_upper * big(tokens_out) * 101u32, "small trade far from spot");
+        }
+    }
+}
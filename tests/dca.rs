@@ -0,0 +1,59 @@
+// DCA tests
+// Checks that DCA tranches drain their escrow exactly
+
+mod common;
+
+use borsh::BorshSerialize;
+use common::{amount, big, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{processor::MAX_DCA_KEEPER_TIP_BPS, state::{DcaPosition, LIMIT_PRICE_SCALE}};
+use solana_program::pubkey::Pubkey;
+
+/// DCA tranches sell exactly the escrow in `ceil(amount / amount_per_fill)`
+/// fills, tip the keeper the rounded-down bps share, and floor each fill at
+/// the rounded-up limit price
+#[test]
+fn dca_tranches_drain_the_escrow_exactly() {
+    let mut rng = StdRng::seed_from_u64(SEED + 36);
+    for _ in 0..CASES {
+        let escrow = amount(&mut rng);
+        let mut position = DcaPosition {
+            owner: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            dca_id: rng.gen(),
+            is_sol_input: rng.gen(),
+            amount_per_fill: escrow.div_ceil(rng.gen_range(1..=64)),
+            interval_slots: rng.gen(),
+            next_fill_slot: 0,
+            remaining_amount: escrow,
+            filled_output: 0,
+            limit_price: amount(&mut rng) >> rng.gen_range(0..64),
+            keeper_tip_bps: rng.gen_range(0..=MAX_DCA_KEEPER_TIP_BPS.get()),
+            bump: rng.gen(),
+        };
+        assert_eq!(position.try_to_vec().unwrap().len(), DcaPosition::LEN);
+
+        let mut fills = 0;
+        while position.remaining_amount > 0 {
+            let fill = position.next_fill_amount();
+            assert!(fill > 0 && fill <= position.amount_per_fill);
+
+            let tip = position.keeper_tip(fill);
+            let exact_tip = big(fill) * big(position.keeper_tip_bps);
+            assert!(big(tip) * big(10000) <= exact_tip && exact_tip < big(tip + 1) * big(10000));
+
+            let exact_floor = big(fill) * big(position.limit_price);
+            match position.required_output(fill) {
+                Some(floor) => {
+                    assert!(big(floor) * big(LIMIT_PRICE_SCALE) >= exact_floor);
+                    assert!(floor == 0 || big(floor - 1) * big(LIMIT_PRICE_SCALE) < exact_floor);
+                }
+                None => assert!(exact_floor > big(u64::MAX) * big(LIMIT_PRICE_SCALE)),
+            }
+
+            position.remaining_amount -= fill;
+            fills += 1;
+        }
+        assert_eq!(fills, escrow.div_ceil(position.amount_per_fill));
+    }
+}
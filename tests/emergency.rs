@@ -0,0 +1,84 @@
+// Emergency and reserve sync tests
+// Checks emergency withdrawals, reserve syncs and skims against the vault balances
+
+mod common;
+
+use common::{amount, big, pool, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::processor::Processor;
+
+/// Emergency withdrawals pay each LP their floor share of the vault net of
+/// set-aside fees, so LPs exiting one after another never overdraw the vault
+#[test]
+fn emergency_withdrawals_never_overdraw_the_vault() {
+    let mut rng = StdRng::seed_from_u64(SEED + 40);
+    for _ in 0..CASES {
+        let mut balance = amount(&mut rng);
+        let set_aside = rng.gen_range(0..=balance);
+        let mut supply = amount(&mut rng).max(1);
+        let available = balance - set_aside;
+
+        let mut paid = 0u64;
+        while supply > 0 {
+            let lp_tokens = rng.gen_range(1..=supply);
+            let share = Processor::emergency_withdraw_amount(balance, set_aside, lp_tokens, supply).unwrap();
+            let exact = big(balance - set_aside) * big(lp_tokens);
+            assert!(big(share) * big(supply) <= exact && exact < big(share + 1) * big(supply));
+            balance -= share;
+            supply -= lp_tokens;
+            paid += share;
+        }
+        assert_eq!(paid, available);
+        assert_eq!(balance, set_aside);
+        assert!(Processor::emergency_withdraw_amount(balance, set_aside, 1, 0).is_err());
+    }
+}
+
+/// Syncing accounts for exactly the vault balance beyond the reserves and
+/// set-aside fees, into the reserves or the protocol fees, and is idempotent
+#[test]
+fn sync_reserves_absorbs_only_the_excess() {
+    let mut rng = StdRng::seed_from_u64(SEED + 41);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng) / 4, amount(&mut rng) / 4);
+        state.protocol_fee_sol = amount(&mut rng) / 8;
+        state.lp_fees_token = amount(&mut rng) / 8;
+        let (sol_reserve, token_reserve) = (state.sol_reserve, state.token_reserve);
+        let (sol_owed, token_owed) = (sol_reserve + state.protocol_fee_sol, token_reserve + state.lp_fees_token);
+        let (protocol_fee_sol, protocol_fee_token) = (state.protocol_fee_sol, state.protocol_fee_token);
+        let sol_balance = sol_owed.saturating_add_signed(rng.gen_range(-1000..=1000));
+        let token_balance = token_owed + amount(&mut rng) / 4;
+        let skim = rng.gen();
+
+        let [sol_excess, token_excess] = state.sync_reserves(sol_balance, token_balance, skim).unwrap();
+        assert_eq!(sol_excess, sol_balance.saturating_sub(sol_owed));
+        assert_eq!(token_excess, token_balance - token_owed);
+        if skim {
+            assert_eq!((state.sol_reserve, state.token_reserve), (sol_reserve, token_reserve));
+            assert_eq!(state.protocol_fee_sol, protocol_fee_sol + sol_excess);
+            assert_eq!(state.protocol_fee_token, protocol_fee_token + token_excess);
+        } else {
+            assert_eq!(state.sol_reserve, sol_reserve + sol_excess);
+            assert_eq!(state.token_reserve + state.lp_fees_token, token_balance);
+        }
+        assert_eq!(state.sync_reserves(sol_balance, token_balance, skim).unwrap(), [0, 0]);
+    }
+}
+
+/// Skim pays out exactly what a sync would fold in, after which the vaults
+/// hold no excess
+#[test]
+fn skim_takes_the_excess_sync_would_fold_in() {
+    let mut rng = StdRng::seed_from_u64(SEED + 42);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng) / 4, amount(&mut rng) / 4);
+        state.lp_fees_sol = amount(&mut rng) / 8;
+        state.protocol_fee_token = amount(&mut rng) / 8;
+        let sol_balance = state.sol_reserve + state.lp_fees_sol + amount(&mut rng) / 4;
+        let token_balance = (state.token_reserve + state.protocol_fee_token).saturating_sub(rng.gen_range(0..=1000));
+
+        let [sol_excess, token_excess] = state.vault_excess(sol_balance, token_balance);
+        assert_eq!(state.vault_excess(sol_balance - sol_excess, token_balance - token_excess), [0, 0]);
+        assert_eq!(state.sync_reserves(sol_balance, token_balance, rng.gen()).unwrap(), [sol_excess, token_excess]);
+    }
+}
@@ -0,0 +1,86 @@
+// Farm tests
+// Checks farm rewards against what the farm emitted
+
+mod common;
+
+use common::{amount, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::farm::{FarmReward, FarmStake, FarmState, MAX_FARM_REWARDS};
+use solana_program::pubkey::Pubkey;
+
+/// Farm stakers are never owed more of any reward than the farm released
+/// while they were staked, and short of it only by rounding
+#[test]
+fn farm_rewards_never_exceed_emission() {
+    let mut rng = StdRng::seed_from_u64(SEED + 21);
+    for _ in 0..CASES / 10 {
+        let rewards = [(); MAX_FARM_REWARDS].map(|_| FarmReward {
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            emission_rate: rng.gen_range(1..=u32::MAX as u64),
+            emission_end_slot: rng.gen_range(1..=1_000_000),
+            reward_per_share: 0,
+            last_update_slot: 0,
+        });
+        let mut farm = FarmState {
+            pool: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            lp_vault: Pubkey::new_unique(),
+            rewards,
+            total_staked: 0,
+            bump: 0,
+        };
+        let mut stakes: Vec<FarmStake> = (0..rng.gen_range(1..=8))
+            .map(|_| FarmStake {
+                farm: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                amount: 0,
+                reward_per_share_checkpoints: [0; MAX_FARM_REWARDS],
+                rewards_owed: [0; MAX_FARM_REWARDS],
+                bump: 0,
+            })
+            .collect();
+
+        // Stake and unstake at random slots, tracking what each reward released while anything was staked
+        let (mut slot, mut released, mut steps) = (0u64, [0u128; MAX_FARM_REWARDS], 0u128);
+        for _ in 0..rng.gen_range(1..=16) {
+            let next = slot + rng.gen_range(0..=100_000);
+            if farm.total_staked > 0 {
+                for (reward, released) in farm.rewards.iter().zip(&mut released) {
+                    let end = reward.emission_end_slot;
+                    *released += reward.emission_rate as u128 * (next.min(end).saturating_sub(slot.min(end))) as u128;
+                }
+            }
+            slot = next;
+            farm.accrue(slot).unwrap();
+            steps += 1;
+
+            let index = rng.gen_range(0..stakes.len());
+            let stake = &mut stakes[index];
+            stake.settle(&farm).unwrap();
+            if rng.gen_bool(0.5) {
+                let amount = amount(&mut rng) >> 8;
+                stake.amount += amount;
+                farm.total_staked += amount;
+            } else {
+                let amount = rng.gen_range(0..=stake.amount);
+                stake.amount -= amount;
+                farm.total_staked -= amount;
+            }
+        }
+
+        let mut owed = [0u128; MAX_FARM_REWARDS];
+        for stake in &mut stakes {
+            stake.settle(&farm).unwrap();
+            for (owed, rewards_owed) in owed.iter_mut().zip(stake.rewards_owed) {
+                *owed += rewards_owed as u128;
+            }
+        }
+        // Each accrual and each settlement rounds down by under one unit
+        let settlements = steps + stakes.len() as u128;
+        for (owed, released) in owed.into_iter().zip(released) {
+            assert!(owed <= released, "stakers owed {owed} of {released} released");
+            assert!(owed + steps + settlements >= released, "stakers shorted beyond rounding");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// Fee tests
+// Checks the protocol, referral, launch, variable and keeper fees against their bounds
+
+mod common;
+
+use common::{amount, big, pool, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{
+    amount::Bps,
+    client::SwapTxBuilder,
+    instruction::TokenExchangeInstruction,
+    math::{self, Rounding},
+    processor::{
+        Processor, MAX_KEEPER_BOUNTY_BPS, MAX_PLATFORM_FEE_BPS, MAX_PROTOCOL_FEE_SHARE_BPS,
+        MAX_REFERRAL_FEE_SHARE_BPS,
+    },
+    state::{ProtocolConfig, VOLATILITY_HALF_LIFE_SLOTS},
+};
+use solana_program::pubkey::Pubkey;
+
+/// The protocol and referral shares at their maximums never exceed the
+/// trading fee, and a referred swap carries its share and referrer
+#[test]
+fn referral_and_protocol_shares_fit_in_fee() {
+    let mut rng = StdRng::seed_from_u64(SEED + 22);
+    for _ in 0..CASES {
+        let amount_in = amount(&mut rng);
+        let fee_rate = Bps(rng.gen_range(0..=100));
+        let trading_fee = math::mul_div(amount_in as u128, fee_rate.get() as u128, 10000, Rounding::Up).unwrap();
+        let protocol_fee = Processor::calculate_protocol_fee(amount_in, fee_rate, MAX_PROTOCOL_FEE_SHARE_BPS).unwrap();
+        let referral_fee = Processor::calculate_protocol_fee(amount_in, fee_rate, MAX_REFERRAL_FEE_SHARE_BPS).unwrap();
+        assert!(protocol_fee as u128 + referral_fee as u128 <= trading_fee);
+    }
+
+    let program_id = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let share = Bps(rng.gen_range(1..=MAX_REFERRAL_FEE_SHARE_BPS.get()));
+    let builder = SwapTxBuilder::new(
+        program_id,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        amount(&mut rng),
+        0,
+        true,
+    )
+    .compute_unit_limit(None)
+    .create_token_account(false)
+    .referral(share, referrer);
+    let swap = builder.build().unwrap().pop().unwrap();
+    assert_eq!(swap.accounts[7].pubkey, ProtocolConfig::find_address(&program_id).0);
+    assert_eq!(swap.accounts[8].pubkey, referrer);
+    match TokenExchangeInstruction::unpack(&swap.data).unwrap() {
+        TokenExchangeInstruction::Swap { referral_fee_share_bps, .. } => assert_eq!(referral_fee_share_bps, share),
+        other => panic!("decoded {other:?}"),
+    }
+
+    // An integrator fee alone also passes the config, which bounds it
+    let fee_account = Pubkey::new_unique();
+    let swap = SwapTxBuilder::new(
+        program_id,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        amount(&mut rng),
+        0,
+        true,
+    )
+    .compute_unit_limit(None)
+    .create_token_account(false)
+    .platform_fee(MAX_PLATFORM_FEE_BPS, fee_account)
+    .build()
+    .unwrap()
+    .pop()
+    .unwrap();
+    assert_eq!(swap.accounts[7].pubkey, fee_account);
+    assert_eq!(swap.accounts[8].pubkey, ProtocolConfig::find_address(&program_id).0);
+    assert_eq!(swap.accounts.len(), 9);
+}
+
+/// The launch fee starts at its configured rate, never rises as time passes,
+/// and reaches the base fee once its decay ends
+#[test]
+fn launch_fee_decays_to_base_fee() {
+    let mut rng = StdRng::seed_from_u64(SEED + 24);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.fee_rate = rng.gen_range(0..=100);
+        state.launch_fee_bps = rng.gen_range(state.fee_rate + 1..=5000);
+        state.launch_fee_decay_seconds = rng.gen_range(1..=3600);
+        state.trade_start_timestamp = rng.gen_range(0..=i64::MAX / 2);
+        let start = state.trade_start_timestamp;
+        let end = start + state.launch_fee_decay_seconds as i64;
+
+        assert_eq!(state.fee_rate_at(start - rng.gen_range(0..=3600)), Bps(state.launch_fee_bps));
+        assert_eq!(state.fee_rate_at(end + rng.gen_range(0..=3600)), Bps(state.fee_rate));
+        let earlier = rng.gen_range(start..=end);
+        let later = rng.gen_range(earlier..=end);
+        let (earlier_fee, later_fee) = (state.fee_rate_at(earlier), state.fee_rate_at(later));
+        assert!(later_fee <= earlier_fee && later_fee.get() >= state.fee_rate, "{earlier_fee:?} then {later_fee:?}");
+    }
+}
+
+/// The variable fee never exceeds its cap and fades as volatility decays,
+/// and a swap's price move only ever adds to the decayed accumulator
+#[test]
+fn variable_fee_tracks_decaying_volatility() {
+    let mut rng = StdRng::seed_from_u64(SEED + 25);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.volatility_accumulator = amount(&mut rng);
+        state.volatility_update_slot = rng.gen_range(0..=u64::MAX / 2);
+        state.variable_fee_control = rng.gen_range(0..=1_000_000);
+        state.max_variable_fee_bps = rng.gen_range(0..=100);
+        let start = state.volatility_update_slot;
+
+        let earlier = start + rng.gen_range(0..=10 * VOLATILITY_HALF_LIFE_SLOTS);
+        let later = earlier + rng.gen_range(0..=10 * VOLATILITY_HALF_LIFE_SLOTS);
+        assert_eq!(state.volatility_at(start), state.volatility_accumulator);
+        assert!(state.volatility_at(later) <= state.volatility_at(earlier));
+        assert!(state.variable_fee_at(later) <= state.variable_fee_at(earlier));
+        assert!(state.variable_fee_at(earlier) <= state.max_variable_fee_bps);
+        assert_eq!(state.volatility_at(start + 64 * VOLATILITY_HALF_LIFE_SLOTS), 0);
+
+        // An unchanged price only decays the accumulator
+        let (sol_reserve, token_reserve) = (state.sol_reserve, state.token_reserve);
+        let decayed = state.volatility_at(later);
+        state.accumulate_volatility(later, state.price_movement_bps(sol_reserve, token_reserve));
+        assert_eq!((state.volatility_accumulator, state.volatility_update_slot), (decayed, later));
+
+        // Doubling the price adds at least a full 10000 bps
+        if let Some(doubled) = sol_reserve.checked_mul(2) {
+            state.sol_reserve = doubled;
+            state.accumulate_volatility(later, state.price_movement_bps(sol_reserve, token_reserve));
+            assert!(state.volatility_accumulator >= decayed.saturating_add(10000), "{state:?}");
+        }
+    }
+}
+
+/// A keeper bounty is the rounded-down bps share of the trading fee on the
+/// input, so it never exceeds the fee itself
+#[test]
+fn keeper_bounty_stays_within_the_fee() {
+    let mut rng = StdRng::seed_from_u64(SEED + 37);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.fee_rate = rng.gen_range(0..=1000);
+        state.keeper_bounty_bps = rng.gen_range(0..=MAX_KEEPER_BOUNTY_BPS.get());
+        let amount_in = amount(&mut rng);
+
+        let bounty = state.keeper_bounty(amount_in);
+        let exact = big(amount_in) * big(state.fee_rate) * big(state.keeper_bounty_bps);
+        assert!(big(bounty) * big(10000 * 10000) <= exact && exact < big(bounty + 1) * big(10000 * 10000));
+        assert!(big(bounty) * big(10000) <= big(amount_in) * big(state.fee_rate));
+    }
+}
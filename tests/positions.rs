@@ -0,0 +1,59 @@
+// Position tests
+// Checks the fees user positions claim against what the pool set aside
+
+mod common;
+
+use common::{amount, big, pool, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{processor::Processor, state::UserPosition};
+use solana_program::pubkey::Pubkey;
+
+/// Positions claim their share of the fees set aside for them, and together
+/// never more than was set aside
+#[test]
+fn position_fees_never_exceed_set_aside() {
+    let mut rng = StdRng::seed_from_u64(SEED + 20);
+    for _ in 0..CASES / 10 {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        let mut positions: Vec<UserPosition> = (0..rng.gen_range(1..=4))
+            .map(|_| UserPosition {
+                discriminator: UserPosition::DISCRIMINATOR,
+                owner: Pubkey::new_unique(),
+                pool: Pubkey::default(),
+                lp_tokens: rng.gen_range(1..=u32::MAX as u64),
+                fee_growth_checkpoint_sol: state.fee_growth_sol,
+                fee_growth_checkpoint_token: state.fee_growth_token,
+                fees_owed_sol: 0,
+                fees_owed_token: 0,
+                bump: 0,
+            })
+            .collect();
+        state.position_lp_supply = positions.iter().map(|position| position.lp_tokens).sum();
+        state.lp_supply = state.position_lp_supply + rng.gen_range(0..=u32::MAX as u64);
+
+        let mut lp_fees = 0u64;
+        for _ in 0..rng.gen_range(1..=8) {
+            let lp_fee = rng.gen_range(0..=u32::MAX as u64);
+            let (position_fee, fee_growth) = Processor::position_fee_share(&state, lp_fee).unwrap();
+            assert!(position_fee <= lp_fee);
+            state.fee_growth_sol = state.fee_growth_sol.wrapping_add(fee_growth);
+            lp_fees += position_fee;
+
+            // Settling between swaps must not change what is owed in total
+            let index = rng.gen_range(0..positions.len());
+            positions[index].settle(&state).unwrap();
+        }
+
+        let mut claimed = 0u64;
+        for position in &mut positions {
+            position.settle(&state).unwrap();
+            assert_eq!(position.fee_growth_checkpoint_sol, state.fee_growth_sol);
+            let fair = big(lp_fees) * big(position.lp_tokens) / big(state.position_lp_supply);
+            assert!(big(position.fees_owed_sol) <= fair, "position paid more than its share");
+            // Each swap's growth and each settlement round down by under one unit
+            assert!(big(position.fees_owed_sol) + big(2 * 9) >= fair, "position shorted beyond rounding");
+            claimed += position.fees_owed_sol;
+        }
+        assert!(claimed <= lp_fees, "claims {claimed} exceed set-aside fees {lp_fees}");
+    }
+}
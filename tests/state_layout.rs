@@ -0,0 +1,230 @@
+// Account layout tests
+// Checks account discriminators, layout versions, the zero-copy pool view and derived PDAs
+
+mod common;
+
+use borsh::BorshSerialize;
+use common::{amount, pool, CASES, SEED};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{
+    instruction::TokenExchangeInstruction,
+    processor::DEFAULT_FEE_TIERS_BPS,
+    state::{
+        CurveType, PoolRegistry, PoolRegistryEntry, PoolState, PoolStatePod, ProtocolParameters, StateVersion,
+        UserPosition, MAX_FEE_TIERS,
+    },
+};
+use solana_program::pubkey::Pubkey;
+
+/// Pool and position data only unpack as their own account type
+#[test]
+fn discriminators_separate_account_types() {
+    let mut rng = StdRng::seed_from_u64(SEED + 13);
+    for _ in 0..CASES {
+        let pool_data = pool(amount(&mut rng), amount(&mut rng)).try_to_vec().unwrap();
+        let position_data = UserPosition {
+            discriminator: UserPosition::DISCRIMINATOR,
+            owner: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            lp_tokens: amount(&mut rng),
+            fee_growth_checkpoint_sol: rng.gen(),
+            fee_growth_checkpoint_token: rng.gen(),
+            fees_owed_sol: amount(&mut rng),
+            fees_owed_token: amount(&mut rng),
+            bump: rng.gen(),
+        }
+        .try_to_vec()
+        .unwrap();
+        assert_eq!(pool_data.len(), PoolState::LEN);
+        assert_eq!(position_data.len(), UserPosition::LEN);
+
+        assert!(PoolState::unpack(&pool_data).is_ok());
+        assert!(UserPosition::unpack(&position_data).is_ok());
+        assert!(PoolState::unpack(&position_data).is_err(), "position data parsed as a pool");
+        assert!(UserPosition::unpack(&pool_data[..UserPosition::LEN]).is_err(), "pool data parsed as a position");
+    }
+}
+
+/// Current pools detect as the current layout and migrate to themselves,
+/// while baseline pools and unknown versions are rejected
+#[test]
+fn only_versioned_pools_migrate() {
+    let mut rng = StdRng::seed_from_u64(SEED + 14);
+    for _ in 0..CASES {
+        let state = pool(amount(&mut rng), amount(&mut rng));
+        let current = state.try_to_vec().unwrap();
+        assert_eq!(StateVersion::detect(&current).unwrap(), StateVersion::CURRENT);
+
+        let mut data = current.clone();
+        StateVersion::CURRENT.upgrade(&mut data).unwrap();
+        assert_eq!(data, current);
+        assert!(StateVersion::CURRENT.upgrade(&mut data[1..]).is_err(), "upgraded a short buffer");
+
+        // The baseline layout: authority, reserves, LP mint, fee rate, token
+        // mint and initialized flag, with no discriminator or version byte
+        let baseline = [
+            &state.authority.to_bytes()[..],
+            &state.sol_reserve.to_le_bytes(),
+            &state.token_reserve.to_le_bytes(),
+            &state.lp_mint.to_bytes(),
+            &state.fee_rate.to_le_bytes(),
+            &state.token_mint.to_bytes(),
+            &[1],
+        ]
+        .concat();
+        assert_eq!(baseline.len(), 121);
+        assert!(StateVersion::detect(&baseline).is_err(), "baseline pool detected as versioned");
+        assert!(PoolState::unpack(&baseline).is_err(), "baseline pool loaded");
+
+        let mut unknown = current.clone();
+        unknown[8] = rng.gen_range(2..=u8::MAX);
+        assert!(StateVersion::detect(&unknown).is_err(), "unknown version {} detected", unknown[8]);
+        assert!(PoolState::unpack(&unknown).is_err(), "unknown version {} loaded", unknown[8]);
+    }
+}
+
+/// The zero-copy view reads the Borsh layout exactly, and a swap's write-back
+/// leaves the same bytes as re-serializing the whole state
+#[test]
+fn pod_view_matches_borsh_layout() {
+    assert_eq!(std::mem::size_of::<PoolStatePod>(), PoolState::LEN);
+    let mut rng = StdRng::seed_from_u64(SEED + 15);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.fee_rate = rng.gen_range(0..10000);
+        state.curve_type = CurveType::Offset;
+        state.token_offset = amount(&mut rng);
+        state.price_cumulative_last = rng.gen();
+        state.paused = rng.gen();
+        state.trade_start_timestamp = rng.gen();
+        state.max_swap_per_wallet_during_launch = amount(&mut rng);
+        state.launch_fee_bps = rng.gen_range(0..=5000);
+        state.variable_fee_control = rng.gen();
+        state.price_oracle = Pubkey::new_unique();
+        state.weights = [rng.gen(), rng.gen()];
+        state.sol_offset = amount(&mut rng);
+        state.graduation_threshold = amount(&mut rng);
+        state.max_outflow_bps = rng.gen_range(0..=10000);
+        state.emergency = rng.gen();
+        let mut data = state.try_to_vec().unwrap();
+        let loaded = PoolStatePod::load(&data).unwrap().to_state().unwrap();
+        assert_eq!(loaded.try_to_vec().unwrap(), data);
+
+        state.sol_reserve = amount(&mut rng);
+        state.token_reserve = amount(&mut rng);
+        state.protocol_fee_sol = amount(&mut rng);
+        state.protocol_fee_token = amount(&mut rng);
+        state.price_cumulative_last = rng.gen();
+        state.last_update_slot = amount(&mut rng);
+        state.cumulative_sol_volume = rng.gen();
+        state.cumulative_token_volume = rng.gen();
+        state.cumulative_fees = rng.gen();
+        state.fee_growth_sol = rng.gen();
+        state.fee_growth_token = rng.gen();
+        state.lp_fees_sol = amount(&mut rng);
+        state.lp_fees_token = amount(&mut rng);
+        state.volatility_accumulator = amount(&mut rng);
+        state.volatility_update_slot = amount(&mut rng);
+        state.ema_price = rng.gen();
+        state.ema_update_slot = amount(&mut rng);
+        state.outflow_slot = amount(&mut rng);
+        state.outflow_this_slot = [amount(&mut rng), amount(&mut rng)];
+        PoolStatePod::load_mut(&mut data).unwrap().store_swap(&state);
+        assert_eq!(data, state.try_to_vec().unwrap());
+    }
+}
+
+/// Registry entries serialize at their declared size and each index derives
+/// its own address, distinct from the registry
+#[test]
+fn registry_entries_have_distinct_addresses() {
+    let program_id = Pubkey::new_unique();
+    let registry = PoolRegistry::find_address(&program_id).0;
+    let mut rng = StdRng::seed_from_u64(SEED + 16);
+    for _ in 0..64 {
+        let index = rng.gen();
+        let (address, bump) = PoolRegistryEntry::find_address(index, &program_id);
+        let entry = PoolRegistryEntry {
+            index,
+            pool: Pubkey::new_unique(),
+            mint_a: Pubkey::default(),
+            token_mint: Pubkey::new_unique(),
+            fee_tier: rng.gen_range(0..10000),
+            bump,
+        };
+        assert_eq!(entry.try_to_vec().unwrap().len(), PoolRegistryEntry::LEN);
+        assert_ne!(address, registry);
+        assert_ne!(address, PoolRegistryEntry::find_address(index.wrapping_add(1), &program_id).0);
+    }
+}
+
+/// Every offered fee tier of a pair derives its own pool, and only offered
+/// tiers are accepted
+#[test]
+fn fee_tiers_derive_distinct_pools() {
+    let program_id = Pubkey::new_unique();
+    let mut fee_tiers = [0; MAX_FEE_TIERS];
+    fee_tiers[..DEFAULT_FEE_TIERS_BPS.len()].copy_from_slice(&DEFAULT_FEE_TIERS_BPS);
+    let parameters = ProtocolParameters {
+        default_fee_rate_bps: 30,
+        max_fee_rate_bps: 100,
+        protocol_fee_share_bps: 0,
+        pool_creation_fee: 0,
+        treasury: Pubkey::default(),
+        pool_creation_paused: false,
+        fee_tier_count: DEFAULT_FEE_TIERS_BPS.len() as u8,
+        fee_tiers,
+        max_referral_fee_share_bps: 0,
+        max_variable_fee_bps: 0,
+        max_keeper_bounty_bps: 0,
+        max_keeper_tip_bps: 0,
+        skim_excess_reserves: false,
+        max_platform_fee_bps: 0,
+    };
+    assert_eq!(parameters.try_to_vec().unwrap().len(), ProtocolParameters::LEN);
+    assert_eq!(parameters.offered_fee_tiers(), DEFAULT_FEE_TIERS_BPS);
+    assert!(!parameters.allows_fee_tier(0) && !parameters.allows_fee_tier(31));
+
+    let mut rng = StdRng::seed_from_u64(SEED + 17);
+    for _ in 0..64 {
+        let mint_a = if rng.gen() { Pubkey::default() } else { Pubkey::new_unique() };
+        let token_mint = Pubkey::new_unique();
+        let pools = PoolState::find_tier_addresses(&mint_a, &token_mint, parameters.offered_fee_tiers(), &program_id);
+        assert_eq!(pools.len(), DEFAULT_FEE_TIERS_BPS.len());
+        for (i, &(fee_tier, pool)) in pools.iter().enumerate() {
+            assert_eq!(pool, PoolState::find_address(&mint_a, &token_mint, fee_tier, &program_id).0);
+            assert!(pools[..i].iter().all(|&(_, other)| other != pool));
+        }
+    }
+}
+
+/// A position's stored bump recomputes its PDA, and position instructions
+/// keep their tags
+#[test]
+fn position_address_recomputes_from_bump() {
+    let program_id = Pubkey::new_unique();
+    for _ in 0..CASES / 100 {
+        let (pool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (address, bump) = UserPosition::find_address(&pool, &owner, &program_id);
+        let mut position = UserPosition {
+            discriminator: UserPosition::DISCRIMINATOR,
+            owner,
+            pool,
+            lp_tokens: 0,
+            fee_growth_checkpoint_sol: 0,
+            fee_growth_checkpoint_token: 0,
+            fees_owed_sol: 0,
+            fees_owed_token: 0,
+            bump,
+        };
+        assert_eq!(position.address(&program_id).unwrap(), address);
+        position.pool = Pubkey::new_unique();
+        assert_ne!(position.address(&program_id).ok(), Some(address), "position moved pools");
+    }
+    assert!(matches!(TokenExchangeInstruction::unpack(&[56]).unwrap(), TokenExchangeInstruction::ClaimFees));
+    assert!(matches!(
+        TokenExchangeInstruction::unpack(&[57]).unwrap(),
+        TokenExchangeInstruction::InitializeUserPosition
+    ));
+    assert!(matches!(TokenExchangeInstruction::unpack(&[58]).unwrap(), TokenExchangeInstruction::CloseUserPosition));
+}
@@ -0,0 +1,252 @@
+// Swap guard tests
+// Checks launch protection, price impact, oracle bands, outflow limits and sandwich protection
+
+mod common;
+
+use common::{amount, big, pool, CASES, SEED};
+use num_bigint::BigUint;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use simple_token_exchange::{
+    amount::{Lamports, SwapAmount},
+    client::SwapTxBuilder,
+    instruction::TokenExchangeInstruction,
+    processor::Processor,
+    sandwich,
+    state::EMA_HALF_LIFE_SLOTS,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    sysvar::{
+        self,
+        instructions::{construct_instructions_data, store_current_index, BorrowedAccountMeta, BorrowedInstruction},
+    },
+};
+
+/// Launch protection blocks every swap before trade start and caps only buys,
+/// only inside the launch window
+#[test]
+fn launch_protection_caps_buys_in_window() {
+    let mut rng = StdRng::seed_from_u64(SEED + 23);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.trade_start_timestamp = rng.gen_range(0..=i64::MAX / 2);
+        state.launch_window_seconds = rng.gen_range(0..=3600);
+        state.max_swap_per_wallet_during_launch = amount(&mut rng);
+        let (is_sol_input, amount_out) = (rng.gen(), amount(&mut rng));
+        let now = state.trade_start_timestamp + rng.gen_range(-7200..=7200);
+
+        let allowed = state.check_launch_protection(now, is_sol_input, amount_out).is_ok();
+        let started = now >= state.trade_start_timestamp;
+        let in_window = now < state.trade_start_timestamp + state.launch_window_seconds as i64;
+        let capped = is_sol_input && in_window && amount_out > state.max_swap_per_wallet_during_launch;
+        assert_eq!(allowed, started && !capped, "now {now} in {state:?}");
+    }
+}
+
+/// The measured price move matches the exact move of the reserve ratio, and
+/// the price impact limit rejects exactly the swaps that move it further
+#[test]
+fn price_impact_limit_bounds_swap_moves() {
+    let mut rng = StdRng::seed_from_u64(SEED + 26);
+    for _ in 0..CASES {
+        let (sol_before, token_before) = (amount(&mut rng) >> 16, amount(&mut rng) >> 16);
+        let mut state = pool(sol_before.max(1), token_before.max(1));
+        let amount_in = rng.gen_range(0..=state.sol_reserve);
+        let amount_out = Processor::calculate_swap_output(&state, SwapAmount::Sol(Lamports(amount_in))).unwrap().get();
+        let (sol_before, token_before) = (state.sol_reserve, state.token_reserve);
+        state.sol_reserve += amount_in;
+        state.token_reserve -= amount_out;
+
+        // Price rises by sol_after * token_before / (sol_before * token_after) - 1,
+        // at most 4x, so the Q64.64 prices are off by well under a basis point
+        let exact = (BigUint::from(state.sol_reserve) * token_before * 10000u32)
+            / (BigUint::from(sol_before) * state.token_reserve);
+        let movement_bps = state.price_movement_bps(sol_before, token_before);
+        let exact_bps = u64::try_from(exact).unwrap_or(u64::MAX).saturating_sub(10000);
+        assert!(movement_bps.abs_diff(exact_bps) <= 2, "{movement_bps} vs {exact_bps} in {state:?}");
+
+        state.max_price_impact_bps = rng.gen_range(0..=20000);
+        let limited = state.max_price_impact_bps > 0 && movement_bps > state.max_price_impact_bps;
+        assert_eq!(state.check_price_impact(movement_bps).is_err(), limited);
+    }
+}
+
+/// A pool with an oracle accepts exactly the swaps executing within its band
+/// of the oracle price, and none without a price
+#[test]
+fn oracle_band_bounds_execution_price() {
+    let mut rng = StdRng::seed_from_u64(SEED + 27);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        let oracle_price = rng.gen_range(1_000_000..=1_000_000_000_000u64);
+        let token_amount = rng.gen_range(1_000_000_000..=1_000_000_000_000u64);
+        assert!(state.check_oracle_band(None, 1, 0).is_ok());
+
+        state.price_oracle = Pubkey::new_unique();
+        state.oracle_max_deviation_bps = rng.gen_range(1..=5000);
+        assert!(state.check_oracle_band(None, 1, 1).is_err());
+
+        // Execute at the oracle price moved by `deviation_bps` either way
+        let deviation_bps = rng.gen_range(0..=6000u64);
+        let execution_price = if rng.gen() {
+            oracle_price as u128 * (10000 + deviation_bps) as u128 / 10000
+        } else {
+            oracle_price as u128 * (10000 - deviation_bps) as u128 / 10000
+        };
+        let sol_amount = (execution_price * token_amount as u128 / 1_000_000_000) as u64;
+        if deviation_bps.abs_diff(state.oracle_max_deviation_bps) <= 1 {
+            continue;
+        }
+        let within = deviation_bps < state.oracle_max_deviation_bps;
+        assert_eq!(
+            state.check_oracle_band(Some(oracle_price), sol_amount, token_amount).is_ok(),
+            within,
+            "{deviation_bps} bps from {oracle_price} in a {} bps band",
+            state.oracle_max_deviation_bps,
+        );
+    }
+}
+
+/// The moving average price moves toward the spot price as slots pass,
+/// never past it, and ignores moves within its own slot
+#[test]
+fn ema_price_converges_to_spot() {
+    let mut rng = StdRng::seed_from_u64(SEED + 28);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        let spot = ((state.sol_reserve as u128) << 64) / state.token_reserve as u128;
+        let start = rng.gen_range(0..=u64::MAX / 2);
+        assert_eq!(state.ema_price_at(start), spot, "an unseeded average reads the spot price");
+
+        state.ema_price = rng.gen_range(1..=u128::MAX >> 1);
+        state.ema_update_slot = start;
+        assert_eq!(state.ema_price_at(start), state.ema_price);
+        assert_eq!(state.ema_price_at(start + 128 * EMA_HALF_LIFE_SLOTS), spot);
+
+        let earlier = start + rng.gen_range(0..=10 * EMA_HALF_LIFE_SLOTS);
+        let later = earlier + rng.gen_range(0..=10 * EMA_HALF_LIFE_SLOTS);
+        let (earlier_gap, later_gap) =
+            (state.ema_price_at(earlier).abs_diff(spot), state.ema_price_at(later).abs_diff(spot));
+        assert!(later_gap <= earlier_gap && earlier_gap <= state.ema_price.abs_diff(spot));
+        assert_eq!(state.ema_price_at(earlier) > spot, state.ema_price > spot && earlier_gap > 0);
+
+        // A full half-life closes at least half the gap
+        let half_life_gap = state.ema_price_at(start + EMA_HALF_LIFE_SLOTS).abs_diff(spot);
+        assert!(half_life_gap <= state.ema_price.abs_diff(spot).div_ceil(2));
+
+        let ema_before = state.ema_price_at(later);
+        state.update_ema_price(later);
+        state.sol_reserve = amount(&mut rng);
+        state.update_ema_price(later);
+        assert_eq!((state.ema_price, state.ema_update_slot), (ema_before, later));
+    }
+}
+
+/// Each reserve loses at most the outflow limit of its start-of-slot balance
+/// within a slot, and the count starts over in the next slot
+#[test]
+fn outflow_limit_caps_each_slot() {
+    let mut rng = StdRng::seed_from_u64(SEED + 39);
+    for _ in 0..CASES {
+        let mut state = pool(amount(&mut rng), amount(&mut rng));
+        state.max_outflow_bps = rng.gen_range(1..=10000);
+        let (sol_start, token_start) = (state.sol_reserve, state.token_reserve);
+        let slot = amount(&mut rng);
+
+        let mut drained = 0u64;
+        for _ in 0..64 {
+            let out = rng.gen_range(0..=state.token_reserve / 4 + 1).min(state.token_reserve);
+            if state.record_outflow(slot, 0, out).is_err() {
+                break;
+            }
+            state.token_reserve -= out;
+            drained += out;
+        }
+        assert!(big(drained) * big(10000) <= big(token_start) * big(state.max_outflow_bps));
+        assert_eq!(state.sol_reserve, sol_start);
+
+        // A new slot measures against the reserves that are left
+        let limit = state.token_reserve as u128 * state.max_outflow_bps as u128 / 10000;
+        assert!(state.record_outflow(slot + 1, 0, limit as u64).is_ok());
+        assert_eq!(state.outflow_this_slot, [0, limit as u64]);
+        state.max_outflow_bps = 0;
+        assert!(state.record_outflow(slot + 1, state.sol_reserve, 0).is_ok());
+    }
+}
+
+/// A sandwich-protected swap passes only when no other instruction in the
+/// transaction references its pool, and the builder passes the sysvar
+#[test]
+fn sandwich_protection_rejects_shared_pools() {
+    let mut rng = StdRng::seed_from_u64(SEED + 38);
+    let program_id = Pubkey::new_unique();
+    let pool = Pubkey::new_unique();
+    let swap = SwapTxBuilder::new(
+        program_id,
+        Pubkey::new_unique(),
+        pool,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        amount(&mut rng),
+        0,
+        true,
+    )
+    .compute_unit_limit(None)
+    .create_token_account(false)
+    .sandwich_protection(true)
+    .build()
+    .unwrap()
+    .pop()
+    .unwrap();
+    assert_eq!(swap.accounts[7].pubkey, sysvar::instructions::id());
+    match TokenExchangeInstruction::unpack(&swap.data).unwrap() {
+        TokenExchangeInstruction::Swap { sandwich_protection, .. } => assert!(sandwich_protection),
+        other => panic!("decoded {other:?}"),
+    }
+
+    for _ in 0..256 {
+        // Surround the swap with unrelated instructions, some touching the pool
+        let mut instructions: Vec<Instruction> = (0..rng.gen_range(0..6))
+            .map(|_| {
+                let mut other = swap.clone();
+                other.program_id = if rng.gen() { program_id } else { Pubkey::new_unique() };
+                for meta in other.accounts.iter_mut() {
+                    if meta.pubkey != pool || rng.gen_bool(0.8) {
+                        meta.pubkey = Pubkey::new_unique();
+                    }
+                }
+                other
+            })
+            .collect();
+        let current_index = rng.gen_range(0..=instructions.len());
+        instructions.insert(current_index, swap.clone());
+        let sandwiched = instructions
+            .iter()
+            .enumerate()
+            .any(|(index, other)| index != current_index && other.accounts.iter().any(|meta| meta.pubkey == pool));
+
+        let borrowed: Vec<_> = instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|meta| BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &instruction.data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        store_current_index(&mut data, current_index as u16);
+        let (key, owner, mut lamports) = (sysvar::instructions::id(), sysvar::id(), 0);
+        let sysvar_account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert_eq!(sandwich::verify_pool_isolated(&sysvar_account, &pool).is_err(), sandwiched);
+    }
+}
@@ -0,0 +1,341 @@
+// Adversarial scenarios against a single pool
+//
+// Each scenario replays an attack sequence and asserts that the program
+// either rejects it or that the pool's LPs lose no value: the stored
+// invariant (sol_reserve * token_reserve) never shrinks and the pool stays
+// solvent against its stored reserves.
+import {
+  Connection,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  sendAndConfirmTransaction,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  getAccount,
+  mintTo,
+  transfer,
+} from "@solana/spl-token";
+import assert from "assert";
+import BN from "bn.js";
+
+// Program ID from our deployment
+const PROGRAM_ID = new PublicKey("F11d9Ct1MHaQhyRGR7TgyPavoCSPkrzhGf6Wh9g41JS");
+
+// Initial reserves and fee of the pool under attack
+const POOL_SOL = 1_000_000_000; // 1 SOL
+const POOL_TOKENS = 1_000_000_000; // 1 Token
+const FEE_RATE = 30; // 0.3%
+
+// Custom error codes (TokenExchangeError discriminants)
+const SLIPPAGE_EXCEEDED = 6;
+const INVALID_TOKEN_VAULT = 37;
+
+interface Pool {
+  pool: PublicKey;
+  tokenMint: PublicKey;
+  vault: PublicKey;
+  solVault: PublicKey;
+}
+
+interface Reserves {
+  sol: bigint;
+  token: bigint;
+}
+
+async function airdrop(connection: Connection, to: PublicKey, lamports: number) {
+  const signature = await connection.requestAirdrop(to, lamports);
+  await connection.confirmTransaction(signature);
+}
+
+// Derives the pool registry and the entry PDA the next created pool is recorded at
+async function nextRegistryEntry(connection: Connection): Promise<[PublicKey, PublicKey]> {
+  const [registry] = PublicKey.findProgramAddressSync([Buffer.from("pool_registry")], PROGRAM_ID);
+  const info = await connection.getAccountInfo(registry);
+  const poolCount = info ? info.data.readBigUInt64LE(0) : 0n;
+  const [entry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_registry_entry"), new BN(poolCount.toString()).toArrayLike(Buffer, "le", 8)],
+    PROGRAM_ID
+  );
+  return [registry, entry];
+}
+
+// Creates a pool seeded through its initial deposit, so its vaults match its stored reserves
+async function setupPool(connection: Connection, payer: Keypair): Promise<Pool> {
+  const tokenMint = await createMint(connection, payer, payer.publicKey, null, 9);
+  const [pool] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("pool"),
+      PublicKey.default.toBuffer(), // Native SOL side
+      tokenMint.toBuffer(),
+      new BN(FEE_RATE).toArrayLike(Buffer, "le", 8),
+    ],
+    PROGRAM_ID
+  );
+  const [solVault] = PublicKey.findProgramAddressSync([Buffer.from("sol_vault"), pool.toBuffer()], PROGRAM_ID);
+  const [protocolConfig] = PublicKey.findProgramAddressSync([Buffer.from("protocol_config")], PROGRAM_ID);
+  const [poolRegistry, registryEntry] = await nextRegistryEntry(connection);
+  const lpMint = await createMint(connection, payer, pool, null, 9);
+  const vault = await createAccount(connection, payer, tokenMint, pool, Keypair.generate());
+  const payerTokenAccount = await createAccount(connection, payer, tokenMint, payer.publicKey);
+  await mintTo(connection, payer, tokenMint, payerTokenAccount, payer, POOL_TOKENS);
+  const payerLpAccount = await createAccount(connection, payer, lpMint, payer.publicKey);
+
+  // The program creates the pool and SOL vault PDAs, moves POOL_SOL and
+  // POOL_TOKENS into the vaults and mints the payer the initial LP tokens
+  const tx = new Transaction().add(
+    new TransactionInstruction({
+      keys: [
+        { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+        { pubkey: pool, isSigner: false, isWritable: true },
+        { pubkey: tokenMint, isSigner: false, isWritable: false },
+        { pubkey: lpMint, isSigner: false, isWritable: true },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: solVault, isSigner: false, isWritable: true },
+        { pubkey: protocolConfig, isSigner: false, isWritable: false },
+        { pubkey: poolRegistry, isSigner: false, isWritable: true },
+        { pubkey: registryEntry, isSigner: false, isWritable: true },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: vault, isSigner: false, isWritable: true },
+        { pubkey: payerLpAccount, isSigner: false, isWritable: true },
+      ],
+      programId: PROGRAM_ID,
+      data: Buffer.from([
+        0, // Initialize pool instruction
+        ...new BN(POOL_SOL).toArray("le", 8),
+        ...new BN(POOL_TOKENS).toArray("le", 8),
+        ...new BN(FEE_RATE).toArray("le", 8),
+      ]),
+    })
+  );
+  await sendAndConfirmTransaction(connection, tx, [payer]);
+
+  return { pool, tokenMint, vault, solVault };
+}
+
+// Creates a trader holding `tokens` of the pool's token
+async function setupTrader(
+  connection: Connection,
+  payer: Keypair,
+  pool: Pool,
+  tokens: number
+): Promise<{ trader: Keypair; tokenAccount: PublicKey }> {
+  const trader = Keypair.generate();
+  await airdrop(connection, trader.publicKey, 1_000_000_000);
+  const tokenAccount = await createAccount(connection, payer, pool.tokenMint, trader.publicKey);
+  await mintTo(connection, payer, pool.tokenMint, tokenAccount, payer, tokens);
+  return { trader, tokenAccount };
+}
+
+async function readReserves(connection: Connection, pool: PublicKey): Promise<Reserves> {
+  const info = await connection.getAccountInfo(pool);
+  assert(info, "pool account missing");
+  return {
+    sol: info.data.readBigUInt64LE(41),
+    token: info.data.readBigUInt64LE(49),
+  };
+}
+
+// Output of a swap, mirroring Processor::calculate_swap_output on a constant product pool
+function quote(amountIn: bigint, reserveIn: bigint, reserveOut: bigint): bigint {
+  const amountInWithFee = (amountIn * BigInt(10000 - FEE_RATE)) / 10000n;
+  return (reserveOut * amountInWithFee) / (reserveIn + amountInWithFee);
+}
+
+// Token → SOL swap paying out to `solDestination`
+async function sellTokens(
+  connection: Connection,
+  pool: Pool,
+  trader: Keypair,
+  tokenAccount: PublicKey,
+  amountIn: bigint,
+  minimumAmountOut: bigint,
+  options: { solDestination?: PublicKey; poolAccount?: PublicKey; vault?: PublicKey } = {}
+) {
+  const tx = new Transaction().add(
+    new TransactionInstruction({
+      keys: [
+        { pubkey: trader.publicKey, isSigner: true, isWritable: true },
+        { pubkey: options.poolAccount ?? pool.pool, isSigner: false, isWritable: true },
+        { pubkey: options.solDestination ?? trader.publicKey, isSigner: false, isWritable: true },
+        { pubkey: tokenAccount, isSigner: false, isWritable: true },
+        { pubkey: options.vault ?? pool.vault, isSigner: false, isWritable: true },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: pool.solVault, isSigner: false, isWritable: true },
+      ],
+      programId: PROGRAM_ID,
+      data: Buffer.from([
+        1, // Swap instruction
+        ...new BN(amountIn.toString()).toArray("le", 8),
+        ...new BN(minimumAmountOut.toString()).toArray("le", 8),
+        0, // Token → SOL
+      ]),
+    })
+  );
+  return sendAndConfirmTransaction(connection, tx, [trader]);
+}
+
+async function expectRejected(action: Promise<unknown>, errorCode?: number) {
+  try {
+    await action;
+  } catch (error) {
+    if (errorCode !== undefined) {
+      assert(
+        String(error).includes(`custom program error: 0x${errorCode.toString(16)}`),
+        `expected error ${errorCode}, got ${error}`
+      );
+    }
+    return;
+  }
+  assert.fail("attack was not rejected");
+}
+
+// LPs never lose value: the invariant never shrinks and the pool stays solvent
+async function assertLpsWhole(connection: Connection, pool: Pool, before: Reserves) {
+  const after = await readReserves(connection, pool.pool);
+  assert(after.sol * after.token >= before.sol * before.token, "pool invariant decreased");
+
+  const rent = await connection.getMinimumBalanceForRentExemption(0);
+  const lamports = await connection.getBalance(pool.solVault);
+  assert(BigInt(lamports) >= BigInt(rent) + after.sol, "SOL vault below the SOL reserve");
+  const vault = await getAccount(connection, pool.vault);
+  assert(vault.amount >= after.token, "vault tokens below the token reserve");
+}
+
+// A front-runner moves the price before the victim; the victim's slippage bound must hold
+async function sandwich(connection: Connection, payer: Keypair) {
+  const pool = await setupPool(connection, payer);
+  const before = await readReserves(connection, pool.pool);
+  const attacker = await setupTrader(connection, payer, pool, 200_000_000);
+  const victim = await setupTrader(connection, payer, pool, 100_000_000);
+
+  const victimIn = 100_000_000n;
+  const victimQuote = quote(victimIn, before.token, before.sol);
+
+  await sellTokens(connection, pool, attacker.trader, attacker.tokenAccount, 200_000_000n, 0n);
+  await expectRejected(
+    sellTokens(connection, pool, victim.trader, victim.tokenAccount, victimIn, victimQuote),
+    SLIPPAGE_EXCEEDED
+  );
+
+  await assertLpsWhole(connection, pool, before);
+  console.log("sandwich: victim protected by minimum_amount_out");
+}
+
+// Tokens donated straight into the vault must not move the price
+async function vaultDonation(connection: Connection, payer: Keypair) {
+  const pool = await setupPool(connection, payer);
+  const before = await readReserves(connection, pool.pool);
+  const donor = await setupTrader(connection, payer, pool, 500_000_000);
+  const trader = await setupTrader(connection, payer, pool, 50_000_000);
+
+  await transfer(connection, payer, donor.tokenAccount, pool.vault, donor.trader, 500_000_000);
+  const afterDonation = await readReserves(connection, pool.pool);
+  assert.deepStrictEqual(afterDonation, before, "donation changed the stored reserves");
+
+  // The swap still prices off the stored reserves
+  const amountIn = 50_000_000n;
+  const expected = quote(amountIn, before.token, before.sol);
+  const recipient = Keypair.generate().publicKey;
+  await sellTokens(connection, pool, trader.trader, trader.tokenAccount, amountIn, 0n, {
+    solDestination: recipient,
+  });
+  assert.strictEqual(BigInt(await connection.getBalance(recipient)), expected);
+
+  await assertLpsWhole(connection, pool, before);
+  console.log("vault donation: price unaffected");
+}
+
+// Dust swaps must never round in the trader's favour
+async function roundingGrinding(connection: Connection, payer: Keypair) {
+  const pool = await setupPool(connection, payer);
+  const start = await readReserves(connection, pool.pool);
+  const grinder = await setupTrader(connection, payer, pool, 1_000);
+
+  for (const amountIn of [1n, 2n, 3n, 7n, 333n, 334n]) {
+    const before = await readReserves(connection, pool.pool);
+    try {
+      await sellTokens(connection, pool, grinder.trader, grinder.tokenAccount, amountIn, 0n);
+    } catch (error) {
+      continue; // Rejecting a dust swap is fine
+    }
+    await assertLpsWhole(connection, pool, before);
+  }
+
+  await assertLpsWhole(connection, pool, start);
+  console.log("rounding grinding: invariant never decreased");
+}
+
+// Substituted vaults and pool accounts must be rejected
+async function fakeAccountSubstitution(connection: Connection, payer: Keypair) {
+  const pool = await setupPool(connection, payer);
+  const before = await readReserves(connection, pool.pool);
+  const attacker = await setupTrader(connection, payer, pool, 100_000_000);
+
+  // Paying the swap input into a vault the attacker controls
+  const fakeVault = await createAccount(
+    connection,
+    payer,
+    pool.tokenMint,
+    attacker.trader.publicKey,
+    Keypair.generate()
+  );
+  await expectRejected(
+    sellTokens(connection, pool, attacker.trader, attacker.tokenAccount, 100_000_000n, 0n, {
+      vault: fakeVault,
+    }),
+    INVALID_TOKEN_VAULT
+  );
+
+  // Pointing the swap at a pool-shaped account the attacker owns
+  const fakePool = Keypair.generate();
+  const poolInfo = await connection.getAccountInfo(pool.pool);
+  assert(poolInfo, "pool account missing");
+  const poolStateLen = poolInfo.data.length;
+  const rent = await connection.getMinimumBalanceForRentExemption(poolStateLen);
+  await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: attacker.trader.publicKey,
+        newAccountPubkey: fakePool.publicKey,
+        lamports: rent,
+        space: poolStateLen,
+        programId: SystemProgram.programId,
+      })
+    ),
+    [attacker.trader, fakePool]
+  );
+  await expectRejected(
+    sellTokens(connection, pool, attacker.trader, attacker.tokenAccount, 100_000_000n, 0n, {
+      poolAccount: fakePool.publicKey,
+    })
+  );
+
+  await assertLpsWhole(connection, pool, before);
+  console.log("fake account substitution: rejected");
+}
+
+async function main() {
+  // Connect to local test validator
+  const connection = new Connection("http://localhost:8899", "confirmed");
+
+  const payer = Keypair.generate();
+  await airdrop(connection, payer.publicKey, 10 * 1_000_000_000);
+
+  await sandwich(connection, payer);
+  await vaultDonation(connection, payer);
+  await roundingGrinding(connection, payer);
+  await fakeAccountSubstitution(connection, payer);
+}
+
+main().catch((error) => {
+  console.error("Error:", error);
+  process.exit(1);
+});
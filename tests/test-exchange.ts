@@ -16,9 +16,6 @@ import BN from "bn.js";
 
 // Program ID from our deployment
 const PROGRAM_ID = new PublicKey("F11d9Ct1MHaQhyRGR7TgyPavoCSPkrzhGf6Wh9g41JS");
-const TEST_TOKEN = new PublicKey(
-  "FKr7f2zhbjPyuyUoPw8uFyLYfQbaoGHwDA8a5ehr769B"
-);
 
 async function main() {
   // Connect to local test validator
@@ -32,27 +29,68 @@ async function main() {
   );
   await connection.confirmTransaction(airdropSignature);
 
-  // Create LP token mint
-  const lpMint = await createMint(connection, payer, payer.publicKey, null, 9);
+  // Create the token to trade and fund the payer with the initial deposit
+  const tokenMint = await createMint(connection, payer, payer.publicKey, null, 9);
+  const payerTokenAccount = await createAccount(connection, payer, tokenMint, payer.publicKey);
+  await mintTo(connection, payer, tokenMint, payerTokenAccount, payer, 1000000000);
 
-  // Create pool state account
-  const poolState = Keypair.generate();
-  const createPoolStateIx = SystemProgram.createAccount({
-    fromPubkey: payer.publicKey,
-    newAccountPubkey: poolState.publicKey,
-    lamports: await connection.getMinimumBalanceForRentExemption(165),
-    space: 165,
-    programId: PROGRAM_ID,
-  });
+  // Derive the pool and SOL vault PDAs; the program creates both
+  const [poolState] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("pool"),
+      PublicKey.default.toBuffer(), // Native SOL side
+      tokenMint.toBuffer(),
+      new BN(30).toArrayLike(Buffer, "le", 8),
+    ],
+    PROGRAM_ID
+  );
+  const [solVault] = PublicKey.findProgramAddressSync(
+    [Buffer.from("sol_vault"), poolState.toBuffer()],
+    PROGRAM_ID
+  );
+  const [protocolConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config")],
+    PROGRAM_ID
+  );
+
+  // The new pool is registered at the registry's current pool count
+  const [poolRegistry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_registry")],
+    PROGRAM_ID
+  );
+  const registryInfo = await connection.getAccountInfo(poolRegistry);
+  const poolCount = registryInfo ? registryInfo.data.readBigUInt64LE(0) : 0n;
+  const [registryEntry] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("pool_registry_entry"),
+      new BN(poolCount.toString()).toArrayLike(Buffer, "le", 8),
+    ],
+    PROGRAM_ID
+  );
+
+  // Create LP token mint, minted only by the pool PDA
+  const lpMint = await createMint(connection, payer, poolState, null, 9);
+  const payerLpAccount = await createAccount(connection, payer, lpMint, payer.publicKey);
+
+  // The pool's token vault, owned by the pool PDA
+  const poolTokenVault = await createAccount(connection, payer, tokenMint, poolState, Keypair.generate());
 
   // Initialize pool
   const initPoolIx = new Transaction().add({
     keys: [
       { pubkey: payer.publicKey, isSigner: true, isWritable: true },
-      { pubkey: poolState.publicKey, isSigner: false, isWritable: true },
-      { pubkey: TEST_TOKEN, isSigner: false, isWritable: false },
+      { pubkey: poolState, isSigner: false, isWritable: true },
+      { pubkey: tokenMint, isSigner: false, isWritable: false },
       { pubkey: lpMint, isSigner: false, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: solVault, isSigner: false, isWritable: true },
+      { pubkey: protocolConfig, isSigner: false, isWritable: false },
+      { pubkey: poolRegistry, isSigner: false, isWritable: true },
+      { pubkey: registryEntry, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: poolTokenVault, isSigner: false, isWritable: true },
+      { pubkey: payerLpAccount, isSigner: false, isWritable: true },
     ],
     programId: PROGRAM_ID,
     data: Buffer.from([
@@ -64,13 +102,10 @@ async function main() {
   });
 
   // Send transaction
-  const tx = new Transaction().add(createPoolStateIx, initPoolIx);
+  const tx = new Transaction().add(initPoolIx);
 
   try {
-    const signature = await sendAndConfirmTransaction(connection, tx, [
-      payer,
-      poolState,
-    ]);
+    const signature = await sendAndConfirmTransaction(connection, tx, [payer]);
     console.log("Pool initialized! Signature:", signature);
   } catch (error) {
     console.error("Error:", error);